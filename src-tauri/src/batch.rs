@@ -0,0 +1,255 @@
+// ==========================================
+// 🌟 批量处理：有界线程池 + 逐张进度事件 + 中途停止
+// ==========================================
+// 线程数取 CPU 核数，同时按可用内存给每张图预留解码所需的空间，
+// 大图排队等内存，小图可以并行。停止只影响尚未开始的文件，正在写的文件照常完成（原子替换保证不会半截）。
+// 单张失败不影响其余文件，最终报告列出每个文件的结果。
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::error::{AppError, ErrorCode};
+use crate::output::OutputOptions;
+use crate::pipeline::{self, ProcessRequest};
+
+/// 可用内存中留给批处理的比例
+const MEMORY_SHARE: f64 = 0.6;
+/// 每像素的峰值内存：16 位 RGBA 解码 + 白底画布 + 输出缓冲
+const BYTES_PER_PIXEL: u64 = 24;
+
+/// 单个文件的排版参数（与 process_image 的参数一一对应）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProcessJob {
+    pub path: String,
+    pub mode: String,
+    pub target_w_cm: f32,
+    pub target_h_cm: f32,
+    pub crop_x: f32,
+    pub crop_y: f32,
+    pub crop_w: f32,
+    pub crop_h: f32,
+    #[serde(default)]
+    pub border_top_cm: f32,
+    #[serde(default)]
+    pub border_right_cm: f32,
+    #[serde(default)]
+    pub border_bottom_cm: f32,
+    #[serde(default)]
+    pub border_left_cm: f32,
+    #[serde(default)]
+    pub paper: Option<String>,
+}
+
+/// 整批共用的设置
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JobSettings {
+    pub color_policy: Option<String>,
+    pub output_dpi: Option<f64>,
+    pub output: Option<OutputOptions>,
+}
+
+/// 逐张事件：batch-progress（开始）、batch-success、batch-failure
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BatchEvent<'a> {
+    batch_id: &'a str,
+    index: usize,
+    path: &'a str,
+    done: usize,
+    total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a AppError>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BatchItem {
+    pub index: usize,
+    pub path: String,
+    /// "succeeded" | "failed" | "cancelled"
+    pub status: &'static str,
+    pub new_path: Option<String>,
+    pub new_name: Option<String>,
+    pub error: Option<AppError>,
+}
+
+impl BatchItem {
+    fn new(index: usize, path: &str, status: &'static str) -> Self {
+        BatchItem { index, path: path.to_string(), status, new_path: None, new_name: None, error: None }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BatchReport {
+    pub batch_id: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub items: Vec<BatchItem>,
+}
+
+/// 进行中的批次及其停止标记
+#[derive(Default)]
+pub(crate) struct Batches(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl Batches {
+    pub fn register(&self, batch_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        if let Ok(mut batches) = self.0.lock() {
+            batches.insert(batch_id.to_string(), flag.clone());
+        }
+        flag
+    }
+
+    pub fn cancel(&self, batch_id: &str) -> bool {
+        let Ok(batches) = self.0.lock() else { return false };
+        match batches.get(batch_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, batch_id: &str) {
+        if let Ok(mut batches) = self.0.lock() {
+            batches.remove(batch_id);
+        }
+    }
+}
+
+/// 按字节计的内存配额，超出时排队
+struct MemoryBudget {
+    limit: u64,
+    used: Mutex<u64>,
+    freed: Condvar,
+}
+
+struct Permit<'a> {
+    budget: &'a MemoryBudget,
+    amount: u64,
+}
+
+impl MemoryBudget {
+    fn acquire(&self, amount: u64) -> Permit<'_> {
+        // 单张超过总配额时也要能跑，只是独占
+        let amount = amount.min(self.limit);
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        while *used > 0 && *used + amount > self.limit {
+            used = self.freed.wait(used).unwrap_or_else(|e| e.into_inner());
+        }
+        *used += amount;
+        Permit { budget: self, amount }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut used = self.budget.used.lock().unwrap_or_else(|e| e.into_inner());
+        *used -= self.amount;
+        self.budget.freed.notify_all();
+    }
+}
+
+fn memory_limit() -> u64 {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    let available = system.available_memory();
+    // 取不到时按 2GB 估计
+    let available = if available > 0 { available } else { 2 * 1024 * 1024 * 1024 };
+    (available as f64 * MEMORY_SHARE) as u64
+}
+
+/// 单张处理的峰值内存估算：原图与成品取大
+pub(crate) fn memory_estimate(input: &Path, req: &ProcessRequest) -> u64 {
+    let source = pipeline::dimensions(input).map(|(w, h, _)| w as u64 * h as u64).unwrap_or(0);
+    let output = pipeline::expected_output(input, req).map(|e| e.width as u64 * e.height as u64).unwrap_or(0);
+    source.max(output) * BYTES_PER_PIXEL
+}
+
+/// 执行整批任务；worker 按序号处理单个文件，返回 (新路径, 新文件名)
+pub(crate) fn run<F>(app: &AppHandle, batch_id: &str, cancel: &AtomicBool, jobs: &[ProcessJob], estimates: &[u64], worker: F) -> BatchReport
+where
+    F: Fn(usize, &ProcessJob) -> Result<(String, String), AppError> + Sync,
+{
+    let total = jobs.len();
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2).min(total).max(1);
+    let budget = MemoryBudget { limit: memory_limit(), used: Mutex::new(0), freed: Condvar::new() };
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let items: Mutex<Vec<Option<BatchItem>>> = Mutex::new(vec![None; total]);
+
+    let emit = |name: &str, event: BatchEvent| {
+        let _ = app.emit(name, event);
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(job) = jobs.get(index) else { break };
+                let event = |done: usize| BatchEvent {
+                    batch_id, index, path: &job.path, done, total, new_path: None, new_name: None, error: None,
+                };
+
+                let item = {
+                    let _permit = (!cancel.load(Ordering::SeqCst)).then(|| budget.acquire(estimates.get(index).copied().unwrap_or(0)));
+                    // 排队等内存期间也可能被停止
+                    if cancel.load(Ordering::SeqCst) {
+                        BatchItem::new(index, &job.path, "cancelled")
+                    } else {
+                        emit("batch-progress", event(done.load(Ordering::SeqCst)));
+                        match worker(index, job) {
+                            Ok((new_path, new_name)) => BatchItem {
+                                new_path: Some(new_path),
+                                new_name: Some(new_name),
+                                ..BatchItem::new(index, &job.path, "succeeded")
+                            },
+                            // 停止时被结束的 magick 进程算作未处理
+                            Err(e) if e.code == ErrorCode::Cancelled => BatchItem::new(index, &job.path, "cancelled"),
+                            Err(e) => BatchItem { error: Some(e), ..BatchItem::new(index, &job.path, "failed") },
+                        }
+                    }
+                };
+
+                let finished = done.fetch_add(1, Ordering::SeqCst) + 1;
+                match item.status {
+                    "succeeded" => emit(
+                        "batch-success",
+                        BatchEvent { new_path: item.new_path.as_deref(), new_name: item.new_name.as_deref(), ..event(finished) },
+                    ),
+                    "failed" => emit("batch-failure", BatchEvent { error: item.error.as_ref(), ..event(finished) }),
+                    _ => {}
+                }
+                if let Ok(mut items) = items.lock() {
+                    items[index] = Some(item);
+                }
+            });
+        }
+    });
+
+    let items: Vec<BatchItem> = items.into_inner().unwrap_or_default().into_iter().flatten().collect();
+    let count = |status: &str| items.iter().filter(|item| item.status == status).count();
+    BatchReport {
+        batch_id: batch_id.to_string(),
+        total,
+        succeeded: count("succeeded"),
+        failed: count("failed"),
+        cancelled: count("cancelled"),
+        items,
+    }
+}
@@ -0,0 +1,659 @@
+// ==========================================
+// 🌟 色彩管理：ICC 读取、内置 sRGB / Adobe RGB、色彩策略
+// ==========================================
+// 只处理“矩阵 + 曲线”型 RGB 描述文件（相机、显示器、sRGB、Adobe RGB、ProPhoto 等均属此类），
+// 按相对比色意图换算；CMYK/LUT 型描述文件交给 magick 的 `-profile` 处理。
+
+use image::{DynamicImage, Rgb, Rgba};
+
+use crate::i18n;
+
+/// 写出时对 ICC 的处理方式
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum ColorPolicy {
+    /// 原样带回原图的描述文件（无描述文件则不写）
+    #[default]
+    Keep,
+    /// 转换到 sRGB 并嵌入
+    ConvertSrgb,
+    /// 转换到 Adobe RGB (1998) 并嵌入
+    ConvertAdobeRgb,
+    /// 原图无描述文件时指定为 sRGB（不改像素）
+    AssignSrgb,
+    /// 原图无描述文件时指定为 Adobe RGB（不改像素）
+    AssignAdobeRgb,
+}
+
+impl ColorPolicy {
+    /// 前端传入的策略名；未传时按 keep 处理
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("keep") {
+            "keep" => Ok(ColorPolicy::Keep),
+            "convert_srgb" => Ok(ColorPolicy::ConvertSrgb),
+            "convert_adobe_rgb" => Ok(ColorPolicy::ConvertAdobeRgb),
+            "assign_srgb" => Ok(ColorPolicy::AssignSrgb),
+            "assign_adobe_rgb" => Ok(ColorPolicy::AssignAdobeRgb),
+            other => Err(i18n::tf("err.unknown_color_policy", &[&other])),
+        }
+    }
+
+    /// 策略涉及的内置描述文件
+    pub fn target(self) -> Option<Target> {
+        match self {
+            ColorPolicy::Keep => None,
+            ColorPolicy::ConvertSrgb | ColorPolicy::AssignSrgb => Some(Target::Srgb),
+            ColorPolicy::ConvertAdobeRgb | ColorPolicy::AssignAdobeRgb => Some(Target::AdobeRgb),
+        }
+    }
+
+    pub fn converts(self) -> bool {
+        matches!(self, ColorPolicy::ConvertSrgb | ColorPolicy::ConvertAdobeRgb)
+    }
+}
+
+/// 内置的目标色彩空间
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Target {
+    Srgb,
+    AdobeRgb,
+}
+
+/// D50 PCS 白点
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+impl Target {
+    pub fn description(self) -> &'static str {
+        match self {
+            Target::Srgb => "sRGB IEC61966-2.1",
+            Target::AdobeRgb => "Adobe RGB (1998)",
+        }
+    }
+
+    /// RGB → XYZ(D50) 矩阵（已做 Bradford 适应），列依次为 R/G/B
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            Target::Srgb => [
+                [0.4360747, 0.3850649, 0.1430804],
+                [0.2225045, 0.7168786, 0.0606169],
+                [0.0139322, 0.0971045, 0.7141733],
+            ],
+            Target::AdobeRgb => [
+                [0.6097559, 0.2052401, 0.1492240],
+                [0.3111242, 0.6256560, 0.0632197],
+                [0.0194811, 0.0608902, 0.7448387],
+            ],
+        }
+    }
+
+    /// 线性光 → 编码值
+    fn encode(self, linear: f64) -> f64 {
+        let l = linear.clamp(0.0, 1.0);
+        match self {
+            Target::Srgb => {
+                if l <= 0.0031308 {
+                    12.92 * l
+                } else {
+                    1.055 * l.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Target::AdobeRgb => l.powf(1.0 / ADOBE_GAMMA),
+        }
+    }
+
+    fn curve(self) -> Curve {
+        match self {
+            Target::Srgb => Curve::Param(3, [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045, 0.0, 0.0]),
+            Target::AdobeRgb => Curve::Gamma(ADOBE_GAMMA),
+        }
+    }
+
+    /// 生成可嵌入文件的 ICC v2 显示器描述文件
+    pub fn profile(self) -> Vec<u8> {
+        let trc = match self {
+            // sRGB 用 1024 点查找表，兼容只认 v2 curv 的老软件
+            Target::Srgb => {
+                let curve = self.curve();
+                let table: Vec<u16> = (0..1024)
+                    .map(|i| (curve.eval(i as f64 / 1023.0) * 65535.0).round() as u16)
+                    .collect();
+                curv_tag(&table)
+            }
+            // 2.19921875 = 0x0233 (u8Fixed8)
+            Target::AdobeRgb => curv_tag(&[0x0233]),
+        };
+        let m = self.matrix();
+        build_profile(&[
+            (*b"desc", desc_tag(self.description())),
+            (*b"cprt", text_tag("No copyright, use freely")),
+            (*b"wtpt", xyz_tag(D50)),
+            (*b"rXYZ", xyz_tag([m[0][0], m[1][0], m[2][0]])),
+            (*b"gXYZ", xyz_tag([m[0][1], m[1][1], m[2][1]])),
+            (*b"bXYZ", xyz_tag([m[0][2], m[1][2], m[2][2]])),
+            (*b"rTRC", trc.clone()),
+            (*b"gTRC", trc.clone()),
+            (*b"bTRC", trc),
+        ])
+    }
+}
+
+const ADOBE_GAMMA: f64 = 563.0 / 256.0;
+
+fn s15f16(v: f64) -> [u8; 4] {
+    ((v * 65536.0).round() as i32).to_be_bytes()
+}
+
+fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+    let mut t = b"XYZ \0\0\0\0".to_vec();
+    for v in xyz {
+        t.extend_from_slice(&s15f16(v));
+    }
+    t
+}
+
+fn curv_tag(table: &[u16]) -> Vec<u8> {
+    let mut t = b"curv\0\0\0\0".to_vec();
+    t.extend_from_slice(&(table.len() as u32).to_be_bytes());
+    for v in table {
+        t.extend_from_slice(&v.to_be_bytes());
+    }
+    t
+}
+
+fn text_tag(text: &str) -> Vec<u8> {
+    let mut t = b"text\0\0\0\0".to_vec();
+    t.extend_from_slice(text.as_bytes());
+    t.push(0);
+    t
+}
+
+/// v2 textDescriptionType：ASCII 段 + 空 Unicode 段 + 空 ScriptCode 段
+fn desc_tag(text: &str) -> Vec<u8> {
+    let mut t = b"desc\0\0\0\0".to_vec();
+    t.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+    t.extend_from_slice(text.as_bytes());
+    t.push(0);
+    t.extend_from_slice(&[0u8; 8]);
+    t.extend_from_slice(&[0u8; 3]);
+    t.extend_from_slice(&[0u8; 67]);
+    t
+}
+
+fn build_profile(tags: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let table_len = 4 + tags.len() * 12;
+    let mut body = Vec::new();
+    let mut table = Vec::with_capacity(table_len);
+    table.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+    for (sig, data) in tags {
+        let offset = 128 + table_len + body.len();
+        table.extend_from_slice(sig);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(data);
+        // 标签按 4 字节对齐
+        while body.len() % 4 != 0 {
+            body.push(0);
+        }
+    }
+
+    let size = 128 + table_len + body.len();
+    let mut header = vec![0u8; 128];
+    header[0..4].copy_from_slice(&(size as u32).to_be_bytes());
+    header[8..12].copy_from_slice(&[0x02, 0x10, 0x00, 0x00]);
+    header[12..16].copy_from_slice(b"mntr");
+    header[16..20].copy_from_slice(b"RGB ");
+    header[20..24].copy_from_slice(b"XYZ ");
+    // 创建日期固定为 2024-01-01，保证每次生成的字节一致
+    for (i, v) in [2024u16, 1, 1, 0, 0, 0].iter().enumerate() {
+        header[24 + i * 2..26 + i * 2].copy_from_slice(&v.to_be_bytes());
+    }
+    header[36..40].copy_from_slice(b"acsp");
+    for (i, v) in D50.iter().enumerate() {
+        header[68 + i * 4..72 + i * 4].copy_from_slice(&s15f16(*v));
+    }
+
+    let mut out = header;
+    out.extend_from_slice(&table);
+    out.extend_from_slice(&body);
+    out
+}
+
+/// 在标签表中查找标签数据
+fn find_tag<'a>(icc: &'a [u8], sig: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = u32::from_be_bytes(icc.get(128..132)?.try_into().ok()?) as usize;
+    for i in 0..count.min(256) {
+        let entry = icc.get(132 + i * 12..144 + i * 12)?;
+        if &entry[0..4] == sig {
+            let offset = u32::from_be_bytes(entry[4..8].try_into().ok()?) as usize;
+            let size = u32::from_be_bytes(entry[8..12].try_into().ok()?) as usize;
+            return icc.get(offset..offset.checked_add(size)?);
+        }
+    }
+    None
+}
+
+/// 读取描述文件名称（v2 desc 或 v4 mluc）
+pub(crate) fn description(icc: &[u8]) -> Option<String> {
+    let tag = find_tag(icc, b"desc")?;
+    match tag.get(0..4)? {
+        b"desc" => {
+            let len = u32::from_be_bytes(tag.get(8..12)?.try_into().ok()?) as usize;
+            let text = tag.get(12..12 + len)?;
+            let text = String::from_utf8_lossy(text).trim_end_matches('\0').trim().to_string();
+            (!text.is_empty()).then_some(text)
+        }
+        b"mluc" => {
+            let records = u32::from_be_bytes(tag.get(8..12)?.try_into().ok()?);
+            if records == 0 {
+                return None;
+            }
+            let len = u32::from_be_bytes(tag.get(20..24)?.try_into().ok()?) as usize;
+            let offset = u32::from_be_bytes(tag.get(24..28)?.try_into().ok()?) as usize;
+            let units: Vec<u16> = tag
+                .get(offset..offset + len)?
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            let text = String::from_utf16_lossy(&units).trim_end_matches('\0').trim().to_string();
+            (!text.is_empty()).then_some(text)
+        }
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Curve {
+    Gamma(f64),
+    Table(Vec<u16>),
+    /// ICC parametricCurveType：函数类型 + (g, a, b, c, d, e, f)
+    Param(u16, [f64; 7]),
+}
+
+impl Curve {
+    /// 编码值 → 线性光
+    fn eval(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            Curve::Gamma(g) => x.powf(*g),
+            Curve::Table(t) if t.is_empty() => x,
+            Curve::Table(t) if t.len() == 1 => x.powf(t[0] as f64 / 256.0),
+            Curve::Table(t) => {
+                let pos = x * (t.len() - 1) as f64;
+                let i = (pos.floor() as usize).min(t.len() - 2);
+                let frac = pos - i as f64;
+                (t[i] as f64 * (1.0 - frac) + t[i + 1] as f64 * frac) / 65535.0
+            }
+            Curve::Param(kind, [g, a, b, c, d, e, f]) => match kind {
+                0 => x.powf(*g),
+                1 => if x >= -b / a { (a * x + b).powf(*g) } else { 0.0 },
+                2 => if x >= -b / a { (a * x + b).powf(*g) + c } else { *c },
+                3 => if x >= *d { (a * x + b).powf(*g) } else { c * x },
+                _ => if x >= *d { (a * x + b).powf(*g) + e } else { c * x + f },
+            },
+        }
+    }
+}
+
+fn parse_curve(tag: &[u8]) -> Option<Curve> {
+    match tag.get(0..4)? {
+        b"curv" => {
+            let n = u32::from_be_bytes(tag.get(8..12)?.try_into().ok()?) as usize;
+            let table = tag.get(12..12 + n * 2)?.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+            Some(Curve::Table(table))
+        }
+        b"para" => {
+            let kind = u16::from_be_bytes(tag.get(8..10)?.try_into().ok()?);
+            let n = match kind {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => return None,
+            };
+            let mut p = [0.0; 7];
+            for (i, v) in p.iter_mut().take(n).enumerate() {
+                *v = i32::from_be_bytes(tag.get(12 + i * 4..16 + i * 4)?.try_into().ok()?) as f64 / 65536.0;
+            }
+            Some(Curve::Param(kind, p))
+        }
+        _ => None,
+    }
+}
+
+fn parse_xyz(tag: &[u8]) -> Option<[f64; 3]> {
+    if tag.get(0..4)? != b"XYZ " {
+        return None;
+    }
+    let mut v = [0.0; 3];
+    for (i, out) in v.iter_mut().enumerate() {
+        *out = i32::from_be_bytes(tag.get(8 + i * 4..12 + i * 4)?.try_into().ok()?) as f64 / 65536.0;
+    }
+    Some(v)
+}
+
+/// 矩阵-曲线型 RGB 描述文件
+struct MatrixShaper {
+    matrix: [[f64; 3]; 3],
+    curves: [Curve; 3],
+}
+
+fn parse_matrix_shaper(icc: &[u8]) -> Option<MatrixShaper> {
+    if icc.get(16..20)? != b"RGB " {
+        return None;
+    }
+    let r = parse_xyz(find_tag(icc, b"rXYZ")?)?;
+    let g = parse_xyz(find_tag(icc, b"gXYZ")?)?;
+    let b = parse_xyz(find_tag(icc, b"bXYZ")?)?;
+    let curves = [
+        parse_curve(find_tag(icc, b"rTRC")?)?,
+        parse_curve(find_tag(icc, b"gTRC")?)?,
+        parse_curve(find_tag(icc, b"bTRC")?)?,
+    ];
+    Some(MatrixShaper {
+        matrix: [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]],
+        curves,
+    })
+}
+
+fn invert(m: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    let inv_det = 1.0 / det;
+    [
+        [
+            (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv_det,
+            (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv_det,
+            (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv_det,
+        ],
+        [
+            (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv_det,
+            (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv_det,
+            (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv_det,
+        ],
+        [
+            (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv_det,
+            (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv_det,
+            (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv_det,
+        ],
+    ]
+}
+
+fn multiply(a: [[f64; 3]; 3], b: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+/// 转换目标：XYZ → RGB 矩阵的逆 + 线性光 → 编码值查找表（按 65536 级量化）
+struct Destination {
+    matrix: [[f64; 3]; 3],
+    output: [Vec<f32>; 3],
+}
+
+impl Destination {
+    fn from_target(target: Target) -> Self {
+        let table: Vec<f32> = (0..65536).map(|i| target.encode(i as f64 / 65535.0) as f32).collect();
+        Destination { matrix: target.matrix(), output: [table.clone(), table.clone(), table] }
+    }
+
+    /// 任意矩阵型描述文件：对单调曲线二分求逆
+    fn from_shaper(dst: &MatrixShaper) -> Self {
+        let output = [0, 1, 2].map(|c| {
+            let curve = &dst.curves[c];
+            (0..65536)
+                .map(|i| {
+                    let target = i as f64 / 65535.0;
+                    let (mut lo, mut hi) = (0.0f64, 1.0f64);
+                    for _ in 0..24 {
+                        let mid = (lo + hi) / 2.0;
+                        if curve.eval(mid) < target {
+                            lo = mid;
+                        } else {
+                            hi = mid;
+                        }
+                    }
+                    ((lo + hi) / 2.0) as f32
+                })
+                .collect()
+        });
+        Destination { matrix: dst.matrix, output }
+    }
+}
+
+/// 源 → 目标的逐像素变换（输入查找表 + 3x3 矩阵 + 输出查找表）
+struct Transform<'a> {
+    inputs: [Vec<f32>; 3],
+    matrix: [[f32; 3]; 3],
+    output: &'a [Vec<f32>; 3],
+}
+
+impl<'a> Transform<'a> {
+    fn new(src: &MatrixShaper, dst: &'a Destination, levels: usize) -> Self {
+        let max = (levels - 1) as f64;
+        let inputs = [0, 1, 2].map(|c| (0..levels).map(|i| src.curves[c].eval(i as f64 / max) as f32).collect());
+        let m = multiply(invert(dst.matrix), src.matrix);
+        Transform { inputs, matrix: m.map(|row| row.map(|v| v as f32)), output: &dst.output }
+    }
+
+    fn apply(&self, idx: [usize; 3]) -> [f32; 3] {
+        let lin = [self.inputs[0][idx[0]], self.inputs[1][idx[1]], self.inputs[2][idx[2]]];
+        let m = &self.matrix;
+        [0, 1, 2].map(|row| {
+            let v = m[row][0] * lin[0] + m[row][1] * lin[1] + m[row][2] * lin[2];
+            self.output[row][(v.clamp(0.0, 1.0) * 65535.0).round() as usize]
+        })
+    }
+}
+
+fn convert(img: DynamicImage, src: &MatrixShaper, dst: &Destination) -> DynamicImage {
+    match img {
+        DynamicImage::ImageRgb8(mut buf) => {
+            let t = Transform::new(src, dst, 256);
+            for p in buf.pixels_mut() {
+                let out = t.apply(p.0.map(|v| v as usize));
+                *p = Rgb(out.map(|v| (v * 255.0).round() as u8));
+            }
+            DynamicImage::ImageRgb8(buf)
+        }
+        DynamicImage::ImageRgba8(mut buf) => {
+            let t = Transform::new(src, dst, 256);
+            for p in buf.pixels_mut() {
+                let [r, g, b, a] = p.0;
+                let out = t.apply([r as usize, g as usize, b as usize]);
+                *p = Rgba([(out[0] * 255.0).round() as u8, (out[1] * 255.0).round() as u8, (out[2] * 255.0).round() as u8, a]);
+            }
+            DynamicImage::ImageRgba8(buf)
+        }
+        DynamicImage::ImageRgb16(mut buf) => {
+            let t = Transform::new(src, dst, 65536);
+            for p in buf.pixels_mut() {
+                let out = t.apply(p.0.map(|v| v as usize));
+                *p = Rgb(out.map(|v| (v * 65535.0).round() as u16));
+            }
+            DynamicImage::ImageRgb16(buf)
+        }
+        DynamicImage::ImageRgba16(mut buf) => {
+            let t = Transform::new(src, dst, 65536);
+            for p in buf.pixels_mut() {
+                let [r, g, b, a] = p.0;
+                let out = t.apply([r as usize, g as usize, b as usize]);
+                *p = Rgba([(out[0] * 65535.0).round() as u16, (out[1] * 65535.0).round() as u16, (out[2] * 65535.0).round() as u16, a]);
+            }
+            DynamicImage::ImageRgba16(buf)
+        }
+        // 灰度图不做换算，保留原灰度描述文件
+        other => other,
+    }
+}
+
+/// 无描述文件的 RGB 图一律视为 sRGB
+fn srgb_shaper() -> MatrixShaper {
+    MatrixShaper { matrix: Target::Srgb.matrix(), curves: [Target::Srgb.curve(), Target::Srgb.curve(), Target::Srgb.curve()] }
+}
+
+fn is_gray(img: &DynamicImage) -> bool {
+    matches!(img, DynamicImage::ImageLuma8(_) | DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_))
+}
+
+/// 按策略处理像素与描述文件，返回写出时应嵌入的 ICC
+pub(crate) fn apply_policy(img: DynamicImage, icc: Option<Vec<u8>>, policy: ColorPolicy) -> Result<(DynamicImage, Option<Vec<u8>>), String> {
+    let Some(target) = policy.target() else { return Ok((img, icc)) };
+    // RGB 描述文件不能指给灰度图
+    if is_gray(&img) {
+        return Ok((img, icc));
+    }
+    if !policy.converts() {
+        return Ok(match icc {
+            Some(profile) => (img, Some(profile)),
+            None => (img, Some(target.profile())),
+        });
+    }
+
+    // 已经是目标空间时不重复换算，原描述文件原样保留
+    if let Some(profile) = &icc {
+        if description(profile).is_some_and(|d| d == target.description()) {
+            return Ok((img, icc));
+        }
+    }
+    let src = match &icc {
+        Some(profile) => parse_matrix_shaper(profile).ok_or_else(|| i18n::t("err.icc_not_matrix"))?,
+        None if target == Target::Srgb => return Ok((img, Some(target.profile()))),
+        None => srgb_shaper(),
+    };
+    Ok((convert(img, &src, &Destination::from_target(target)), Some(target.profile())))
+}
+
+/// 预览图统一转到 sRGB（webview 按 sRGB 显示），转换失败时原样返回
+pub(crate) fn to_srgb_preview(img: DynamicImage, icc: Option<&[u8]>) -> DynamicImage {
+    let Some(src) = icc.and_then(parse_matrix_shaper) else { return img };
+    if is_gray(&img) || description(icc.unwrap_or_default()).is_some_and(|d| d == Target::Srgb.description()) {
+        return img;
+    }
+    let rgb = match img {
+        DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgb16(_) => img,
+        other => DynamicImage::ImageRgb8(other.into_rgb8()),
+    };
+    convert(rgb, &src, &Destination::from_target(Target::Srgb))
+}
+
+/// 转换到任意矩阵型 RGB 输出描述文件（纸张配置用），返回写出时应嵌入的 ICC；源无描述文件时按 sRGB 理解
+pub(crate) fn convert_to_profile(img: DynamicImage, src_icc: Option<Vec<u8>>, dst_icc: Vec<u8>) -> Result<(DynamicImage, Option<Vec<u8>>), String> {
+    // 与 apply_policy 一致：灰度图不转换，也不能嵌入 RGB 描述文件，保留原有的
+    if is_gray(&img) {
+        return Ok((img, src_icc));
+    }
+    if src_icc.as_ref() == Some(&dst_icc) {
+        return Ok((img, Some(dst_icc)));
+    }
+    let dst = parse_matrix_shaper(&dst_icc).ok_or_else(|| i18n::t("err.output_icc_not_matrix"))?;
+    let src = match &src_icc {
+        Some(profile) => parse_matrix_shaper(profile).ok_or_else(|| i18n::t("err.icc_not_matrix"))?,
+        None => srgb_shaper(),
+    };
+    Ok((convert(img, &src, &Destination::from_shaper(&dst)), Some(dst_icc)))
+}
+
+/// 供 magick 兜底路径使用：把内置描述文件落盘到临时目录。
+/// 批处理的多个 worker 会同时调用：进程内加锁，内容已正确时直接复用，
+/// 否则写唯一临时文件再改名，magick 不会读到写了一半的文件
+pub(crate) fn profile_file(target: Target) -> Result<std::path::PathBuf, String> {
+    static WRITING: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let name = match target {
+        Target::Srgb => "imagetool-srgb.icc",
+        Target::AdobeRgb => "imagetool-adobe-rgb.icc",
+    };
+    let path = std::env::temp_dir().join(name);
+    let profile = target.profile();
+    let _guard = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+    let written = || std::fs::read(&path).is_ok_and(|bytes| bytes == profile);
+    if written() {
+        return Ok(path);
+    }
+    let temp = crate::replace::temp_path(&path);
+    std::fs::write(&temp, &profile).map_err(|e| format!("{}: {}", i18n::t("err.write_icc"), e))?;
+    if let Err(e) = std::fs::rename(&temp, &path) {
+        let _ = std::fs::remove_file(&temp);
+        // Windows 上另一个进程的 magick 正打开着目标时改名会失败，内容已正确就照常使用
+        if !written() {
+            return Err(format!("{}: {}", i18n::t("err.write_icc"), e));
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gray_keeps_its_own_profile_under_paper_icc() {
+        let gray = DynamicImage::ImageLuma8(image::GrayImage::from_pixel(4, 4, image::Luma([90])));
+        let gray_icc = b"gray-gamma-2.2".to_vec();
+        let (img, icc) = convert_to_profile(gray.clone(), Some(gray_icc.clone()), Target::AdobeRgb.profile()).unwrap();
+        assert_eq!(img, gray);
+        assert_eq!(icc, Some(gray_icc));
+        let (_, icc) = convert_to_profile(gray, None, Target::AdobeRgb.profile()).unwrap();
+        assert_eq!(icc, None);
+
+        let rgb = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(4, 4, image::Rgb([200, 40, 90])));
+        let (_, icc) = convert_to_profile(rgb, None, Target::AdobeRgb.profile()).unwrap();
+        assert_eq!(icc, Some(Target::AdobeRgb.profile()));
+    }
+
+    #[test]
+    fn builtin_profiles_parse_back() {
+        for target in [Target::Srgb, Target::AdobeRgb] {
+            let icc = target.profile();
+            assert_eq!(description(&icc).as_deref(), Some(target.description()));
+            let shaper = parse_matrix_shaper(&icc).unwrap();
+            for (row, expected) in shaper.matrix.iter().zip(target.matrix()) {
+                for (v, e) in row.iter().zip(expected) {
+                    assert!((v - e).abs() < 1e-4, "{:?} {} {}", target, v, e);
+                }
+            }
+            for curve in &shaper.curves {
+                for x in [0.0, 0.02, 0.2, 0.5, 0.8, 1.0] {
+                    assert!((curve.eval(x) - target.curve().eval(x)).abs() < 1e-3, "{:?} {}", target, x);
+                }
+            }
+        }
+        assert!(description(b"not a profile").is_none());
+        assert!(parse_matrix_shaper(b"not a profile").is_none());
+    }
+
+    #[test]
+    fn srgb_adobe_rgb_round_trip() {
+        // 16 位往返：8 位时近零通道会被另外两个通道的量化误差放大
+        let src = DynamicImage::ImageRgb16(image::ImageBuffer::from_fn(16, 16, |x, y| image::Rgb([x as u16 * 4369, y as u16 * 4369, 65535 - x as u16 * 2048])));
+        let (adobe, icc) = convert_to_profile(src.clone(), Some(Target::Srgb.profile()), Target::AdobeRgb.profile()).unwrap();
+        assert_eq!(icc, Some(Target::AdobeRgb.profile()));
+        // sRGB 纯红在 Adobe RGB 中约为 (219, 0, 0)
+        let red = convert_to_profile(DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1, 1, image::Rgb([255, 0, 0]))), None, Target::AdobeRgb.profile())
+            .unwrap()
+            .0
+            .to_rgb8();
+        assert!(red.get_pixel(0, 0).0.iter().zip([219u8, 0, 0]).all(|(v, e)| v.abs_diff(e) <= 2), "{:?}", red.get_pixel(0, 0));
+
+        let (back, _) = convert_to_profile(adobe, icc, Target::Srgb.profile()).unwrap();
+        let (src, back) = (src.to_rgb16(), back.to_rgb16());
+        let worst = src.as_raw().iter().zip(back.as_raw()).map(|(a, b)| a.abs_diff(*b)).max().unwrap();
+        assert!(worst <= 64, "max diff {}", worst);
+    }
+
+    #[test]
+    fn profile_file_is_complete_under_parallel_calls() {
+        let paths: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(|| profile_file(Target::AdobeRgb).unwrap()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect();
+        for path in paths {
+            assert_eq!(std::fs::read(path).unwrap(), Target::AdobeRgb.profile());
+        }
+    }
+}
@@ -0,0 +1,263 @@
+// ==========================================
+// 🌟 引擎诊断：magick 版本、可读写的格式、资源上限与无头浏览器
+// ==========================================
+// 探测一次要起三次 magick，结果缓存到改引擎设置或手动刷新为止。
+// 文件是否可处理也按探测结果判断：原生管线能解码的不依赖 magick，
+// 其余格式要看 magick 有没有对应的 delegate（HEIC、WebP 等），
+// 未开“最大兼容”的 PSD 不论如何都处理不了。
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::engine::{self, EngineLocation};
+use crate::error::AppError;
+use crate::runner::{self, Task};
+use crate::{i18n, pipeline, psd};
+
+/// 扩展名 → magick 的格式名
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("jpg", "JPEG"),
+    ("jpeg", "JPEG"),
+    ("png", "PNG"),
+    ("tif", "TIFF"),
+    ("tiff", "TIFF"),
+    ("psd", "PSD"),
+    ("heic", "HEIC"),
+    ("heif", "HEIC"),
+    ("webp", "WEBP"),
+];
+
+/// 扩展名对应的 magick 格式名
+pub(crate) fn magick_format(ext: &str) -> Option<&'static str> {
+    EXTENSIONS.iter().find(|(e, _)| *e == ext).map(|(_, format)| *format)
+}
+
+/// 原生管线覆盖的格式，magick 缺失时这些文件多数仍可处理
+const NATIVE_FORMATS: &[&str] = &["JPEG", "PNG", "TIFF", "PSD"];
+
+/// 诊断页重点提示的格式
+const KEY_FORMATS: &[&str] = &["JPEG", "PNG", "TIFF", "PSD", "HEIC", "WEBP", "PDF"];
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FormatSupport {
+    pub name: String,
+    pub read: bool,
+    pub write: bool,
+    pub description: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ResourceLimit {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EngineStatus {
+    pub magick: Option<EngineLocation>,
+    /// 如 "ImageMagick 7.1.1-21 Q16-HDRI x86_64"
+    pub version: Option<String>,
+    /// 编译进来的 delegate 库
+    pub delegates: Vec<String>,
+    pub formats: Vec<FormatSupport>,
+    /// KEY_FORMATS 中 magick 读不了的
+    pub missing_formats: Vec<String>,
+    pub resources: Vec<ResourceLimit>,
+    /// PDF/EPS 依赖的 Ghostscript
+    pub ghostscript: Option<String>,
+    /// html_to_pdf 用的浏览器
+    pub browser: Option<String>,
+    /// 可以加入列表的扩展名
+    pub supported_extensions: Vec<String>,
+    /// magick 未找到或运行失败的原因
+    pub error: Option<AppError>,
+}
+
+impl EngineStatus {
+    fn format(&self, name: &str) -> Option<&FormatSupport> {
+        self.formats.iter().find(|f| f.name == name)
+    }
+
+    fn magick_reads(&self, name: &str) -> bool {
+        self.format(name).is_some_and(|f| f.read)
+    }
+
+    fn magick_round_trips(&self, name: &str) -> bool {
+        self.format(name).is_some_and(|f| f.read && f.write)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileSupport {
+    pub path: String,
+    pub is_supported: bool,
+    /// 不支持的原因
+    pub reason: Option<String>,
+}
+
+/// "Version: ImageMagick 7.1.1-21 Q16-HDRI ..." 与 "Delegates (built-in): bzlib heic jpeg ..."
+pub(crate) fn parse_version(text: &str) -> (Option<String>, Vec<String>) {
+    let mut version = None;
+    let mut delegates = Vec::new();
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("Version:") {
+            let rest = rest.trim();
+            // 去掉版权网址等尾巴
+            let end = rest.find(" https://").or_else(|| rest.find(" http://")).unwrap_or(rest.len());
+            version = Some(rest[..end].trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("Delegates (built-in):") {
+            delegates = rest.split_whitespace().map(str::to_string).collect();
+        }
+    }
+    (version, delegates)
+}
+
+/// `-list format` 的表格：格式名（可能带 * 号）、三位模式 rw+、说明；缩进的续行跳过
+pub(crate) fn parse_formats(text: &str) -> Vec<FormatSupport> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?.trim_end_matches('*');
+            let mode = parts.next()?;
+            let bytes = mode.as_bytes();
+            let valid = bytes.len() == 3 && matches!(bytes[0], b'r' | b'-') && matches!(bytes[1], b'w' | b'-') && matches!(bytes[2], b'+' | b'-');
+            if !valid || name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return None;
+            }
+            Some(FormatSupport {
+                name: name.to_uppercase(),
+                read: bytes[0] == b'r',
+                write: bytes[1] == b'w',
+                description: parts.collect::<Vec<_>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+/// `-list resource`：首行标题之后每行 "Memory: 15.6GiB"
+pub(crate) fn parse_resources(text: &str) -> Vec<ResourceLimit> {
+    text.lines()
+        .filter(|line| line.starts_with(' ') || line.starts_with('\t'))
+        .filter_map(|line| {
+            let (name, value) = line.trim().split_once(':')?;
+            Some(ResourceLimit { name: name.trim().to_string(), value: value.trim().to_string() })
+        })
+        .collect()
+}
+
+fn run_magick(args: &[&str]) -> Result<String, AppError> {
+    let output = runner::magick(Task::Probe, args).output()?;
+    if !output.status.success() {
+        return Err(AppError::engine(&i18n::t("err.engine_error"), &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn ghostscript() -> Option<String> {
+    let names: &[&str] = if cfg!(target_os = "windows") { &["gswin64c", "gswin32c"] } else { &["gs"] };
+    engine::search_path(names).map(|p| p.to_string_lossy().to_string())
+}
+
+/// 完整探测一次
+pub(crate) fn probe() -> EngineStatus {
+    let mut status = EngineStatus {
+        magick: engine::magick(),
+        version: None,
+        delegates: Vec::new(),
+        formats: Vec::new(),
+        missing_formats: Vec::new(),
+        resources: Vec::new(),
+        ghostscript: ghostscript(),
+        browser: engine::find_browser().map(|p| p.to_string_lossy().to_string()),
+        supported_extensions: Vec::new(),
+        error: None,
+    };
+
+    let probed = run_magick(&["-version"]).and_then(|version| {
+        let formats = run_magick(&["-list", "format"])?;
+        let resources = run_magick(&["-list", "resource"])?;
+        Ok((version, formats, resources))
+    });
+    match probed {
+        Ok((version, formats, resources)) => {
+            (status.version, status.delegates) = parse_version(&version);
+            status.formats = parse_formats(&formats);
+            status.resources = parse_resources(&resources);
+        }
+        Err(e) => status.error = Some(e),
+    }
+
+    status.missing_formats = KEY_FORMATS.iter().filter(|f| !status.magick_reads(f)).map(|f| f.to_string()).collect();
+    status.supported_extensions = EXTENSIONS
+        .iter()
+        .filter(|(_, format)| NATIVE_FORMATS.contains(format) || status.magick_round_trips(format))
+        .map(|(ext, _)| ext.to_string())
+        .collect();
+    status
+}
+
+/// 加入列表前判断文件能否处理
+pub(crate) fn support(path: &Path, status: &EngineStatus) -> FileSupport {
+    let verdict = |reason: Option<String>| FileSupport { path: path.to_string_lossy().to_string(), is_supported: reason.is_none(), reason };
+
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let Some((_, format)) = EXTENSIONS.iter().find(|(e, _)| *e == ext) else {
+        return verdict(Some(i18n::tf("support.extension", &[&ext])));
+    };
+    if !path.is_file() {
+        return verdict(Some(i18n::t("err.file_not_found")));
+    }
+    if psd::is_psd(path) {
+        if let Err(e) = psd::ensure_merged_data(path) {
+            return verdict(Some(e));
+        }
+    }
+    if NATIVE_FORMATS.contains(format) && pipeline::can_decode(path) {
+        return verdict(None);
+    }
+    // 原生管线读不了（CMYK TIFF、HEIC 等），全靠 magick
+    let needs_write = !NATIVE_FORMATS.contains(format);
+    let ok = if needs_write { status.magick_round_trips(format) } else { status.magick_reads(format) };
+    if ok {
+        verdict(None)
+    } else if status.error.is_some() {
+        verdict(Some(i18n::t("support.needs_engine")))
+    } else {
+        verdict(Some(i18n::tf("support.delegate_missing", &[format])))
+    }
+}
+
+/// 探测结果缓存
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    status: Mutex<Option<EngineStatus>>,
+}
+
+impl Diagnostics {
+    /// 取缓存；refresh 或尚未探测时重新探测
+    pub fn status(&self, refresh: bool) -> EngineStatus {
+        if !refresh {
+            if let Some(status) = self.status.lock().ok().and_then(|s| s.clone()) {
+                return status;
+            }
+        }
+        let status = probe();
+        if let Ok(mut slot) = self.status.lock() {
+            *slot = Some(status.clone());
+        }
+        status
+    }
+
+    /// 引擎设置变了，下次重新探测
+    pub fn invalidate(&self) {
+        if let Ok(mut slot) = self.status.lock() {
+            *slot = None;
+        }
+    }
+}
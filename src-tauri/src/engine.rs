@@ -0,0 +1,276 @@
+// ==========================================
+// 🌟 引擎定位：ImageMagick 与无头浏览器，三个平台同一套查找顺序
+// ==========================================
+// magick：随包附带的 sidecar → 用户在设置里指定的路径 → PATH（含各平台常见安装目录）。
+// macOS 与 Linux 安装包附带 sidecar（bin/magick-<目标三元组>，Linux 放官方 AppImage 版的单文件 magick），
+// 发行版仓库里多是不带 magick 命令的 ImageMagick 6，不能指望系统包；Windows 安装包不附带，走设置与 PATH。
+// 浏览器：用户指定 → Chrome / Chromium / Edge 的常见安装位置 → PATH。
+// 定位结果全局缓存，批处理线程、瓦片解码等拿不到 AppHandle 的地方也能直接用。
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::ShellExt;
+
+use crate::i18n;
+
+const SETTINGS_FILE: &str = "engine.json";
+const SIDECAR_NAME: &str = "magick";
+
+/// 用户手动指定的引擎路径，留空表示自动查找
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EngineSettings {
+    pub magick_path: Option<String>,
+    pub browser_path: Option<String>,
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+}
+
+/// 各类外部进程的超时（秒），0 表示不限时
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct Timeouts {
+    /// identify 与引擎探测
+    pub probe: u64,
+    /// 缩略图、预览与瓦片解码
+    pub preview: u64,
+    /// 正式输出
+    pub process: u64,
+    /// 浏览器打印 PDF
+    pub print: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts { probe: 30, preview: 120, process: 900, print: 120 }
+    }
+}
+
+/// magick 的 -limit；留空按本机内存自动计算
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ResourceLimits {
+    pub memory_mib: Option<u64>,
+    pub map_mib: Option<u64>,
+    pub disk_gib: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum EngineSource {
+    /// 随包附带
+    Sidecar,
+    /// 设置里指定
+    Setting,
+    /// 系统 PATH 或常见安装目录
+    Path,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EngineLocation {
+    pub path: String,
+    pub source: EngineSource,
+}
+
+#[derive(Default)]
+struct State {
+    settings: EngineSettings,
+    sidecar: Option<PathBuf>,
+    magick: Option<EngineLocation>,
+}
+
+static STATE: RwLock<Option<State>> = RwLock::new(None);
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("{}: {}", i18n::t("err.save_engine_settings"), e))?;
+    Ok(dir.join(SETTINGS_FILE))
+}
+
+/// 各平台可执行文件名
+fn exe_name(name: &str) -> String {
+    if cfg!(target_os = "windows") { format!("{}.exe", name) } else { name.to_string() }
+}
+
+/// 从桌面启动的程序往往拿不到 shell 里的 PATH，额外找一遍常见安装目录
+fn extra_dirs() -> Vec<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        ["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin"].iter().map(PathBuf::from).collect()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // 安装时没勾选“加入 PATH”也能找到 Program Files 下的 ImageMagick-7.x
+        std::env::var_os("ProgramFiles")
+            .and_then(|root| std::fs::read_dir(root).ok())
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| e.file_name().to_string_lossy().starts_with("ImageMagick"))
+                    .map(|e| e.path())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        ["/usr/local/bin", "/usr/bin", "/snap/bin", "/var/lib/flatpak/exports/bin"].iter().map(PathBuf::from).collect()
+    }
+}
+
+/// 依次在 PATH 与常见目录里找第一个存在的程序
+pub(crate) fn search_path(names: &[&str]) -> Option<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH").map(|p| std::env::split_paths(&p).collect()).unwrap_or_default();
+    dirs.extend(extra_dirs());
+    names
+        .iter()
+        .flat_map(|name| dirs.iter().map(move |dir| dir.join(exe_name(name))))
+        .find(|candidate| candidate.is_file())
+}
+
+/// 设置里的路径：去掉首尾空白，文件存在才采用
+fn configured(path: Option<&str>) -> Option<PathBuf> {
+    path.map(str::trim).filter(|p| !p.is_empty()).map(PathBuf::from).filter(|p| p.is_file())
+}
+
+fn resolve_magick(state: &State) -> Option<EngineLocation> {
+    let found = state
+        .sidecar
+        .clone()
+        .map(|p| (p, EngineSource::Sidecar))
+        .or_else(|| configured(state.settings.magick_path.as_deref()).map(|p| (p, EngineSource::Setting)))
+        .or_else(|| search_path(&["magick"]).map(|p| (p, EngineSource::Path)))?;
+    Some(EngineLocation { path: found.0.to_string_lossy().to_string(), source: found.1 })
+}
+
+/// 通过 sidecar 接口解析随包附带的 magick；开发环境或未附带（Windows 安装包）时为 None
+fn sidecar_path(app: &AppHandle) -> Option<PathBuf> {
+    let command = app.shell().sidecar(SIDECAR_NAME).ok()?;
+    let program = PathBuf::from(Command::from(command).get_program());
+    program.is_file().then_some(program)
+}
+
+/// 启动时读回设置并定位引擎
+pub(crate) fn init(app: &AppHandle) {
+    let settings: EngineSettings = settings_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+    let mut state = State { settings, sidecar: sidecar_path(app), magick: None };
+    state.magick = resolve_magick(&state);
+    if let Ok(mut slot) = STATE.write() {
+        *slot = Some(state);
+    }
+}
+
+pub(crate) fn settings() -> EngineSettings {
+    STATE.read().ok().and_then(|s| s.as_ref().map(|s| s.settings.clone())).unwrap_or_default()
+}
+
+/// 保存设置并重新定位
+pub(crate) fn save_settings(app: &AppHandle, settings: EngineSettings) -> Result<(), String> {
+    let path = settings_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", i18n::t("err.save_engine_settings"), e))?;
+    }
+    let text = serde_json::to_string_pretty(&settings).map_err(|e| format!("{}: {}", i18n::t("err.save_engine_settings"), e))?;
+    std::fs::write(&path, text).map_err(|e| format!("{}: {}", i18n::t("err.save_engine_settings"), e))?;
+
+    let mut slot = STATE.write().map_err(|_| i18n::t("err.save_engine_settings"))?;
+    let state = slot.get_or_insert_with(State::default);
+    state.settings = settings;
+    state.magick = resolve_magick(state);
+    Ok(())
+}
+
+/// 当前使用的 magick；未找到时为 None
+pub(crate) fn magick() -> Option<EngineLocation> {
+    STATE.read().ok().and_then(|s| s.as_ref().and_then(|s| s.magick.clone()))
+}
+
+/// 构造 magick 命令；未定位到时仍按 "magick" 启动，由启动失败给出 EngineMissing
+pub(crate) fn magick_command() -> Command {
+    let program = magick().map(|m| PathBuf::from(m.path)).unwrap_or_else(|| PathBuf::from("magick"));
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(program);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW，不弹黑框
+    }
+    cmd
+}
+
+/// 各平台 Chrome / Chromium / Edge 的固定安装位置
+fn browser_candidates() -> Vec<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        [
+            "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+            "/Applications/Chromium.app/Contents/MacOS/Chromium",
+            "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let local = std::env::var("LOCALAPPDATA").ok();
+        let program_files = std::env::var("ProgramFiles").ok();
+        let program_files_x86 = std::env::var("ProgramFiles(x86)").ok();
+        [
+            local.as_ref().map(|s| format!(r"{}\Google\Chrome\Application\chrome.exe", s)),
+            program_files.as_ref().map(|s| format!(r"{}\Google\Chrome\Application\chrome.exe", s)),
+            program_files_x86.as_ref().map(|s| format!(r"{}\Google\Chrome\Application\chrome.exe", s)),
+            program_files.as_ref().map(|s| format!(r"{}\Microsoft\Edge\Application\msedge.exe", s)),
+            program_files_x86.as_ref().map(|s| format!(r"{}\Microsoft\Edge\Application\msedge.exe", s)),
+            local.as_ref().map(|s| format!(r"{}\Microsoft\Edge\Application\msedge.exe", s)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+        .collect()
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        Vec::new()
+    }
+}
+
+/// PATH 里查找的浏览器命令名（Linux 发行版的包名各不相同）
+const BROWSER_NAMES: &[&str] = &[
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "microsoft-edge",
+    "microsoft-edge-stable",
+];
+
+/// 可用来打印 PDF 的无头浏览器
+pub(crate) fn find_browser() -> Option<PathBuf> {
+    configured(settings().browser_path.as_deref())
+        .or_else(|| browser_candidates().into_iter().find(|p| p.is_file()))
+        .or_else(|| search_path(BROWSER_NAMES))
+}
+
+/// 设置面板显示的内容：用户填写的路径与实际生效的引擎
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EngineInfo {
+    pub settings: EngineSettings,
+    pub magick: Option<EngineLocation>,
+    pub browser: Option<String>,
+}
+
+pub(crate) fn info() -> EngineInfo {
+    EngineInfo { settings: settings(), magick: magick(), browser: find_browser().map(|p| p.to_string_lossy().to_string()) }
+}
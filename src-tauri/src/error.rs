@@ -0,0 +1,162 @@
+// ==========================================
+// 🌟 错误模型：稳定的错误代码 + 提示文字 + 涉及的文件 + 引擎原始输出
+// ==========================================
+// 所有命令都返回 AppError，前端按 code 决定重试、跳过还是提示安装引擎，
+// 不再靠匹配中文提示。内部模块仍返回 String，经 From 转成 Internal，
+// 需要区分的地方在 lib.rs 里按场景指定代码。
+
+use std::fmt;
+use std::path::Path;
+use std::process::Output;
+
+use serde::Serialize;
+
+use crate::i18n;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorCode {
+    /// 找不到 ImageMagick（未安装或无法启动）
+    EngineMissing,
+    /// 外部引擎（ImageMagick / 无头浏览器）运行报错，原始输出见 stderr
+    EngineFailed,
+    /// 外部进程超过设定时间未结束，已被终止
+    Timeout,
+    /// 批次被停止或程序退出，外部进程已被终止
+    Cancelled,
+    /// 图像超出 magick 的内存 / 磁盘缓存上限
+    ResourceLimit,
+    /// 找不到可打印 PDF 的 Chrome / Edge
+    BrowserMissing,
+    FileNotFound,
+    /// 文件损坏或无法解码
+    FileCorrupt,
+    /// 格式或模式暂不支持（如未开“最大兼容”的 PSD）
+    Unsupported,
+    DiskFull,
+    PermissionDenied,
+    /// 其他读写失败
+    Io,
+    /// 参数不合法（未知的色彩策略、输出方式等）
+    InvalidInput,
+    /// 成品超出纸张最大幅宽
+    PaperTooNarrow,
+    /// 写出的文件校验不通过，原图未被替换
+    VerifyFailed,
+    Internal,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// 出错的文件
+    pub path: Option<String>,
+    /// 引擎的原始错误输出
+    pub stderr: Option<String>,
+}
+
+pub(crate) type AppResult<T> = Result<T, AppError>;
+
+/// 常见的磁盘已满错误号：Linux/macOS 的 ENOSPC，Windows 的 ERROR_HANDLE_DISK_FULL / ERROR_DISK_FULL
+fn is_disk_full(e: &std::io::Error) -> bool {
+    match e.raw_os_error() {
+        #[cfg(unix)]
+        Some(28) => true,
+        #[cfg(windows)]
+        Some(39) | Some(112) => true,
+        _ => false,
+    }
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError { code, message: message.into(), path: None, stderr: None }
+    }
+
+    /// 记下出错的文件；已有路径时保留最具体的那个
+    pub fn at(mut self, path: &Path) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.to_string_lossy().to_string());
+        }
+        self
+    }
+
+    pub fn not_found(path: &Path) -> Self {
+        AppError::new(ErrorCode::FileNotFound, i18n::t("err.file_not_found")).at(path)
+    }
+
+    /// 文件读写错误，按错误类型归类
+    pub fn io(context: &str, e: &std::io::Error) -> Self {
+        let code = if is_disk_full(e) {
+            ErrorCode::DiskFull
+        } else {
+            match e.kind() {
+                std::io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+                std::io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+                _ => ErrorCode::Io,
+            }
+        };
+        AppError::new(code, format!("{}: {}", context, e))
+    }
+
+    /// magick 进程没能启动
+    pub fn spawn(e: &std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            AppError::new(ErrorCode::EngineMissing, i18n::t("err.engine_missing"))
+        } else {
+            AppError::new(ErrorCode::EngineFailed, i18n::tf("err.engine_start", &[e]))
+        }
+    }
+
+    /// magick 运行失败：按 stderr 里的关键字细分，原文一并带上
+    pub fn engine(context: &str, output: &Output) -> Self {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let lower = stderr.to_lowercase();
+        let code = if lower.contains("no space left") || lower.contains("disk full") {
+            ErrorCode::DiskFull
+        } else if lower.contains("no such file") {
+            ErrorCode::FileNotFound
+        } else if lower.contains("permission denied") {
+            ErrorCode::PermissionDenied
+        } else if ["cache resources exhausted", "memory allocation failed", "width or height exceeds limit", "resource limit"]
+            .iter()
+            .any(|k| lower.contains(k))
+        {
+            ErrorCode::ResourceLimit
+        } else if lower.contains("no decode delegate") || lower.contains("no encode delegate") {
+            ErrorCode::Unsupported
+        } else if ["corrupt", "improper image header", "premature end", "insufficient image data", "not a jpeg file"]
+            .iter()
+            .any(|k| lower.contains(k))
+        {
+            ErrorCode::FileCorrupt
+        } else {
+            ErrorCode::EngineFailed
+        };
+        AppError { code, message: context.to_string(), path: None, stderr: Some(stderr).filter(|s| !s.is_empty()) }
+    }
+
+    /// 在提示后补充说明（如原生管线的失败原因）
+    pub fn context(mut self, detail: impl fmt::Display) -> Self {
+        self.message = i18n::tf("err.with_detail", &[&self.message, &detail]);
+        self
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::new(ErrorCode::Internal, message)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(stderr) = &self.stderr {
+            write!(f, ": {}", stderr)?;
+        }
+        Ok(())
+    }
+}
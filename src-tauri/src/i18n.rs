@@ -0,0 +1,292 @@
+// ==========================================
+// 🌟 多语言：中英文提示文案目录 + 记住的界面语言
+// ==========================================
+// 后端直接给用户看的文字（错误提示、印前检查报告、窗口标题、导出的文件名）
+// 都从这里按 key 取；前端的报价单等 HTML 也通过 get_messages 取同一份文案。
+// 语言保存在配置目录下，启动时读回，错误构造处无需传 AppHandle。
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+const LOCALE_FILE: &str = "locale.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub(crate) enum Locale {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Locale {
+    pub fn parse(value: &str) -> Option<Locale> {
+        match value {
+            "zh-CN" | "zh" => Some(Locale::ZhCn),
+            "en" | "en-US" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub(crate) fn current() -> Locale {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Locale::En,
+        _ => Locale::ZhCn,
+    }
+}
+
+pub(crate) fn set_current(locale: Locale) {
+    CURRENT.store(locale as u8, Ordering::Relaxed);
+}
+
+/// (key, 中文, English)；`{}` 为按顺序替换的占位符
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("app.title", "墨印众合-imagetool v{}", "imagetool v{}"),
+    ("file.export", "导出", "export"),
+    ("file.output_suffix", "_输出", "_output"),
+    // ---- 错误提示 ----
+    ("err.file_not_found", "文件不存在", "File not found"),
+    ("err.engine_missing", "未找到 ImageMagick，请安装后重试", "ImageMagick not found, please install it and try again"),
+    ("err.engine_start", "启动引擎失败: {}", "Failed to start the engine: {}"),
+    ("err.engine_error", "引擎报错", "The engine reported an error"),
+    ("err.timeout", "超过 {} 秒仍未完成，已终止引擎进程", "Not finished after {} s; the engine process was stopped"),
+    ("err.cancelled", "已停止", "Stopped"),
+    ("err.size_parse", "解析尺寸失败", "Failed to read the image size"),
+    ("err.size_invalid", "获取尺寸异常", "Unexpected image size"),
+    ("err.decode", "解码失败: {}", "Failed to decode: {}"),
+    ("err.magick_decode", "magick 无法解码", "ImageMagick could not decode the file"),
+    ("err.preview", "生成预览失败", "Failed to generate the preview"),
+    ("err.native_pipeline", "原生管线: {}", "native pipeline: {}"),
+    ("err.verify_undecodable", "输出校验失败：无法解码（{}；{}）", "Output check failed: cannot decode ({}; {})"),
+    ("err.batch_thread", "批处理线程异常: {}", "Batch worker crashed: {}"),
+    ("err.write_named", "写入 {} 失败: {}", "Failed to write {}: {}"),
+    ("err.write_file", "写入文件失败", "Failed to write the file"),
+    ("err.read_file", "读取文件失败", "Failed to read the file"),
+    ("err.copy", "复制失败", "Copy failed"),
+    ("err.rename", "重命名失败", "Rename failed"),
+    ("err.remove_original", "删除原图失败", "Failed to remove the original file"),
+    ("err.invalid_path", "路径无效", "Invalid path"),
+    ("err.browser_missing", "未找到 Chrome、Chromium 或 Edge，请安装后重试，或在“引擎设置”里指定浏览器路径", "Chrome, Chromium or Edge not found; install one and try again, or set the browser path in Engine settings"),
+    ("err.browser_start", "启动浏览器失败: {}", "Failed to start the browser: {}"),
+    ("err.html_missing", "HTML 文件不存在", "HTML file not found"),
+    ("err.print_pdf", "打印 PDF 失败", "Failed to print the PDF"),
+    ("err.pdf_missing", "未生成 PDF 文件", "No PDF file was produced"),
+    ("err.disk_space", "磁盘空间不足：需要约 {} MB，剩余 {} MB", "Not enough disk space: about {} MB needed, {} MB left"),
+    ("err.paper_too_narrow", "成品短边 {}cm 超出「{}」最大幅宽 {}cm", "Short side {}cm exceeds the maximum width of \"{}\" ({}cm)"),
+    ("err.psd_no_merged", "该 PSD 保存时未勾选“最大兼容”，文件中没有合并图像，请在 Photoshop 中开启“最大兼容”后重新保存", "This PSD was saved without \"Maximize Compatibility\" and has no composite image. Enable it in Photoshop and save again"),
+    ("err.unknown_output", "未知的输出方式: {}", "Unknown output mode: {}"),
+    ("err.unknown_color_policy", "未知的色彩策略: {}", "Unknown color policy: {}"),
+    ("err.mirror_needs_root", "镜像输出需要先选择输出目录", "Choose an output folder before using mirrored output"),
+    ("err.mirror_needs_source", "镜像输出需要所有文件位于同一个源目录下", "Mirrored output needs all files under one source folder"),
+    ("err.mirror_outside_root", "{} 不在源目录 {} 之下，无法镜像输出", "{} is not inside the source folder {}; cannot mirror it"),
+    ("err.create_output_dir", "创建输出目录失败: {}", "Failed to create the output folder: {}"),
+    ("err.output_same_as_input", "输出路径与原图相同，请更换后缀或输出目录", "The output path is the original file; change the suffix or output folder"),
+    ("err.replace_output", "替换输出文件失败: {}", "Failed to replace the output file: {}"),
+    ("err.verify_size", "输出校验失败：尺寸 {}x{}，预期 {}x{}", "Output check failed: size {}x{}, expected {}x{}"),
+    ("err.verify_dpi", "输出校验失败：DPI {}，预期 {}", "Output check failed: DPI {}, expected {}"),
+    ("err.verify_no_dpi", "输出校验失败：缺少 DPI", "Output check failed: DPI missing"),
+    ("err.unrecognized_image", "无法识别的图像文件", "Unrecognized image file"),
+    ("err.nothing_to_undo", "没有可以撤销的操作", "Nothing to undo"),
+    ("err.invalid_session", "无效的会话号", "Invalid session id"),
+    ("err.invalid_batch", "无效的批次号", "Invalid batch id"),
+    ("undo.backup_truncated", "本次会话部分早期操作的备份已超出容量上限被清理，无法完整还原", "Backups of some early operations in this session were pruned by the size limit; the session cannot be fully restored"),
+    ("undo.no_backup", "{}: 超出备份容量上限，未保留原文件", "{}: over the backup size limit, the original was not kept"),
+    ("err.unknown_locale", "不支持的语言: {}", "Unsupported language: {}"),
+    ("err.save_locale", "保存语言设置失败", "Failed to save the language setting"),
+    ("err.save_engine_settings", "保存引擎设置失败", "Failed to save the engine settings"),
+    ("err.engine_path_invalid", "找不到指定的程序: {}", "Program not found: {}"),
+    ("err.template_empty", "命名模板为空", "The naming template is empty"),
+    ("err.template_brace", "命名模板的花括号不成对，花括号本身请写成 {{ 或 }}", "Unbalanced braces in the naming template; write {{ or }} for a literal brace"),
+    ("err.template_token", "命名模板中有未知字段: {}", "Unknown field in the naming template: {}"),
+    ("err.name_empty", "按模板生成的文件名为空", "The template produced an empty file name"),
+    ("err.rename_no_path", "第 {} 项缺少文件路径", "Item {} has no file path"),
+    ("err.rename_duplicate", "改名后重名: {}，请在模板中加入 {index} 等字段", "Duplicate name after renaming: {}; add a field such as {index} to the template"),
+    ("err.rename_exists", "目标文件已存在: {}", "Target file already exists: {}"),
+    ("err.save_rename_presets", "保存命名预设失败", "Failed to save the naming presets"),
+    ("err.with_detail", "{}（{}）", "{} ({})"),
+    ("err.read_meta", "读取文件信息失败", "Failed to read the file info"),
+    ("err.create_output", "创建输出文件失败", "Failed to create the output file"),
+    ("err.write_output", "写入输出文件失败", "Failed to write the output file"),
+    ("err.open_temp", "打开临时文件失败", "Failed to open the temporary file"),
+    ("err.sync", "写入磁盘失败", "Failed to flush the file to disk"),
+    ("err.create_dir", "创建目录 {} 失败: {}", "Failed to create the folder {}: {}"),
+    ("err.data_dir", "获取数据目录失败", "Failed to locate the app data folder"),
+    ("err.config_dir", "获取配置目录失败", "Failed to locate the config folder"),
+    ("err.cache_dir", "获取缓存目录失败", "Failed to locate the cache folder"),
+    ("err.unsupported_format", "不支持的格式", "Unsupported format"),
+    ("err.unsupported_output", "不支持的输出格式", "Unsupported output format"),
+    ("err.encode", "{} 编码失败: {}", "Failed to encode {}: {}"),
+    ("err.icc_not_matrix", "该 ICC 描述文件不是矩阵型 RGB，无法原生转换", "The ICC profile is not a matrix RGB profile and cannot be converted natively"),
+    ("err.output_icc_not_matrix", "输出描述文件不是矩阵型 RGB，无法原生转换", "The output profile is not a matrix RGB profile and cannot be converted natively"),
+    ("err.write_icc", "写入 ICC 失败", "Failed to write the ICC profile"),
+    ("err.paper_icc_read", "读取纸张 ICC 失败 ({}): {}", "Failed to read the paper ICC profile ({}): {}"),
+    ("err.save_paper_profiles", "保存纸张配置失败", "Failed to save the paper profiles"),
+    ("err.psd_parse", "PSD 解析失败: {}", "Failed to parse the PSD: {}"),
+    ("err.psd_invalid", "不是有效的 PSD 文件", "Not a valid PSD file"),
+    ("err.psd_version", "不支持的 PSD 版本: {}", "Unsupported PSD version: {}"),
+    ("err.psd_rle", "PSD 压缩数据损坏", "The PSD compressed data is corrupt"),
+    ("err.psd_mode", "暂不支持的 PSD 颜色模式: {}", "Unsupported PSD color mode: {}"),
+    ("err.psd_depth", "暂不支持的 PSD 位深: {}", "Unsupported PSD bit depth: {}"),
+    ("err.psd_channels", "PSD 通道数异常", "Unexpected PSD channel count"),
+    ("err.psd_compression", "暂不支持的 PSD 合并图压缩方式: {}", "Unsupported PSD composite compression: {}"),
+    ("err.psd_merged_length", "PSD 合并图数据长度异常", "Unexpected PSD composite data length"),
+    ("err.psd_structure", "PSD 文件结构异常", "Unexpected PSD file structure"),
+    ("err.tiff_parse", "TIFF 解析失败: {}", "Failed to parse the TIFF: {}"),
+    ("err.tiff_header", "文件头无效", "invalid header"),
+    ("err.tiff_tag_count", "标签过多", "too many tags"),
+    ("err.tiff_color", "暂不支持的 TIFF 色彩类型: {}", "Unsupported TIFF color type: {}"),
+    ("err.tiff_write_color", "暂不支持写出该色彩类型的 TIFF", "Writing TIFFs of this color type is not supported"),
+    ("err.tiff_compression", "暂不支持写回该 TIFF 的压缩方式", "Writing back this TIFF compression is not supported"),
+    ("err.tiff_too_large", "TIFF 文件超过 4GB，无法补写分辨率", "The TIFF is larger than 4 GB; its resolution cannot be patched"),
+    ("err.journal_write", "写入操作日志失败", "Failed to write the operation log"),
+    ("err.journal_read", "读取会话日志失败", "Failed to read the session log"),
+    ("err.journal_corrupt", "会话日志损坏", "The session log is corrupt"),
+    ("err.journal_busy", "操作日志被占用", "The operation log is busy"),
+    ("err.queue_write", "写入队列失败", "Failed to write the batch queue"),
+    ("err.queue_read", "读取队列失败", "Failed to read the batch queue"),
+    ("err.queue_corrupt", "队列文件损坏", "The batch queue file is corrupt"),
+    ("err.queue_busy", "队列被占用", "The batch queue is busy"),
+    ("err.thumb_cache_write", "写入缩略图缓存失败", "Failed to write the thumbnail cache"),
+    ("err.thumb_cache_read", "读取缩略图缓存失败", "Failed to read the thumbnail cache"),
+    ("err.thumb_invalid", "无效的缩略图地址", "Invalid thumbnail address"),
+    ("err.tile_info_write", "写入瓦片信息失败", "Failed to write the tile info"),
+    ("err.tile_source_missing", "瓦片源不存在", "Tile source not found"),
+    ("err.tile_level", "瓦片级数越界", "Tile level out of range"),
+    ("err.tile_coord", "瓦片坐标越界", "Tile coordinates out of range"),
+    ("err.tile_invalid", "无效的瓦片地址", "Invalid tile address"),
+    ("err.source_changed", "原图已变化", "The original file has changed"),
+    ("rename.default_preset", "默认", "Default"),
+    ("hint.engine_install", "请先安装 ImageMagick 7：macOS 执行 brew install imagemagick；Windows 从 imagemagick.org 下载安装包，安装时勾选“Add application directory to your system path”；Linux 安装发行版的 ImageMagick 7 软件包或官方 AppImage（需能以 magick 命令运行）。装好后重启本软件，或在“引擎设置”里指定 magick 的路径。", "Please install ImageMagick 7 first: on macOS run brew install imagemagick; on Windows download the installer from imagemagick.org, tick \"Add application directory to your system path\"; on Linux install your distribution's ImageMagick 7 package or the official AppImage (it must run as the magick command). Then restart this app, or set the magick path in Engine settings."),
+    ("hint.resource_limit", "图像超出了引擎的内存 / 磁盘缓存上限，可在“引擎设置”里调高上限后重试。", "The image exceeds the engine's memory / disk cache limits; raise them in Engine settings and try again."),
+    ("support.extension", "不支持的格式: .{}", "Unsupported format: .{}"),
+    ("support.needs_engine", "需要 ImageMagick 才能处理", "ImageMagick is required to process this file"),
+    ("support.delegate_missing", "当前 ImageMagick 不支持 {} 格式（缺少对应的 delegate）", "The installed ImageMagick cannot handle {} (delegate missing)"),
+    // ---- 印前检查 ----
+    ("preflight.title", "印前检查报告", "Preflight report"),
+    ("preflight.file_name", "印前检查", "preflight"),
+    ("preflight.severity.error", "错误", "Error"),
+    ("preflight.severity.warning", "警告", "Warning"),
+    ("preflight.severity.info", "提示", "Info"),
+    ("preflight.unreadable", "无法读取: {}", "Cannot read: {}"),
+    ("preflight.low_ppi", "有效 PPI 仅 {}（下限 {}），按 {}×{}cm 输出会发虚", "Effective PPI is only {} (minimum {}); printing at {}×{}cm will look soft"),
+    ("preflight.cmyk", "CMYK 原图，转换到输出色彩时颜色可能偏移", "CMYK source; colors may shift when converted for output"),
+    ("preflight.lab", "Lab 原图，部分环节不支持，建议先转为 RGB", "Lab source; some steps do not support it, convert to RGB first"),
+    ("preflight.high_bit_depth", "{} 位/通道，处理更慢、文件更大", "{} bits per channel; slower to process and larger files"),
+    ("preflight.missing_icc_cmyk", "CMYK 原图没有嵌入 ICC，无法准确换算颜色", "CMYK source has no embedded ICC profile; colors cannot be converted accurately"),
+    ("preflight.missing_icc", "没有嵌入 ICC，按 sRGB 理解", "No embedded ICC profile; treated as sRGB"),
+    ("preflight.unusual_icc", "少见的 ICC 配置「{}」，请确认来源", "Unusual ICC profile \"{}\"; please check where it came from"),
+    ("preflight.transparency", "含透明通道，输出时透明处会填白", "Has transparency; transparent areas will be filled with white"),
+    ("preflight.aspect_crop", "原图比例与目标不符，裁切会去掉 {}% 的画面", "Aspect ratio differs from the target; cropping removes {}% of the image"),
+    ("preflight.tiny_file", "文件仅 {} KB、{}×{}px，疑似截图或网络小图", "Only {} KB and {}×{}px; probably a screenshot or web image"),
+    ("preflight.missing_dpi", "文件未记录 DPI，按 {} 计算", "No DPI recorded in the file; assuming {}"),
+    ("preflight.passed", "通过", "Passed"),
+    ("preflight.bits", "{} 位", "{}-bit"),
+    ("preflight.summary", "共 {} 张：{} 张通过，{} 张有错误，{} 张有警告。有效 PPI 下限 {}。", "{} images: {} passed, {} with errors, {} with warnings. Minimum effective PPI {}."),
+    ("preflight.col.index", "序号", "No."),
+    ("preflight.col.file", "文件名", "File"),
+    ("preflight.col.paper", "纸张", "Paper"),
+    ("preflight.col.size", "目标尺寸(cm)", "Target size (cm)"),
+    ("preflight.col.pixels", "像素", "Pixels"),
+    ("preflight.col.ppi", "有效 PPI", "Effective PPI"),
+    ("preflight.col.color", "色彩", "Color"),
+    ("preflight.col.result", "检查结果", "Result"),
+    // ---- 报价单（前端生成 HTML） ----
+    ("quote.title", "报价单", "Quotation"),
+    ("quote.file_name", "报价单", "quotation"),
+    ("quote.col.index", "序号", "No."),
+    ("quote.col.thumb", "缩略图", "Thumbnail"),
+    ("quote.col.file", "文件名", "File"),
+    ("quote.col.qty", "数量", "Qty"),
+    ("quote.col.size", "物理尺寸(cm)", "Size (cm)"),
+    ("quote.col.area", "单张面积(㎡)", "Area each (㎡)"),
+    ("quote.col.total_area", "总面积(㎡)", "Total area (㎡)"),
+    ("quote.col.unit_price", "单价(元/㎡)", "Unit price (CNY/㎡)"),
+    ("quote.col.amount", "金额(元)", "Amount (CNY)"),
+    ("quote.col.note", "备注", "Note"),
+    ("quote.total", "合计", "Total"),
+];
+
+fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+    MESSAGES.iter().find(|(k, _, _)| *k == key).map(|(_, zh, en)| match locale {
+        Locale::ZhCn => *zh,
+        Locale::En => *en,
+    })
+}
+
+/// 当前语言的文案；目录里没有的 key 原样返回，便于发现遗漏
+pub(crate) fn t(key: &str) -> String {
+    lookup(current(), key).unwrap_or(key).to_string()
+}
+
+/// 带参数的文案，按顺序替换 `{}`；需要保留小数位的参数由调用方先格式化
+pub(crate) fn tf(key: &str, args: &[&dyn Display]) -> String {
+    let template = lookup(current(), key).unwrap_or(key);
+    let mut out = String::with_capacity(template.len() + 16);
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(pos) = rest.find("{}") {
+        out.push_str(&rest[..pos]);
+        match args.next() {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str("{}"),
+        }
+        rest = &rest[pos + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 指定前缀的全部文案，给前端拼报表用
+pub(crate) fn messages(prefix: &str) -> HashMap<&'static str, &'static str> {
+    let locale = current();
+    MESSAGES
+        .iter()
+        .filter(|(k, _, _)| k.starts_with(prefix))
+        .filter_map(|(k, _, _)| lookup(locale, k).map(|text| (*k, text)))
+        .collect()
+}
+
+fn locale_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("{}: {}", t("err.save_locale"), e))?;
+    Ok(dir.join(LOCALE_FILE))
+}
+
+/// 启动时读回上次选的语言，没有则用中文
+pub(crate) fn load(app: &AppHandle) {
+    let locale: Locale = locale_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+    set_current(locale);
+}
+
+pub(crate) fn save(app: &AppHandle, locale: Locale) -> Result<(), String> {
+    let path = locale_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", t("err.save_locale"), e))?;
+    }
+    let text = serde_json::to_string(&locale).map_err(|e| format!("{}: {}", t("err.save_locale"), e))?;
+    std::fs::write(&path, text).map_err(|e| format!("{}: {}", t("err.save_locale"), e))
+}
+
+/// 窗口标题随语言变化
+pub(crate) fn window_title(version: &str) -> String {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    tf("app.title", &[&version])
+}
@@ -0,0 +1,316 @@
+// ==========================================
+// 🌟 图像体检：格式、色彩模型、位深、透明、ICC、压缩、页数/图层、EXIF
+// ==========================================
+// 像素层面的信息来自 `magick identify -ping`（不解码像素，整批文件一次调用），
+// magick 缺失或不认识文件时改用原生读取器（JPEG/PNG/TIFF/PSD）拼出同样的字段；
+// ICC 名称与 EXIF 相机、拍摄时间、方向由本地直接读取。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::ImageDecoder;
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::i18n;
+use crate::pipeline::NativeFormat;
+use crate::{color, orientation, pipeline, psd, tiff_io};
+
+/// 字段分隔符用 ASCII 单元分隔符，文件名与 ICC 名称里不会出现
+const SEP: char = '\u{1f}';
+
+/// identify 的 -format 参数，每帧一行
+pub(crate) fn identify_format() -> String {
+    ["%i", "%m", "%[colorspace]", "%z", "%A", "%C", "%n", "%w", "%h", "%x", "%U", "%[icc:description]"].join(&SEP.to_string()) + "\n"
+}
+
+/// identify 输出的第一帧
+#[derive(Clone, Debug)]
+pub(crate) struct Identified {
+    format: String,
+    colorspace: String,
+    depth: u32,
+    alpha: String,
+    compression: String,
+    frames: u32,
+    width: u32,
+    height: u32,
+    resolution: f64,
+    units: String,
+    icc_description: String,
+}
+
+/// 按文件名归组，多页 TIFF 与 PSD 图层只取第一帧
+pub(crate) fn parse_identify(stdout: &str) -> HashMap<String, Identified> {
+    let mut found = HashMap::new();
+    for line in stdout.lines() {
+        let fields: Vec<&str> = line.split(SEP).collect();
+        if fields.len() < 12 {
+            continue;
+        }
+        // 部分版本给后续帧的文件名带 [n]
+        let name = match fields[0].rfind('[') {
+            Some(i) if fields[0].ends_with(']') => &fields[0][..i],
+            _ => fields[0],
+        };
+        found.entry(name.to_string()).or_insert_with(|| Identified {
+            format: fields[1].to_string(),
+            colorspace: fields[2].to_string(),
+            depth: fields[3].parse().unwrap_or(8),
+            alpha: fields[4].to_string(),
+            compression: fields[5].to_string(),
+            frames: fields[6].parse().unwrap_or(1),
+            width: fields[7].parse().unwrap_or(0),
+            height: fields[8].parse().unwrap_or(0),
+            resolution: fields[9].parse().unwrap_or(0.0),
+            units: fields[10].to_string(),
+            icc_description: fields[11].trim().to_string(),
+        });
+    }
+    found
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImageInspection {
+    pub path: String,
+    pub file_size: u64,
+    /// JPEG / PNG / TIFF / PSD …
+    pub format: String,
+    /// RGB / CMYK / Gray / Lab，其余原样给出
+    pub color_model: String,
+    /// 每通道位数
+    pub bit_depth: u32,
+    pub has_alpha: bool,
+    pub icc_description: Option<String>,
+    /// 压缩方式（JPEG、LZW、Zip、RLE、None …）
+    pub compression: Option<String>,
+    /// 多页 TIFF 的页数，其余为 1
+    pub page_count: u32,
+    /// PSD 的图层数（不含合并图），其他格式为 None
+    pub layer_count: Option<u32>,
+    /// 转正后的像素尺寸
+    pub width_px: u32,
+    pub height_px: u32,
+    /// EXIF Orientation（1..=8）
+    pub orientation: u16,
+    pub dpi: f64,
+    /// false 表示文件没有记录 DPI，dpi 为生效的默认值
+    pub dpi_from_file: bool,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// EXIF 拍摄时间（原样，如 2024:05:01 10:20:30）
+    pub capture_date: Option<String>,
+}
+
+/// 批量体检的单项结果，失败的文件带错误原因
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InspectItem {
+    pub path: String,
+    pub info: Option<ImageInspection>,
+    pub error: Option<AppError>,
+}
+
+fn color_model(colorspace: &str) -> String {
+    match colorspace.to_lowercase().as_str() {
+        "srgb" | "rgb" | "scrgb" => "RGB".to_string(),
+        "cmyk" => "CMYK".to_string(),
+        "gray" | "lineargray" => "Gray".to_string(),
+        "lab" => "Lab".to_string(),
+        _ => colorspace.to_string(),
+    }
+}
+
+/// 读取 EXIF 中的文本字段
+fn exif_text(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+    let field = exif.get_field(tag, exif::In::PRIMARY)?;
+    match &field.value {
+        exif::Value::Ascii(values) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).trim_matches(char::from(0)).trim().to_string())
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    }
+}
+
+/// (色彩空间, 位深, 是否带透明)，名称与 identify 的 %[colorspace] 一致
+fn image_color(color: image::ColorType) -> (&'static str, u32, bool) {
+    let space = if color.has_color() { "sRGB" } else { "Gray" };
+    (space, (color.bits_per_pixel() / color.channel_count() as u16) as u32, color.has_alpha())
+}
+
+fn tiff_color(color: tiff::ColorType) -> (&'static str, u32, bool) {
+    match color {
+        tiff::ColorType::Gray(b) => ("Gray", b as u32, false),
+        tiff::ColorType::GrayA(b) => ("Gray", b as u32, true),
+        tiff::ColorType::RGB(b) | tiff::ColorType::Palette(b) => ("sRGB", b as u32, false),
+        tiff::ColorType::RGBA(b) => ("sRGB", b as u32, true),
+        tiff::ColorType::CMYK(b) => ("CMYK", b as u32, false),
+        tiff::ColorType::YCbCr(b) => ("YCbCr", b as u32, false),
+    }
+}
+
+/// TIFF Compression 标签值 → identify 的 %C 名称
+fn tiff_compression(method: u16) -> String {
+    match method {
+        1 => "None",
+        2 | 32773 => "RLE",
+        3 => "Group3",
+        4 => "Group4",
+        5 => "LZW",
+        6 | 7 => "JPEG",
+        8 | 32946 => "Zip",
+        other => return other.to_string(),
+    }
+    .to_string()
+}
+
+/// identify 缺失或没认出文件时，只读文件头拼出同样的字段
+fn identify_natively(path: &Path) -> Option<Identified> {
+    let (width, height, dpi) = pipeline::dimensions(path)?;
+    let open = || File::open(path).ok().map(BufReader::new);
+    let (format, (colorspace, depth, alpha), compression, frames) = match NativeFormat::sniff(path)? {
+        NativeFormat::Jpeg => ("JPEG", image_color(JpegDecoder::new(open()?).ok()?.color_type()), "JPEG".to_string(), 1),
+        NativeFormat::Png => ("PNG", image_color(PngDecoder::new(open()?).ok()?.color_type()), "Zip".to_string(), 1),
+        NativeFormat::Tiff => {
+            let (color, compression, pages) = tiff_io::probe(path)?;
+            ("TIFF", tiff_color(color), tiff_compression(compression), pages)
+        }
+        NativeFormat::Psd => {
+            let info = psd::read_info(path).ok()?;
+            let (layers, compression) = psd::layout(path).ok()?;
+            // 颜色模式：0 位图、1 灰度、2 索引、3 RGB、4 CMYK、8 双色调、9 Lab
+            let (space, color_channels) = match info.color_mode {
+                3 => ("sRGB", 3),
+                4 => ("CMYK", 4),
+                9 => ("Lab", 3),
+                2 => ("sRGB", 1),
+                _ => ("Gray", 1),
+            };
+            let compression = match compression {
+                0 => "None",
+                1 => "RLE",
+                _ => "Zip",
+            };
+            let format = if info.version == 2 { "PSB" } else { "PSD" };
+            // identify 把合并图算作第一帧，其后才是图层
+            (format, (space, info.depth as u32, info.channels > color_channels), compression.to_string(), layers + 1)
+        }
+    };
+    Some(Identified {
+        format: format.to_string(),
+        colorspace: colorspace.to_string(),
+        depth,
+        alpha: if alpha { "True" } else { "False" }.to_string(),
+        compression,
+        frames,
+        width,
+        height,
+        resolution: dpi.unwrap_or(0.0),
+        units: if dpi.is_some() { "PixelsPerInch" } else { "Undefined" }.to_string(),
+        icc_description: String::new(),
+    })
+}
+
+/// 汇总一张图的体检结果；identified 为 None 表示 identify 缺失或没认出这个文件，此时改用原生读取
+pub(crate) fn inspect(path: &Path, identified: Option<&Identified>, fallback_dpi: f64) -> Result<ImageInspection, String> {
+    let file_size = std::fs::metadata(path).map_err(|e| format!("{}: {}", i18n::t("err.read_meta"), e))?.len();
+    let native;
+    let id = match identified {
+        Some(id) => id,
+        None => {
+            native = identify_natively(path).ok_or_else(|| i18n::t("err.unrecognized_image"))?;
+            &native
+        }
+    };
+
+    let orientation = orientation::read(path);
+    let (width_px, height_px) = if orientation::swaps_dimensions(orientation) { (id.height, id.width) } else { (id.width, id.height) };
+    let units = id.units.to_lowercase();
+    let file_dpi = Some(id.resolution)
+        .filter(|v| *v > 0.0 && !units.contains("undefined"))
+        .map(|v| if units.contains("centimeter") { v * 2.54 } else { v });
+
+    let exif = File::open(path).ok().and_then(|f| exif::Reader::new().read_from_container(&mut BufReader::new(f)).ok());
+    let exif_field = |tag| exif.as_ref().and_then(|e| exif_text(e, tag));
+
+    let is_psd = id.format.eq_ignore_ascii_case("PSD") || id.format.eq_ignore_ascii_case("PSB");
+    let compression = Some(id.compression.clone()).filter(|c| !c.is_empty() && !c.eq_ignore_ascii_case("undefined"));
+    let icc_description = pipeline::read_profile(path)
+        .as_deref()
+        .and_then(color::description)
+        .or_else(|| Some(id.icc_description.clone()).filter(|d| !d.is_empty()));
+
+    Ok(ImageInspection {
+        path: path.to_string_lossy().to_string(),
+        file_size,
+        format: id.format.clone(),
+        color_model: color_model(&id.colorspace),
+        bit_depth: id.depth,
+        has_alpha: !matches!(id.alpha.to_lowercase().as_str(), "" | "false" | "undefined"),
+        icc_description,
+        compression,
+        page_count: if is_psd { 1 } else { id.frames.max(1) },
+        // PSD 的第一帧是合并图，其后才是图层
+        layer_count: is_psd.then(|| id.frames.saturating_sub(1)),
+        width_px,
+        height_px,
+        orientation,
+        dpi: file_dpi.unwrap_or(fallback_dpi),
+        dpi_from_file: file_dpi.is_some(),
+        camera_make: exif_field(exif::Tag::Make),
+        camera_model: exif_field(exif::Tag::Model),
+        capture_date: exif_field(exif::Tag::DateTimeOriginal).or_else(|| exif_field(exif::Tag::DateTime)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::DynamicImage;
+
+    #[test]
+    fn native_fallback_fills_the_identify_fields() {
+        let dir = std::env::temp_dir().join(format!("inspect-native-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let rgb = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(30, 20, image::Rgb([10, 20, 30])));
+
+        let jpeg = dir.join("a.jpg");
+        rgb.save(&jpeg).unwrap();
+        let png = dir.join("a.png");
+        DynamicImage::ImageRgba16(image::ImageBuffer::from_pixel(30, 20, image::Rgba([1u16, 2, 3, 4]))).save(&png).unwrap();
+        let tif = dir.join("a.tif");
+        tiff_io::encode(&DynamicImage::ImageLuma8(image::GrayImage::new(30, 20)), &tif, 240.0, &tiff_io::TiffInfo::default(), Some(&color::Target::AdobeRgb.profile())).unwrap();
+        let psd_path = dir.join("a.psd");
+        psd::encode(&rgb, &psd_path, 300.0, None).unwrap();
+
+        let got = |path: &Path| inspect(path, None, 72.0).unwrap();
+        let jpeg = got(&jpeg);
+        assert_eq!((jpeg.format.as_str(), jpeg.color_model.as_str(), jpeg.bit_depth, jpeg.has_alpha), ("JPEG", "RGB", 8, false));
+        assert_eq!((jpeg.width_px, jpeg.height_px), (30, 20));
+
+        let png = got(&png);
+        assert_eq!((png.format.as_str(), png.bit_depth, png.has_alpha, png.dpi_from_file), ("PNG", 16, true, false));
+        assert_eq!(png.dpi, 72.0);
+
+        let tif = got(&tif);
+        assert_eq!((tif.format.as_str(), tif.color_model.as_str(), tif.compression.as_deref()), ("TIFF", "Gray", Some("LZW")));
+        assert_eq!((tif.page_count, tif.dpi, tif.dpi_from_file), (1, 240.0, true));
+        assert_eq!(tif.icc_description.as_deref(), Some(color::Target::AdobeRgb.description()));
+
+        let psd = got(&psd_path);
+        assert_eq!((psd.format.as_str(), psd.color_model.as_str(), psd.compression.as_deref()), ("PSD", "RGB", Some("RLE")));
+        assert_eq!((psd.page_count, psd.layer_count, psd.dpi), (1, Some(0), 300.0));
+
+        let text = dir.join("a.txt");
+        std::fs::write(&text, "not an image").unwrap();
+        assert_eq!(inspect(&text, None, 72.0).err(), Some(i18n::t("err.unrecognized_image")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,441 @@
+// ==========================================
+// 🌟 操作日志：记录每次覆盖 / 改名 / 复制，支持撤销上一步与整次会话还原
+// ==========================================
+// 每次启动应用开一个会话目录：<应用数据目录>/journal/<会话号>/
+//   journal.json  按命令分组的操作记录（每组对应一次命令调用）
+//   backups/      被覆盖、被删除文件的原样备份
+// 备份总量超过上限时从最早的一组开始清理，被清理的组不再能撤销。
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::i18n;
+
+const JOURNAL_FILE: &str = "journal.json";
+const BACKUP_DIR: &str = "backups";
+/// 单个会话备份总量上限
+const BACKUP_CAP_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+/// 保留最近几次会话的日志（含本次）
+const KEEP_SESSIONS: usize = 10;
+
+/// 单步文件操作，撤销时按相反顺序逆向执行
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Step {
+    /// 新建了文件，撤销时删除
+    Created { path: PathBuf },
+    /// 覆盖了已有文件；backup 为空表示超出备份上限、无法还原
+    Replaced { path: PathBuf, backup: Option<PathBuf> },
+    /// 删除了文件
+    Removed { path: PathBuf, backup: Option<PathBuf> },
+    /// 改名 / 移动
+    Moved { from: PathBuf, to: PathBuf },
+}
+
+/// 一次命令调用产生的全部操作
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Group {
+    command: String,
+    /// 同一批次（process_batch）的各组一起撤销
+    #[serde(default)]
+    batch: Option<String>,
+    time: u64,
+    steps: Vec<Step>,
+    backup_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct Session {
+    id: String,
+    groups: Vec<Group>,
+    /// 是否因备份上限丢弃过最早的记录
+    truncated: bool,
+}
+
+/// 撤销 / 还原结果，前端据此刷新列表
+#[derive(Serialize, Default, Debug)]
+pub(crate) struct UndoReport {
+    /// 重新出现的文件
+    pub restored: Vec<String>,
+    /// 已不存在的文件
+    pub removed: Vec<String>,
+    /// 无法还原的文件及原因
+    pub failed: Vec<String>,
+}
+
+pub(crate) struct Journal {
+    root: PathBuf,
+    /// 备份总量上限，默认 BACKUP_CAP_BYTES
+    cap_bytes: u64,
+    session: Mutex<Session>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+impl Journal {
+    /// 启动时创建新会话，并清理过旧的会话目录
+    pub fn open(app: &AppHandle) -> Result<Self, String> {
+        let root = app.path().app_data_dir().map_err(|e| format!("{}: {}", i18n::t("err.data_dir"), e))?.join("journal");
+        Self::open_at(root)
+    }
+
+    /// 以指定目录作为日志根目录（测试用临时目录）
+    pub fn open_at(root: PathBuf) -> Result<Self, String> {
+        std::fs::create_dir_all(&root).map_err(|e| i18n::tf("err.create_dir", &[&root.display(), &e]))?;
+
+        let mut old: Vec<PathBuf> = std::fs::read_dir(&root)
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect())
+            .unwrap_or_default();
+        old.sort();
+        let excess = (old.len() + 1).saturating_sub(KEEP_SESSIONS);
+        for dir in old.iter().take(excess) {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+
+        let id = format!("{}-{}", now(), std::process::id());
+        let backup = root.join(&id).join(BACKUP_DIR);
+        std::fs::create_dir_all(&backup).map_err(|e| i18n::tf("err.create_dir", &[&backup.display(), &e]))?;
+        Ok(Journal { root, cap_bytes: BACKUP_CAP_BYTES, session: Mutex::new(Session { id, ..Default::default() }) })
+    }
+
+    fn session_dir(&self, id: &str) -> PathBuf {
+        self.root.join(id)
+    }
+
+    /// 开始记录一次命令；返回的记录器在析构时自动提交（出错提前返回也不会漏记已做的操作）
+    pub fn begin(&self, command: &str) -> Operation<'_> {
+        Operation {
+            journal: self,
+            group: Group { command: command.to_string(), batch: None, time: now(), steps: Vec::new(), backup_bytes: 0 },
+        }
+    }
+
+    /// 批处理中的单个文件；撤销上一步时整批一起撤销
+    pub fn begin_batch(&self, command: &str, batch_id: &str) -> Operation<'_> {
+        let mut op = self.begin(command);
+        op.group.batch = Some(batch_id.to_string());
+        op
+    }
+
+    fn save(&self, session: &Session) -> Result<(), String> {
+        write_session(&self.session_dir(&session.id).join(JOURNAL_FILE), session)
+    }
+
+    fn commit(&self, group: Group) {
+        if group.steps.is_empty() {
+            return;
+        }
+        let Ok(mut session) = self.session.lock() else { return };
+        session.groups.push(group);
+        let _ = self.save(&session);
+    }
+
+    /// 为即将写入 size 字节的备份腾出空间：从最早的组开始整组丢弃
+    fn reserve(&self, size: u64) -> bool {
+        if size > self.cap_bytes {
+            return false;
+        }
+        let Ok(mut session) = self.session.lock() else { return false };
+        let mut used: u64 = session.groups.iter().map(|g| g.backup_bytes).sum();
+        while used + size > self.cap_bytes && !session.groups.is_empty() {
+            let dropped = session.groups.remove(0);
+            used -= dropped.backup_bytes;
+            for step in dropped.steps {
+                if let Step::Replaced { backup: Some(b), .. } | Step::Removed { backup: Some(b), .. } = step {
+                    let _ = std::fs::remove_file(b);
+                }
+            }
+            session.truncated = true;
+        }
+        let _ = self.save(&session);
+        true
+    }
+
+    fn backup_path(&self, original: &Path) -> Result<PathBuf, String> {
+        let id = self.session.lock().map_err(|_| i18n::t("err.journal_busy"))?.id.clone();
+        let dir = self.session_dir(&id).join(BACKUP_DIR);
+        let name = original.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        Ok(dir.join(format!("{}-{}", nanos, name)))
+    }
+
+    /// 撤销最近一次命令（批处理按整批撤销）
+    pub fn undo_last(&self) -> Result<UndoReport, String> {
+        let groups = {
+            let mut session = self.session.lock().map_err(|_| i18n::t("err.journal_busy"))?;
+            let last = session.groups.pop().ok_or_else(|| i18n::t("err.nothing_to_undo"))?;
+            let mut groups = vec![last];
+            while let Some(prev) = session.groups.last() {
+                if prev.batch.is_none() || prev.batch != groups[0].batch {
+                    break;
+                }
+                groups.extend(session.groups.pop());
+            }
+            self.save(&session)?;
+            groups
+        };
+        let mut report = UndoReport::default();
+        for group in &groups {
+            revert(group, &mut report);
+        }
+        Ok(report)
+    }
+
+    /// 本次会话号（持久化队列记录它，回滚时据此找到日志）
+    pub fn session_id(&self) -> String {
+        self.session.lock().map(|s| s.id.clone()).unwrap_or_default()
+    }
+
+    /// 从指定会话取出满足条件的记录组并写回日志；session_id 为空或为本次会话时操作内存中的记录
+    fn take_groups(&self, session_id: Option<&str>, mut matches: impl FnMut(&Group) -> bool) -> Result<(Vec<Group>, bool), String> {
+        let current_id = self.session_id();
+        match session_id.filter(|id| *id != current_id) {
+            None => {
+                let mut session = self.session.lock().map_err(|_| i18n::t("err.journal_busy"))?;
+                let (taken, kept) = std::mem::take(&mut session.groups).into_iter().partition(|g| matches(g));
+                session.groups = kept;
+                self.save(&session)?;
+                Ok((taken, session.truncated))
+            }
+            Some(id) => {
+                // 只接受会话号本身，防止拼出日志目录之外的路径
+                if id.contains(['/', '\\']) || id.contains("..") {
+                    return Err(i18n::t("err.invalid_session"));
+                }
+                let path = self.session_dir(id).join(JOURNAL_FILE);
+                let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", i18n::t("err.journal_read"), e))?;
+                let mut session: Session = serde_json::from_str(&text).map_err(|e| format!("{}: {}", i18n::t("err.journal_corrupt"), e))?;
+                let (taken, kept) = std::mem::take(&mut session.groups).into_iter().partition(|g| matches(g));
+                session.groups = kept;
+                write_session(&path, &session)?;
+                Ok((taken, session.truncated))
+            }
+        }
+    }
+
+    /// 按相反顺序撤销整次会话；session_id 为空时还原本次会话
+    pub fn restore_session(&self, session_id: Option<&str>) -> Result<UndoReport, String> {
+        let mut report = UndoReport::default();
+        let (groups, truncated) = self.take_groups(session_id, |_| true)?;
+        if truncated {
+            report.failed.push(i18n::t("undo.backup_truncated"));
+        }
+        for group in groups.iter().rev() {
+            revert(group, &mut report);
+        }
+        Ok(report)
+    }
+
+    /// 撤销某个批次在指定会话中的全部改动（回滚未完成的持久化队列）
+    pub fn revert_batch(&self, session_id: &str, batch_id: &str, report: &mut UndoReport) -> Result<(), String> {
+        let (groups, _) = self.take_groups(Some(session_id), |g| g.batch.as_deref() == Some(batch_id))?;
+        for group in groups.iter().rev() {
+            revert(group, report);
+        }
+        Ok(())
+    }
+}
+
+/// 先写临时文件再改名，写到一半崩溃也不会留下截断的日志
+fn write_session(path: &Path, session: &Session) -> Result<(), String> {
+    let temp = path.with_extension("json.tmp");
+    let text = serde_json::to_string_pretty(session).map_err(|e| format!("{}: {}", i18n::t("err.journal_write"), e))?;
+    std::fs::write(&temp, text).map_err(|e| format!("{}: {}", i18n::t("err.journal_write"), e))?;
+    std::fs::rename(&temp, path).map_err(|e| format!("{}: {}", i18n::t("err.journal_write"), e))
+}
+
+/// 把备份放回原位（优先改名，跨盘时复制）
+fn put_back(backup: &Path, path: &Path) -> std::io::Result<()> {
+    if std::fs::rename(backup, path).is_err() {
+        std::fs::copy(backup, path)?;
+        let _ = std::fs::remove_file(backup);
+    }
+    Ok(())
+}
+
+fn revert(group: &Group, report: &mut UndoReport) {
+    for step in group.steps.iter().rev() {
+        let result = match step {
+            Step::Created { path } => {
+                let r = if path.exists() { std::fs::remove_file(path) } else { Ok(()) };
+                r.map(|_| report.removed.push(path.to_string_lossy().to_string()))
+            }
+            Step::Replaced { path, backup } | Step::Removed { path, backup } => match backup {
+                Some(backup) => put_back(backup, path).map(|_| report.restored.push(path.to_string_lossy().to_string())),
+                None => {
+                    report.failed.push(i18n::tf("undo.no_backup", &[&path.to_string_lossy()]));
+                    Ok(())
+                }
+            },
+            Step::Moved { from, to } => std::fs::rename(to, from).map(|_| {
+                report.removed.push(to.to_string_lossy().to_string());
+                report.restored.push(from.to_string_lossy().to_string());
+            }),
+        };
+        if let Err(e) = result {
+            report.failed.push(format!("{}: {}", group.command, e));
+        }
+    }
+}
+
+/// 单次命令的操作记录器
+pub(crate) struct Operation<'a> {
+    journal: &'a Journal,
+    group: Group,
+}
+
+impl Operation<'_> {
+    /// 复制一份备份；超出上限时返回 None
+    fn backup(&mut self, path: &Path) -> Option<PathBuf> {
+        let size = file_size(path);
+        if !self.journal.reserve(self.group.backup_bytes + size) {
+            return None;
+        }
+        let backup = self.journal.backup_path(path).ok()?;
+        std::fs::copy(path, &backup).ok()?;
+        self.group.backup_bytes += size;
+        Some(backup)
+    }
+
+    /// 写 path 之前调用：已存在则先备份（记为覆盖），否则记为新建
+    pub fn before_write(&mut self, path: &Path) {
+        if path.exists() {
+            let backup = self.backup(path);
+            self.group.steps.push(Step::Replaced { path: path.to_path_buf(), backup });
+        } else {
+            self.group.steps.push(Step::Created { path: path.to_path_buf() });
+        }
+    }
+
+    /// 删除 path 之前调用
+    pub fn before_remove(&mut self, path: &Path) {
+        if path.exists() {
+            let backup = self.backup(path);
+            self.group.steps.push(Step::Removed { path: path.to_path_buf(), backup });
+        }
+    }
+
+    /// 改名成功之后调用
+    pub fn moved(&mut self, from: &Path, to: &Path) {
+        self.group.steps.push(Step::Moved { from: from.to_path_buf(), to: to.to_path_buf() });
+    }
+}
+
+impl Drop for Operation<'_> {
+    fn drop(&mut self) {
+        let group = std::mem::replace(
+            &mut self.group,
+            Group { command: String::new(), batch: None, time: 0, steps: Vec::new(), backup_bytes: 0 },
+        );
+        self.journal.commit(group);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("journal-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn saved(journal: &Journal) -> Session {
+        let path = journal.session_dir(&journal.session_id()).join(JOURNAL_FILE);
+        assert!(!path.with_extension("json.tmp").exists());
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn undo_last_reverts_overwrite_rename_and_create() {
+        let dir = test_dir("undo");
+        let journal = Journal::open_at(dir.join("journal")).unwrap();
+        let (a, b, c, d) = (dir.join("a.txt"), dir.join("b.txt"), dir.join("c.txt"), dir.join("d.txt"));
+        std::fs::write(&a, "old").unwrap();
+        std::fs::write(&b, "b").unwrap();
+        {
+            let mut op = journal.begin("process_image");
+            op.before_write(&a);
+            std::fs::write(&a, "new").unwrap();
+            op.before_write(&c);
+            std::fs::write(&c, "created").unwrap();
+            std::fs::rename(&b, &d).unwrap();
+            op.moved(&b, &d);
+        }
+        assert_eq!(saved(&journal).groups.len(), 1);
+
+        let report = journal.undo_last().unwrap();
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "old");
+        assert_eq!(std::fs::read_to_string(&b).unwrap(), "b");
+        assert!(!c.exists() && !d.exists());
+        assert!(saved(&journal).groups.is_empty());
+        assert_eq!(journal.undo_last().err(), Some(i18n::t("err.nothing_to_undo")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn undo_last_reverts_a_whole_batch() {
+        let dir = test_dir("batch");
+        let journal = Journal::open_at(dir.join("journal")).unwrap();
+        let files: Vec<PathBuf> = ["single.jpg", "b1.jpg", "b2.jpg", "b3.jpg"].iter().map(|n| dir.join(n)).collect();
+        for (i, path) in files.iter().enumerate() {
+            let mut op = if i == 0 { journal.begin("process_image") } else { journal.begin_batch("process_batch", "batch-1") };
+            op.before_write(path);
+            std::fs::write(path, "x").unwrap();
+        }
+
+        let report = journal.undo_last().unwrap();
+        assert_eq!(report.removed.len(), 3);
+        assert!(files[0].exists());
+        assert!(files[1..].iter().all(|p| !p.exists()));
+
+        journal.undo_last().unwrap();
+        assert!(!files[0].exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backup_cap_evicts_oldest_groups() {
+        let dir = test_dir("cap");
+        let mut journal = Journal::open_at(dir.join("journal")).unwrap();
+        journal.cap_bytes = 100;
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        std::fs::write(&a, [b'a'; 60]).unwrap();
+        std::fs::write(&b, [b'b'; 60]).unwrap();
+        for path in [&a, &b] {
+            let mut op = journal.begin("process_image");
+            op.before_write(path);
+            std::fs::write(path, "new").unwrap();
+        }
+        let session = saved(&journal);
+        assert!(session.truncated);
+        assert_eq!(session.groups.len(), 1);
+        let backups = journal.session_dir(&journal.session_id()).join(BACKUP_DIR);
+        assert_eq!(std::fs::read_dir(&backups).unwrap().count(), 1);
+
+        // 单个文件超过上限时不备份，撤销时如实报告
+        let big = dir.join("big.txt");
+        std::fs::write(&big, [b'c'; 200]).unwrap();
+        journal.begin("process_image").before_write(&big);
+
+        let report = journal.restore_session(None).unwrap();
+        assert_eq!(std::fs::read_to_string(&b).unwrap(), "b".repeat(60));
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "new");
+        assert!(report.failed.contains(&i18n::t("undo.backup_truncated")));
+        assert!(report.failed.contains(&i18n::tf("undo.no_backup", &[&big.to_string_lossy()])));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
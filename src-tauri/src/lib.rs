@@ -1,53 +1,70 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 use base64::{engine::general_purpose, Engine as _};
 use serde::Serialize;
 use tauri::{Manager, Emitter};
 
+use error::{AppError, AppResult, ErrorCode};
+use runner::Task;
+
+mod batch;
+mod color;
+mod diagnostics;
+mod engine;
+mod error;
+mod i18n;
+mod inspect;
+mod journal;
+mod magick_io;
+mod naming;
+mod orientation;
+mod output;
+mod paper;
+mod pipeline;
+mod plan;
+mod preflight;
+mod psd;
+mod queue;
+mod replace;
+mod runner;
+mod thumbs;
+mod tiff_io;
+mod tiles;
+
 // ==========================================
-// 🌟 辅助引擎：跨平台 Magick 唤醒器
-// ==========================================
-// ==========================================
-// 🌟 辅助引擎：跨平台 Magick 唤醒器 (终极无黑框版)
+// 🌟 核心引擎 A：万能探针
 // ==========================================
-fn magick_command() -> std::process::Command {
-    #[cfg(target_os = "macos")]
-    {
-        if Path::new("/opt/homebrew/bin/magick").exists() {
-            return std::process::Command::new("/opt/homebrew/bin/magick");
-        } else if Path::new("/usr/local/bin/magick").exists() {
-            return std::process::Command::new("/usr/local/bin/magick");
-        }
-        std::process::Command::new("magick")
-    }
+/// 原图缺 DPI 时按生效的输出 DPI（纸张 → 全局默认 → 300）理解
+fn fallback_dpi(app: &tauri::AppHandle, output_dpi: Option<f64>, paper: Option<&str>) -> f64 {
+    let profile = paper.and_then(|name| paper::find(app, name));
+    pipeline::resolve_dpi(profile.as_ref(), output_dpi)
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt; // 引入 Windows 专属扩展
-        let mut cmd = std::process::Command::new("magick");
-        cmd.creation_flags(0x08000000); // 🚀 核心魔法：CREATE_NO_WINDOW，彻底隐藏黑框！
-        cmd
-    }
+/// 尺寸标签附带计算所用的 DPI，便于核对输出分辨率
+fn size_label(w_px: f32, h_px: f32, dpi: f32) -> String {
+    format!("{:.1} x {:.1} cm @{}dpi", (w_px / dpi) * 2.54, (h_px / dpi) * 2.54, dpi.round())
 }
 
-// ==========================================
-// 🌟 核心引擎 A：万能探针
-// ==========================================
 #[tauri::command]
-fn get_image_size(path_str: String) -> Result<String, String> {
-    let output = magick_command()
-        .args(["identify", "-format", "%w %h %x %U\n", &path_str])
-        .output().map_err(|e| format!("启动探测引擎失败: {}", e))?;
+fn get_image_size(app: tauri::AppHandle, path_str: String, output_dpi: Option<f64>, paper: Option<String>) -> AppResult<String> {
+    let default_dpi = fallback_dpi(&app, output_dpi, paper.as_deref()) as f32;
+    // PSD 只读文件头与分辨率资源，无需 magick 逐层解析
+    if psd::is_psd(Path::new(&path_str)) {
+        let info = psd::read_info(Path::new(&path_str)).map_err(|e| AppError::new(ErrorCode::FileCorrupt, e).at(Path::new(&path_str)))?;
+        let dpi = info.dpi.map(|v| v as f32).unwrap_or(default_dpi);
+        return Ok(size_label(info.width as f32, info.height as f32, dpi));
+    }
 
-    if !output.status.success() { return Err("解析尺寸失败".to_string()); }
-    
-    let dim_str = String::from_utf8_lossy(&output.stdout);
+    let dim_str = magick_io::identify(Path::new(&path_str), "%w %h %x %U\n", None)?;
     let first_line = dim_str.lines().next().unwrap_or("");
     let dims: Vec<&str> = first_line.trim().split_whitespace().collect();
     
     if dims.len() >= 2 {
         let w_px: f32 = dims[0].parse().unwrap_or(0.0);
         let h_px: f32 = dims[1].parse().unwrap_or(0.0);
-        let mut dpi: f32 = 300.0; 
+        // 按 EXIF 方向报告转正后的尺寸
+        let (w_px, h_px) = if orientation::swaps_dimensions(orientation::read(Path::new(&path_str))) { (h_px, w_px) } else { (w_px, h_px) };
+        let mut dpi: f32 = default_dpi;
         if dims.len() >= 3 {
             let parsed_dpi: f32 = dims[2].parse().unwrap_or(0.0);
             if parsed_dpi > 0.0 {
@@ -55,8 +72,8 @@ fn get_image_size(path_str: String) -> Result<String, String> {
                 if dims.len() >= 4 && dims[3].to_lowercase().contains("centimeter") { dpi *= 2.54; }
             }
         }
-        Ok(format!("{:.1} x {:.1} cm", (w_px / dpi) * 2.54, (h_px / dpi) * 2.54))
-    } else { Err("解析尺寸失败".to_string()) }
+        Ok(size_label(w_px, h_px, dpi))
+    } else { Err(AppError::new(ErrorCode::FileCorrupt, i18n::t("err.size_parse")).at(Path::new(&path_str))) }
 }
 
 // ==========================================
@@ -68,30 +85,55 @@ struct ImageMeta {
     height_px: u32,
     dpi: f32,
     unit: String,
+    /// EXIF Orientation（1..=8）；width_px/height_px 已是转正后的尺寸
+    orientation: u16,
+    /// 内嵌 ICC 描述文件的名称，未嵌入时为 None
+    icc_description: Option<String>,
+    /// dpi 是否读自文件；false 表示文件缺 DPI，dpi 为生效的默认值
+    dpi_from_file: bool,
+    /// 排版输出时实际写入的 DPI（纸张 → 全局默认 → 300）
+    output_dpi: f64,
 }
 
 #[tauri::command]
-fn get_image_meta(path_str: String) -> Result<ImageMeta, String> {
-    let output = magick_command()
-        .args(["identify", "-format", "%w %h %x %U\n", &path_str])
-        .output().map_err(|e| format!("启动探测引擎失败: {}", e))?;
+fn get_image_meta(app: tauri::AppHandle, path_str: String, output_dpi: Option<f64>, paper: Option<String>) -> AppResult<ImageMeta> {
+    image_meta(Path::new(&path_str), fallback_dpi(&app, output_dpi, paper.as_deref()))
+}
 
-    if !output.status.success() { return Err("解析尺寸失败".to_string()); }
+/// 读取尺寸、DPI 与方向；原图缺 DPI 时按 effective_dpi 理解
+fn image_meta(path: &Path, effective_dpi: f64) -> AppResult<ImageMeta> {
+    if psd::is_psd(path) {
+        let info = psd::read_info(path).map_err(|e| AppError::new(ErrorCode::FileCorrupt, e).at(path))?;
+        return Ok(ImageMeta {
+            width_px: info.width,
+            height_px: info.height,
+            dpi: info.dpi.unwrap_or(effective_dpi) as f32,
+            unit: String::from("PixelsPerInch"),
+            orientation: 1,
+            icc_description: info.icc.as_deref().and_then(color::description),
+            dpi_from_file: info.dpi.is_some(),
+            output_dpi: effective_dpi,
+        });
+    }
 
-    let dim_str = String::from_utf8_lossy(&output.stdout);
+    let dim_str = magick_io::identify(path, "%w %h %x %U\n", None)?;
     let first_line = dim_str.lines().next().unwrap_or("");
     let dims: Vec<&str> = first_line.trim().split_whitespace().collect();
 
     if dims.len() >= 2 {
         let w_px: u32 = dims[0].parse().unwrap_or(0);
         let h_px: u32 = dims[1].parse().unwrap_or(0);
-        let mut dpi: f32 = 300.0;
+        let orientation = orientation::read(path);
+        let (w_px, h_px) = if orientation::swaps_dimensions(orientation) { (h_px, w_px) } else { (w_px, h_px) };
+        let mut dpi: f32 = effective_dpi as f32;
         let mut unit = String::from("PixelsPerInch");
+        let mut dpi_from_file = false;
 
         if dims.len() >= 3 {
             let parsed_dpi: f32 = dims[2].parse().unwrap_or(0.0);
             if parsed_dpi > 0.0 {
                 dpi = parsed_dpi;
+                dpi_from_file = true;
                 if dims.len() >= 4 {
                     unit = dims[3].to_string();
                     if unit.to_lowercase().contains("centimeter") {
@@ -102,365 +144,829 @@ fn get_image_meta(path_str: String) -> Result<ImageMeta, String> {
             }
         }
 
-        Ok(ImageMeta { width_px: w_px, height_px: h_px, dpi, unit })
-    } else { Err("解析尺寸失败".to_string()) }
+        let icc_description = pipeline::read_profile(path).as_deref().and_then(color::description);
+        Ok(ImageMeta { width_px: w_px, height_px: h_px, dpi, unit, orientation, icc_description, dpi_from_file, output_dpi: effective_dpi })
+    } else { Err(AppError::new(ErrorCode::FileCorrupt, i18n::t("err.size_parse")).at(path)) }
 }
 
 // ==========================================
-// 🌟 核心引擎 B：终极预览图生成
+// 🌟 核心引擎 A.5：图像体检（单张 / 整个文件夹）
 // ==========================================
-#[tauri::command]
-fn generate_thumbnail(path_str: String) -> Result<String, String> {
-    let ext = Path::new(&path_str).extension().unwrap_or_default().to_string_lossy().to_lowercase();
-    
-    if ext == "jpg" || ext == "jpeg" || ext == "png" {
-        #[cfg(target_os = "windows")]
-        {
-            // Windows 必须走 http://asset.localhost/ 协议
-            // 并且要把反斜杠换成斜杠，把盘符的冒号(如 C:) 转码成 C%3A
-            let final_path = path_str.replace("\\", "/").replacen(":", "%3A", 1);
-            return Ok(format!("http://asset.localhost/{}", final_path));
+/// identify 单次调用的文件数，避免命令行过长
+const INSPECT_CHUNK: usize = 64;
+
+/// 一次 `identify -ping` 读取一批文件；个别文件读不出时 magick 仍会输出其余文件。
+/// 文件名带 magick 语法字符（`[0]`、`%d`、`png:` 等）的不进批次，逐个经 stdin 读取。
+/// magick 不可用时返回空表，由 inspect 改用原生读取器
+fn magick_identify(paths: &[String]) -> std::collections::HashMap<String, inspect::Identified> {
+    let format = inspect::identify_format();
+    let mut found = std::collections::HashMap::new();
+    let mut named = Vec::new();
+    for path in paths {
+        if let Some(arg) = magick_io::named_arg(Path::new(path)) {
+            named.push((path, arg));
+            continue;
         }
-        #[cfg(not(target_os = "windows"))]
-        {
-            // Mac 保持原样，极其稳定
-            return Ok(format!("asset://localhost/{}", path_str));
+        // 打不开的与整批时读不出的一样，留给 inspect 报错
+        let Ok(input) = magick_io::open(Path::new(path)) else { continue };
+        let Ok(output) = runner::magick(Task::Probe, ["identify", "-ping", "-format", format.as_str(), input.arg.as_str()]).stdin(input.file).output() else { continue };
+        if let Some(first) = inspect::parse_identify(&String::from_utf8_lossy(&output.stdout)).into_values().next() {
+            found.insert(path.clone(), first);
         }
     }
+    if !named.is_empty() {
+        let mut args = vec!["identify".to_string(), "-ping".to_string(), "-format".to_string(), format];
+        args.extend(named.iter().map(|(_, arg)| arg.clone()));
+        let Ok(output) = runner::magick(Task::Probe, args).output() else { return found };
+        let mut parsed = inspect::parse_identify(&String::from_utf8_lossy(&output.stdout));
+        for (path, arg) in named {
+            // %i 是否带上格式前缀随版本而异，两种都认
+            if let Some(item) = parsed.remove(path.as_str()).or_else(|| parsed.remove(&arg)) {
+                found.insert(path.clone(), item);
+            }
+        }
+    }
+    found
+}
 
-    // 处理 PSD/TIFF 等需要借用 Magick 算力的情况（生成 Base64）
-    let target_layer = format!("{}[0]", path_str);
-    let output = magick_command()
-        .args([&target_layer, "-background", "white", "-flatten", "-resize", "400x400>", "-strip", "jpeg:-"])
-        .output().map_err(|e| format!("引擎启动失败: {}", e))?;
+#[tauri::command]
+async fn inspect_image(app: tauri::AppHandle, path_str: String, output_dpi: Option<f64>, paper: Option<String>) -> AppResult<inspect::ImageInspection> {
+    let path = Path::new(&path_str);
+    if !path.exists() { return Err(AppError::not_found(path)); }
+    let identified = magick_identify(std::slice::from_ref(&path_str));
+    inspect::inspect(path, identified.get(&path_str), fallback_dpi(&app, output_dpi, paper.as_deref()))
+        .map_err(|e| AppError::new(ErrorCode::FileCorrupt, e).at(path))
+}
 
-    if output.status.success() {
-        Ok(format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(&output.stdout)))
-    } else { 
-        Err("生成预览图失败".to_string()) 
+#[tauri::command]
+async fn inspect_images(app: tauri::AppHandle, paths: Vec<String>, output_dpi: Option<f64>, paper: Option<String>) -> AppResult<Vec<inspect::InspectItem>> {
+    let dpi = fallback_dpi(&app, output_dpi, paper.as_deref());
+    let mut items = Vec::with_capacity(paths.len());
+    for chunk in paths.chunks(INSPECT_CHUNK) {
+        let identified = magick_identify(chunk);
+        for path in chunk {
+            let (info, error) = match inspect::inspect(Path::new(path), identified.get(path), dpi) {
+                Ok(info) => (Some(info), None),
+                Err(_) if !Path::new(path).exists() => (None, Some(AppError::not_found(Path::new(path)))),
+                Err(e) => (None, Some(AppError::new(ErrorCode::FileCorrupt, e).at(Path::new(path)))),
+            };
+            items.push(inspect::InspectItem { path: path.clone(), info, error });
+        }
     }
+    Ok(items)
 }
 
 // ==========================================
-// 🌟 核心引擎 C：图像排版与导出 (PSD 强制合并图层防破图)
+// 🌟 核心引擎 A.6：整单印前检查
 // ==========================================
+/// 每个任务按各自的纸张与目标尺寸出计划，再逐项检查；报告 HTML 一并返回，由前端走 export_file / html_to_pdf 导出
 #[tauri::command]
-async fn process_image(
-    path_str: String, mode: String, target_w_cm: f32, target_h_cm: f32,
-    crop_x: f32, crop_y: f32, crop_w: f32, crop_h: f32,
-    border_top_cm: f32,
-    border_right_cm: f32,
-    border_bottom_cm: f32,
-    border_left_cm: f32,
-) -> Result<(String, String), String> { 
-    let input_path = Path::new(&path_str);
-    if !input_path.exists() { return Err("文件不存在".to_string()); }
+async fn preflight(
+    app: tauri::AppHandle,
+    jobs: Vec<batch::ProcessJob>,
+    output_dpi: Option<f64>,
+    min_ppi: Option<f64>,
+    title: Option<String>,
+) -> AppResult<preflight::PreflightReport> {
+    let min_ppi = min_ppi.filter(|v| *v > 0.0).unwrap_or(preflight::DEFAULT_MIN_PPI);
+    let settings = batch::JobSettings { color_policy: None, output_dpi, output: None };
+    let mut items = Vec::with_capacity(jobs.len());
+    for chunk in jobs.chunks(INSPECT_CHUNK) {
+        let paths: Vec<String> = chunk.iter().map(|job| job.path.clone()).collect();
+        let identified = magick_identify(&paths);
+        for job in chunk {
+            let checked = build_request(&app, job, &settings).and_then(|req| {
+                let dpi = fallback_dpi(&app, output_dpi, job.paper.as_deref());
+                let info = inspect::inspect(Path::new(&job.path), identified.get(&job.path), dpi)?;
+                // 只用 identify 的尺寸与文件 DPI 出计划，不解码像素
+                let plan = plan::plan(&req, info.width_px, info.height_px, info.dpi_from_file.then_some(info.dpi));
+                let issues = preflight::check(&info, &plan, req.paper.as_ref(), min_ppi);
+                Ok((info, plan, issues))
+            });
+            items.push(match checked {
+                Ok((info, plan, issues)) => preflight::PreflightItem {
+                    path: job.path.clone(), paper: job.paper.clone(), mode: job.mode.clone(),
+                    target_w_cm: job.target_w_cm, target_h_cm: job.target_h_cm,
+                    info: Some(info), plan: Some(plan), issues,
+                },
+                Err(e) => preflight::unreadable(job.path.clone(), job.paper.clone(), job.mode.clone(), job.target_w_cm, job.target_h_cm, e.message),
+            });
+        }
+    }
+    Ok(preflight::report(&title.unwrap_or_else(|| i18n::t("preflight.title")), min_ppi, items))
+}
 
-    let target_layer = format!("{}[0]", path_str);
-    let output_dim = magick_command().args(["identify", "-format", "%w %h %x %U", &target_layer]).output().map_err(|e| format!("启动探测引擎失败: {}", e))?;
-    if !output_dim.status.success() { return Err("无法解析尺寸".to_string()); }
+// ==========================================
+// 🌟 核心引擎 B：终极预览图生成
+// ==========================================
+#[tauri::command]
+fn generate_thumbnail(path_str: String, thumbs: tauri::State<'_, thumbs::Thumbs>) -> AppResult<String> {
+    thumbnail_url(&path_str, &thumbs)
+}
 
-    let dim_str = String::from_utf8_lossy(&output_dim.stdout);
-    let dims: Vec<&str> = dim_str.trim().split_whitespace().collect();
-    if dims.len() < 2 { return Err("获取尺寸异常".to_string()); }
+/// 缩略图地址：转正的 JPEG/PNG 直接引用原图，其余生成缓存
+fn thumbnail_url(path_str: &str, thumbs: &thumbs::Thumbs) -> AppResult<String> {
+    let ext = Path::new(&path_str).extension().unwrap_or_default().to_string_lossy().to_lowercase();
     
-    let orig_w: f64 = dims[0].parse().unwrap_or(1.0);
-    let orig_h: f64 = dims[1].parse().unwrap_or(1.0);
-    let mut src_dpi: f64 = 300.0;
-    if dims.len() >= 3 {
-        let parsed_dpi: f64 = dims[2].parse().unwrap_or(0.0);
-        if parsed_dpi > 0.0 {
-            src_dpi = parsed_dpi;
-            if dims.len() >= 4 && dims[3].to_lowercase().contains("centimeter") {
-                src_dpi *= 2.54;
-            }
-        }
+    // 带旋转标记的照片不交给 webview 自行解读 EXIF，统一走下方生成转正后的预览
+    let upright = orientation::read(Path::new(&path_str)) == 1;
+    if upright && (ext == "jpg" || ext == "jpeg" || ext == "png") {
+        // 路径整段编码，文件名里的 [ ] % # ? 不会被 webview 当成 URL 语法
+        return Ok(thumbs::asset_url(path_str));
     }
 
-    let px = (crop_x as f64 / 100.0 * orig_w).round() as u32;
-    let py = (crop_y as f64 / 100.0 * orig_h).round() as u32;
-    let pw = ((crop_w as f64 / 100.0 * orig_w).round() as u32).max(1);
-    let ph = ((crop_h as f64 / 100.0 * orig_h).round() as u32).max(1);
-
-    let target_w_px = (target_w_cm as f64 / 2.54 * 300.0).round() as u32;
-    let target_h_px = (target_h_cm as f64 / 2.54 * 300.0).round() as u32;
+    // PSD/TIFF 等生成一次后落盘缓存，经 thumb:// 提供，重开文件夹不再逐张拉起 magick
+    let name = thumbs.ensure(Path::new(path_str), 400, || render_thumbnail(Path::new(path_str), 400))?;
+    Ok(thumbs.url(&name))
+}
 
-    let ext = input_path.extension().unwrap_or_default().to_string_lossy();
-    let file_stem = input_path.file_stem().unwrap_or_default().to_string_lossy();
-    let parent_dir = input_path.parent().unwrap_or(Path::new(""));
-    let temp_output = parent_dir.join(format!("{}_temp.{}", file_stem, ext));
+/// 白底 JPEG 缩略图：原生解码优先（PSD 读合并图，不再依赖 -flatten），原生解码不了的（如 CMYK）借用 Magick 算力
+fn render_thumbnail(path: &Path, max_side: u32) -> AppResult<Vec<u8>> {
+    if !path.exists() { return Err(AppError::not_found(path)); }
+    psd::ensure_merged_data(path).map_err(|e| AppError::new(ErrorCode::Unsupported, e).at(path))?;
+    if let Ok(jpeg) = pipeline::thumbnail(path, max_side) {
+        return Ok(jpeg);
+    }
+    magick_io::thumbnail(path, max_side)
+}
 
-    let mut args = vec![format!("{}[0]", input_path.to_string_lossy())];
+// ==========================================
+// 🌟 瓦片查看：超大原图按需切成多级瓦片，经 imagetile:// 平移缩放
+// ==========================================
+/// 原尺寸的查看用图像：原生管线优先，读不了的（如 CMYK）交给 magick 转成 sRGB
+fn decode_view(path: &Path) -> Result<image::RgbImage, String> {
+    psd::ensure_merged_data(path)?;
+    if let Ok(image) = pipeline::decode_view(path) {
+        return Ok(image);
+    }
+    let input = magick_io::open(path).map_err(|e| e.to_string())?;
+    let output = runner::magick(Task::Preview, [input.arg.as_str(), "-auto-orient", "-background", "white", "-flatten", "-colorspace", "sRGB", "-depth", "8", "-strip", "PPM:-"])
+        .stdin(input.file)
+        .output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(i18n::tf("err.decode", &[&String::from_utf8_lossy(&output.stderr).trim()]));
+    }
+    image::load_from_memory(&output.stdout).map(|image| image.into_rgb8()).map_err(|e| i18n::tf("err.decode", &[&e]))
+}
 
-    if mode == "crop" {
-        args.push("-crop".to_string()); args.push(format!("{}x{}+{}+{}", pw, ph, px, py));
-        args.push("+repage".to_string()); 
-        args.push("-resize".to_string()); args.push(format!("{}x{}^", target_w_px, target_h_px)); 
-        args.push("-gravity".to_string()); args.push("center".to_string()); 
-        args.push("-background".to_string()); args.push("white".to_string());
-        args.push("-extent".to_string()); args.push(format!("{}x{}", target_w_px, target_h_px));
-        // 🚀 核弹修复：强行拍扁图层，杜绝 PSD 预览失效
-        args.push("-flatten".to_string());
-    } else if mode == "resize" {
-        args.push("-resize".to_string()); args.push(format!("{}x{}!", target_w_px, target_h_px));
-        args.push("-background".to_string()); args.push("white".to_string());
-        args.push("-flatten".to_string());
-    } else if mode == "border" {
-        let cm_to_px = |cm: f32| -> i32 { (((cm as f64) / 2.54) * src_dpi).round() as i32 };
-
-        let top_px_raw = cm_to_px(border_top_cm);
-        let right_px_raw = cm_to_px(border_right_cm);
-        let bottom_px_raw = cm_to_px(border_bottom_cm);
-        let left_px_raw = cm_to_px(border_left_cm);
-
-        let add_top = top_px_raw.max(0) as u32;
-        let add_right = right_px_raw.max(0) as u32;
-        let add_bottom = bottom_px_raw.max(0) as u32;
-        let add_left = left_px_raw.max(0) as u32;
-
-        let crop_top_raw = (-top_px_raw).max(0) as u32;
-        let crop_right_raw = (-right_px_raw).max(0) as u32;
-        let crop_bottom_raw = (-bottom_px_raw).max(0) as u32;
-        let crop_left_raw = (-left_px_raw).max(0) as u32;
-
-        let expanded_w = (orig_w.round() as u32)
-            .saturating_add(add_left)
-            .saturating_add(add_right)
-            .max(1);
-        let expanded_h = (orig_h.round() as u32)
-            .saturating_add(add_top)
-            .saturating_add(add_bottom)
-            .max(1);
-
-        let max_crop_w = expanded_w.saturating_sub(1);
-        let crop_left = crop_left_raw.min(max_crop_w);
-        let remaining_w = expanded_w.saturating_sub(crop_left).max(1);
-        let crop_right = crop_right_raw.min(remaining_w.saturating_sub(1));
-
-        let max_crop_h = expanded_h.saturating_sub(1);
-        let crop_top = crop_top_raw.min(max_crop_h);
-        let remaining_h = expanded_h.saturating_sub(crop_top).max(1);
-        let crop_bottom = crop_bottom_raw.min(remaining_h.saturating_sub(1));
-
-        let final_w = expanded_w
-            .saturating_sub(crop_left)
-            .saturating_sub(crop_right)
-            .max(1);
-        let final_h = expanded_h
-            .saturating_sub(crop_top)
-            .saturating_sub(crop_bottom)
-            .max(1);
+#[tauri::command]
+async fn open_tiles(path_str: String, tiles: tauri::State<'_, tiles::Tiles>) -> AppResult<tiles::TileSource> {
+    let path = Path::new(&path_str);
+    if !path.exists() { return Err(AppError::not_found(path)); }
+    tiles.source(path).map_err(|e| AppError::new(ErrorCode::FileCorrupt, e).at(path))
+}
 
-        args.push("-background".to_string()); args.push("white".to_string());
-        args.push("-gravity".to_string()); args.push("northwest".to_string());
-        args.push("-splice".to_string()); args.push(format!("{}x{}", add_left, add_top));
-        args.push("-gravity".to_string()); args.push("southeast".to_string());
-        args.push("-splice".to_string()); args.push(format!("{}x{}", add_right, add_bottom));
-        args.push("-gravity".to_string()); args.push("northwest".to_string());
-        args.push("-extent".to_string()); args.push(format!("{}x{}", expanded_w, expanded_h));
+// ==========================================
+// 🌟 核心引擎 C：图像排版与导出 (PSD 强制合并图层防破图)
+// ==========================================
+// JPEG/PNG 走 pipeline 原生管线；其余格式交给 magick
+/// magick 读取的原图像素（按 EXIF 方向转正）与 DPI；原生管线读不了的格式用它做计划
+fn magick_source(input_path: &Path, cancel: Option<&AtomicBool>) -> AppResult<(u32, u32, Option<f64>)> {
+    let dim_str = magick_io::identify(input_path, "%w %h %x %U", cancel)?;
+    let dims: Vec<&str> = dim_str.trim().split_whitespace().collect();
+    if dims.len() < 2 { return Err(AppError::new(ErrorCode::FileCorrupt, i18n::t("err.size_invalid")).at(input_path)); }
+    
+    let orig_w: u32 = dims[0].parse().unwrap_or(1);
+    let orig_h: u32 = dims[1].parse().unwrap_or(1);
+    // -auto-orient 转正后再裁切，尺寸也按转正后计算
+    let (orig_w, orig_h) = if orientation::swaps_dimensions(orientation::read(input_path)) { (orig_h, orig_w) } else { (orig_w, orig_h) };
+    let src_dpi = dims.get(2).and_then(|v| v.parse::<f64>().ok()).filter(|v| *v > 0.0).map(|dpi| {
+        if dims.len() >= 4 && dims[3].to_lowercase().contains("centimeter") { dpi * 2.54 } else { dpi }
+    });
+    Ok((orig_w, orig_h, src_dpi))
+}
 
-        if crop_left > 0 || crop_right > 0 || crop_top > 0 || crop_bottom > 0 {
-            args.push("-gravity".to_string()); args.push("northwest".to_string());
-            args.push("-crop".to_string());
-            args.push(format!("{}x{}+{}+{}", final_w, final_h, crop_left, crop_top));
-            args.push("+repage".to_string());
-        }
+/// cancel 为批次的停止标记，置位后连同 magick 进程一起结束
+fn process_with_magick(input_path: &Path, temp_output: &Path, req: &pipeline::ProcessRequest, cancel: Option<&AtomicBool>) -> AppResult<()> {
+    let (orig_w, orig_h, src_dpi) = magick_source(input_path, cancel)?;
 
-        args.push("-flatten".to_string());
-    } else if mode == "mirror" {
-        let top_px = ((((border_top_cm.max(0.0)) as f64) / 2.54) * src_dpi).round() as u32;
-        let right_px = ((((border_right_cm.max(0.0)) as f64) / 2.54) * src_dpi).round() as u32;
-        let bottom_px = ((((border_bottom_cm.max(0.0)) as f64) / 2.54) * src_dpi).round() as u32;
-        let left_px = ((((border_left_cm.max(0.0)) as f64) / 2.54) * src_dpi).round() as u32;
-
-        let expanded_w = (orig_w.round() as u32)
-            .saturating_add(left_px)
-            .saturating_add(right_px)
-            .max(1);
-        let expanded_h = (orig_h.round() as u32)
-            .saturating_add(top_px)
-            .saturating_add(bottom_px)
-            .max(1);
+    // 几何全部取自排版计划，与原生管线逐像素一致
+    let plan = plan::plan(req, orig_w, orig_h, src_dpi);
+    let out_dpi = plan.output_dpi;
+    // 输入输出经 stdin / stdout 传递，这里只有处理步骤
+    let mut args = vec!["-auto-orient".to_string()];
 
+    if let Some(rect) = plan.crop {
+        args.push("-crop".to_string()); args.push(format!("{}x{}+{}+{}", rect.width, rect.height, rect.x, rect.y));
+        args.push("+repage".to_string());
+    }
+    if let Some(r) = plan.resample {
+        args.push("-resize".to_string()); args.push(format!("{}x{}!", r.width, r.height));
+    }
+    let sides = plan.sides;
+    let (final_w, final_h) = (plan.final_px.width, plan.final_px.height);
+    if plan.mirror_fill && !sides.is_zero() {
         args.push("-virtual-pixel".to_string()); args.push("mirror".to_string());
         args.push("-set".to_string());
         args.push("option:distort:viewport".to_string());
-        args.push(format!("{}x{}-{}-{}", expanded_w, expanded_h, left_px, top_px));
+        args.push(format!("{}x{}-{}-{}", final_w, final_h, sides.left, sides.top));
         args.push("-filter".to_string()); args.push("point".to_string());
         args.push("-distort".to_string()); args.push("SRT".to_string()); args.push("0".to_string());
         args.push("+repage".to_string());
-        args.push("-flatten".to_string());
-    } else {
-        // Pad mode (等比留白)
-        args.push("-resize".to_string()); args.push(format!("{}x{}", target_w_px, target_h_px));
-        args.push("-background".to_string()); args.push("white".to_string()); 
-        args.push("-gravity".to_string()); args.push("center".to_string()); 
-        args.push("-extent".to_string()); args.push(format!("{}x{}", target_w_px, target_h_px));
-        // 🚀 核弹修复：强行拍扁图层，杜绝 PSD 预览失效
-        args.push("-flatten".to_string());
+    } else if !sides.is_zero() {
+        // 加边与裁边合成一次 extent：偏移为负即加白边，为正即裁掉
+        args.push("-background".to_string()); args.push("white".to_string());
+        args.push("-gravity".to_string()); args.push("northwest".to_string());
+        args.push("-extent".to_string()); args.push(format!("{}x{}{:+}{:+}", final_w, final_h, -sides.left, -sides.top));
+    }
+    // 🚀 核弹修复：强行拍扁图层，杜绝 PSD 预览失效
+    args.push("-background".to_string()); args.push("white".to_string());
+    args.push("-flatten".to_string());
+
+    // 色彩策略：magick 默认保留原图 ICC，只有转换/指定时才追加 -profile
+    args.extend(magick_profile_args(input_path, req)?);
+
+    // 纸张输出调整，顺序与原生管线一致：加深 → 锐化 → 镜像
+    if let Some(paper) = &req.paper {
+        if paper.tone_boost != 0.0 { args.push("-gamma".to_string()); args.push(format!("{:.4}", paper.magick_gamma())); }
+        if paper.sharpen > 0.0 { args.push("-unsharp".to_string()); args.push(format!("0x{}", paper.sharpen)); }
+        if paper.mirror { args.push("-flop".to_string()); }
     }
 
-    args.push("-density".to_string()); args.push("300".to_string()); args.push("-units".to_string()); args.push("PixelsPerInch".to_string());
-    if ext.to_lowercase() == "tif" || ext.to_lowercase() == "tiff" { args.push("-compress".to_string()); args.push("None".to_string()); }
-    args.push(temp_output.to_string_lossy().to_string());
+    // 不指定 -compress，magick 会沿用原图的压缩方式
+    // keep 模式像素数不变，原图有 DPI 时不改写，保持物理尺寸
+    let keeps_density = req.mode == "keep" && src_dpi.is_some();
+    if !keeps_density {
+        args.push("-density".to_string()); args.push(format!("{}", out_dpi)); args.push("-units".to_string()); args.push("PixelsPerInch".to_string());
+    }
+    magick_io::convert(input_path, &args, temp_output, Task::Process, cancel)?;
+
+    // 幅宽检查放在输出之后，按实际写出的像素计算
+    if let Some(paper) = &req.paper {
+        if let Some((w, h, dpi)) = pipeline::dimensions(temp_output) {
+            if let Err(e) = paper.check_width(w, h, dpi.unwrap_or(out_dpi)) {
+                let _ = std::fs::remove_file(temp_output);
+                return Err(AppError::new(ErrorCode::PaperTooNarrow, e).at(input_path));
+            }
+        }
+    }
+    Ok(())
+}
 
-    let output = magick_command().args(args).output().map_err(|e| format!("无法启动引擎: {}", e))?;
+/// 临时文件落盘后完整解码，核对尺寸与 DPI；原生解码不了的（如 CMYK）交给 magick 全量读取
+fn verify_output(temp_output: &Path, expected: Option<replace::Expected>) -> AppResult<()> {
+    replace::sync_file(temp_output).map_err(|e| AppError::new(ErrorCode::Io, e))?;
+    let actual = match pipeline::decode_output(temp_output) {
+        Ok(actual) => actual,
+        Err(native_err) => magick_decode(temp_output).map_err(|e| AppError {
+            code: ErrorCode::VerifyFailed,
+            message: i18n::tf("err.verify_undecodable", &[&native_err, &e.message]),
+            ..e
+        })?,
+    };
+    replace::check(actual, expected).map_err(|e| AppError::new(ErrorCode::VerifyFailed, e))
+}
 
+fn magick_decode(path: &Path) -> AppResult<(u32, u32, Option<f64>)> {
+    let input = magick_io::open(path)?;
+    let output = runner::magick(Task::Process, [input.arg.as_str(), "-regard-warnings", "-format", "%w %h %x %U", "info:"]).stdin(input.file).output()?;
     if !output.status.success() {
-        let _ = std::fs::remove_file(&temp_output);
-        return Err(format!("引擎报错: {}", String::from_utf8_lossy(&output.stderr)));
+        return Err(AppError::engine(&i18n::t("err.magick_decode"), &output));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    let (Some(w), Some(h)) = (fields.first().and_then(|v| v.parse().ok()), fields.get(1).and_then(|v| v.parse().ok())) else {
+        return Err(AppError::new(ErrorCode::FileCorrupt, i18n::t("err.size_invalid")));
+    };
+    let units = fields.get(3).map(|u| u.to_lowercase()).unwrap_or_default();
+    let dpi = fields.get(2).and_then(|v| v.parse::<f64>().ok()).filter(|v| *v > 0.0 && !units.contains("undefined"));
+    let dpi = if units.contains("centimeter") { dpi.map(|v| v * 2.54) } else { dpi };
+    Ok((w, h, dpi))
+}
+
+/// 把色彩策略（或纸张输出 ICC）翻译成 magick 的 `-profile` 参数
+fn magick_profile_args(input_path: &Path, req: &pipeline::ProcessRequest) -> Result<Vec<String>, String> {
+    let has_profile = pipeline::read_profile(input_path).is_some();
+    let mut args = Vec::new();
+
+    // 纸张配置了输出 ICC 时以它为准，按配置的渲染意图转换
+    if let Some(paper) = &req.paper {
+        if let Some(path) = paper.output_profile_path()? {
+            if !has_profile {
+                args.push("-profile".to_string());
+                args.push(color::profile_file(color::Target::Srgb)?.to_string_lossy().to_string());
+            }
+            args.push("-intent".to_string()); args.push(paper.intent.magick_name().to_string());
+            args.push("-profile".to_string()); args.push(path.to_string_lossy().to_string());
+            return Ok(args);
+        }
     }
 
-    let final_path = input_path.to_path_buf();
-    let final_name = input_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let policy = req.color_policy;
+    let Some(target) = policy.target() else { return Ok(args) };
+    if policy.converts() {
+        // 无描述文件时 magick 的第一个 -profile 只是“指定”，先按 sRGB 指定再转换
+        if !has_profile && target != color::Target::Srgb {
+            args.push("-profile".to_string());
+            args.push(color::profile_file(color::Target::Srgb)?.to_string_lossy().to_string());
+        }
+        args.push("-profile".to_string());
+        args.push(color::profile_file(target)?.to_string_lossy().to_string());
+    } else if !has_profile {
+        args.push("-profile".to_string());
+        args.push(color::profile_file(target)?.to_string_lossy().to_string());
+    }
+    Ok(args)
+}
+
+/// 组装排版参数（纸张配置按名称查找）
+fn build_request(app: &tauri::AppHandle, job: &batch::ProcessJob, settings: &batch::JobSettings) -> AppResult<pipeline::ProcessRequest> {
+    Ok(pipeline::ProcessRequest {
+        mode: job.mode.clone(),
+        target_w_cm: job.target_w_cm, target_h_cm: job.target_h_cm,
+        crop_x: job.crop_x, crop_y: job.crop_y, crop_w: job.crop_w, crop_h: job.crop_h,
+        border_top_cm: job.border_top_cm, border_right_cm: job.border_right_cm,
+        border_bottom_cm: job.border_bottom_cm, border_left_cm: job.border_left_cm,
+        color_policy: color::ColorPolicy::parse(settings.color_policy.as_deref()).map_err(|e| AppError::new(ErrorCode::InvalidInput, e))?,
+        paper: job.paper.as_deref().and_then(|name| paper::find(app, name)),
+        default_dpi: settings.output_dpi,
+    })
+}
+
+/// 处理单个文件，返回 (输出路径, 输出文件名)；process_image 与 process_batch 共用
+fn process_file(
+    app: &tauri::AppHandle,
+    journal: &journal::Journal,
+    job: &batch::ProcessJob,
+    settings: &batch::JobSettings,
+    batch_id: Option<&str>,
+    cancel: Option<&AtomicBool>,
+) -> AppResult<(String, String)> {
+    write_processed(app, journal, job, settings, batch_id, cancel).map_err(|e| e.at(Path::new(&job.path)))
+}
 
-    if let Err(_) = std::fs::rename(&temp_output, &final_path) {
-        std::fs::copy(&temp_output, &final_path).map_err(|e| format!("覆盖原文件失败: {}", e))?;
+fn write_processed(
+    app: &tauri::AppHandle,
+    journal: &journal::Journal,
+    job: &batch::ProcessJob,
+    settings: &batch::JobSettings,
+    batch_id: Option<&str>,
+    cancel: Option<&AtomicBool>,
+) -> AppResult<(String, String)> {
+    let input_path = Path::new(&job.path);
+    if !input_path.exists() { return Err(AppError::not_found(input_path)); }
+    // 未开“最大兼容”的 PSD 没有合并图，magick 兜底也只会输出破图
+    psd::ensure_merged_data(input_path).map_err(|e| AppError::new(ErrorCode::Unsupported, e))?;
+
+    // 唯一临时文件放在最终输出旁边，保证最后一步 rename 不跨盘
+    let final_path = output::OutputStrategy::from_options(settings.output.as_ref())
+        .and_then(|strategy| strategy.destination(input_path))
+        .map_err(|e| AppError::new(ErrorCode::InvalidInput, e))?;
+    let temp_output = replace::temp_path(&final_path);
+
+    let req = build_request(app, job, settings)?;
+    let expected = pipeline::expected_output(input_path, &req);
+    replace::ensure_space(final_path.parent().unwrap_or(Path::new("")), replace::estimate_size(input_path, expected))
+        .map_err(|e| AppError::new(ErrorCode::DiskFull, e))?;
+
+    // 原生管线读不了的文件（如 CMYK JPEG）再交给 magick 兜底
+    if pipeline::can_process(input_path, &temp_output) {
+        if let Err(native_err) = pipeline::process(input_path, &temp_output, &req) {
+            let _ = std::fs::remove_file(&temp_output);
+            process_with_magick(input_path, &temp_output, &req, cancel)
+                .map_err(|e| e.context(i18n::tf("err.native_pipeline", &[&native_err])))?;
+        }
+    } else {
+        process_with_magick(input_path, &temp_output, &req, cancel)?;
+    }
+    if let Err(e) = verify_output(&temp_output, expected) {
         let _ = std::fs::remove_file(&temp_output);
+        return Err(e);
     }
 
+    let final_name = final_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+    // 覆盖前先备份，供撤销；同一批次的记录一起撤销
+    let mut op = match batch_id {
+        Some(id) => journal.begin_batch("process_batch", id),
+        None => journal.begin("process_image"),
+    };
+    op.before_write(&final_path);
+    replace::commit(&temp_output, &final_path).map_err(|e| AppError::new(ErrorCode::Io, e))?;
+
     Ok((final_path.to_string_lossy().to_string(), final_name))
 }
 
+#[tauri::command]
+async fn process_image(
+    app: tauri::AppHandle,
+    path_str: String, mode: String, target_w_cm: f32, target_h_cm: f32,
+    crop_x: f32, crop_y: f32, crop_w: f32, crop_h: f32,
+    border_top_cm: f32,
+    border_right_cm: f32,
+    border_bottom_cm: f32,
+    border_left_cm: f32,
+    color_policy: Option<String>,
+    paper: Option<String>,
+    output_dpi: Option<f64>,
+    output: Option<output::OutputOptions>,
+    journal: tauri::State<'_, journal::Journal>,
+) -> AppResult<(String, String)> { 
+    let job = batch::ProcessJob {
+        path: path_str, mode, target_w_cm, target_h_cm, crop_x, crop_y, crop_w, crop_h,
+        border_top_cm, border_right_cm, border_bottom_cm, border_left_cm, paper,
+    };
+    let settings = batch::JobSettings { color_policy, output_dpi, output };
+    process_file(&app, &journal, &job, &settings, None, None)
+}
+
+// ==========================================
+// 🌟 排版计划：只算几何，不读写像素
+// ==========================================
+// 参数即 process_batch 的单个任务，返回裁切框、重采样、四边增减与成品尺寸，执行时用的也是这份计划
+#[tauri::command]
+fn plan_process(app: tauri::AppHandle, job: batch::ProcessJob, output_dpi: Option<f64>) -> AppResult<plan::Plan> {
+    let input_path = Path::new(&job.path);
+    if !input_path.exists() { return Err(AppError::not_found(input_path)); }
+    let settings = batch::JobSettings { color_policy: None, output_dpi, output: None };
+    let req = build_request(&app, &job, &settings)?;
+    if let Some(plan) = pipeline::source_plan(input_path, &req) {
+        return Ok(plan);
+    }
+    let (w, h, dpi) = magick_source(input_path, None)?;
+    Ok(plan::plan(&req, w, h, dpi))
+}
+
+/// 成品预览的默认长边像素
+const PREVIEW_MAX_SIDE: u32 = 1200;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProcessPreview {
+    /// 可直接给 <img> 用的 data URL
+    url: String,
+    /// 完整尺寸的排版计划，预览只是它的等比缩小
+    plan: plan::Plan,
+}
+
+// 同一份计划作用在缩小的原图上，不写任何文件；几何与 process_image 的成品一致
+#[tauri::command]
+async fn preview_process(app: tauri::AppHandle, job: batch::ProcessJob, output_dpi: Option<f64>, max_side: Option<u32>) -> AppResult<ProcessPreview> {
+    let input_path = Path::new(&job.path);
+    if !input_path.exists() { return Err(AppError::not_found(input_path)); }
+    psd::ensure_merged_data(input_path).map_err(|e| AppError::new(ErrorCode::Unsupported, e).at(input_path))?;
+    let settings = batch::JobSettings { color_policy: None, output_dpi, output: None };
+    let req = build_request(&app, &job, &settings)?;
+    let max_side = max_side.unwrap_or(PREVIEW_MAX_SIDE).max(16);
+
+    let (jpeg, plan) = match pipeline::preview(input_path, &req, max_side) {
+        Ok(result) => result,
+        Err(native_err) => {
+            // 原生解码不了的（如 CMYK）让 magick 缩到预览比例，排版仍走同一份计划
+            let (w, h, dpi) = magick_source(input_path, None)?;
+            let plan = plan::plan(&req, w, h, dpi);
+            let source = plan.scaled(plan.preview_scale(max_side)).source_px;
+            let input = magick_io::open(input_path)?;
+            let output = runner::magick(Task::Preview, [input.arg.as_str(), "-auto-orient", "-background", "white", "-flatten", "-resize", &format!("{}x{}!", source.width, source.height), "-colorspace", "sRGB", "-strip", "PNG:-"])
+                .stdin(input.file)
+                .output().map_err(|e| e.at(input_path))?;
+            if !output.status.success() {
+                return Err(AppError::engine(&i18n::t("err.preview"), &output).context(i18n::tf("err.native_pipeline", &[&native_err])).at(input_path));
+            }
+            let image = image::load_from_memory(&output.stdout)
+                .map_err(|e| AppError::new(ErrorCode::FileCorrupt, format!("{}: {}", i18n::t("err.preview"), e)).at(input_path))?;
+            (pipeline::render_preview(image, None, &plan, req.paper.as_ref(), max_side)?, plan)
+        }
+    };
+    Ok(ProcessPreview { url: format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(&jpeg)), plan })
+}
+
+// ==========================================
+// 🌟 批量处理：线程池并行，逐张推送进度，可中途停止
+// ==========================================
+#[tauri::command]
+async fn process_batch(
+    app: tauri::AppHandle,
+    batch_id: String,
+    jobs: Vec<batch::ProcessJob>,
+    color_policy: Option<String>,
+    output_dpi: Option<f64>,
+    output: Option<output::OutputOptions>,
+) -> AppResult<batch::BatchReport> {
+    let settings = batch::JobSettings { color_policy, output_dpi, output };
+    let session = app.state::<journal::Journal>().session_id();
+    app.state::<queue::Queue>()
+        .start(queue::QueueFile::new(&batch_id, settings.clone(), jobs.clone(), session))
+        .map_err(|e| AppError::new(ErrorCode::Io, e))?;
+    run_queued(app, batch_id, jobs, settings).await
+}
+
+/// 按持久化队列执行批次：每张开始前、结束后都落盘状态，已完成的直接跳过
+async fn run_queued(
+    app: tauri::AppHandle,
+    batch_id: String,
+    jobs: Vec<batch::ProcessJob>,
+    settings: batch::JobSettings,
+) -> AppResult<batch::BatchReport> {
+    let cancel = app.state::<batch::Batches>().register(&batch_id);
+    let handle = app.clone();
+    let id = batch_id.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let journal = handle.state::<journal::Journal>();
+        let queue = handle.state::<queue::Queue>();
+        let estimates: Vec<u64> = jobs
+            .iter()
+            .enumerate()
+            .map(|(index, job)| {
+                if queue.finished_result(&batch_id, index).is_some() { return 0; }
+                build_request(&handle, job, &settings).map(|req| batch::memory_estimate(Path::new(&job.path), &req)).unwrap_or(0)
+            })
+            .collect();
+        batch::run(&handle, &batch_id, &cancel, &jobs, &estimates, |index, job| {
+            if let Some(done) = queue.finished_result(&batch_id, index) {
+                return Ok(done);
+            }
+            queue.mark_running(&batch_id, index);
+            let result = process_file(&handle, &journal, job, &settings, Some(&batch_id), Some(cancel.as_ref()));
+            queue.mark_result(&batch_id, index, &result);
+            result
+        })
+    })
+    .await
+    .map_err(|e| AppError::new(ErrorCode::Internal, i18n::tf("err.batch_thread", &[&e])));
+    app.state::<batch::Batches>().finish(&id);
+    // 线程异常时保留队列文件，下次启动可以继续
+    if report.is_ok() {
+        app.state::<queue::Queue>().finish(&id);
+    }
+    report
+}
+
+// ==========================================
+// 🌟 未完成批次：启动时检测，继续或回滚
+// ==========================================
+#[tauri::command]
+fn get_unfinished_batches(queue: tauri::State<'_, queue::Queue>) -> Vec<queue::QueueSummary> {
+    queue.unfinished()
+}
+
+#[tauri::command]
+async fn resume_batch(app: tauri::AppHandle, batch_id: String) -> AppResult<batch::BatchReport> {
+    let session = app.state::<journal::Journal>().session_id();
+    let file = app.state::<queue::Queue>().resume(&batch_id, session).map_err(|e| AppError::new(ErrorCode::Io, e))?;
+    let jobs = file.jobs.into_iter().map(|queued| queued.job).collect();
+    run_queued(app, batch_id, jobs, file.settings).await
+}
+
+#[tauri::command]
+fn rollback_batch(
+    journal: tauri::State<'_, journal::Journal>,
+    queue: tauri::State<'_, queue::Queue>,
+    batch_id: String,
+) -> AppResult<journal::UndoReport> {
+    let file = queue.take(&batch_id).map_err(|e| AppError::new(ErrorCode::Io, e))?;
+    let mut report = journal::UndoReport::default();
+    for session in &file.journal_sessions {
+        if let Err(e) = journal.revert_batch(session, &batch_id, &mut report) {
+            report.failed.push(e);
+        }
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+fn discard_batch(queue: tauri::State<'_, queue::Queue>, batch_id: String) -> AppResult<()> {
+    queue.take(&batch_id).map(|_| ()).map_err(|e| AppError::new(ErrorCode::Io, e))
+}
+
+#[tauri::command]
+fn cancel_batch(batches: tauri::State<'_, batch::Batches>, batch_id: String) -> bool {
+    batches.cancel(&batch_id)
+}
+
 // ==========================================
 // 🌟 核心引擎 D：极速重命名
 // ==========================================
 #[tauri::command]
-fn rename_files(files_to_process: Vec<Vec<String>>) -> Result<Vec<(String, String, String)>, String> {
+fn rename_files(
+    app: tauri::AppHandle,
+    journal: tauri::State<'_, journal::Journal>,
+    items: Vec<naming::RenameItem>,
+    template: naming::RenameTemplate,
+    context: naming::RenameContext,
+    output_dpi: Option<f64>,
+) -> AppResult<Vec<(String, String, String)>> {
+    rename_with(&paper::load(&app), &journal, &items, &template, &context, output_dpi)
+}
+
+/// 按模板改名并补写 DPI；registry 为全部纸张配置
+fn rename_with(
+    registry: &[paper::PaperProfile],
+    journal: &journal::Journal,
+    items: &[naming::RenameItem],
+    template: &naming::RenameTemplate,
+    context: &naming::RenameContext,
+    output_dpi: Option<f64>,
+) -> AppResult<Vec<(String, String, String)>> {
+    // 先整体检查幅宽并生成全部新名字，避免改到一半才发现放不下或重名
+    let mut profiles = Vec::with_capacity(items.len());
+    for item in items {
+        let profile = item.paper.as_ref().and_then(|name| registry.iter().find(|p| &p.name == name).cloned());
+        if let Some(profile) = &profile {
+            if let Some((w, h, dpi)) = pipeline::dimensions(Path::new(&item.path)) {
+                let dpi = dpi.unwrap_or_else(|| pipeline::resolve_dpi(Some(profile), output_dpi));
+                profile.check_width(w, h, dpi).map_err(|e| AppError::new(ErrorCode::PaperTooNarrow, e).at(Path::new(&item.path)))?;
+            }
+        }
+        profiles.push(profile);
+    }
+    let planned = naming::plan(items, template, context, |item| {
+        let profile = item.paper.as_ref().and_then(|name| registry.iter().find(|p| &p.name == name));
+        finished_size_cm(Path::new(&item.path), profile, output_dpi)
+    })?;
+
+    let mut op = journal.begin("rename_files");
+    // 新名字正好是本批另一张的原名时（如 1↔2 对调），先把那张挪到临时名；
+    // 只改大小写（IMG.JPG → img.jpg）在不区分大小写的文件系统上新旧是同一个文件，也先挪走，
+    // 否则写好新文件后删除“原图”会把刚写的文件删掉
+    let key = |path: &str| naming::path_key(Path::new(path));
+    let mut sources: Vec<PathBuf> = planned.iter().map(|p| PathBuf::from(&p.path)).collect();
+    for (index, entry) in planned.iter().enumerate() {
+        let taken = planned.iter().enumerate().any(|(other, p)| other != index && key(&p.new_path) == key(&entry.path));
+        let case_only = entry.path != entry.new_path && replace::same_file(Path::new(&entry.path), Path::new(&entry.new_path));
+        if taken || case_only {
+            let temp = replace::temp_path(&sources[index]);
+            std::fs::rename(&sources[index], &temp).map_err(|e| AppError::io(&i18n::t("err.rename"), &e).at(&sources[index]))?;
+            op.moved(&sources[index], &temp);
+            sources[index] = temp;
+        }
+    }
+
     let mut results = Vec::new();
-    for (index, file_info) in files_to_process.iter().enumerate() {
-        if file_info.len() < 2 { continue; }
-        let old_path_str = &file_info[0];
-        let paper_type = &file_info[1];
-        let old_path = Path::new(old_path_str);
-        if !old_path.exists() { continue; }
-        
+    for (index, entry) in planned.into_iter().enumerate() {
+        let old_path = sources[index].as_path();
         let parent = old_path.parent().unwrap_or(Path::new(""));
         let ext = old_path.extension().unwrap_or_default().to_string_lossy();
-        
-        let new_name = format!("{}-{}.{}", paper_type, index + 1, ext);
-        let new_path = parent.join(&new_name);
-        
-        let target_layer = format!("{}[0]", old_path_str);
-        let id_output = magick_command().args(["identify", "-format", "%x", &target_layer]).output();
-
-        let mut has_valid_dpi = false;
-        if let Ok(out) = id_output {
-            if out.status.success() {
-                let parsed_dpi: f32 = String::from_utf8_lossy(&out.stdout).trim().parse().unwrap_or(0.0);
-                if parsed_dpi > 0.0 { has_valid_dpi = true; }
+        let new_name = entry.new_name;
+        let new_path = PathBuf::from(&entry.new_path);
+
+        // 纸张配置要改像素（ICC、锐化、镜像、加深）时整图重写，顺带补齐 DPI
+        if let Some(profile) = profiles[index].clone().filter(|p| p.adjusts_pixels()) {
+            let temp_output = replace::temp_path(&new_path);
+            let req = pipeline::ProcessRequest::keep(profile, output_dpi);
+            let expected = pipeline::expected_output(old_path, &req);
+            replace::ensure_space(parent, replace::estimate_size(old_path, expected)).map_err(|e| AppError::new(ErrorCode::DiskFull, e).at(old_path))?;
+            let native = pipeline::can_process(old_path, &temp_output) && pipeline::process(old_path, &temp_output, &req).is_ok();
+            if !native {
+                let _ = std::fs::remove_file(&temp_output);
+                process_with_magick(old_path, &temp_output, &req, None).map_err(|e| e.at(old_path))?;
+            }
+            if let Err(e) = verify_output(&temp_output, expected) {
+                let _ = std::fs::remove_file(&temp_output);
+                return Err(e.at(old_path));
             }
+            // 新文件就位后才删除原图
+            op.before_write(&new_path);
+            replace::commit(&temp_output, &new_path).map_err(|e| AppError::new(ErrorCode::Io, i18n::tf("err.write_named", &[&new_name, &e])).at(old_path))?;
+            remove_original(&mut op, old_path, &new_path)?;
+            results.push((entry.path, entry.new_path, new_name));
+            continue;
         }
+        let stamp_dpi = pipeline::resolve_dpi(profiles[index].as_ref(), output_dpi);
+        
+        // TIFF/PSD 直接读标签，读不了（如 CMYK TIFF）再问 magick
+        let is_tiff = ext.to_lowercase() == "tif" || ext.to_lowercase() == "tiff";
+        let tiff_info = if is_tiff { tiff_io::read_info(old_path).ok() } else { None };
+        let psd_info = if tiff_info.is_none() && psd::is_psd(old_path) { psd::read_info(old_path).ok() } else { None };
+
+        let has_valid_dpi = if let Some(info) = &tiff_info {
+            info.dpi().is_some_and(|dpi| dpi > 0.0)
+        } else if let Some(info) = &psd_info {
+            info.dpi.is_some_and(|dpi| dpi > 0.0)
+        } else {
+            magick_io::density(old_path).is_some()
+        };
 
         if has_valid_dpi {
-            if let Err(_) = std::fs::rename(&old_path, &new_path) {
-                let _ = std::fs::copy(&old_path, &new_path); let _ = std::fs::remove_file(&old_path);
-            }
+            move_file(&mut op, old_path, &new_path)?;
         } else {
-            let mut args = vec![
-                format!("{}[0]", old_path_str), "-density".to_string(), "300".to_string(),
-                "-units".to_string(), "PixelsPerInch".to_string()
-            ];
-            if ext.to_lowercase() == "tif" || ext.to_lowercase() == "tiff" {
-                args.push("-compress".to_string()); args.push("LZW".to_string());
-            }
-            args.push(new_path.to_string_lossy().to_string());
-            let output = magick_command().args(args).output();
-            if let Ok(out) = output {
-                if out.status.success() {
-                    if old_path != new_path { let _ = std::fs::remove_file(&old_path); }
-                } else {
-                    if let Err(_) = std::fs::rename(&old_path, &new_path) {
-                        let _ = std::fs::copy(&old_path, &new_path); let _ = std::fs::remove_file(&old_path);
-                    }
-                }
+            op.before_write(&new_path);
+            if tiff_info.is_some() && tiff_io::stamp_density(old_path, &new_path, stamp_dpi).is_ok() {
+                // 原生补写 DPI，压缩方式与分辨率单位沿用原图
+                remove_original(&mut op, old_path, &new_path)?;
+            } else if psd_info.is_some() && psd::stamp_density(old_path, &new_path, stamp_dpi).is_ok() {
+                // 只改分辨率资源，图层完整保留
+                remove_original(&mut op, old_path, &new_path)?;
             } else {
-                if let Err(_) = std::fs::rename(&old_path, &new_path) {
-                    let _ = std::fs::copy(&old_path, &new_path); let _ = std::fs::remove_file(&old_path);
+                if magick_io::stamp_density(old_path, &new_path, stamp_dpi).is_ok() {
+                    remove_original(&mut op, old_path, &new_path)?;
+                } else {
+                    move_file(&mut op, old_path, &new_path)?;
                 }
             }
         }
-        results.push((old_path_str.to_string(), new_path.to_string_lossy().to_string(), new_name));
+        results.push((entry.path, entry.new_path, new_name));
     }
     Ok(results)
 }
 
+/// 成品宽高（cm）：按图内 DPI，没有时按纸张 / 输出 DPI；EXIF 方向为竖拍时交换宽高
+fn finished_size_cm(path: &Path, profile: Option<&paper::PaperProfile>, output_dpi: Option<f64>) -> Option<(f64, f64)> {
+    let (w, h, dpi) = pipeline::dimensions(path)?;
+    let dpi = dpi.filter(|d| *d > 0.0).unwrap_or_else(|| pipeline::resolve_dpi(profile, output_dpi));
+    let (w, h) = if orientation::swaps_dimensions(orientation::read(path)) { (h, w) } else { (w, h) };
+    Some((w as f64 / dpi * 2.54, h as f64 / dpi * 2.54))
+}
+
+/// 改名（跨盘时复制后删除），并记入操作日志
+fn move_file(op: &mut journal::Operation<'_>, from: &Path, to: &Path) -> AppResult<()> {
+    if replace::same_file(from, to) { return Ok(()); }
+    if std::fs::rename(from, to).is_ok() {
+        op.moved(from, to);
+        return Ok(());
+    }
+    op.before_write(to);
+    if let Err(e) = std::fs::copy(from, to) {
+        let _ = std::fs::remove_file(to);
+        return Err(AppError::io(&i18n::t("err.copy"), &e).at(from));
+    }
+    remove_original(op, from, to)
+}
+
+/// 新文件写好后删除原图；新旧是同一个文件（名字没变）时保留
+fn remove_original(op: &mut journal::Operation<'_>, old_path: &Path, new_path: &Path) -> AppResult<()> {
+    if replace::same_file(old_path, new_path) { return Ok(()); }
+    op.before_remove(old_path);
+    std::fs::remove_file(old_path).map_err(|e| AppError::io(&i18n::t("err.remove_original"), &e).at(old_path))
+}
+
 // ==========================================
-// 🌟 核心引擎 B.5：缩略图 Base64（供报价单 HTML 内嵌）
+// 🌟 纸张输出配置：读取 / 保存
 // ==========================================
 #[tauri::command]
-fn get_thumbnail_base64(path_str: String) -> Result<String, String> {
-    let target_layer = format!("{}[0]", path_str);
-    let output = magick_command()
-        .args([&target_layer, "-background", "white", "-flatten", "-resize", "120x120>", "-strip", "jpeg:-"])
-        .output().map_err(|e| format!("引擎启动失败: {}", e))?;
+fn get_paper_profiles(app: tauri::AppHandle) -> Vec<paper::PaperProfile> {
+    paper::load(&app)
+}
 
-    if output.status.success() {
-        Ok(format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(&output.stdout)))
-    } else {
-        Err("生成缩略图失败".to_string())
-    }
+#[tauri::command]
+fn save_paper_profiles(app: tauri::AppHandle, profiles: Vec<paper::PaperProfile>) -> AppResult<()> {
+    paper::save(&app, &profiles).map_err(|e| AppError::new(ErrorCode::Io, e))
 }
 
 // ==========================================
-// 🌟 核心引擎 E.5.5：HTML 转 PDF（调用系统 Chrome/Edge 无头）
+// 🌟 改名模板：预设读取 / 保存，执行前预览新文件名
 // ==========================================
-fn find_chrome_or_edge() -> Option<std::path::PathBuf> {
-    #[cfg(target_os = "macos")]
-    {
-        let candidates = [
-            "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
-            "/Applications/Chromium.app/Contents/MacOS/Chromium",
-            "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
-        ];
-        for p in &candidates {
-            if Path::new(p).exists() {
-                return Some(std::path::PathBuf::from(p));
-            }
-        }
-    }
-    #[cfg(target_os = "windows")]
-    {
-        let local = std::env::var("LOCALAPPDATA").ok();
-        let program_files = std::env::var("ProgramFiles").ok();
-        let program_files_x86 = std::env::var("ProgramFiles(x86)").ok();
-        let candidates = [
-            local.as_ref().map(|s| format!(r"{}\Google\Chrome\Application\chrome.exe", s)),
-            program_files.as_ref().map(|s| format!(r"{}\Google\Chrome\Application\chrome.exe", s)),
-            program_files_x86.as_ref().map(|s| format!(r"{}\Google\Chrome\Application\chrome.exe", s)),
-            program_files.as_ref().map(|s| format!(r"{}\Microsoft\Edge\Application\msedge.exe", s)),
-            local.as_ref().map(|s| format!(r"{}\Microsoft\Edge\Application\msedge.exe", s)),
-        ];
-        for opt in &candidates {
-            if let Some(ref p) = opt {
-                if Path::new(p).exists() {
-                    return Some(std::path::PathBuf::from(p));
-                }
-            }
-        }
-    }
-    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-    {
-        // Linux 等：可在此扩展常见路径
-        let _ = ();
+#[tauri::command]
+fn get_rename_presets(app: tauri::AppHandle) -> Vec<naming::RenamePreset> {
+    naming::load(&app)
+}
+
+#[tauri::command]
+fn save_rename_presets(app: tauri::AppHandle, presets: Vec<naming::RenamePreset>) -> AppResult<()> {
+    for preset in &presets {
+        naming::validate(&preset.template).map_err(|e| e.context(&preset.name))?;
     }
-    None
+    naming::save(&app, &presets).map_err(|e| AppError::new(ErrorCode::Io, e))
+}
+
+#[tauri::command]
+fn preview_rename(
+    app: tauri::AppHandle,
+    items: Vec<naming::RenameItem>,
+    template: naming::RenameTemplate,
+    context: naming::RenameContext,
+    output_dpi: Option<f64>,
+) -> AppResult<Vec<naming::PlannedName>> {
+    let registry = paper::load(&app);
+    naming::plan(&items, &template, &context, |item| {
+        let profile = item.paper.as_ref().and_then(|name| registry.iter().find(|p| &p.name == name));
+        finished_size_cm(Path::new(&item.path), profile, output_dpi)
+    })
 }
 
-/// 将本地 HTML 文件用系统浏览器无头打印为 PDF。未找到 Chrome/Edge 时返回 Err。
+// ==========================================
+// 🌟 核心引擎 B.5：缩略图 Base64（供报价单 HTML 内嵌）
+// ==========================================
 #[tauri::command]
-fn html_to_pdf(html_path: String, pdf_path: String) -> Result<String, String> {
-    let browser = find_chrome_or_edge().ok_or_else(|| "未找到 Chrome 或 Edge，请安装后重试".to_string())?;
+fn get_thumbnail_base64(path_str: String, thumbs: tauri::State<'_, thumbs::Thumbs>) -> AppResult<String> {
+    thumbnail_base64(&path_str, &thumbs)
+}
+
+/// 120 像素白底缩略图的 data URL，与列表缩略图共用缓存
+fn thumbnail_base64(path_str: &str, thumbs: &thumbs::Thumbs) -> AppResult<String> {
+    let path = Path::new(path_str);
+    let name = thumbs.ensure(path, 120, || render_thumbnail(path, 120))?;
+    let bytes = thumbs.read(&name).map_err(|e| AppError::new(ErrorCode::Io, e).at(path))?;
+    Ok(format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(bytes)))
+}
+
+// ==========================================
+// 🌟 核心引擎 E.5.5：HTML 转 PDF（调用系统 Chrome/Chromium/Edge 无头）
+// ==========================================
+/// 将本地 HTML 文件用系统浏览器无头打印为 PDF。未找到浏览器时返回 Err。
+#[tauri::command]
+fn html_to_pdf(html_path: String, pdf_path: String) -> AppResult<String> {
+    let browser = engine::find_browser().ok_or_else(|| AppError::new(ErrorCode::BrowserMissing, i18n::t("err.browser_missing")))?;
     let html = Path::new(&html_path);
     if !html.exists() {
-        return Err("HTML 文件不存在".to_string());
+        return Err(AppError::new(ErrorCode::FileNotFound, i18n::t("err.html_missing")).at(html));
     }
     let pdf = Path::new(&pdf_path);
     let parent = pdf.parent().unwrap_or(Path::new("."));
     let _ = std::fs::create_dir_all(parent);
 
     let file_url = {
-        let abs = std::fs::canonicalize(html).map_err(|e| format!("路径无效: {}", e))?;
+        let abs = std::fs::canonicalize(html).map_err(|e| AppError::io(&i18n::t("err.invalid_path"), &e).at(html))?;
         let s = abs.to_string_lossy();
         #[cfg(target_os = "windows")]
         let s = format!("file:///{}", s.replace('\\', "/"));
@@ -471,6 +977,7 @@ fn html_to_pdf(html_path: String, pdf_path: String) -> Result<String, String> {
 
     #[cfg(target_os = "windows")]
     use std::os::windows::process::CommandExt;
+    // 浏览器卡在加载或打印上时按超时结束，连同渲染子进程一起清理
     let mut cmd = std::process::Command::new(&browser);
     cmd.arg("--headless")
         .arg("--disable-gpu")
@@ -480,13 +987,15 @@ fn html_to_pdf(html_path: String, pdf_path: String) -> Result<String, String> {
     #[cfg(target_os = "windows")]
     cmd.creation_flags(0x08000000);
     cmd.arg(&file_url);
-    let out = cmd.output().map_err(|e| format!("启动浏览器失败: {}", e))?;
+    let out = runner::Run::new(cmd, Task::Print)
+        .spawn_error(|e| AppError::new(ErrorCode::BrowserMissing, i18n::tf("err.browser_start", &[e])))
+        .output()
+        .map_err(|e| e.at(pdf))?;
     if !out.status.success() {
-        let stderr = String::from_utf8_lossy(&out.stderr);
-        return Err(format!("打印 PDF 失败: {}", stderr));
+        return Err(AppError::engine(&i18n::t("err.print_pdf"), &out).at(pdf));
     }
     if !pdf.exists() {
-        return Err("未生成 PDF 文件".to_string());
+        return Err(AppError::new(ErrorCode::EngineFailed, i18n::t("err.pdf_missing")).at(pdf));
     }
     Ok(pdf.to_string_lossy().to_string())
 }
@@ -495,7 +1004,7 @@ fn html_to_pdf(html_path: String, pdf_path: String) -> Result<String, String> {
 // 🌟 核心引擎 E.5：报价单导出（CSV/HTML 等）
 // ==========================================
 #[tauri::command]
-fn export_file(content: String, filename: String) -> Result<String, String> {
+fn export_file(content: String, filename: String) -> AppResult<String> {
     let desktop = dirs_next::desktop_dir()
         .or_else(dirs_next::home_dir)
         .unwrap_or_else(|| std::path::PathBuf::from("."));
@@ -505,7 +1014,7 @@ fn export_file(content: String, filename: String) -> Result<String, String> {
         .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || c == ' ' { c } else { '_' })
         .collect::<String>();
     let final_name = safe_name.trim().to_string();
-    let dest = desktop.join(if final_name.is_empty() { "export".into() } else { final_name });
+    let dest = desktop.join(if final_name.is_empty() { i18n::t("file.export") } else { final_name });
 
     let bytes: Vec<u8> = if dest.extension().map(|e| e.to_string_lossy().to_lowercase()) == Some("csv".into()) {
         let mut b = vec![0xEF, 0xBB, 0xBF];
@@ -514,7 +1023,7 @@ fn export_file(content: String, filename: String) -> Result<String, String> {
     } else {
         content.into_bytes()
     };
-    std::fs::write(&dest, &bytes).map_err(|e| format!("写入文件失败: {}", e))?;
+    std::fs::write(&dest, &bytes).map_err(|e| AppError::io(&i18n::t("err.write_file"), &e).at(&dest))?;
 
     Ok(dest.to_string_lossy().to_string())
 }
@@ -523,14 +1032,16 @@ fn export_file(content: String, filename: String) -> Result<String, String> {
 // 🌟 核心引擎 E：图像多份复制裂变
 // ==========================================
 #[tauri::command]
-async fn replicate_image(path_str: String, total_copies: u32) -> Result<Vec<String>, String> {
+async fn replicate_image(path_str: String, total_copies: u32, output: Option<output::OutputOptions>, journal: tauri::State<'_, journal::Journal>) -> AppResult<Vec<String>> {
     let input_path = Path::new(&path_str);
     if !input_path.exists() || total_copies <= 1 { return Ok(vec![]); }
 
+    let strategy = output::OutputStrategy::from_options(output.as_ref()).map_err(|e| AppError::new(ErrorCode::InvalidInput, e))?;
     let stem = input_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
     let ext = input_path.extension().unwrap_or_default().to_string_lossy().to_string();
-    let parent = input_path.parent().unwrap_or(Path::new(""));
+    let parent = strategy.directory_for(input_path).map_err(|e| AppError::new(ErrorCode::Io, e))?;
 
+    let mut op = journal.begin("replicate_image");
     let mut new_paths = Vec::new();
     let mut first_path: Option<std::path::PathBuf> = None;
 
@@ -538,12 +1049,19 @@ async fn replicate_image(path_str: String, total_copies: u32) -> Result<Vec<Stri
         let new_name = format!("{}-{}-{}.{}", stem, i, total_copies, ext);
         let new_path = parent.join(&new_name);
 
-        if i == 1 {
-            std::fs::rename(input_path, &new_path).map_err(|e| e.to_string())?;
+        // 保留原图时第一份也是复制出来的
+        if i == 1 && strategy.keeps_original() {
+            op.before_write(&new_path);
+            std::fs::copy(input_path, &new_path).map_err(|e| AppError::io(&i18n::t("err.copy"), &e).at(input_path))?;
+            first_path = Some(new_path.clone());
+        } else if i == 1 {
+            std::fs::rename(input_path, &new_path).map_err(|e| AppError::io(&i18n::t("err.rename"), &e).at(input_path))?;
+            op.moved(input_path, &new_path);
             first_path = Some(new_path.clone());
         } else {
             if let Some(ref src) = first_path {
-                std::fs::copy(src, &new_path).map_err(|e| e.to_string())?;
+                op.before_write(&new_path);
+                std::fs::copy(src, &new_path).map_err(|e| AppError::io(&i18n::t("err.copy"), &e).at(src))?;
             }
         }
         new_paths.push(new_path.to_string_lossy().to_string());
@@ -551,20 +1069,115 @@ async fn replicate_image(path_str: String, total_copies: u32) -> Result<Vec<Stri
     Ok(new_paths)
 }
 
+// ==========================================
+// 🌟 撤销：上一步 / 整次会话
+// ==========================================
+#[tauri::command]
+fn undo_last(journal: tauri::State<'_, journal::Journal>) -> AppResult<journal::UndoReport> {
+    journal.undo_last().map_err(|e| AppError::new(ErrorCode::Io, e))
+}
+
+#[tauri::command]
+fn restore_session(journal: tauri::State<'_, journal::Journal>, session_id: Option<String>) -> AppResult<journal::UndoReport> {
+    journal.restore_session(session_id.as_deref()).map_err(|e| AppError::new(ErrorCode::Io, e))
+}
+
+// ==========================================
+// 🌟 界面语言：中文 / English，记在配置目录里
+// ==========================================
+#[tauri::command]
+fn get_locale() -> String {
+    i18n::current().code().to_string()
+}
+
+#[tauri::command]
+fn set_locale(app: tauri::AppHandle, locale: String) -> AppResult<String> {
+    let parsed = i18n::Locale::parse(&locale).ok_or_else(|| AppError::new(ErrorCode::InvalidInput, i18n::tf("err.unknown_locale", &[&locale])))?;
+    i18n::save(&app, parsed).map_err(|e| AppError::new(ErrorCode::Io, e))?;
+    i18n::set_current(parsed);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_title(&i18n::window_title(&app.package_info().version.to_string()));
+    }
+    Ok(parsed.code().to_string())
+}
+
+/// 前端拼报价单等 HTML 用的文案，如 prefix = "quote."
+#[tauri::command]
+fn get_messages(prefix: String) -> std::collections::HashMap<&'static str, &'static str> {
+    i18n::messages(&prefix)
+}
+
+// ==========================================
+// 🌟 引擎设置：手动指定 magick / 浏览器路径
+// ==========================================
+#[tauri::command]
+fn get_engine_settings() -> engine::EngineInfo {
+    engine::info()
+}
+
+#[tauri::command]
+fn set_engine_settings(app: tauri::AppHandle, settings: engine::EngineSettings, diagnostics: tauri::State<'_, diagnostics::Diagnostics>) -> AppResult<engine::EngineInfo> {
+    for path in [&settings.magick_path, &settings.browser_path].into_iter().flatten() {
+        let path = path.trim();
+        if !path.is_empty() && !Path::new(path).is_file() {
+            return Err(AppError::new(ErrorCode::InvalidInput, i18n::tf("err.engine_path_invalid", &[&path])).at(Path::new(path)));
+        }
+    }
+    engine::save_settings(&app, settings).map_err(|e| AppError::new(ErrorCode::Io, e))?;
+    diagnostics.invalidate();
+    Ok(engine::info())
+}
+
+// ==========================================
+// 🌟 引擎诊断：版本、格式、资源上限、浏览器；加图前判断能否处理
+// ==========================================
+#[tauri::command]
+async fn engine_status(refresh: Option<bool>, diagnostics: tauri::State<'_, diagnostics::Diagnostics>) -> AppResult<diagnostics::EngineStatus> {
+    Ok(diagnostics.status(refresh.unwrap_or(false)))
+}
+
+#[tauri::command]
+async fn check_support(paths: Vec<String>, diagnostics: tauri::State<'_, diagnostics::Diagnostics>) -> AppResult<Vec<diagnostics::FileSupport>> {
+    let status = diagnostics.status(false);
+    Ok(paths.iter().map(|path| diagnostics::support(Path::new(path), &status)).collect())
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_deep_link::init())
+        .register_asynchronous_uri_scheme_protocol(thumbs::SCHEME, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            let path = request.uri().path().to_string();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(app.state::<thumbs::Thumbs>().respond(&path));
+            });
+        })
+        // 瓦片切图可能要先解码整张大图，放到阻塞线程里，不卡 webview
+        .register_asynchronous_uri_scheme_protocol(tiles::SCHEME, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            let path = request.uri().path().to_string();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(app.state::<tiles::Tiles>().respond(&path));
+            });
+        })
         .setup(|app| {
-            let version = app.package_info().version.to_string();
-            let normalized_version = version.strip_prefix('v').unwrap_or(&version);
-            let title = format!("墨印众合-imagetool v{}", normalized_version);
+            i18n::load(app.handle());
+            engine::init(app.handle());
+            let title = i18n::window_title(&app.package_info().version.to_string());
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.set_title(&title);
             }
 
+            app.manage(journal::Journal::open(app.handle())?);
+            app.manage(batch::Batches::default());
+            app.manage(queue::Queue::open(app.handle())?);
+            app.manage(tiles::Tiles::open(app.handle(), decode_view)?);
+            app.manage(thumbs::Thumbs::open(app.handle())?);
+            app.manage(diagnostics::Diagnostics::default());
+
             // 监听从 Finder 拖图标打开文件的事件，将文件路径转发给前端
             #[cfg(target_os = "macos")]
             {
@@ -594,8 +1207,18 @@ pub fn run() {
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
-            rename_files, get_image_size, get_image_meta, generate_thumbnail, get_thumbnail_base64, process_image, replicate_image, export_file, html_to_pdf
+            rename_files, get_image_size, get_image_meta, generate_thumbnail, get_thumbnail_base64, process_image, replicate_image, export_file, html_to_pdf,
+            get_paper_profiles, save_paper_profiles, get_rename_presets, save_rename_presets, preview_rename, undo_last, restore_session, plan_process, preview_process, open_tiles, inspect_image, inspect_images, preflight,
+            process_batch, cancel_batch, get_unfinished_batches, resume_batch, rollback_batch, discard_batch,
+            get_locale, set_locale, get_messages, get_engine_settings, set_engine_settings,
+            engine_status, check_support
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            // 退出时结束仍在运行的 magick / 浏览器，不留孤儿进程
+            if let tauri::RunEvent::Exit = event {
+                runner::shutdown();
+            }
+        });
 }
\ No newline at end of file
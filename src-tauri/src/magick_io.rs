@@ -0,0 +1,423 @@
+// ==========================================
+// 🌟 magick 的输入输出：文件经 stdin / stdout 传递，命令行里不出现文件名
+// ==========================================
+// magick 会把文件名里的 `[0]`、`%d`、`png:` 前缀、开头的 `-`、通配符 `*?` 当成语法，
+// 客户发来的 `photo[final].jpg`、`100%.jpg` 之类每周都会读错或写错。
+// 单个文件一律打开后作为子进程的 stdin / stdout 交给 magick，参数里只写 `JPEG:-[0]`，
+// 格式按文件头判断，判断不了再看扩展名。整批 identify 仍按文件名传，
+// 只有“安全”的路径才进批次，并带上显式格式前缀；其余逐个走 stdin。
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::runner::{self, Task};
+use crate::{diagnostics, i18n, replace};
+
+/// magick 会特殊解释的字符
+const SPECIAL_CHARS: &[char] = &['[', ']', '%', '*', '?', '{', '}', '<', '>', ':'];
+
+/// 按文件头判断 magick 的格式名；扩展名与内容不符（把 PNG 存成 .jpg）时以内容为准
+pub(crate) fn sniff_format(path: &Path) -> Option<&'static str> {
+    let mut head = [0u8; 16];
+    let n = File::open(path).and_then(|mut f| f.read(&mut head)).ok()?;
+    let head = &head[..n];
+    let format = if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "JPEG"
+    } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "PNG"
+    } else if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+        "TIFF"
+    } else if head.starts_with(b"II+\0") || head.starts_with(b"MM\0+") {
+        // BigTIFF
+        "TIFF64"
+    } else if head.starts_with(b"8BPS\0\x01") {
+        "PSD"
+    } else if head.starts_with(b"8BPS\0\x02") {
+        "PSB"
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        "WEBP"
+    } else if head.get(4..8) == Some(b"ftyp") && head.get(8..12).is_some_and(|brand| [b"heic", b"heix", b"mif1", b"msf1"].iter().any(|b| brand == *b)) {
+        "HEIC"
+    } else {
+        return None;
+    };
+    Some(format)
+}
+
+/// 按扩展名推断格式（写出时文件还不存在）
+pub(crate) fn extension_format(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    diagnostics::magick_format(&ext)
+}
+
+fn input_format(path: &Path) -> Option<&'static str> {
+    sniff_format(path).or_else(|| extension_format(path))
+}
+
+/// 可以原样写在命令行里的路径：没有特殊字符，也不以 `-` `@` `~` 开头
+pub(crate) fn is_plain(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    // Windows 盘符里的冒号不算
+    let rest = match text.as_bytes() {
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => &text[2..],
+        _ => &text[..],
+    };
+    !text.starts_with(['-', '@', '~']) && !rest.contains(SPECIAL_CHARS) && !text.contains(['\n', '\r'])
+}
+
+/// 整批 identify 用的参数：带显式格式前缀；返回 None 表示要逐个走 stdin
+pub(crate) fn named_arg(path: &Path) -> Option<String> {
+    if !is_plain(path) {
+        return None;
+    }
+    let name = path.to_string_lossy();
+    Some(match input_format(path) {
+        Some(format) => format!("{}:{}", format, name),
+        None => name.to_string(),
+    })
+}
+
+/// 经 stdin 读入的第一帧
+#[derive(Debug)]
+pub(crate) struct Input {
+    /// 如 `JPEG:-[0]`；格式未知时为 `-[0]`，由 magick 按内容判断
+    pub arg: String,
+    pub file: File,
+}
+
+pub(crate) fn open(path: &Path) -> AppResult<Input> {
+    let file = File::open(path).map_err(|e| AppError::io(&i18n::t("err.read_file"), &e).at(path))?;
+    let arg = match input_format(path) {
+        Some(format) => format!("{}:-[0]", format),
+        None => "-[0]".to_string(),
+    };
+    Ok(Input { arg, file })
+}
+
+/// 经 stdout 写出的目标文件
+#[derive(Debug)]
+pub(crate) struct Sink {
+    /// 如 `TIFF:-`
+    pub arg: String,
+    pub file: File,
+}
+
+pub(crate) fn create(path: &Path) -> AppResult<Sink> {
+    let format = extension_format(path).ok_or_else(|| {
+        let ext = path.extension().unwrap_or_default().to_string_lossy().to_string();
+        AppError::new(ErrorCode::Unsupported, i18n::tf("support.extension", &[&ext])).at(path)
+    })?;
+    let file = File::create(path).map_err(|e| AppError::io(&i18n::t("err.write_file"), &e).at(path))?;
+    Ok(Sink { arg: format!("{}:-", format), file })
+}
+
+/// identify 第一帧，返回按 format 输出的文本
+pub(crate) fn identify(path: &Path, format: &str, cancel: Option<&AtomicBool>) -> AppResult<String> {
+    let input = open(path)?;
+    let output = runner::magick(Task::Probe, ["identify", "-format", format, input.arg.as_str()])
+        .stdin(input.file)
+        .cancel_on(cancel)
+        .output()
+        .map_err(|e| e.at(path))?;
+    if !output.status.success() {
+        return Err(AppError::engine(&i18n::t("err.size_parse"), &output).at(path));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// 白底 JPEG 缩略图（原生解码不了时用）
+pub(crate) fn thumbnail(path: &Path, max_side: u32) -> AppResult<Vec<u8>> {
+    let input = open(path)?;
+    let resize = format!("{0}x{0}>", max_side);
+    let output = runner::magick(Task::Preview, [input.arg.as_str(), "-auto-orient", "-background", "white", "-flatten", "-resize", &resize, "-strip", "JPEG:-"])
+        .stdin(input.file)
+        .output()
+        .map_err(|e| e.at(path))?;
+    if !output.status.success() {
+        return Err(AppError::engine(&i18n::t("err.preview"), &output).at(path));
+    }
+    Ok(output.stdout)
+}
+
+/// 读 input 第一帧，依次执行 ops，写到 output；失败时删掉写了一半的 output
+pub(crate) fn convert(input_path: &Path, ops: &[String], output_path: &Path, task: Task, cancel: Option<&AtomicBool>) -> AppResult<()> {
+    let input = open(input_path)?;
+    let sink = create(output_path)?;
+    let mut args = Vec::with_capacity(ops.len() + 2);
+    args.push(input.arg.clone());
+    args.extend_from_slice(ops);
+    args.push(sink.arg.clone());
+    let result = runner::magick(task, args).stdin(input.file).stdout(sink.file).cancel_on(cancel).output();
+    match result {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => {
+            let _ = std::fs::remove_file(output_path);
+            Err(AppError::engine(&i18n::t("err.engine_error"), &output).at(input_path))
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(output_path);
+            Err(e.at(input_path))
+        }
+    }
+}
+
+/// 文件里记录的 DPI（没有时为 None）
+pub(crate) fn density(path: &Path) -> Option<f64> {
+    identify(path, "%x", None).ok()?.trim().parse::<f64>().ok().filter(|dpi| *dpi > 0.0)
+}
+
+/// 复制一份并写入 DPI，像素不变；from 与 to 相同时先写临时文件再替换
+pub(crate) fn stamp_density(from: &Path, to: &Path, dpi: f64) -> AppResult<()> {
+    let ops = ["-density".to_string(), format!("{}", dpi), "-units".to_string(), "PixelsPerInch".to_string()];
+    let temp = replace::temp_path(to);
+    convert(from, &ops, &temp, Task::Process, None)?;
+    std::fs::rename(&temp, to).map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        AppError::io(&i18n::t("err.write_file"), &e).at(to)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline;
+    use crate::thumbs;
+    use std::path::PathBuf;
+
+    /// 客户文件里出现过的名字，外加 magick 会当成语法的各种写法
+    const CORPUS: &[&str] = &[
+        "photo[final].jpg",
+        "scan[0].png",
+        "100%.jpg",
+        "frame_%03d.png",
+        "%[filename:x].tif",
+        "png:trap.jpg",
+        "-density.jpg",
+        "@list.png",
+        "~home.jpg",
+        "star*and?.png",
+        "{brace}.tif",
+        "a<b>c.jpg",
+        "客户 原图 (2).jpg",
+        "semi;colon&amp #1.png",
+    ];
+
+    /// 测试用临时目录，名字里也带特殊字符
+    fn corpus_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("magick_io [{}] %{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 按扩展名写一张 40x30 的小图
+    fn write_corpus(dir: &Path) -> Vec<PathBuf> {
+        let image = image::RgbImage::from_fn(40, 30, |x, y| image::Rgb([x as u8 * 6, y as u8 * 8, 128]));
+        CORPUS
+            .iter()
+            // Windows 不允许 < > * ? :
+            .filter(|name| !cfg!(windows) || !name.contains(['<', '>', '*', '?', ':']))
+            .map(|name| {
+                let path = dir.join(name);
+                image.save(&path).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    fn percent_decode(text: &str) -> String {
+        let bytes = text.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                out.push(u8::from_str_radix(&text[i + 1..i + 3], 16).unwrap());
+                i += 3;
+            } else {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn file_names_never_reach_the_command_line() {
+        let dir = corpus_dir("args");
+        for path in write_corpus(&dir) {
+            let input = open(&path).unwrap();
+            let expected = match path.extension().unwrap().to_str().unwrap() {
+                "jpg" => "JPEG:-[0]",
+                "png" => "PNG:-[0]",
+                _ => "TIFF:-[0]",
+            };
+            assert_eq!(input.arg, expected, "{}", path.display());
+            // 目录名里也有特殊字符，整批 identify 不能带上它
+            assert_eq!(named_arg(&path), None);
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn format_follows_content_over_extension() {
+        let dir = corpus_dir("sniff");
+        let path = dir.join("really_png.jpg");
+        image::RgbImage::new(4, 4).save_with_format(&path, image::ImageFormat::Png).unwrap();
+        assert_eq!(sniff_format(&path), Some("PNG"));
+        assert_eq!(open(&path).unwrap().arg, "PNG:-[0]");
+        // 读不出文件头时退回扩展名
+        let empty = dir.join("empty.tif");
+        std::fs::write(&empty, b"").unwrap();
+        assert_eq!(open(&empty).unwrap().arg, "TIFF:-[0]");
+        let unknown = dir.join("unknown.xyz");
+        std::fs::write(&unknown, b"????").unwrap();
+        assert_eq!(open(&unknown).unwrap().arg, "-[0]");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn plain_paths_keep_batch_identify() {
+        let plain = Path::new("/orders/2024/photo_final.jpg");
+        assert!(is_plain(plain));
+        assert!(is_plain(Path::new(r"C:\orders\photo.jpg")));
+        assert!(!is_plain(Path::new(r"C:\orders\png:photo.jpg")));
+        assert!(!is_plain(Path::new("-rf.jpg")));
+        // 文件不存在时按扩展名带格式
+        assert_eq!(named_arg(plain).as_deref(), Some("JPEG:/orders/2024/photo_final.jpg"));
+    }
+
+    #[test]
+    fn output_format_comes_from_the_extension() {
+        let dir = corpus_dir("sink");
+        assert_eq!(create(&dir.join(".photo[final].1-2.tmp.tif")).unwrap().arg, "TIFF:-");
+        assert_eq!(create(&dir.join("png:x.jpg")).unwrap().arg, "JPEG:-");
+        assert_eq!(create(&dir.join("x.exe")).unwrap_err().code, ErrorCode::Unsupported);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// 不改几何的排版参数
+    fn keep_request() -> pipeline::ProcessRequest {
+        pipeline::ProcessRequest {
+            mode: "keep".to_string(),
+            target_w_cm: 0.0,
+            target_h_cm: 0.0,
+            crop_x: 0.0,
+            crop_y: 0.0,
+            crop_w: 100.0,
+            crop_h: 100.0,
+            border_top_cm: 0.0,
+            border_right_cm: 0.0,
+            border_bottom_cm: 0.0,
+            border_left_cm: 0.0,
+            color_policy: crate::color::ColorPolicy::Keep,
+            paper: None,
+            default_dpi: Some(300.0),
+        }
+    }
+
+    /// generate_thumbnail：转正的 JPEG/PNG 直接引用原图，TIFF 原生生成并落盘缓存；
+    /// get_thumbnail_base64 一律走缓存
+    #[test]
+    fn generate_thumbnail_handles_awkward_names() {
+        let dir = corpus_dir("thumbs");
+        let cache = thumbs::Thumbs::open_at(dir.join("cache")).unwrap();
+        for path in write_corpus(&dir) {
+            let url = crate::thumbnail_url(&path.to_string_lossy(), &cache).unwrap();
+            let (_, encoded) = url.split_once("localhost/").unwrap();
+            assert!(!encoded.contains(['[', ']', '#', '?', ' ', '/', '\\']), "{}", url);
+            if url.contains("asset") {
+                assert_eq!(percent_decode(encoded), path.to_string_lossy(), "{}", url);
+            } else {
+                assert!(cache.read(encoded).unwrap().starts_with(&[0xFF, 0xD8]), "{}", path.display());
+            }
+            // get_thumbnail_base64（报价单内嵌）：FF D8 FF 的 base64 是 /9j/
+            let data = crate::thumbnail_base64(&path.to_string_lossy(), &cache).unwrap();
+            assert!(data.starts_with("data:image/jpeg;base64,/9j/"), "{}", path.display());
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// generate_thumbnail 的 magick 兜底（原生解码不了的文件走这里）
+    #[test]
+    #[ignore = "需要安装 magick"]
+    fn magick_thumbnail_handles_awkward_names() {
+        let dir = corpus_dir("magick thumbs");
+        for path in write_corpus(&dir) {
+            let jpeg = thumbnail(&path, 20).unwrap();
+            assert!(jpeg.starts_with(&[0xFF, 0xD8]), "{}", path.display());
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// get_image_meta：尺寸与 DPI 经 magick identify 读取
+    #[test]
+    #[ignore = "需要安装 magick"]
+    fn get_image_meta_reads_awkward_names() {
+        let dir = corpus_dir("meta");
+        for path in write_corpus(&dir) {
+            let meta = crate::image_meta(&path, 300.0).unwrap();
+            assert_eq!((meta.width_px, meta.height_px), (40, 30), "{}", path.display());
+
+            let stamped = dir.join(format!("stamped {}", path.file_name().unwrap().to_string_lossy()));
+            stamp_density(&path, &stamped, 300.0).unwrap();
+            assert_eq!(density(&stamped).map(|d| d.round()), Some(300.0), "{}", stamped.display());
+            // 不改名时原地补写
+            stamp_density(&stamped, &stamped, 150.0).unwrap();
+            assert_eq!(density(&stamped).map(|d| d.round()), Some(150.0), "{}", stamped.display());
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// rename_files：按模板改名，读 DPI、补写 DPI 与删除原图都不经过文件名
+    #[test]
+    #[ignore = "需要安装 magick"]
+    fn rename_files_handles_awkward_names() {
+        let dir = corpus_dir("rename");
+        let journal = crate::journal::Journal::open_at(dir.join("journal")).unwrap();
+        let paths = write_corpus(&dir);
+        let items: Vec<_> = paths
+            .iter()
+            .map(|path| crate::naming::RenameItem { path: path.to_string_lossy().to_string(), paper: None, craft: None, copies: None })
+            .collect();
+        let template = crate::naming::RenameTemplate { pattern: "{stem} 改名".to_string(), ..Default::default() };
+        let renamed = crate::rename_with(&[], &journal, &items, &template, &Default::default(), Some(300.0)).unwrap();
+        assert_eq!(renamed.len(), paths.len());
+        for (old, new, _) in renamed {
+            assert!(!Path::new(&old).exists(), "{}", old);
+            assert_eq!(identify(Path::new(&new), "%w %h", None).unwrap().trim(), "40 30", "{}", new);
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// process_image：原生管线读写怪名字并通过落盘校验
+    #[test]
+    fn process_image_writes_awkward_names() {
+        let dir = corpus_dir("process");
+        let req = keep_request();
+        for path in write_corpus(&dir) {
+            let out = dir.join(format!("native {}", path.file_name().unwrap().to_string_lossy()));
+            assert!(pipeline::can_process(&path, &out), "{}", path.display());
+            pipeline::process(&path, &out, &req).unwrap();
+            crate::verify_output(&out, pipeline::expected_output(&path, &req)).unwrap();
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// process_image 的 magick 兜底：排版参数与输入输出都不经过文件名
+    #[test]
+    #[ignore = "需要安装 magick"]
+    fn process_with_magick_writes_awkward_names() {
+        let dir = corpus_dir("magick process");
+        let req = keep_request();
+        for path in write_corpus(&dir) {
+            let out = dir.join(format!("magick {}", path.file_name().unwrap().to_string_lossy()));
+            crate::process_with_magick(&path, &out, &req, None).unwrap();
+            crate::verify_output(&out, pipeline::expected_output(&path, &req)).unwrap();
+            assert_eq!(identify(&out, "%w %h", None).unwrap().trim(), "40 30");
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
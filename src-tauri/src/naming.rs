@@ -0,0 +1,646 @@
+// ==========================================
+// 🌟 改名模板：各家冲印厂的命名规则不同，按模板拼文件名
+// ==========================================
+// 模板里用花括号写字段，其余原样保留，`{{` / `}}` 表示花括号本身：
+//   {paper} 纸张  {craft} 工艺  {index} 序号  {stem} 原文件名（不含后缀）
+//   {size} 成品尺寸 30x40  {w} / {h} 宽 / 高（cm）
+//   {order} 订单号  {customer} 客户  {date} 日期 YYYYMMDD
+//   {copy} / {copies} 第几份 / 共几份（复制出的副本按 名称-i-n 识别）
+// 字段后加 `:N`：序号、份数补零到 N 位，文字字段最多保留 N 个字符，如 {index:3}、{customer:6}。
+// 某个字段为空时连带去掉它后面紧跟的分隔符，不会出现 “A--1”。
+// 后缀沿用原图。预设保存在应用配置目录的 rename_presets.json。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::i18n;
+
+const PRESETS_FILE: &str = "rename_presets.json";
+
+/// 文件名（含后缀）的字节上限，留出余量给临时文件名
+const MAX_NAME_BYTES: usize = 240;
+
+/// 字段之间常用的分隔符
+const SEPARATORS: &[char] = &['-', '_', ' ', '.'];
+
+/// Windows 不允许的文件名字符，统一替换成下划线
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Windows 保留的设备名
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 大小写规则
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CaseRule {
+    #[default]
+    Keep,
+    Lower,
+    Upper,
+}
+
+/// 命名模板
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct RenameTemplate {
+    pub pattern: String,
+    /// 第一张的序号
+    pub start: u32,
+    /// 序号补零位数（`{index:N}` 优先）
+    pub pad: usize,
+    pub case: CaseRule,
+    /// 后缀统一转小写
+    pub lower_ext: bool,
+    /// 文件名（不含后缀）最多字符数，0 为不限
+    pub max_len: usize,
+}
+
+impl Default for RenameTemplate {
+    fn default() -> Self {
+        RenameTemplate {
+            pattern: "{paper}-{craft}-{index}".to_string(),
+            start: 1,
+            pad: 0,
+            case: CaseRule::Keep,
+            lower_ext: false,
+            max_len: 0,
+        }
+    }
+}
+
+/// 保存的命名预设
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RenamePreset {
+    pub name: String,
+    pub template: RenameTemplate,
+}
+
+/// 整批共用的字段
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct RenameContext {
+    pub order_no: String,
+    pub customer: String,
+    /// 前端按本地时间给出；留空取当天（UTC）
+    pub date: String,
+}
+
+/// 待改名的一项
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RenameItem {
+    pub path: String,
+    #[serde(default)]
+    pub paper: Option<String>,
+    #[serde(default)]
+    pub craft: Option<String>,
+    /// 不是复制出的副本时，按这个份数填 {copies}
+    #[serde(default)]
+    pub copies: Option<u32>,
+}
+
+/// 一项的改名结果
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlannedName {
+    pub path: String,
+    pub new_path: String,
+    pub new_name: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    Paper,
+    Craft,
+    Index,
+    Stem,
+    Size,
+    Width,
+    Height,
+    Order,
+    Customer,
+    Date,
+    Copy,
+    Copies,
+}
+
+impl Token {
+    fn parse(name: &str) -> Option<Token> {
+        Some(match name {
+            "paper" => Token::Paper,
+            "craft" => Token::Craft,
+            "index" => Token::Index,
+            "stem" => Token::Stem,
+            "size" => Token::Size,
+            "w" => Token::Width,
+            "h" => Token::Height,
+            "order" => Token::Order,
+            "customer" => Token::Customer,
+            "date" => Token::Date,
+            "copy" => Token::Copy,
+            "copies" => Token::Copies,
+            _ => return None,
+        })
+    }
+
+    fn numeric(self) -> bool {
+        matches!(self, Token::Index | Token::Copy | Token::Copies)
+    }
+
+    /// 超长时可以截短的字段；序号、日期、尺寸截了会重名或失去意义
+    fn shrinkable(self) -> bool {
+        matches!(self, Token::Paper | Token::Craft | Token::Stem | Token::Order | Token::Customer)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    Field(Token, Option<usize>),
+}
+
+fn parse(pattern: &str) -> AppResult<Vec<Part>> {
+    if pattern.trim().is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidInput, i18n::t("err.template_empty")));
+    }
+    let brace = || AppError::new(ErrorCode::InvalidInput, i18n::t("err.template_brace"));
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => { chars.next(); text.push('{'); }
+            '}' if chars.peek() == Some(&'}') => { chars.next(); text.push('}'); }
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') | None => return Err(brace()),
+                        Some(c) => field.push(c),
+                    }
+                }
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                parts.push(parse_field(&field)?);
+            }
+            '}' => return Err(brace()),
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    Ok(parts)
+}
+
+fn parse_field(field: &str) -> AppResult<Part> {
+    let unknown = || AppError::new(ErrorCode::InvalidInput, i18n::tf("err.template_token", &[&format!("{{{}}}", field)]));
+    let (name, width) = match field.split_once(':') {
+        Some((name, width)) => (name, Some(width.trim().parse::<usize>().map_err(|_| unknown())?)),
+        None => (field, None),
+    };
+    let token = Token::parse(name.trim()).ok_or_else(unknown)?;
+    Ok(Part::Field(token, width))
+}
+
+/// 检查模板能否解析（保存预设前调用）
+pub(crate) fn validate(template: &RenameTemplate) -> AppResult<()> {
+    parse(&template.pattern).map(|_| ())
+}
+
+/// 替换非法字符与控制字符
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() || ILLEGAL_CHARS.contains(&c) { '_' } else { c })
+        .collect()
+}
+
+fn apply_case(text: &str, case: CaseRule) -> String {
+    match case {
+        CaseRule::Keep => text.to_string(),
+        CaseRule::Lower => text.to_lowercase(),
+        CaseRule::Upper => text.to_uppercase(),
+    }
+}
+
+/// 厘米保留一位小数，整数不带 .0
+fn format_cm(value: f64) -> String {
+    let rounded = (value * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 { format!("{}", rounded as i64) } else { format!("{:.1}", rounded) }
+}
+
+/// 复制出的副本命名为 名称-i-n，从中取回第几份 / 共几份
+pub(crate) fn copy_of_total(stem: &str) -> Option<(u32, u32)> {
+    let mut tail = stem.rsplitn(3, '-');
+    let total: u32 = tail.next()?.parse().ok()?;
+    let copy: u32 = tail.next()?.parse().ok()?;
+    tail.next().filter(|rest| !rest.is_empty())?;
+    (copy >= 1 && copy <= total).then_some((copy, total))
+}
+
+/// 当天日期 YYYYMMDD（UTC）
+fn today() -> String {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    // 按公历从 1970-01-01 起的天数换算年月日
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}{:02}{:02}", year, month, day)
+}
+
+/// 单个文件的字段取值
+pub(crate) struct Fields<'a> {
+    pub paper: &'a str,
+    pub craft: &'a str,
+    pub stem: &'a str,
+    /// 成品宽高（cm），读不到尺寸时为空
+    pub size_cm: Option<(f64, f64)>,
+    pub index: u32,
+    pub copy: u32,
+    pub copies: u32,
+}
+
+/// 一段渲染结果；shrinkable 表示超长时可以截短
+struct Piece {
+    text: String,
+    field: bool,
+    shrinkable: bool,
+}
+
+fn render_field(token: Token, width: Option<usize>, template: &RenameTemplate, fields: &Fields, context: &RenameContext, date: &str) -> String {
+    let number = |value: u32, pad: usize| format!("{:0pad$}", value, pad = pad);
+    let value = match token {
+        Token::Paper => fields.paper.to_string(),
+        Token::Craft => fields.craft.to_string(),
+        Token::Index => return number(fields.index, width.unwrap_or(template.pad)),
+        Token::Stem => fields.stem.to_string(),
+        Token::Size => fields.size_cm.map(|(w, h)| format!("{}x{}", format_cm(w), format_cm(h))).unwrap_or_default(),
+        Token::Width => fields.size_cm.map(|(w, _)| format_cm(w)).unwrap_or_default(),
+        Token::Height => fields.size_cm.map(|(_, h)| format_cm(h)).unwrap_or_default(),
+        Token::Order => context.order_no.trim().to_string(),
+        Token::Customer => context.customer.trim().to_string(),
+        Token::Date => date.to_string(),
+        Token::Copy => return number(fields.copy, width.unwrap_or(0)),
+        Token::Copies => return number(fields.copies, width.unwrap_or(0)),
+    };
+    match width {
+        Some(max) if !token.numeric() => value.chars().take(max).collect(),
+        _ => value,
+    }
+}
+
+/// 拼接各段：空字段后紧跟的分隔符一并去掉，首尾不留分隔符
+fn assemble(pieces: &[Piece]) -> String {
+    let mut out = String::new();
+    let mut after_empty = false;
+    for piece in pieces {
+        if piece.field {
+            out.push_str(&piece.text);
+            after_empty = piece.text.is_empty();
+            continue;
+        }
+        let text = if after_empty && (out.is_empty() || out.ends_with(SEPARATORS)) {
+            piece.text.trim_start_matches(SEPARATORS)
+        } else {
+            piece.text.as_str()
+        };
+        out.push_str(text);
+        after_empty = false;
+    }
+    out.trim_matches(SEPARATORS).to_string()
+}
+
+/// 按模板生成文件名（含后缀）
+fn render(parts: &[Part], template: &RenameTemplate, fields: &Fields, context: &RenameContext, date: &str, ext: &str) -> AppResult<String> {
+    let mut pieces: Vec<Piece> = parts.iter().map(|part| {
+        let (text, field, shrinkable) = match part {
+            Part::Text(text) => (text.clone(), false, false),
+            Part::Field(token, width) => (render_field(*token, *width, template, fields, context, date), true, token.shrinkable()),
+        };
+        Piece { text: apply_case(&sanitize(&text), template.case), field, shrinkable }
+    }).collect();
+
+    let ext = if ext.is_empty() {
+        String::new()
+    } else if template.lower_ext {
+        format!(".{}", sanitize(ext).to_lowercase())
+    } else {
+        format!(".{}", sanitize(ext))
+    };
+    let too_long = |name: &str| {
+        (template.max_len > 0 && name.chars().count() > template.max_len) || name.len() + ext.len() > MAX_NAME_BYTES
+    };
+
+    // 超长时从最长的文字字段末尾逐字截短，序号等字段保持完整
+    let mut name = assemble(&pieces);
+    while too_long(&name) {
+        let longest = pieces.iter_mut()
+            .filter(|p| p.shrinkable && !p.text.is_empty())
+            .max_by_key(|p| p.text.chars().count());
+        match longest {
+            Some(piece) => {
+                piece.text.pop();
+                let kept = piece.text.trim_end_matches(SEPARATORS).len();
+                piece.text.truncate(kept);
+            }
+            None => break,
+        }
+        name = assemble(&pieces);
+    }
+    // 文字字段都截完仍超长时直接截断
+    while too_long(&name) && name.pop().is_some() {}
+
+    // Windows 不接受末尾的点和空格，也不能用设备名
+    let mut name = name.trim_end_matches(['.', ' ']).to_string();
+    let device = name.split('.').next().unwrap_or("").to_uppercase();
+    if RESERVED_NAMES.contains(&device.as_str()) {
+        name.insert(0, '_');
+    }
+    if name.is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidInput, i18n::t("err.name_empty")));
+    }
+    Ok(format!("{}{}", name, ext))
+}
+
+/// 比较路径用的键：Windows 与 macOS 默认的文件系统不区分大小写，A.jpg 与 a.jpg 是同一个文件；
+/// 其他系统按原样比较
+pub(crate) fn path_key(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    if cfg!(any(windows, target_os = "macos")) { text.to_lowercase() } else { text.into_owned() }
+}
+
+/// 为整批文件生成新名字并检查冲突；不改动任何文件。
+/// size_cm 给出每个文件的成品宽高（cm）
+pub(crate) fn plan(
+    items: &[RenameItem],
+    template: &RenameTemplate,
+    context: &RenameContext,
+    size_cm: impl Fn(&RenameItem) -> Option<(f64, f64)>,
+) -> AppResult<Vec<PlannedName>> {
+    let parts = parse(&template.pattern)?;
+    let date = Some(context.date.trim()).filter(|d| !d.is_empty()).map(sanitize).unwrap_or_else(today);
+    let sources: HashSet<String> = items.iter().map(|item| path_key(Path::new(&item.path))).collect();
+
+    let mut planned = Vec::with_capacity(items.len());
+    let mut targets = HashSet::new();
+    for (position, item) in items.iter().enumerate() {
+        if item.path.trim().is_empty() {
+            return Err(AppError::new(ErrorCode::InvalidInput, i18n::tf("err.rename_no_path", &[&(position + 1)])));
+        }
+        let path = Path::new(&item.path);
+        if !path.is_file() {
+            return Err(AppError::not_found(path));
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        let (copy, copies) = copy_of_total(&stem).unwrap_or((1, item.copies.unwrap_or(1).max(1)));
+        let fields = Fields {
+            paper: item.paper.as_deref().unwrap_or(""),
+            craft: item.craft.as_deref().unwrap_or(""),
+            stem: &stem,
+            size_cm: size_cm(item),
+            index: template.start.saturating_add(position as u32),
+            copy,
+            copies,
+        };
+        let new_name = render(&parts, template, &fields, context, &date, &ext).map_err(|e| e.at(path))?;
+        let new_path: PathBuf = path.parent().unwrap_or(Path::new("")).join(&new_name);
+
+        let target = path_key(&new_path);
+        if !targets.insert(target.clone()) {
+            return Err(AppError::new(ErrorCode::InvalidInput, i18n::tf("err.rename_duplicate", &[&new_name])).at(path));
+        }
+        // 已存在的文件只允许是本批里要改走的原图
+        if new_path.exists() && !sources.contains(&target) {
+            return Err(AppError::new(ErrorCode::InvalidInput, i18n::tf("err.rename_exists", &[&new_name])).at(path));
+        }
+        planned.push(PlannedName {
+            path: item.path.clone(),
+            new_path: new_path.to_string_lossy().to_string(),
+            new_name,
+        });
+    }
+    Ok(planned)
+}
+
+// ==========================================
+// 🌟 命名预设：读取 / 保存
+// ==========================================
+fn builtin() -> Vec<RenamePreset> {
+    vec![RenamePreset { name: i18n::t("rename.default_preset"), template: RenameTemplate::default() }]
+}
+
+fn presets_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("{}: {}", i18n::t("err.save_rename_presets"), e))?;
+    Ok(dir.join(PRESETS_FILE))
+}
+
+/// 没有保存过时给出内置预设
+pub(crate) fn load(app: &AppHandle) -> Vec<RenamePreset> {
+    presets_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str::<Vec<RenamePreset>>(&text).ok())
+        .filter(|saved| !saved.is_empty())
+        .unwrap_or_else(builtin)
+}
+
+pub(crate) fn save(app: &AppHandle, presets: &[RenamePreset]) -> Result<(), String> {
+    let path = presets_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", i18n::t("err.save_rename_presets"), e))?;
+    }
+    let text = serde_json::to_string_pretty(presets).map_err(|e| format!("{}: {}", i18n::t("err.save_rename_presets"), e))?;
+    std::fs::write(&path, text).map_err(|e| format!("{}: {}", i18n::t("err.save_rename_presets"), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(pattern: &str) -> RenameTemplate {
+        RenameTemplate { pattern: pattern.to_string(), ..Default::default() }
+    }
+
+    fn fields(stem: &str) -> Fields<'_> {
+        Fields { paper: "210蚀刻", craft: "做框", stem, size_cm: Some((29.7, 42.0)), index: 1, copy: 1, copies: 1 }
+    }
+
+    fn context() -> RenameContext {
+        RenameContext { order_no: "A001".to_string(), customer: "张三".to_string(), date: "20260101".to_string() }
+    }
+
+    fn name(template: &RenameTemplate, fields: &Fields, ext: &str) -> String {
+        let parts = parse(&template.pattern).unwrap();
+        render(&parts, template, fields, &context(), "20260101", ext).unwrap()
+    }
+
+    /// 测试用临时目录，放几张空文件
+    fn dir_with(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("naming-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in files {
+            std::fs::write(dir.join(file), b"x").unwrap();
+        }
+        dir
+    }
+
+    fn items(dir: &Path, files: &[&str]) -> Vec<RenameItem> {
+        files.iter().map(|file| RenameItem {
+            path: dir.join(file).to_string_lossy().to_string(),
+            paper: Some("210蚀刻".to_string()),
+            craft: Some("做框".to_string()),
+            copies: None,
+        }).collect()
+    }
+
+    #[test]
+    fn index_is_padded_and_offset_by_start() {
+        let dir = dir_with("index", &["a.jpg", "b.jpg", "c.jpg"]);
+        let mut t = template("{paper}-{index:3}");
+        t.start = 9;
+        let planned = plan(&items(&dir, &["a.jpg", "b.jpg", "c.jpg"]), &t, &context(), |_| None).unwrap();
+        let names: Vec<_> = planned.iter().map(|p| p.new_name.as_str()).collect();
+        assert_eq!(names, ["210蚀刻-009.jpg", "210蚀刻-010.jpg", "210蚀刻-011.jpg"]);
+
+        // 没写 :N 时按模板的补零位数
+        let mut t = template("{index}");
+        t.pad = 2;
+        assert_eq!(name(&t, &fields("x"), "tif"), "01.tif");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn braces_escape_and_unbalanced_braces_are_rejected() {
+        assert_eq!(name(&template("{{{paper}}}-{{x}}"), &fields("x"), "jpg"), "{210蚀刻}-{x}.jpg");
+        for bad in ["{index", "a}", "{a{b}}", "{bogus}", "{index:x}", "  "] {
+            assert_eq!(parse(bad).unwrap_err().code, ErrorCode::InvalidInput, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn fields_cover_size_context_and_copies() {
+        let mut f = fields("IMG_01");
+        f.copy = 2;
+        f.copies = 3;
+        let t = template("{order}_{customer}_{size}cm_{w}_{h}_{date}_{stem}_{copy}of{copies}");
+        assert_eq!(name(&t, &f, "jpg"), "A001_张三_29.7x42cm_29.7_42_20260101_IMG_01_2of3.jpg");
+        assert_eq!(copy_of_total("IMG-2-3"), Some((2, 3)));
+        assert_eq!(copy_of_total("IMG-4-3"), None);
+        assert_eq!(copy_of_total("2-3"), None);
+    }
+
+    #[test]
+    fn empty_field_drops_its_separator() {
+        let mut ctx = context();
+        ctx.order_no.clear();
+        let t = template("{order}-{paper}-{order}-{index}");
+        let parts = parse(&t.pattern).unwrap();
+        assert_eq!(render(&parts, &t, &fields("x"), &ctx, "20260101", "jpg").unwrap(), "210蚀刻-1.jpg");
+        let t = template("{order}");
+        let parts = parse(&t.pattern).unwrap();
+        assert_eq!(render(&parts, &t, &fields("x"), &ctx, "20260101", "jpg").unwrap_err().code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn illegal_characters_and_reserved_names_are_sanitized() {
+        assert_eq!(name(&template("{stem}"), &fields("a/b:c*d?\"e|f"), "jpg"), "a_b_c_d__e_f.jpg");
+        assert_eq!(name(&template("{stem}"), &fields("con"), "png"), "_con.png");
+        assert_eq!(name(&template("{stem}"), &fields("LPT1.draft"), "png"), "_LPT1.draft.png");
+        assert_eq!(name(&template("{stem}"), &fields("console"), "png"), "console.png");
+        // Windows 不接受末尾的点和空格
+        assert_eq!(name(&template("{stem}."), &fields("end "), "jpg"), "end.jpg");
+    }
+
+    #[test]
+    fn max_len_cuts_multibyte_text_and_keeps_index() {
+        let mut t = template("{stem}-{index:2}");
+        t.max_len = 6;
+        // 按字符计数，中文不会被截成半个字
+        assert_eq!(name(&t, &fields("客户原图照片"), "jpg"), "客户原-01.jpg");
+        // 字节上限：全是三字节汉字时总长不超过 MAX_NAME_BYTES
+        let long = "图".repeat(200);
+        let t = template("{stem}-{index}");
+        let result = name(&t, &fields(&long), "jpg");
+        assert!(result.len() <= MAX_NAME_BYTES, "{}", result.len());
+        assert!(result.ends_with("图-1.jpg"));
+    }
+
+    #[test]
+    fn case_rules_apply_to_name_and_optionally_extension() {
+        let mut t = template("{stem}-{size}");
+        t.case = CaseRule::Upper;
+        assert_eq!(name(&t, &fields("Photo"), "jpg"), "PHOTO-29.7X42.jpg");
+        t.case = CaseRule::Lower;
+        t.lower_ext = true;
+        assert_eq!(name(&t, &fields("Photo"), "JPG"), "photo-29.7x42.jpg");
+        t.case = CaseRule::Keep;
+        t.lower_ext = false;
+        assert_eq!(name(&t, &fields("Photo"), "JPG"), "Photo-29.7x42.JPG");
+    }
+
+    #[test]
+    fn duplicate_targets_are_rejected() {
+        let dir = dir_with("duplicate", &["a.jpg", "b.jpg"]);
+        let err = plan(&items(&dir, &["a.jpg", "b.jpg"]), &template("{paper}"), &context(), |_| None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert_eq!(err.path.as_deref(), Some(dir.join("b.jpg").to_string_lossy().as_ref()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn existing_targets_are_rejected_unless_renamed_away() {
+        // 1.jpg 不在本批里：不能覆盖
+        let dir = dir_with("exists", &["a.jpg", "1.jpg"]);
+        let err = plan(&items(&dir, &["a.jpg"]), &template("{index}"), &context(), |_| None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+
+        // 1.jpg 本身也在改名：允许（执行时先挪到临时名）
+        let planned = plan(&items(&dir, &["a.jpg", "1.jpg"]), &template("{index}"), &context(), |_| None).unwrap();
+        assert_eq!(planned[0].new_name, "1.jpg");
+        assert_eq!(planned[1].new_name, "2.jpg");
+
+        // 缺文件直接报错，不再悄悄跳过
+        let err = plan(&items(&dir, &["missing.jpg"]), &template("{index}"), &context(), |_| None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::FileNotFound);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn case_folding_follows_the_platform() {
+        let dir = dir_with("case", &["A.jpg", "b.jpg"]);
+        if cfg!(any(windows, target_os = "macos")) {
+            // 不区分大小写：a.jpg 就是已有的 A.jpg
+            let result = plan(&items(&dir, &["b.jpg"]), &template("a"), &context(), |_| None);
+            assert_eq!(result.unwrap_err().code, ErrorCode::InvalidInput);
+        } else {
+            // 区分大小写：A.jpg 已存在不能覆盖，a.jpg 是另一个文件
+            let result = plan(&items(&dir, &["b.jpg"]), &template("A"), &context(), |_| None);
+            assert_eq!(result.unwrap_err().code, ErrorCode::InvalidInput);
+            let planned = plan(&items(&dir, &["b.jpg"]), &template("a"), &context(), |_| None).unwrap();
+            assert_eq!(planned[0].new_name, "a.jpg");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
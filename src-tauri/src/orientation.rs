@@ -0,0 +1,80 @@
+// ==========================================
+// 🌟 EXIF 方向校正：手机竖拍照片先转正，再做裁切计算
+// ==========================================
+// 前端传来的裁切百分比与显示尺寸都以“转正后”的图像为准；
+// 写出的文件保留原 EXIF，但 Orientation 改写为 1，避免下游软件二次旋转。
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use image::DynamicImage;
+
+/// 读取 EXIF Orientation（1..=8），读不到或非法时按 1 处理
+pub(crate) fn read(path: &Path) -> u16 {
+    let Ok(file) = File::open(path) else { return 1 };
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut BufReader::new(file)) else { return 1 };
+    exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .filter(|v| (1..=8).contains(v))
+        .map(|v| v as u16)
+        .unwrap_or(1)
+}
+
+/// 读取原始 EXIF（TIFF 结构，不含 `Exif\0\0` 前缀），并把 Orientation 改写为 1
+pub(crate) fn read_exif(path: &Path) -> Option<Vec<u8>> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new().read_from_container(&mut BufReader::new(file)).ok()?;
+    let mut raw = exif.buf().to_vec();
+    reset_orientation(&mut raw);
+    Some(raw)
+}
+
+/// IFD0 条目：(标签, 类型, 条目偏移)
+pub(crate) type IfdEntry = (u16, u16, usize);
+
+/// 原始 EXIF 的 IFD0 条目及是否小端；结构不合法时返回 None
+pub(crate) fn ifd0_entries(raw: &[u8]) -> Option<(bool, Vec<IfdEntry>)> {
+    let little = match raw.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| raw.get(at..at + 2).map(|b| if little { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) });
+    let ifd = raw.get(4..8).map(|b| if little { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) } else { u32::from_be_bytes([b[0], b[1], b[2], b[3]]) })? as usize;
+    let count = u16_at(ifd)? as usize;
+    let entries = (0..count)
+        .map(|i| ifd + 2 + i * 12)
+        .take_while(|entry| entry + 12 <= raw.len())
+        .filter_map(|entry| Some((u16_at(entry)?, u16_at(entry + 2)?, entry)))
+        .collect();
+    Some((little, entries))
+}
+
+/// 就地把 IFD0 的 Orientation（SHORT，值直接存在条目里）改为 1
+fn reset_orientation(raw: &mut [u8]) {
+    let Some((little, entries)) = ifd0_entries(raw) else { return };
+    if let Some((_, _, entry)) = entries.into_iter().find(|(tag, kind, _)| *tag == 0x0112 && *kind == 3) {
+        let one = if little { 1u16.to_le_bytes() } else { 1u16.to_be_bytes() };
+        raw[entry + 8..entry + 10].copy_from_slice(&one);
+    }
+}
+
+/// 5~8 表示图像存储时旋转了 90°，转正后宽高互换
+pub(crate) fn swaps_dimensions(orientation: u16) -> bool {
+    (5..=8).contains(&orientation)
+}
+
+/// 按 Orientation 把像素转正
+pub(crate) fn apply(img: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
@@ -0,0 +1,139 @@
+// ==========================================
+// 🌟 输出策略：覆盖原图 / 原图旁加后缀 / 镜像输出目录
+// ==========================================
+// 覆盖是老行为；另外两种保证客户原图不被改动。
+// 镜像目录按 source_root 之下的相对路径重建目录树；文件不在 source_root 内时报错，
+// 不把不同目录的同名文件平铺到输出根目录里互相覆盖。
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n;
+
+/// 前端传入的输出选项；未传时按覆盖处理
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OutputOptions {
+    /// "overwrite" | "beside" | "mirror"
+    pub mode: Option<String>,
+    /// beside 模式的文件名后缀
+    pub suffix: Option<String>,
+    /// mirror 模式下源目录树的根（一般为本批文件的公共父目录）
+    pub source_root: Option<String>,
+    /// mirror 模式的输出根目录
+    pub output_root: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum OutputStrategy {
+    Overwrite,
+    Beside { suffix: String },
+    Mirror { source_root: PathBuf, output_root: PathBuf },
+}
+
+impl OutputStrategy {
+    pub fn from_options(options: Option<&OutputOptions>) -> Result<Self, String> {
+        let Some(options) = options else { return Ok(OutputStrategy::Overwrite) };
+        match options.mode.as_deref().unwrap_or("overwrite") {
+            "overwrite" => Ok(OutputStrategy::Overwrite),
+            "beside" => {
+                let suffix = options.suffix.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+                Ok(OutputStrategy::Beside { suffix: suffix.unwrap_or_else(|| i18n::t("file.output_suffix")) })
+            }
+            "mirror" => {
+                let output_root = options
+                    .output_root
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .ok_or_else(|| i18n::t("err.mirror_needs_root"))?;
+                let source_root = options
+                    .source_root
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .ok_or_else(|| i18n::t("err.mirror_needs_source"))?;
+                Ok(OutputStrategy::Mirror { source_root: PathBuf::from(source_root), output_root: PathBuf::from(output_root) })
+            }
+            other => Err(i18n::tf("err.unknown_output", &[&other])),
+        }
+    }
+
+    /// 是否会改动原图所在的文件
+    pub fn keeps_original(&self) -> bool {
+        !matches!(self, OutputStrategy::Overwrite)
+    }
+
+    /// 输入文件对应的输出目录（mirror 模式会顺带建好目录）
+    pub fn directory_for(&self, input: &Path) -> Result<PathBuf, String> {
+        let parent = input.parent().unwrap_or(Path::new("")).to_path_buf();
+        let OutputStrategy::Mirror { source_root, output_root } = self else { return Ok(parent) };
+        let relative = parent
+            .strip_prefix(source_root)
+            .map_err(|_| i18n::tf("err.mirror_outside_root", &[&input.display(), &source_root.display()]))?;
+        let dir = output_root.join(relative);
+        std::fs::create_dir_all(&dir).map_err(|e| i18n::tf("err.create_output_dir", &[&e]))?;
+        Ok(dir)
+    }
+
+    /// process_image 的最终输出路径
+    pub fn destination(&self, input: &Path) -> Result<PathBuf, String> {
+        let file_name = input.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let dest = match self {
+            OutputStrategy::Overwrite => return Ok(input.to_path_buf()),
+            OutputStrategy::Beside { suffix } => {
+                let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+                let name = match input.extension() {
+                    Some(ext) => format!("{}{}.{}", stem, suffix, ext.to_string_lossy()),
+                    None => format!("{}{}", stem, suffix),
+                };
+                self.directory_for(input)?.join(name)
+            }
+            OutputStrategy::Mirror { .. } => self.directory_for(input)?.join(file_name),
+        };
+        // 输出目录选成了源目录时会回到原图本身，宁可报错也不覆盖
+        if crate::replace::same_file(&dest, input) {
+            return Err(i18n::t("err.output_same_as_input"));
+        }
+        Ok(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror(source_root: Option<&str>, output_root: &Path) -> Result<OutputStrategy, String> {
+        OutputStrategy::from_options(Some(&OutputOptions {
+            mode: Some("mirror".to_string()),
+            suffix: None,
+            source_root: source_root.map(str::to_string),
+            output_root: Some(output_root.to_string_lossy().to_string()),
+        }))
+    }
+
+    #[test]
+    fn mirror_keeps_the_tree_and_rejects_files_outside_the_root() {
+        let dir = std::env::temp_dir().join(format!("output-mirror-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (source, out) = (dir.join("src"), dir.join("out"));
+        assert_eq!(mirror(None, &out), Err(i18n::t("err.mirror_needs_source")));
+        assert_eq!(mirror(Some(" "), &out), Err(i18n::t("err.mirror_needs_source")));
+
+        let strategy = mirror(Some(&source.to_string_lossy()), &out).unwrap();
+        // 不同子目录的同名文件各自落到镜像目录里
+        let a = strategy.destination(&source.join("a").join("1.jpg")).unwrap();
+        let b = strategy.destination(&source.join("b").join("1.jpg")).unwrap();
+        assert_eq!(a, out.join("a").join("1.jpg"));
+        assert_eq!(b, out.join("b").join("1.jpg"));
+        assert!(out.join("a").is_dir() && out.join("b").is_dir());
+
+        let outside = dir.join("elsewhere").join("1.jpg");
+        assert_eq!(
+            strategy.destination(&outside),
+            Err(i18n::tf("err.mirror_outside_root", &[&outside.display(), &source.display()]))
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
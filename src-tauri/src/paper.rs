@@ -0,0 +1,256 @@
+// ==========================================
+// 🌟 纸张输出配置：每种纸张自带 ICC、锐化、DPI、镜像、加深与最大幅宽
+// ==========================================
+// 内置一份默认配置，门店在界面里改过的配置保存在应用配置目录的 paper_profiles.json，
+// 同名条目以保存的为准。process_image / rename_files 收到纸张名时自动套用。
+
+use std::path::PathBuf;
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::color::{self, Target};
+use crate::i18n;
+
+const PROFILES_FILE: &str = "paper_profiles.json";
+
+/// 渲染意图（原生管线只处理矩阵型描述文件，四种意图结果相同；magick 路径按 `-intent` 传递）
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RenderingIntent {
+    Perceptual,
+    #[default]
+    Relative,
+    Saturation,
+    Absolute,
+}
+
+impl RenderingIntent {
+    pub fn magick_name(self) -> &'static str {
+        match self {
+            RenderingIntent::Perceptual => "Perceptual",
+            RenderingIntent::Relative => "Relative",
+            RenderingIntent::Saturation => "Saturation",
+            RenderingIntent::Absolute => "Absolute",
+        }
+    }
+}
+
+/// 单种纸张的输出配置
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct PaperProfile {
+    pub name: String,
+    /// 输出描述文件："srgb"、"adobe_rgb" 或本地 .icc/.icm 路径；为空时沿用 process_image 的色彩策略
+    pub output_icc: Option<String>,
+    pub intent: RenderingIntent,
+    /// 输出锐化半径（像素，等同 `-unsharp 0xN`），0 为不锐化
+    pub sharpen: f32,
+    /// 目标输出 DPI（如油画布 240、Epson 原生 360/720），为空时使用全局默认值
+    pub dpi: Option<f64>,
+    /// 水平镜像（灯箱片背打、转印膜）
+    pub mirror: bool,
+    /// 密度加深百分比，正数压暗中间调，负数提亮
+    pub tone_boost: f32,
+    /// 最大卷材/单张幅宽（厘米），成品短边超出时拒绝输出
+    pub max_width_cm: Option<f32>,
+}
+
+impl PaperProfile {
+    fn named(name: &str) -> Self {
+        PaperProfile { name: name.to_string(), ..Default::default() }
+    }
+
+    /// 是否需要改动像素；纯改名且不需要改像素时 rename_files 不重编码
+    pub fn adjusts_pixels(&self) -> bool {
+        self.output_icc.is_some() || self.sharpen > 0.0 || self.mirror || self.tone_boost != 0.0
+    }
+
+    /// 输出描述文件的字节
+    pub fn output_profile(&self) -> Result<Option<Vec<u8>>, String> {
+        match self.output_icc.as_deref() {
+            None | Some("") => Ok(None),
+            Some("srgb") => Ok(Some(Target::Srgb.profile())),
+            Some("adobe_rgb") => Ok(Some(Target::AdobeRgb.profile())),
+            Some(path) => std::fs::read(path).map(Some).map_err(|e| i18n::tf("err.paper_icc_read", &[&path, &e])),
+        }
+    }
+
+    /// 供 magick 使用的输出描述文件路径
+    pub fn output_profile_path(&self) -> Result<Option<PathBuf>, String> {
+        match self.output_icc.as_deref() {
+            None | Some("") => Ok(None),
+            Some("srgb") => color::profile_file(Target::Srgb).map(Some),
+            Some("adobe_rgb") => color::profile_file(Target::AdobeRgb).map(Some),
+            Some(path) => Ok(Some(PathBuf::from(path))),
+        }
+    }
+
+    /// 加深对应的 gamma 指数：v' = v^gamma
+    fn tone_gamma(&self) -> f64 {
+        (1.0 + self.tone_boost.clamp(-90.0, 300.0) as f64 / 100.0).max(0.1)
+    }
+
+    /// magick `-gamma` 取的是倒数
+    pub fn magick_gamma(&self) -> f64 {
+        1.0 / self.tone_gamma()
+    }
+
+    /// 色彩转换之后的输出调整：加深 → 锐化 → 镜像
+    pub fn finish(&self, img: DynamicImage) -> DynamicImage {
+        let mut img = img;
+        if self.tone_boost != 0.0 {
+            img = apply_gamma(img, self.tone_gamma());
+        }
+        if self.sharpen > 0.0 {
+            img = img.unsharpen(self.sharpen, 1);
+        }
+        if self.mirror {
+            img = img.fliph();
+        }
+        img
+    }
+
+    /// 检查成品短边是否放得进最大幅宽
+    pub fn check_width(&self, w_px: u32, h_px: u32, dpi: f64) -> Result<(), String> {
+        let Some(max_cm) = self.max_width_cm.filter(|v| *v > 0.0) else { return Ok(()) };
+        let short_cm = w_px.min(h_px) as f64 / dpi * 2.54;
+        if short_cm > max_cm as f64 + 0.05 {
+            return Err(i18n::tf("err.paper_too_narrow", &[&format!("{:.1}", short_cm), &self.name, &format!("{:.1}", max_cm)]));
+        }
+        Ok(())
+    }
+}
+
+fn apply_gamma(img: DynamicImage, gamma: f64) -> DynamicImage {
+    match img {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) | DynamicImage::ImageLumaA8(_) => {
+            let lut: Vec<u8> = (0..256).map(|i| ((i as f64 / 255.0).powf(gamma) * 255.0).round() as u8).collect();
+            map_samples_u8(img, &lut)
+        }
+        other => {
+            let lut: Vec<u16> = (0..65536).map(|i| ((i as f64 / 65535.0).powf(gamma) * 65535.0).round() as u16).collect();
+            map_samples_u16(other, &lut)
+        }
+    }
+}
+
+/// 只改颜色通道，透明通道原样保留
+fn map_samples_u8(img: DynamicImage, lut: &[u8]) -> DynamicImage {
+    match img {
+        DynamicImage::ImageLuma8(mut buf) => {
+            buf.pixels_mut().for_each(|p| p.0[0] = lut[p.0[0] as usize]);
+            DynamicImage::ImageLuma8(buf)
+        }
+        DynamicImage::ImageLumaA8(mut buf) => {
+            buf.pixels_mut().for_each(|p| p.0[0] = lut[p.0[0] as usize]);
+            DynamicImage::ImageLumaA8(buf)
+        }
+        DynamicImage::ImageRgb8(mut buf) => {
+            buf.pixels_mut().for_each(|p| p.0 = p.0.map(|v| lut[v as usize]));
+            DynamicImage::ImageRgb8(buf)
+        }
+        DynamicImage::ImageRgba8(mut buf) => {
+            buf.pixels_mut().for_each(|p| {
+                for v in &mut p.0[..3] {
+                    *v = lut[*v as usize];
+                }
+            });
+            DynamicImage::ImageRgba8(buf)
+        }
+        other => other,
+    }
+}
+
+fn map_samples_u16(img: DynamicImage, lut: &[u16]) -> DynamicImage {
+    match img {
+        DynamicImage::ImageLuma16(mut buf) => {
+            buf.pixels_mut().for_each(|p| p.0[0] = lut[p.0[0] as usize]);
+            DynamicImage::ImageLuma16(buf)
+        }
+        DynamicImage::ImageLumaA16(mut buf) => {
+            buf.pixels_mut().for_each(|p| p.0[0] = lut[p.0[0] as usize]);
+            DynamicImage::ImageLumaA16(buf)
+        }
+        DynamicImage::ImageRgb16(mut buf) => {
+            buf.pixels_mut().for_each(|p| p.0 = p.0.map(|v| lut[v as usize]));
+            DynamicImage::ImageRgb16(buf)
+        }
+        DynamicImage::ImageRgba16(mut buf) => {
+            buf.pixels_mut().for_each(|p| {
+                for v in &mut p.0[..3] {
+                    *v = lut[*v as usize];
+                }
+            });
+            DynamicImage::ImageRgba16(buf)
+        }
+        // 浮点图先转 16 位再处理
+        other => map_samples_u16(DynamicImage::ImageRgba16(other.into_rgba16()), lut),
+    }
+}
+
+/// 内置默认配置（与前端 PAPER_CATEGORIES 同名）
+fn builtin() -> Vec<PaperProfile> {
+    let mut profiles: Vec<PaperProfile> = [
+        "210蚀刻", "315蚀刻", "水彩纸", "硫化钡", "博物馆蚀刻",
+        "光泽相纸", "绒面相纸", "亚光相纸", "粗面水彩", "纯棉平滑",
+        "金属相纸", "宣纸", "油画布", "灯箱片", "背胶PP",
+    ]
+    .iter()
+    .map(|name| PaperProfile::named(name))
+    .collect();
+    for p in &mut profiles {
+        match p.name.as_str() {
+            // 画布纹理会吃掉细节，输出前略加锐化；240 DPI 已足够
+            "油画布" => {
+                p.sharpen = 1.0;
+                p.dpi = Some(240.0);
+            }
+            // 灯箱片背打：镜像并加深，透光后不发灰；大幅面观看距离远，150 DPI 即可
+            "灯箱片" => {
+                p.mirror = true;
+                p.tone_boost = 20.0;
+                p.dpi = Some(150.0);
+            }
+            _ => {}
+        }
+    }
+    profiles
+}
+
+fn profiles_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("{}: {}", i18n::t("err.config_dir"), e))?;
+    Ok(dir.join(PROFILES_FILE))
+}
+
+/// 内置配置叠加已保存的配置
+pub(crate) fn load(app: &AppHandle) -> Vec<PaperProfile> {
+    let mut profiles = builtin();
+    let saved: Vec<PaperProfile> = profiles_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+    for profile in saved {
+        match profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => profiles.push(profile),
+        }
+    }
+    profiles
+}
+
+/// 按纸张名查找；未登记的纸张没有任何调整
+pub(crate) fn find(app: &AppHandle, name: &str) -> Option<PaperProfile> {
+    load(app).into_iter().find(|p| p.name == name)
+}
+
+pub(crate) fn save(app: &AppHandle, profiles: &[PaperProfile]) -> Result<(), String> {
+    let path = profiles_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| i18n::tf("err.create_dir", &[&dir.display(), &e]))?;
+    }
+    let text = serde_json::to_string_pretty(profiles).map_err(|e| format!("{}: {}", i18n::t("err.save_paper_profiles"), e))?;
+    std::fs::write(&path, text).map_err(|e| format!("{}: {}", i18n::t("err.save_paper_profiles"), e))
+}
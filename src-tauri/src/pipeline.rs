@@ -0,0 +1,945 @@
+// ==========================================
+// 🌟 原生管线：JPEG/PNG/TIFF/PSD 纯 Rust 排版（不再为每张图拉起 magick 进程）
+// ==========================================
+// 几何计算统一来自 plan.rs，与 process_image 里拼 magick 参数用的是同一份计划，
+// 保证两条路径输出的像素尺寸完全一致。
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageBuffer, ImageFormat, Luma, Pixel, Rgb, RgbImage};
+
+use crate::color::{self, ColorPolicy};
+use crate::i18n;
+use crate::orientation;
+use crate::paper::PaperProfile;
+use crate::plan::{self, Plan};
+use crate::psd;
+use crate::replace::Expected;
+use crate::tiff_io::{self, TiffInfo};
+
+/// 既没有纸张 DPI、也没有全局默认值时的输出分辨率
+const OUTPUT_DPI: f64 = 300.0;
+
+/// 生效的输出 DPI：纸张配置 → 全局默认值 → 300
+pub(crate) fn resolve_dpi(paper: Option<&PaperProfile>, default_dpi: Option<f64>) -> f64 {
+    paper
+        .and_then(|p| p.dpi)
+        .filter(|v| *v > 0.0)
+        .or(default_dpi.filter(|v| *v > 0.0))
+        .unwrap_or(OUTPUT_DPI)
+}
+/// 估不出原图质量（非 JPEG 来源）时写 JPEG 的默认质量
+const JPEG_QUALITY: u8 = 92;
+
+/// IJG 标准亮度量化表（质量 50），按量化表总和反推原图质量
+const STD_LUMA_QUANT: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56, 14, 17, 22, 29, 51, 87, 80, 62, 18, 22,
+    37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113, 92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+/// 原生管线能读写的格式
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum NativeFormat {
+    Jpeg,
+    Png,
+    Tiff,
+    Psd,
+}
+
+impl NativeFormat {
+    /// 按文件内容判断格式，避免改了后缀的文件被误判
+    pub(crate) fn sniff(path: &Path) -> Option<Self> {
+        let mut head = [0u8; 8];
+        File::open(path).ok()?.read_exact(&mut head).ok()?;
+        if head.starts_with(b"8BPS") {
+            return Some(Self::Psd);
+        }
+        match image::guess_format(&head).ok()? {
+            ImageFormat::Jpeg => Some(Self::Jpeg),
+            ImageFormat::Png => Some(Self::Png),
+            ImageFormat::Tiff => Some(Self::Tiff),
+            _ => None,
+        }
+    }
+
+    /// magick 按输出文件的扩展名决定编码格式，这里保持同样的规则
+    fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "tif" | "tiff" => Some(Self::Tiff),
+            "psd" | "psb" => Some(Self::Psd),
+            _ => None,
+        }
+    }
+}
+
+/// 判断这次任务能否完全走原生管线：输入可解码、输出可编码
+pub(crate) fn can_process(input: &Path, output: &Path) -> bool {
+    let (Some(from), Some(to)) = (NativeFormat::sniff(input), NativeFormat::from_extension(output)) else { return false };
+    // TIFF 写回 TIFF 要沿用原压缩方式；JPEG、CCITT 等编码器写不了的交给 magick
+    from != NativeFormat::Tiff || to != NativeFormat::Tiff || tiff_io::read_info(input).is_ok_and(|info| info.compression.is_some())
+}
+
+/// 不借助 magick 能否解码这个文件（只读文件头，不解码像素）
+pub(crate) fn can_decode(input: &Path) -> bool {
+    match NativeFormat::sniff(input) {
+        Some(NativeFormat::Tiff) => tiff_io::can_decode(input),
+        Some(NativeFormat::Psd) => psd::read_info(input).is_ok_and(|info| info.natively_decodable()),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// process_image 的全部排版参数
+pub(crate) struct ProcessRequest {
+    pub mode: String,
+    pub target_w_cm: f32,
+    pub target_h_cm: f32,
+    pub crop_x: f32,
+    pub crop_y: f32,
+    pub crop_w: f32,
+    pub crop_h: f32,
+    pub border_top_cm: f32,
+    pub border_right_cm: f32,
+    pub border_bottom_cm: f32,
+    pub border_left_cm: f32,
+    /// 写出时对 ICC 的处理方式
+    pub color_policy: ColorPolicy,
+    /// 分配的纸张输出配置；其输出 ICC 与 DPI 优先
+    pub paper: Option<PaperProfile>,
+    /// 前端设置的全局默认输出 DPI
+    pub default_dpi: Option<f64>,
+}
+
+impl ProcessRequest {
+    /// 不改几何、只套用纸张配置（rename_files 用）
+    pub fn keep(paper: PaperProfile, default_dpi: Option<f64>) -> Self {
+        ProcessRequest {
+            mode: "keep".to_string(),
+            target_w_cm: 0.0,
+            target_h_cm: 0.0,
+            crop_x: 0.0,
+            crop_y: 0.0,
+            crop_w: 100.0,
+            crop_h: 100.0,
+            border_top_cm: 0.0,
+            border_right_cm: 0.0,
+            border_bottom_cm: 0.0,
+            border_left_cm: 0.0,
+            color_policy: ColorPolicy::Keep,
+            paper: Some(paper),
+            default_dpi,
+        }
+    }
+
+    pub fn output_dpi(&self) -> f64 {
+        resolve_dpi(self.paper.as_ref(), self.default_dpi)
+    }
+}
+
+/// 单步像素操作，顺序执行即得到最终画布
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// 裁切到画布内的矩形（等价于 `-crop WxH+X+Y +repage`，越界部分自动收缩）
+    Crop { x: u32, y: u32, w: u32, h: u32 },
+    /// 强制缩放到指定像素
+    Resize { w: u32, h: u32 },
+    /// 以 (x, y) 为原点开一块白底画布，可为负（等价于 `-extent`）
+    Extent { w: u32, h: u32, x: i64, y: i64 },
+    /// 镜像外扩（等价于 `-virtual-pixel mirror` + `distort:viewport`）
+    Mirror { w: u32, h: u32, left: u32, top: u32 },
+}
+
+/// 把排版计划展开成操作序列：裁切 → 重采样 → 四边增减（白底或镜像）
+fn plan_steps(plan: &Plan) -> Vec<Step> {
+    let mut steps = Vec::new();
+    if let Some(rect) = plan.crop {
+        steps.push(Step::Crop { x: rect.x, y: rect.y, w: rect.width, h: rect.height });
+    }
+    if let Some(r) = plan.resample {
+        steps.push(Step::Resize { w: r.width, h: r.height });
+    }
+    let sides = plan.sides;
+    let (w, h) = (plan.final_px.width, plan.final_px.height);
+    if !sides.is_zero() {
+        steps.push(if plan.mirror_fill {
+            Step::Mirror { w, h, left: sides.left.max(0) as u32, top: sides.top.max(0) as u32 }
+        } else {
+            // 加边与裁边合成一次 extent：原点为负即加白边，为正即裁掉
+            Step::Extent { w, h, x: -sides.left, y: -sides.top }
+        });
+    }
+    steps
+}
+
+/// 按计划推算的输出 (宽, 高, DPI)，不解码像素；原生管线与 magick 兜底共用同一套几何
+pub(crate) fn expected_output(input: &Path, req: &ProcessRequest) -> Option<Expected> {
+    let plan = source_plan(input, req)?;
+    Some(Expected { width: plan.final_px.width, height: plan.final_px.height, dpi: plan.output_dpi })
+}
+
+/// 只读文件头得到排版计划（尺寸按 EXIF 方向转正）；原生管线读不了的格式返回 None
+pub(crate) fn source_plan(input: &Path, req: &ProcessRequest) -> Option<Plan> {
+    let (w, h, dpi) = dimensions(input)?;
+    let (w, h) = if orientation::swaps_dimensions(orientation::read(input)) { (h, w) } else { (w, h) };
+    Some(plan::plan(req, w, h, dpi))
+}
+
+/// 完整解码写出的文件，返回 (宽, 高, DPI)；截断或写坏的文件在这里报错
+pub(crate) fn decode_output(path: &Path) -> Result<(u32, u32, Option<f64>), String> {
+    let decoded = decode(path)?;
+    Ok((decoded.image.width(), decoded.image.height(), decoded.dpi))
+}
+
+/// 镜像取样：-1 映射回 0，与 magick 的 mirror 虚拟像素一致
+fn mirror_index(i: i64, len: u32) -> u32 {
+    let len = len.max(1) as i64;
+    let m = i.rem_euclid(len * 2);
+    (if m >= len { len * 2 - 1 - m } else { m }) as u32
+}
+
+/// 画布样本与 f32 互转（画布只有 8 位与 16 位两种）
+trait Sample: Copy {
+    fn to_f32(self) -> f32;
+    fn from_f32(v: f32) -> Self;
+}
+
+impl Sample for u8 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+    fn from_f32(v: f32) -> Self {
+        v.round().clamp(0.0, u8::MAX as f32) as u8
+    }
+}
+
+impl Sample for u16 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+    fn from_f32(v: f32) -> Self {
+        v.round().clamp(0.0, u16::MAX as f32) as u16
+    }
+}
+
+/// Mitchell-Netravali 核（B = C = 1/3），支撑半径 2
+fn mitchell(x: f32) -> f32 {
+    const B: f32 = 1.0 / 3.0;
+    const C: f32 = 1.0 / 3.0;
+    let x = x.abs();
+    if x < 1.0 {
+        ((12.0 - 9.0 * B - 6.0 * C) * x * x * x + (-18.0 + 12.0 * B + 6.0 * C) * x * x + (6.0 - 2.0 * B)) / 6.0
+    } else if x < 2.0 {
+        ((-B - 6.0 * C) * x * x * x + (6.0 * B + 30.0 * C) * x * x + (-12.0 * B - 48.0 * C) * x + (8.0 * B + 24.0 * C)) / 6.0
+    } else {
+        0.0
+    }
+}
+
+/// 一个方向上每个目标像素的 (首个源像素, 归一化权重)，取样方式与 magick 的 -resize 相同
+fn mitchell_weights(src: u32, dst: u32) -> Vec<(usize, Vec<f32>)> {
+    let scale = src as f32 / dst as f32;
+    // 缩小时核按比例展宽，放大时保持原宽
+    let stretch = scale.max(1.0);
+    let support = 2.0 * stretch;
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale;
+            let start = (center - support + 0.5).floor().max(0.0) as usize;
+            let end = ((center + support + 0.5).floor() as usize).min(src as usize).max(start + 1);
+            let weights: Vec<f32> = (start..end).map(|j| mitchell((j as f32 + 0.5 - center) / stretch)).collect();
+            let sum: f32 = weights.iter().sum();
+            let weights = if sum.abs() > f32::EPSILON { weights.iter().map(|w| w / sum).collect() } else { weights };
+            (start, weights)
+        })
+        .collect()
+}
+
+/// 可分离的 Mitchell 重采样：先横向、再纵向，中间结果保留 f32
+fn resize_mitchell<P>(img: &ImageBuffer<P, Vec<P::Subpixel>>, w: u32, h: u32) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Sample + 'static,
+{
+    let (sw, sh) = img.dimensions();
+    let channels = P::CHANNEL_COUNT as usize;
+    let src = img.as_raw();
+
+    let columns = mitchell_weights(sw, w);
+    let mut rows_done = vec![0f32; w as usize * sh as usize * channels];
+    for y in 0..sh as usize {
+        let row = &src[y * sw as usize * channels..(y + 1) * sw as usize * channels];
+        for (x, (start, weights)) in columns.iter().enumerate() {
+            let out = &mut rows_done[(y * w as usize + x) * channels..][..channels];
+            for (k, weight) in weights.iter().enumerate() {
+                let pixel = &row[(start + k) * channels..][..channels];
+                for c in 0..channels {
+                    out[c] += pixel[c].to_f32() * weight;
+                }
+            }
+        }
+    }
+
+    let rows = mitchell_weights(sh, h);
+    let mut data = Vec::with_capacity(w as usize * h as usize * channels);
+    let mut acc = vec![0f32; w as usize * channels];
+    for (start, weights) in &rows {
+        acc.iter_mut().for_each(|v| *v = 0.0);
+        for (k, weight) in weights.iter().enumerate() {
+            let line = &rows_done[(start + k) * w as usize * channels..][..w as usize * channels];
+            for (a, v) in acc.iter_mut().zip(line) {
+                *a += v * weight;
+            }
+        }
+        data.extend(acc.iter().map(|v| P::Subpixel::from_f32(*v)));
+    }
+    ImageBuffer::from_raw(w, h, data).expect("Mitchell 重采样缓冲区尺寸与画布一致")
+}
+
+fn apply_steps<P>(mut img: ImageBuffer<P, Vec<P::Subpixel>>, white: P, steps: &[Step]) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: Sample + 'static,
+{
+    for step in steps {
+        img = match *step {
+            Step::Crop { x, y, w, h } => imageops::crop_imm(&img, x, y, w, h).to_image(),
+            Step::Resize { w, h } => {
+                if (w, h) == img.dimensions() {
+                    continue;
+                }
+                // magick 放大默认 Mitchell、缩小默认 Lanczos；image 没有 Mitchell，放大时自己卷积
+                if w as u64 * h as u64 > img.width() as u64 * img.height() as u64 {
+                    resize_mitchell(&img, w, h)
+                } else {
+                    imageops::resize(&img, w, h, FilterType::Lanczos3)
+                }
+            }
+            Step::Extent { w, h, x, y } => {
+                let mut canvas = ImageBuffer::from_pixel(w, h, white);
+                imageops::replace(&mut canvas, &img, -x, -y);
+                canvas
+            }
+            Step::Mirror { w, h, left, top } => {
+                let (sw, sh) = img.dimensions();
+                let xs: Vec<u32> = (0..w).map(|x| mirror_index(x as i64 - left as i64, sw)).collect();
+                let ys: Vec<u32> = (0..h).map(|y| mirror_index(y as i64 - top as i64, sh)).collect();
+                ImageBuffer::from_fn(w, h, |x, y| *img.get_pixel(xs[x as usize], ys[y as usize]))
+            }
+        };
+    }
+    img
+}
+
+/// 拍平到白底后的画布（相当于 magick 的 `-background white -flatten`）
+enum Canvas {
+    Luma8(ImageBuffer<Luma<u8>, Vec<u8>>),
+    Luma16(ImageBuffer<Luma<u16>, Vec<u16>>),
+    Rgb8(ImageBuffer<Rgb<u8>, Vec<u8>>),
+    Rgb16(ImageBuffer<Rgb<u16>, Vec<u16>>),
+}
+
+/// 按 alpha 混合到白色：out = c·a + max·(1 − a)
+fn over_white(c: u32, a: u32, max: u32) -> u32 {
+    (c * a + max * (max - a) + max / 2) / max
+}
+
+impl Canvas {
+    fn flatten(img: DynamicImage) -> Self {
+        match img {
+            DynamicImage::ImageLuma8(buf) => Canvas::Luma8(buf),
+            DynamicImage::ImageLumaA8(buf) => Canvas::Luma8(ImageBuffer::from_fn(buf.width(), buf.height(), |x, y| {
+                let [l, a] = buf.get_pixel(x, y).0;
+                Luma([over_white(l as u32, a as u32, 0xFF) as u8])
+            })),
+            DynamicImage::ImageLuma16(buf) => Canvas::Luma16(buf),
+            DynamicImage::ImageLumaA16(buf) => Canvas::Luma16(ImageBuffer::from_fn(buf.width(), buf.height(), |x, y| {
+                let [l, a] = buf.get_pixel(x, y).0;
+                Luma([over_white(l as u32, a as u32, 0xFFFF) as u16])
+            })),
+            DynamicImage::ImageRgb8(buf) => Canvas::Rgb8(buf),
+            DynamicImage::ImageRgb16(buf) => Canvas::Rgb16(buf),
+            DynamicImage::ImageRgba16(buf) => Canvas::Rgb16(ImageBuffer::from_fn(buf.width(), buf.height(), |x, y| {
+                let [r, g, b, a] = buf.get_pixel(x, y).0;
+                let a = a as u32;
+                Rgb([
+                    over_white(r as u32, a, 0xFFFF) as u16,
+                    over_white(g as u32, a, 0xFFFF) as u16,
+                    over_white(b as u32, a, 0xFFFF) as u16,
+                ])
+            })),
+            other => {
+                let buf = other.into_rgba8();
+                Canvas::Rgb8(ImageBuffer::from_fn(buf.width(), buf.height(), |x, y| {
+                    let [r, g, b, a] = buf.get_pixel(x, y).0;
+                    let a = a as u32;
+                    Rgb([
+                        over_white(r as u32, a, 0xFF) as u8,
+                        over_white(g as u32, a, 0xFF) as u8,
+                        over_white(b as u32, a, 0xFF) as u8,
+                    ])
+                }))
+            }
+        }
+    }
+
+    fn apply(self, steps: &[Step]) -> Self {
+        match self {
+            Canvas::Luma8(buf) => Canvas::Luma8(apply_steps(buf, Luma([u8::MAX]), steps)),
+            Canvas::Luma16(buf) => Canvas::Luma16(apply_steps(buf, Luma([u16::MAX]), steps)),
+            Canvas::Rgb8(buf) => Canvas::Rgb8(apply_steps(buf, Rgb([u8::MAX; 3]), steps)),
+            Canvas::Rgb16(buf) => Canvas::Rgb16(apply_steps(buf, Rgb([u16::MAX; 3]), steps)),
+        }
+    }
+
+    fn into_dynamic(self) -> DynamicImage {
+        match self {
+            Canvas::Luma8(buf) => DynamicImage::ImageLuma8(buf),
+            Canvas::Luma16(buf) => DynamicImage::ImageLuma16(buf),
+            Canvas::Rgb8(buf) => DynamicImage::ImageRgb8(buf),
+            Canvas::Rgb16(buf) => DynamicImage::ImageRgb16(buf),
+        }
+    }
+}
+
+/// 读取 JPEG 的 JFIF 密度（APP0），返回 DPI
+fn jpeg_density(bytes: &[u8]) -> Option<f64> {
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        // SOS 之后是熵编码数据，不再有 APP 段
+        if marker == 0xDA {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let body = bytes.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xE0 && body.len() >= 12 && &body[..5] == b"JFIF\0" {
+            let units = body[7];
+            let x_density = u16::from_be_bytes([body[8], body[9]]) as f64;
+            return match units {
+                1 if x_density > 0.0 => Some(x_density),
+                2 if x_density > 0.0 => Some(x_density * 2.54),
+                _ => None,
+            };
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// 按 0 号量化表估算 JPEG 的编码质量（IJG 缩放公式的反推），与 magick 的 %Q 思路一致
+fn jpeg_quality(bytes: &[u8]) -> Option<u8> {
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        if marker == 0xDA {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let mut body = bytes.get(pos + 4..pos + 2 + len)?;
+        // 一个 DQT 段里可以连着放多张表
+        while marker == 0xDB && !body.is_empty() {
+            let (precision, id) = (body[0] >> 4, body[0] & 0x0F);
+            let size = if precision == 0 { 64 } else { 128 };
+            let table = body.get(1..1 + size)?;
+            if id == 0 {
+                let sum: u32 = if precision == 0 {
+                    table.iter().map(|v| *v as u32).sum()
+                } else {
+                    table.chunks(2).map(|v| u16::from_be_bytes([v[0], v[1]]) as u32).sum()
+                };
+                let std_sum: u32 = STD_LUMA_QUANT.iter().map(|v| *v as u32).sum();
+                let scale = sum as f64 * 100.0 / std_sum as f64;
+                let quality = if scale <= 100.0 { (200.0 - scale) / 2.0 } else { 5000.0 / scale };
+                return Some(quality.round().clamp(1.0, 100.0) as u8);
+            }
+            body = &body[1 + size..];
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// JPEG APP1 中 EXIF 段的标识
+const EXIF_MARKER: &[u8] = b"Exif\0\0";
+
+/// JPEG APP2 中 ICC 段的标识
+const ICC_MARKER: &[u8] = b"ICC_PROFILE\0";
+/// 单个 APP2 段能容纳的 ICC 数据量（65535 - 长度字段 2 - 标识 12 - 序号 2）
+const ICC_CHUNK: usize = 65519;
+
+/// 拼接 JPEG 的 APP2 ICC 分段（按序号排序，缺段时放弃）
+fn jpeg_icc(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut chunks: Vec<(u8, &[u8])> = Vec::new();
+    let mut total = 0u8;
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        if marker == 0xDA {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let body = bytes.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xE2 && body.len() > 14 && body.starts_with(ICC_MARKER) {
+            total = body[13];
+            chunks.push((body[12], &body[14..]));
+        }
+        pos += 2 + len;
+    }
+    if chunks.is_empty() || chunks.len() != total as usize {
+        return None;
+    }
+    chunks.sort_by_key(|(seq, _)| *seq);
+    Some(chunks.into_iter().flat_map(|(_, data)| data.iter().copied()).collect())
+}
+
+/// 新段的插入位置：SOI 之后紧跟 APP0 时插在 APP0 之后，否则直接插在 SOI 之后
+fn after_jfif(jpeg: &[u8]) -> usize {
+    if jpeg.get(2..4) == Some(&[0xFF, 0xE0]) {
+        4 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize
+    } else {
+        2
+    }
+}
+
+/// 把 EXIF 里已有的 X/YResolution 改成输出 DPI、单位改成英寸，
+/// 否则 Photoshop 等优先读 EXIF 的软件仍会显示原图的 72 DPI
+fn stamp_exif_density(raw: &mut [u8], dpi: f64) {
+    let Some((little, entries)) = orientation::ifd0_entries(raw) else { return };
+    let u32_bytes = |v: u32| if little { v.to_le_bytes() } else { v.to_be_bytes() };
+    for (tag, kind, entry) in entries {
+        match (tag, kind) {
+            // XResolution / YResolution：RATIONAL，8 字节放在偏移处
+            (282 | 283, 5) => {
+                let b = &raw[entry + 8..entry + 12];
+                let at = if little { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) } else { u32::from_be_bytes([b[0], b[1], b[2], b[3]]) } as usize;
+                if let Some(value) = raw.get_mut(at..at + 8) {
+                    value[..4].copy_from_slice(&u32_bytes((dpi * 1000.0).round() as u32));
+                    value[4..].copy_from_slice(&u32_bytes(1000));
+                }
+            }
+            // ResolutionUnit：SHORT，2 = 英寸
+            (296, 3) => raw[entry + 8..entry + 10].copy_from_slice(&if little { 2u16.to_le_bytes() } else { 2u16.to_be_bytes() }),
+            _ => {}
+        }
+    }
+}
+
+/// 把 EXIF 以 APP1 段插到 JFIF 头之后；超出单段容量的 EXIF 放弃
+fn insert_jpeg_exif(jpeg: Vec<u8>, exif: &[u8]) -> Vec<u8> {
+    let len = exif.len() + EXIF_MARKER.len() + 2;
+    if len > u16::MAX as usize {
+        return jpeg;
+    }
+    let at = after_jfif(&jpeg);
+    let mut out = Vec::with_capacity(jpeg.len() + len + 2);
+    out.extend_from_slice(&jpeg[..at]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&(len as u16).to_be_bytes());
+    out.extend_from_slice(EXIF_MARKER);
+    out.extend_from_slice(exif);
+    out.extend_from_slice(&jpeg[at..]);
+    out
+}
+
+/// 把 ICC 以 APP2 分段插到 JFIF 头之后
+fn insert_jpeg_icc(jpeg: Vec<u8>, icc: &[u8]) -> Vec<u8> {
+    let at = after_jfif(&jpeg);
+    let chunks: Vec<&[u8]> = icc.chunks(ICC_CHUNK).collect();
+    let mut out = Vec::with_capacity(jpeg.len() + icc.len() + chunks.len() * 18);
+    out.extend_from_slice(&jpeg[..at]);
+    for (i, chunk) in chunks.iter().enumerate() {
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&((chunk.len() + 16) as u16).to_be_bytes());
+        out.extend_from_slice(ICC_MARKER);
+        out.push(i as u8 + 1);
+        out.push(chunks.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&jpeg[at..]);
+    out
+}
+
+/// 读取 PNG 的 pHYs 密度（DPI）与 iCCP 描述文件
+fn png_meta<R: Read>(source: R) -> (Option<f64>, Option<Vec<u8>>) {
+    let Ok(reader) = png::Decoder::new(source).read_info() else { return (None, None) };
+    let info = reader.info();
+    let dpi = info.pixel_dims.and_then(|dims| match dims.unit {
+        png::Unit::Meter if dims.xppu > 0 => Some(dims.xppu as f64 * 0.0254),
+        _ => None,
+    });
+    (dpi, info.icc_profile.as_ref().map(|icc| icc.to_vec()))
+}
+
+/// 不解码像素读取 (宽, 高, DPI)（rename_files 检查幅宽用）
+pub(crate) fn dimensions(path: &Path) -> Option<(u32, u32, Option<f64>)> {
+    let format = NativeFormat::sniff(path)?;
+    if format == NativeFormat::Psd {
+        let info = psd::read_info(path).ok()?;
+        return Some((info.width, info.height, info.dpi));
+    }
+    let (w, h) = image::image_dimensions(path).ok()?;
+    let dpi = match format {
+        NativeFormat::Tiff => tiff_io::read_info(path).ok()?.dpi(),
+        NativeFormat::Png => png_meta(std::io::BufReader::new(File::open(path).ok()?)).0,
+        _ => {
+            // APP0 紧跟 SOI，读文件头即可
+            let mut head = Vec::new();
+            File::open(path).ok()?.take(1 << 16).read_to_end(&mut head).ok()?;
+            jpeg_density(&head)
+        }
+    };
+    Some((w, h, dpi))
+}
+
+/// 只读原图内嵌的 ICC 描述文件（get_image_meta、magick 兜底路径用）
+pub(crate) fn read_profile(path: &Path) -> Option<Vec<u8>> {
+    match NativeFormat::sniff(path)? {
+        NativeFormat::Jpeg => jpeg_icc(&std::fs::read(path).ok()?),
+        NativeFormat::Png => png_meta(std::io::BufReader::new(File::open(path).ok()?)).1,
+        NativeFormat::Tiff => tiff_io::read_info(path).ok()?.icc,
+        NativeFormat::Psd => psd::read_info(path).ok()?.icc,
+    }
+}
+
+/// 解码后的原图及写回时需要沿用的元数据
+struct Decoded {
+    image: DynamicImage,
+    /// 原始 DPI，缺失时为 None
+    dpi: Option<f64>,
+    /// 原图为 TIFF 时保留其压缩方式与分辨率标签
+    tiff: Option<TiffInfo>,
+    /// 原图内嵌的 ICC 描述文件
+    icc: Option<Vec<u8>>,
+    /// 原图为 JPEG 时估算的编码质量
+    quality: Option<u8>,
+    /// 原图的 EXIF（Orientation 已改为 1，像素已转正）
+    exif: Option<Vec<u8>>,
+}
+
+fn decode(input: &Path) -> Result<Decoded, String> {
+    let format = NativeFormat::sniff(input).ok_or_else(|| i18n::t("err.unsupported_format"))?;
+    if format == NativeFormat::Tiff {
+        let (image, info) = tiff_io::decode(input)?;
+        let image = orientation::apply(image, orientation::read(input));
+        let icc = info.icc.clone();
+        return Ok(Decoded { image, dpi: info.dpi(), tiff: Some(info), icc, quality: None, exif: None });
+    }
+    if format == NativeFormat::Psd {
+        let (image, info) = psd::decode(input)?;
+        return Ok(Decoded { image, dpi: info.dpi, tiff: None, icc: info.icc, quality: None, exif: None });
+    }
+
+    let bytes = std::fs::read(input).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    let ((dpi, icc), quality) = match format {
+        NativeFormat::Jpeg => ((jpeg_density(&bytes), jpeg_icc(&bytes)), jpeg_quality(&bytes)),
+        _ => (png_meta(bytes.as_slice()), None),
+    };
+    let exif = orientation::read_exif(input);
+
+    let mut reader = image::io::Reader::new(std::io::Cursor::new(&bytes));
+    reader.set_format(match format {
+        NativeFormat::Jpeg => ImageFormat::Jpeg,
+        _ => ImageFormat::Png,
+    });
+    // 大幅面原图动辄上亿像素，关闭默认的 512MB 解码上限
+    reader.no_limits();
+    let image = reader.decode().map_err(|e| i18n::tf("err.decode", &[&e]))?;
+    // 先按 EXIF 转正，后续裁切百分比都以转正后的图像为准
+    let image = orientation::apply(image, orientation::read(input));
+    Ok(Decoded { image, dpi, tiff: None, icc, quality, exif })
+}
+
+/// 写出时沿用的原图元数据
+struct Carried<'a> {
+    tiff: Option<&'a TiffInfo>,
+    icc: Option<&'a [u8]>,
+    quality: Option<u8>,
+    exif: Option<&'a [u8]>,
+}
+
+fn encode(img: DynamicImage, output: &Path, dpi: f64, meta: Carried) -> Result<(), String> {
+    let Carried { tiff, icc, quality, exif } = meta;
+    let exif = exif.map(|raw| {
+        let mut raw = raw.to_vec();
+        stamp_exif_density(&mut raw, dpi);
+        raw
+    });
+    let format = NativeFormat::from_extension(output).ok_or_else(|| i18n::t("err.unsupported_output"))?;
+    if format == NativeFormat::Psd {
+        return psd::encode(&img, output, dpi, icc);
+    }
+    if format == NativeFormat::Tiff {
+        // 非 TIFF 来源（改了后缀的文件）按 LZW + 每英寸写出
+        let default_info = TiffInfo::default();
+        return tiff_io::encode(&img, output, dpi, tiff.unwrap_or(&default_info), icc);
+    }
+    let file = File::create(output).map_err(|e| format!("{}: {}", i18n::t("err.create_output"), e))?;
+    let mut writer = BufWriter::new(file);
+
+    match format {
+        NativeFormat::Jpeg => {
+            // JPEG 只有 8 位
+            let img = match img {
+                DynamicImage::ImageLuma16(_) => DynamicImage::ImageLuma8(img.into_luma8()),
+                DynamicImage::ImageRgb16(_) => DynamicImage::ImageRgb8(img.into_rgb8()),
+                other => other,
+            };
+            let mut bytes = Vec::new();
+            // JPEG 来源沿用原质量，避免高质量原图被压低、低质量原图被撑大
+            let mut encoder = JpegEncoder::new_with_quality(&mut bytes, quality.unwrap_or(JPEG_QUALITY));
+            encoder.set_pixel_density(PixelDensity::dpi(dpi.round() as u16));
+            encoder.encode_image(&img).map_err(|e| i18n::tf("err.encode", &[&"JPEG", &e]))?;
+            if let Some(profile) = icc {
+                bytes = insert_jpeg_icc(bytes, profile);
+            }
+            if let Some(exif) = &exif {
+                bytes = insert_jpeg_exif(bytes, exif);
+            }
+            writer.write_all(&bytes).map_err(|e| format!("{}: {}", i18n::t("err.write_output"), e))?;
+        }
+        _ => {
+            let (width, height) = (img.width(), img.height());
+            let (color, depth, data) = match img {
+                DynamicImage::ImageLuma8(buf) => (png::ColorType::Grayscale, png::BitDepth::Eight, buf.into_raw()),
+                DynamicImage::ImageRgb8(buf) => (png::ColorType::Rgb, png::BitDepth::Eight, buf.into_raw()),
+                DynamicImage::ImageLuma16(buf) => (png::ColorType::Grayscale, png::BitDepth::Sixteen, be_bytes(buf.into_raw())),
+                other => (png::ColorType::Rgb, png::BitDepth::Sixteen, be_bytes(other.into_rgb16().into_raw())),
+            };
+            let ppm = (dpi / 0.0254).round() as u32;
+            let mut info = png::Info::with_size(width, height);
+            info.color_type = color;
+            info.bit_depth = depth;
+            info.pixel_dims = Some(png::PixelDimensions { xppu: ppm, yppu: ppm, unit: png::Unit::Meter });
+            info.icc_profile = icc.map(std::borrow::Cow::Borrowed);
+            let encoder = png::Encoder::with_info(&mut writer, info).map_err(|e| i18n::tf("err.encode", &[&"PNG", &e]))?;
+            let mut png_writer = encoder.write_header().map_err(|e| i18n::tf("err.encode", &[&"PNG", &e]))?;
+            if let Some(exif) = &exif {
+                png_writer.write_chunk(png::chunk::ChunkType(*b"eXIf"), exif).map_err(|e| i18n::tf("err.encode", &[&"PNG", &e]))?;
+            }
+            png_writer.write_image_data(&data).map_err(|e| i18n::tf("err.encode", &[&"PNG", &e]))?;
+            png_writer.finish().map_err(|e| i18n::tf("err.encode", &[&"PNG", &e]))?;
+        }
+    }
+    writer.flush().map_err(|e| format!("{}: {}", i18n::t("err.write_output"), e))?;
+    Ok(())
+}
+
+/// PNG 16 位样本按大端序存储
+fn be_bytes(samples: Vec<u16>) -> Vec<u8> {
+    samples.iter().flat_map(|v| v.to_be_bytes()).collect()
+}
+
+/// 原生管线入口：解码 → 按模式排版 → 白底拍平 → 色彩 → 纸张输出调整 → 带 DPI 与 ICC 写出
+pub(crate) fn process(input: &Path, output: &Path, req: &ProcessRequest) -> Result<(), String> {
+    let mut source = decode(input)?;
+    let plan = plan::plan(req, source.image.width(), source.image.height(), source.dpi);
+    let out_dpi = plan.output_dpi;
+    let steps = plan_steps(&plan);
+    let image = std::mem::replace(&mut source.image, DynamicImage::new_luma8(0, 0));
+    let rendered = Canvas::flatten(image).apply(&steps).into_dynamic();
+    if let Some(paper) = &req.paper {
+        paper.check_width(rendered.width(), rendered.height(), out_dpi)?;
+    }
+
+    let paper_icc = match &req.paper {
+        Some(paper) => paper.output_profile()?,
+        None => None,
+    };
+    let (rendered, icc) = match paper_icc {
+        Some(dst) => color::convert_to_profile(rendered, source.icc.take(), dst)?,
+        None => color::apply_policy(rendered, source.icc.take(), req.color_policy)?,
+    };
+    let rendered = match &req.paper {
+        Some(paper) => paper.finish(rendered),
+        None => rendered,
+    };
+    let meta = Carried { tiff: source.tiff.as_ref(), icc: icc.as_deref(), quality: source.quality, exif: source.exif.as_deref() };
+    encode(rendered, output, out_dpi, meta)
+}
+
+/// 生成白底 JPEG 预览（只缩不放，相当于 `-flatten -resize NxN> -strip jpeg:-`）
+pub(crate) fn thumbnail(input: &Path, max_side: u32) -> Result<Vec<u8>, String> {
+    let source = decode(input)?;
+    let mut image = Canvas::flatten(source.image).into_dynamic();
+    if image.width() > max_side || image.height() > max_side {
+        image = image.thumbnail(max_side, max_side);
+    }
+    preview_jpeg(image, source.icc.as_deref())
+}
+
+/// 原尺寸的查看用图像：转正、白底拍平并换算到 sRGB 的 8 位 RGB（瓦片服务用）
+pub(crate) fn decode_view(input: &Path) -> Result<RgbImage, String> {
+    let source = decode(input)?;
+    let image = Canvas::flatten(source.image).into_dynamic();
+    Ok(color::to_srgb_preview(image, source.icc.as_deref()).into_rgb8())
+}
+
+/// 成品预览：按计划排版后长边不超过 max_side，返回 (JPEG, 计划)
+pub(crate) fn preview(input: &Path, req: &ProcessRequest, max_side: u32) -> Result<(Vec<u8>, Plan), String> {
+    let source = decode(input)?;
+    let plan = plan::plan(req, source.image.width(), source.image.height(), source.dpi);
+    let jpeg = render_preview(source.image, source.icc.as_deref(), &plan, req.paper.as_ref(), max_side)?;
+    Ok((jpeg, plan))
+}
+
+/// 原图先缩到预览比例，再执行等比缩小的操作序列，裁切位置与四边增减和成品一致
+pub(crate) fn render_preview(image: DynamicImage, icc: Option<&[u8]>, plan: &Plan, paper: Option<&PaperProfile>, max_side: u32) -> Result<Vec<u8>, String> {
+    let scaled = plan.scaled(plan.preview_scale(max_side));
+    let (w, h) = (scaled.source_px.width, scaled.source_px.height);
+    let image = if (image.width(), image.height()) == (w, h) { image } else { image.resize_exact(w, h, FilterType::Triangle) };
+    let rendered = Canvas::flatten(image).apply(&plan_steps(&scaled)).into_dynamic();
+    // 加深与镜像照常套用；锐化半径按成品像素设定，缩小后再锐化会失真
+    let rendered = match paper {
+        Some(paper) => PaperProfile { sharpen: 0.0, ..paper.clone() }.finish(rendered),
+        None => rendered,
+    };
+    preview_jpeg(rendered, icc)
+}
+
+fn preview_jpeg(image: DynamicImage, icc: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    // webview 按 sRGB 显示，Adobe RGB 等广色域原图先换算，避免预览发灰
+    let image = color::to_srgb_preview(image, icc);
+    let image = DynamicImage::ImageRgb8(image.into_rgb8());
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)
+        .encode_image(&image)
+        .map_err(|e| i18n::tf("err.encode", &[&"JPEG", &e]))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn request(mode: &str) -> ProcessRequest {
+        ProcessRequest {
+            mode: mode.to_string(),
+            target_w_cm: 2.54,
+            target_h_cm: 2.54,
+            crop_x: 0.0,
+            crop_y: 0.0,
+            crop_w: 100.0,
+            crop_h: 100.0,
+            border_top_cm: 0.0,
+            border_right_cm: 0.0,
+            border_bottom_cm: 0.0,
+            border_left_cm: 0.0,
+            color_policy: ColorPolicy::Keep,
+            paper: None,
+            default_dpi: Some(300.0),
+        }
+    }
+
+    fn jpeg(img: &DynamicImage, quality: u8) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut bytes, quality).encode_image(img).unwrap();
+        bytes
+    }
+
+    /// 只有 X/YResolution 与 ResolutionUnit 的小端 EXIF（72 像素每厘米）
+    fn exif_72_cm() -> Vec<u8> {
+        let mut raw = b"II*\0".to_vec();
+        raw.extend_from_slice(&8u32.to_le_bytes());
+        raw.extend_from_slice(&3u16.to_le_bytes());
+        let rational_at = 8 + 2 + 3 * 12 + 4;
+        for (tag, offset) in [(282u16, rational_at), (283, rational_at + 8)] {
+            raw.extend_from_slice(&tag.to_le_bytes());
+            raw.extend_from_slice(&5u16.to_le_bytes());
+            raw.extend_from_slice(&1u32.to_le_bytes());
+            raw.extend_from_slice(&(offset as u32).to_le_bytes());
+        }
+        raw.extend_from_slice(&296u16.to_le_bytes());
+        raw.extend_from_slice(&3u16.to_le_bytes());
+        raw.extend_from_slice(&1u32.to_le_bytes());
+        raw.extend_from_slice(&[3, 0, 0, 0]);
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        for _ in 0..2 {
+            raw.extend_from_slice(&72u32.to_le_bytes());
+            raw.extend_from_slice(&1u32.to_le_bytes());
+        }
+        raw
+    }
+
+    #[test]
+    fn pad_plan_flattens_onto_white() {
+        // 左半透明、右半不透明红，pad 到 1 英寸见方：上下各留白 75 像素
+        let src = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(40, 20, |x, _| if x < 20 { image::Rgba([0, 0, 0, 0]) } else { image::Rgba([200, 0, 0, 255]) }));
+        let plan = plan::plan(&request("pad"), 40, 20, Some(300.0));
+        let steps = plan_steps(&plan);
+        assert_eq!(steps, vec![Step::Resize { w: 300, h: 150 }, Step::Extent { w: 300, h: 300, x: 0, y: -75 }]);
+
+        let out = Canvas::flatten(src).apply(&steps).into_dynamic();
+        assert_eq!((out.width(), out.height()), (plan.final_px.width, plan.final_px.height));
+        let DynamicImage::ImageRgb8(out) = out else { panic!("拍平后应为 8 位 RGB") };
+        assert_eq!(out.get_pixel(150, 10).0, [255, 255, 255]);
+        assert_eq!(out.get_pixel(150, 290).0, [255, 255, 255]);
+        assert_eq!(out.get_pixel(40, 150).0, [255, 255, 255]);
+        assert_eq!(out.get_pixel(260, 150).0, [200, 0, 0]);
+
+        let gray = DynamicImage::ImageLumaA16(ImageBuffer::from_pixel(2, 2, image::LumaA([0u16, 0x8000])));
+        let Canvas::Luma16(gray) = Canvas::flatten(gray) else { panic!("灰度应保持 16 位灰度") };
+        assert_eq!(gray.get_pixel(0, 0).0, [over_white(0, 0x8000, 0xFFFF) as u16]);
+    }
+
+    #[test]
+    fn mirror_plan_samples_the_edges() {
+        let mut req = request("mirror");
+        req.border_left_cm = 2.54;
+        let src = DynamicImage::ImageLuma8(ImageBuffer::from_fn(4, 1, |x, _| Luma([x as u8 * 10])));
+        let plan = plan::plan(&req, 4, 1, Some(2.0));
+        let steps = plan_steps(&plan);
+        assert_eq!(steps, vec![Step::Mirror { w: 6, h: 1, left: 2, top: 0 }]);
+        let DynamicImage::ImageLuma8(out) = Canvas::flatten(src).apply(&steps).into_dynamic() else { panic!("灰度应保持 8 位灰度") };
+        assert_eq!(out.into_raw(), vec![10, 0, 0, 10, 20, 30]);
+    }
+
+    #[test]
+    fn jpeg_quality_reads_back_encoder_quality() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(32, 32, |x, y| Rgb([x as u8 * 8, y as u8 * 8, 90])));
+        for quality in [40, 75, 92, 100] {
+            let found = jpeg_quality(&jpeg(&img, quality)).unwrap();
+            assert!(found.abs_diff(quality) <= 1, "{} → {}", quality, found);
+        }
+        assert_eq!(jpeg_quality(b"\xFF\xD8\xFF\xD9"), None);
+    }
+
+    #[test]
+    fn jpeg_exif_and_icc_survive_insertion() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(24, 16, Rgb([30, 120, 200])));
+        let mut exif = exif_72_cm();
+        stamp_exif_density(&mut exif, 300.0);
+        // 大于单个 APP2 段，必须拆成两段
+        let icc: Vec<u8> = (0..ICC_CHUNK + 100).map(|i| i as u8).collect();
+        let bytes = insert_jpeg_icc(insert_jpeg_exif(jpeg(&img, 90), &exif), &icc);
+
+        let parsed = exif::Reader::new().read_from_container(&mut Cursor::new(&bytes)).unwrap();
+        let field = |tag| parsed.get_field(tag, exif::In::PRIMARY).unwrap().display_value().to_string();
+        assert_eq!(field(exif::Tag::XResolution), "300");
+        assert_eq!(field(exif::Tag::YResolution), "300");
+        assert_eq!(field(exif::Tag::ResolutionUnit), "inch");
+        assert_eq!(jpeg_icc(&bytes), Some(icc));
+        assert_eq!(jpeg_quality(&bytes), jpeg_quality(&jpeg(&img, 90)));
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (24, 16));
+    }
+
+    #[test]
+    fn resize_mitchell_hits_target_size() {
+        let flat = ImageBuffer::from_pixel(7, 5, Rgb([10u8, 128, 250]));
+        let up = resize_mitchell(&flat, 20, 13);
+        assert_eq!(up.dimensions(), (20, 13));
+        // 权重归一化：纯色放大后仍是同一颜色
+        assert!(up.pixels().all(|p| p.0 == [10, 128, 250]));
+
+        let gray = ImageBuffer::from_fn(3, 9, |x, y| Luma([(x * 9000 + y * 700) as u16]));
+        assert_eq!(resize_mitchell(&gray, 11, 31).dimensions(), (11, 31));
+    }
+}
@@ -0,0 +1,362 @@
+// ==========================================
+// 🌟 排版计划：纯几何计算，不读写任何文件
+// ==========================================
+// 原生管线与 magick 兜底都从这里取数：裁切框、重采样尺寸、四边增减像素、成品像素与物理尺寸。
+// plan_process 命令把同一份计划交给前端展示，所见即所得。
+
+use serde::Serialize;
+
+use crate::pipeline::ProcessRequest;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PixelSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 原图上的裁切框（像素，已转正）
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 重采样：目标像素与缩放倍率
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Resample {
+    pub width: u32,
+    pub height: u32,
+    pub scale_x: f64,
+    pub scale_y: f64,
+}
+
+/// 四边像素增减：正数为加白边 / 镜像外扩，负数为裁掉
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Sides {
+    pub top: i64,
+    pub right: i64,
+    pub bottom: i64,
+    pub left: i64,
+}
+
+impl Sides {
+    pub fn is_zero(&self) -> bool {
+        *self == Sides::default()
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Plan {
+    pub mode: String,
+    /// 转正后的原图像素
+    pub source_px: PixelSize,
+    /// 计算边框用的原图 DPI（原图缺失时为输出 DPI）
+    pub source_dpi: f64,
+    pub crop: Option<Rect>,
+    pub resample: Option<Resample>,
+    /// 相对裁切、重采样之后的图像
+    pub sides: Sides,
+    /// 外扩部分镜像取样（否则填白）
+    pub mirror_fill: bool,
+    pub final_px: PixelSize,
+    pub final_width_cm: f64,
+    pub final_height_cm: f64,
+    /// 写入文件的 DPI
+    pub output_dpi: f64,
+    /// 原图像素落到成品上的实际密度（放大时低于输出 DPI）
+    pub effective_dpi: f64,
+}
+
+impl Plan {
+    /// 预览相对成品的缩小比例：成品长边不超过 max_side，不放大
+    pub fn preview_scale(&self, max_side: u32) -> f64 {
+        let long = self.final_px.width.max(self.final_px.height).max(1);
+        (max_side as f64 / long as f64).min(1.0)
+    }
+
+    /// 等比缩小的计划（预览用）：裁切框、重采样、四边与成品一起按同一比例换算
+    pub fn scaled(&self, scale: f64) -> Plan {
+        let px = |v: u32| ((v as f64 * scale).round() as u32).max(1);
+        let side = |v: i64| (v as f64 * scale).round() as i64;
+        let source_px = PixelSize { width: px(self.source_px.width), height: px(self.source_px.height) };
+        let crop = self.crop.map(|r| {
+            let x = ((r.x as f64 * scale).round() as u32).min(source_px.width - 1);
+            let y = ((r.y as f64 * scale).round() as u32).min(source_px.height - 1);
+            Rect { x, y, width: px(r.width).min(source_px.width - x), height: px(r.height).min(source_px.height - y) }
+        });
+        Plan {
+            source_px,
+            crop,
+            resample: self.resample.map(|r| Resample { width: px(r.width), height: px(r.height), ..r }),
+            sides: Sides { top: side(self.sides.top), right: side(self.sides.right), bottom: side(self.sides.bottom), left: side(self.sides.left) },
+            final_px: PixelSize { width: px(self.final_px.width), height: px(self.final_px.height) },
+            ..self.clone()
+        }
+    }
+}
+
+/// 复刻 magick `-resize WxH` / `WxH^` 的尺寸计算：fill 为 true 时铺满，否则装入
+pub(crate) fn scaled_dims(w: u32, h: u32, tw: u32, th: u32, fill: bool) -> (u32, u32) {
+    let fx = tw as f64 / w.max(1) as f64;
+    let fy = th as f64 / h.max(1) as f64;
+    let scale = if fill { fx.max(fy) } else { fx.min(fy) };
+    let nw = ((w as f64 * scale + 0.5).floor() as u32).max(1);
+    let nh = ((h as f64 * scale + 0.5).floor() as u32).max(1);
+    (nw, nh)
+}
+
+fn resample(from_w: u32, from_h: u32, w: u32, h: u32) -> Resample {
+    Resample { width: w, height: h, scale_x: w as f64 / from_w.max(1) as f64, scale_y: h as f64 / from_h.max(1) as f64 }
+}
+
+/// 居中放到 tw×th 画布上时四边的增减（与 `-gravity center -extent` 一致）
+fn centered(rw: u32, rh: u32, tw: u32, th: u32) -> Sides {
+    let left = -((rw as i64 - tw as i64) / 2);
+    let top = -((rh as i64 - th as i64) / 2);
+    Sides { top, right: tw as i64 - rw as i64 - left, bottom: th as i64 - rh as i64 - top, left }
+}
+
+/// 负边框裁切量：不超过剩余尺寸减 1，至少保留 1 像素
+fn clamp_removal(raw: i64, remaining: u32) -> i64 {
+    -(((-raw).max(0) as u32).min(remaining.saturating_sub(1)) as i64)
+}
+
+/// 根据模式生成计划；w/h 为转正后的原图像素，src_dpi 为原图 DPI（缺失时为 None）
+pub(crate) fn plan(req: &ProcessRequest, orig_w: u32, orig_h: u32, src_dpi: Option<f64>) -> Plan {
+    let ow = orig_w as f64;
+    let oh = orig_h as f64;
+    let out_dpi = req.output_dpi();
+    let source_dpi = src_dpi.unwrap_or(out_dpi);
+    // keep 模式像素数不变，沿用原 DPI 才能保持物理尺寸
+    let output_dpi = if req.mode == "keep" { source_dpi } else { out_dpi };
+    let target_w_px = ((req.target_w_cm as f64 / 2.54 * out_dpi).round() as u32).max(1);
+    let target_h_px = ((req.target_h_cm as f64 / 2.54 * out_dpi).round() as u32).max(1);
+    let cm_to_px = |cm: f32| -> i64 { (((cm as f64) / 2.54) * source_dpi).round() as i64 };
+
+    let mut crop = None;
+    let mut resampled = None;
+    let mut sides = Sides::default();
+    let mut mirror_fill = false;
+
+    match req.mode.as_str() {
+        "keep" => {}
+        "crop" => {
+            let px = (req.crop_x as f64 / 100.0 * ow).round() as u32;
+            let py = (req.crop_y as f64 / 100.0 * oh).round() as u32;
+            let pw = ((req.crop_w as f64 / 100.0 * ow).round() as u32).max(1);
+            let ph = ((req.crop_h as f64 / 100.0 * oh).round() as u32).max(1);
+            // 越界部分与 magick 一样直接收缩
+            let x = px.min(orig_w.saturating_sub(1));
+            let y = py.min(orig_h.saturating_sub(1));
+            let w = pw.min(orig_w - x).max(1);
+            let h = ph.min(orig_h - y).max(1);
+            let (rw, rh) = scaled_dims(w, h, target_w_px, target_h_px, true);
+            crop = Some(Rect { x, y, width: w, height: h });
+            resampled = Some(resample(w, h, rw, rh));
+            sides = centered(rw, rh, target_w_px, target_h_px);
+        }
+        "resize" => resampled = Some(resample(orig_w, orig_h, target_w_px, target_h_px)),
+        "border" => {
+            let raw = [cm_to_px(req.border_top_cm), cm_to_px(req.border_right_cm), cm_to_px(req.border_bottom_cm), cm_to_px(req.border_left_cm)];
+            let [top, right, bottom, left] = raw.map(|v| v.max(0));
+            let expanded_w = (orig_w as i64 + left + right).max(1) as u32;
+            let expanded_h = (orig_h as i64 + top + bottom).max(1) as u32;
+            // 先左后右、先上后下裁切，负边框再大也至少留 1 像素
+            let cut_left = clamp_removal(raw[3], expanded_w);
+            let cut_right = clamp_removal(raw[1], (expanded_w as i64 + cut_left).max(1) as u32);
+            let cut_top = clamp_removal(raw[0], expanded_h);
+            let cut_bottom = clamp_removal(raw[2], (expanded_h as i64 + cut_top).max(1) as u32);
+            sides = Sides { top: top + cut_top, right: right + cut_right, bottom: bottom + cut_bottom, left: left + cut_left };
+        }
+        "mirror" => {
+            let grow = |cm: f32| cm_to_px(cm.max(0.0));
+            sides = Sides {
+                top: grow(req.border_top_cm),
+                right: grow(req.border_right_cm),
+                bottom: grow(req.border_bottom_cm),
+                left: grow(req.border_left_cm),
+            };
+            mirror_fill = true;
+        }
+        _ => {
+            // Pad mode (等比留白)
+            let (rw, rh) = scaled_dims(orig_w, orig_h, target_w_px, target_h_px, false);
+            resampled = Some(resample(orig_w, orig_h, rw, rh));
+            sides = centered(rw, rh, target_w_px, target_h_px);
+        }
+    }
+
+    let (base_w, base_h) = resampled.map(|r| (r.width, r.height)).unwrap_or((orig_w, orig_h));
+    let final_px = PixelSize {
+        width: (base_w as i64 + sides.left + sides.right).max(1) as u32,
+        height: (base_h as i64 + sides.top + sides.bottom).max(1) as u32,
+    };
+    let scale = resampled.map(|r| r.scale_x.max(r.scale_y)).unwrap_or(1.0);
+    Plan {
+        mode: req.mode.clone(),
+        source_px: PixelSize { width: orig_w, height: orig_h },
+        source_dpi,
+        crop,
+        resample: resampled,
+        sides,
+        mirror_fill,
+        final_px,
+        final_width_cm: final_px.width as f64 / output_dpi * 2.54,
+        final_height_cm: final_px.height as f64 / output_dpi * 2.54,
+        output_dpi,
+        effective_dpi: output_dpi / scale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorPolicy;
+
+    fn request(mode: &str) -> ProcessRequest {
+        ProcessRequest {
+            mode: mode.to_string(),
+            target_w_cm: 0.0,
+            target_h_cm: 0.0,
+            crop_x: 0.0,
+            crop_y: 0.0,
+            crop_w: 100.0,
+            crop_h: 100.0,
+            border_top_cm: 0.0,
+            border_right_cm: 0.0,
+            border_bottom_cm: 0.0,
+            border_left_cm: 0.0,
+            color_policy: ColorPolicy::Keep,
+            paper: None,
+            default_dpi: Some(300.0),
+        }
+    }
+
+    /// 1 英寸 = 2.54cm，300 DPI 下正好 300 像素
+    const INCH: f32 = 2.54;
+
+    #[test]
+    fn crop_fills_target_and_trims_overflow_evenly() {
+        let mut req = request("crop");
+        req.target_w_cm = INCH;
+        req.target_h_cm = INCH;
+        let p = plan(&req, 600, 400, Some(300.0));
+        assert_eq!(p.crop, Some(Rect { x: 0, y: 0, width: 600, height: 400 }));
+        let r = p.resample.unwrap();
+        assert_eq!((r.width, r.height), (450, 300));
+        assert_eq!(p.sides, Sides { top: 0, right: -75, bottom: 0, left: -75 });
+        assert_eq!(p.final_px, PixelSize { width: 300, height: 300 });
+        assert!((p.final_width_cm - 2.54).abs() < 1e-9);
+        assert!((p.effective_dpi - 400.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn crop_rect_is_clamped_inside_the_image() {
+        let mut req = request("crop");
+        req.target_w_cm = INCH;
+        req.target_h_cm = INCH;
+        req.crop_x = 90.0;
+        req.crop_w = 50.0;
+        let p = plan(&req, 100, 100, Some(300.0));
+        assert_eq!(p.crop, Some(Rect { x: 90, y: 0, width: 10, height: 100 }));
+        assert_eq!(p.final_px, PixelSize { width: 300, height: 300 });
+    }
+
+    #[test]
+    fn pad_fits_and_adds_white_evenly() {
+        let mut req = request("pad");
+        req.target_w_cm = INCH;
+        req.target_h_cm = INCH;
+        let p = plan(&req, 600, 300, Some(72.0));
+        let r = p.resample.unwrap();
+        assert_eq!((r.width, r.height), (300, 150));
+        assert_eq!(p.sides, Sides { top: 75, right: 0, bottom: 75, left: 0 });
+        assert_eq!(p.final_px, PixelSize { width: 300, height: 300 });
+        assert_eq!(p.effective_dpi, 600.0);
+    }
+
+    #[test]
+    fn resize_stretches_to_target() {
+        let mut req = request("resize");
+        req.target_w_cm = INCH * 2.0;
+        req.target_h_cm = INCH;
+        let p = plan(&req, 300, 300, None);
+        let r = p.resample.unwrap();
+        assert_eq!((r.width, r.height), (600, 300));
+        assert_eq!((r.scale_x, r.scale_y), (2.0, 1.0));
+        assert!(p.sides.is_zero());
+        assert_eq!(p.effective_dpi, 150.0);
+    }
+
+    #[test]
+    fn border_uses_source_dpi_and_mixes_add_and_remove() {
+        let mut req = request("border");
+        req.border_top_cm = INCH;
+        req.border_left_cm = -INCH / 2.0;
+        let p = plan(&req, 1000, 1000, Some(200.0));
+        assert_eq!(p.sides, Sides { top: 200, right: 0, bottom: 0, left: -100 });
+        assert_eq!(p.final_px, PixelSize { width: 900, height: 1200 });
+        // 边框模式不重采样，写出 DPI 为输出 DPI
+        assert_eq!(p.output_dpi, 300.0);
+        assert_eq!(p.effective_dpi, 300.0);
+    }
+
+    #[test]
+    fn negative_border_larger_than_image_keeps_one_pixel() {
+        let mut req = request("border");
+        req.border_left_cm = -100.0;
+        req.border_right_cm = -100.0;
+        req.border_top_cm = -100.0;
+        let p = plan(&req, 100, 50, Some(300.0));
+        assert_eq!(p.sides, Sides { top: -49, right: 0, bottom: 0, left: -99 });
+        assert_eq!(p.final_px, PixelSize { width: 1, height: 1 });
+    }
+
+    #[test]
+    fn mirror_ignores_negative_sides() {
+        let mut req = request("mirror");
+        req.border_top_cm = INCH;
+        req.border_right_cm = -INCH;
+        let p = plan(&req, 100, 100, Some(300.0));
+        assert!(p.mirror_fill);
+        assert_eq!(p.sides, Sides { top: 300, right: 0, bottom: 0, left: 0 });
+        assert_eq!(p.final_px, PixelSize { width: 100, height: 400 });
+    }
+
+    #[test]
+    fn keep_preserves_pixels_and_source_dpi() {
+        let p = plan(&request("keep"), 240, 120, Some(240.0));
+        assert_eq!(p.final_px, PixelSize { width: 240, height: 120 });
+        assert_eq!(p.output_dpi, 240.0);
+        assert!((p.final_width_cm - 2.54).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scaled_plan_keeps_geometry_proportional() {
+        let mut req = request("border");
+        req.border_top_cm = INCH;
+        req.border_left_cm = -INCH;
+        let p = plan(&req, 2000, 1000, Some(300.0));
+        assert_eq!(p.preview_scale(425), 0.25);
+        let small = p.scaled(p.preview_scale(425));
+        assert_eq!(small.source_px, PixelSize { width: 500, height: 250 });
+        assert_eq!(small.sides, Sides { top: 75, right: 0, bottom: 0, left: -75 });
+        assert_eq!(small.final_px, PixelSize { width: 425, height: 325 });
+        // 成品比预览上限还小时不放大
+        assert_eq!(p.preview_scale(4000), 1.0);
+    }
+
+    #[test]
+    fn missing_source_dpi_falls_back_to_output_dpi() {
+        let mut req = request("border");
+        req.border_top_cm = INCH;
+        req.default_dpi = Some(150.0);
+        let p = plan(&req, 10, 10, None);
+        assert_eq!(p.source_dpi, 150.0);
+        assert_eq!(p.sides.top, 150);
+    }
+}
@@ -0,0 +1,278 @@
+// ==========================================
+// 🌟 印前检查：整单图片在上纸之前逐项体检，可导出 HTML/PDF 报告
+// ==========================================
+// 每张图按分配的纸张与目标尺寸算出排版计划（只用 identify 的尺寸与 DPI，不解码像素），
+// 再结合体检信息检查：有效 PPI、CMYK/Lab、16 位、ICC 缺失或少见、透明通道、
+// 裁切模式下被裁掉的比例、疑似截图的小文件、缺少 DPI、超出纸张幅宽。
+
+use serde::Serialize;
+
+use crate::i18n;
+use crate::inspect::ImageInspection;
+use crate::paper::PaperProfile;
+use crate::plan::Plan;
+
+/// 有效 PPI 的默认下限
+pub(crate) const DEFAULT_MIN_PPI: f64 = 150.0;
+/// 裁切模式下裁掉超过这个比例的画面时提示
+const CROP_LOSS_WARN: f64 = 0.05;
+/// 小于这个字节数或长边像素的图，多半是截图或网络小图
+const TINY_BYTES: u64 = 200 * 1024;
+const TINY_SIDE: u32 = 800;
+/// 常见的 ICC 名称片段（小写），其余视为少见配置
+const KNOWN_ICC: &[&str] = &[
+    "srgb", "adobe rgb", "adobergb", "display p3", "prophoto", "fogra", "gracol", "swop",
+    "web coated", "japan color", "coated", "uncoated", "gray gamma", "dot gain", "generic",
+];
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    fn class(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    fn label(self) -> String {
+        i18n::t(&format!("preflight.severity.{}", self.class()))
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Issue {
+    /// 稳定的检查项代码，前端按此归类
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+fn issue(code: &'static str, severity: Severity, message: String) -> Issue {
+    Issue { code, severity, message }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreflightItem {
+    pub path: String,
+    pub paper: Option<String>,
+    pub mode: String,
+    pub target_w_cm: f32,
+    pub target_h_cm: f32,
+    pub info: Option<ImageInspection>,
+    pub plan: Option<Plan>,
+    /// 按严重程度排序
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreflightReport {
+    pub min_ppi: f64,
+    pub items: Vec<PreflightItem>,
+    pub errors: usize,
+    pub warnings: usize,
+    /// 可直接交给 export_file / html_to_pdf 的报告
+    pub html: String,
+}
+
+/// 读不出来的文件只有一项错误
+pub(crate) fn unreadable(path: String, paper: Option<String>, mode: String, target_w_cm: f32, target_h_cm: f32, error: String) -> PreflightItem {
+    PreflightItem {
+        path, paper, mode, target_w_cm, target_h_cm,
+        info: None,
+        plan: None,
+        issues: vec![issue("unreadable", Severity::Error, i18n::tf("preflight.unreadable", &[&error]))],
+    }
+}
+
+/// 对一张图逐项检查
+pub(crate) fn check(info: &ImageInspection, plan: &Plan, paper: Option<&PaperProfile>, min_ppi: f64) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if plan.effective_dpi + 0.5 < min_ppi {
+        issues.push(issue(
+            "low_ppi",
+            Severity::Error,
+            i18n::tf(
+                "preflight.low_ppi",
+                &[
+                    &format!("{:.0}", plan.effective_dpi),
+                    &format!("{:.0}", min_ppi),
+                    &format!("{:.1}", plan.final_width_cm),
+                    &format!("{:.1}", plan.final_height_cm),
+                ],
+            ),
+        ));
+    }
+    if let Some(paper) = paper {
+        if let Err(e) = paper.check_width(plan.final_px.width, plan.final_px.height, plan.output_dpi) {
+            issues.push(issue("too_wide", Severity::Error, e));
+        }
+    }
+
+    match info.color_model.as_str() {
+        "CMYK" => issues.push(issue("cmyk", Severity::Warning, i18n::t("preflight.cmyk"))),
+        "Lab" => issues.push(issue("lab", Severity::Warning, i18n::t("preflight.lab"))),
+        _ => {}
+    }
+    if info.bit_depth > 8 {
+        issues.push(issue("high_bit_depth", Severity::Info, i18n::tf("preflight.high_bit_depth", &[&info.bit_depth])));
+    }
+
+    match &info.icc_description {
+        None if info.color_model == "CMYK" => {
+            issues.push(issue("missing_icc", Severity::Warning, i18n::t("preflight.missing_icc_cmyk")))
+        }
+        None => issues.push(issue("missing_icc", Severity::Info, i18n::t("preflight.missing_icc"))),
+        Some(desc) => {
+            let lower = desc.to_lowercase();
+            if !KNOWN_ICC.iter().any(|known| lower.contains(known)) {
+                issues.push(issue("unusual_icc", Severity::Warning, i18n::tf("preflight.unusual_icc", &[desc])));
+            }
+        }
+    }
+
+    if info.has_alpha {
+        issues.push(issue("transparency", Severity::Warning, i18n::t("preflight.transparency")));
+    }
+
+    if let Some(crop) = plan.crop.filter(|_| plan.mode == "crop") {
+        let source_area = plan.source_px.width as f64 * plan.source_px.height as f64;
+        let loss = 1.0 - (crop.width as f64 * crop.height as f64) / source_area.max(1.0);
+        if loss > CROP_LOSS_WARN {
+            issues.push(issue(
+                "aspect_crop",
+                Severity::Warning,
+                i18n::tf("preflight.aspect_crop", &[&format!("{:.0}", loss * 100.0)]),
+            ));
+        }
+    }
+
+    if info.file_size < TINY_BYTES || info.width_px.max(info.height_px) < TINY_SIDE {
+        issues.push(issue(
+            "tiny_file",
+            Severity::Warning,
+            i18n::tf("preflight.tiny_file", &[&(info.file_size / 1024), &info.width_px, &info.height_px]),
+        ));
+    }
+
+    if !info.dpi_from_file {
+        issues.push(issue("missing_dpi", Severity::Info, i18n::tf("preflight.missing_dpi", &[&format!("{:.0}", info.dpi)])));
+    }
+
+    issues.sort_by_key(|i| i.severity);
+    issues
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// 报告页面：与报价单同样的版式，错误与警告标色
+fn render_html(title: &str, min_ppi: f64, items: &[PreflightItem], errors: usize, warnings: usize) -> String {
+    let passed = items.iter().filter(|item| item.issues.iter().all(|i| i.severity == Severity::Info)).count();
+
+    let rows = items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| {
+            let pixels = item.info.as_ref().map(|i| format!("{}×{}", i.width_px, i.height_px)).unwrap_or_else(|| "—".to_string());
+            let color = item
+                .info
+                .as_ref()
+                .map(|i| format!("{} · {}{}", i.color_model, i18n::tf("preflight.bits", &[&i.bit_depth]), i.icc_description.as_deref().map(|d| format!(" · {}", d)).unwrap_or_default()))
+                .unwrap_or_else(|| "—".to_string());
+            let ppi = item.plan.as_ref().map(|p| format!("{:.0}", p.effective_dpi)).unwrap_or_else(|| "—".to_string());
+            let issues = if item.issues.is_empty() {
+                format!("<span class=\"ok\">{}</span>", i18n::t("preflight.passed"))
+            } else {
+                item.issues
+                    .iter()
+                    .map(|i| format!("<div class=\"{}\">[{}] {}</div>", i.severity.class(), i.severity.label(), escape_html(&i.message)))
+                    .collect::<Vec<_>>()
+                    .join("")
+            };
+            format!(
+                "<tr>\n  <td>{}</td>\n  <td>{}</td>\n  <td>{}</td>\n  <td>{:.1}×{:.1}</td>\n  <td>{}</td>\n  <td>{}</td>\n  <td>{}</td>\n  <td>{}</td>\n</tr>",
+                idx + 1,
+                escape_html(file_name(&item.path)),
+                escape_html(item.paper.as_deref().unwrap_or("—")),
+                item.target_w_cm,
+                item.target_h_cm,
+                pixels,
+                ppi,
+                escape_html(&color),
+                issues
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let summary = i18n::tf("preflight.summary", &[&items.len(), &passed, &errors, &warnings, &format!("{:.0}", min_ppi)]);
+    let headers = ["index", "file", "paper", "size", "pixels", "ppi", "color", "result"]
+        .iter()
+        .map(|col| format!("<th>{}</th>", escape_html(&i18n::t(&format!("preflight.col.{}", col)))))
+        .collect::<String>();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8"/>
+<meta name="viewport" content="width=device-width,initial-scale=1"/>
+<title>{title}</title>
+<style>
+  body {{ font-family: "PingFang SC", "Microsoft YaHei", sans-serif; padding: 16px; background: #f5f5f5; }}
+  h1 {{ font-size: 18px; color: #333; margin-bottom: 6px; }}
+  p.summary {{ font-size: 13px; color: #555; margin: 0 0 12px; }}
+  table {{ border-collapse: collapse; background: #fff; box-shadow: 0 1px 3px rgba(0,0,0,0.08); }}
+  th, td {{ border: 1px solid #e5e5e5; padding: 6px 8px; text-align: left; font-size: 12px; vertical-align: top; }}
+  th {{ background: #f8f8f8; font-weight: 600; color: #555; }}
+  .error {{ color: #dc2626; font-weight: 600; }}
+  .warning {{ color: #d97706; }}
+  .info {{ color: #6b7280; }}
+  .ok {{ color: #16a34a; font-weight: 600; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p class="summary">{summary}</p>
+<table>
+<thead><tr>
+  {headers}
+</tr></thead>
+<tbody>
+{rows}
+</tbody>
+</table>
+</body>
+</html>"#,
+        lang = i18n::current().code(),
+        title = escape_html(title),
+        summary = escape_html(&summary),
+        headers = headers,
+        rows = rows,
+    )
+}
+
+/// 汇总整单结果并生成报告
+pub(crate) fn report(title: &str, min_ppi: f64, items: Vec<PreflightItem>) -> PreflightReport {
+    let count = |severity: Severity| items.iter().filter(|item| item.issues.iter().any(|i| i.severity == severity)).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    let html = render_html(title, min_ppi, &items, errors, warnings);
+    PreflightReport { min_ppi, items, errors, warnings, html }
+}
@@ -0,0 +1,640 @@
+// ==========================================
+// 🌟 原生 PSD/PSB：只读“合并图像数据”区，不碰图层
+// ==========================================
+// PSD 文件末尾的 Image Data 区就是 Photoshop 存好的整幅合成图，
+// 直接读它即可替代 magick 的 `[0] -flatten`，同时拿到分辨率 (1005) 与 ICC (1039)。
+// 未勾选“最大兼容”保存的文件这一区是空白的，必须明确报错而不是输出破图。
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use image::{DynamicImage, ImageBuffer};
+
+use crate::i18n;
+
+const SIGNATURE: &[u8; 4] = b"8BPS";
+const RESOURCE_SIGNATURE: &[u8; 4] = b"8BIM";
+const RES_RESOLUTION: u16 = 1005;
+const RES_ICC_PROFILE: u16 = 1039;
+const RES_VERSION_INFO: u16 = 1057;
+/// PSD 单边像素上限，超过必须写成 PSB
+const PSD_MAX_SIDE: u32 = 30000;
+
+const MODE_GRAYSCALE: u16 = 1;
+const MODE_RGB: u16 = 3;
+
+/// 文件头与图像资源中解析出的信息
+#[derive(Clone, Debug)]
+pub(crate) struct PsdInfo {
+    /// 1 = PSD，2 = PSB
+    pub version: u16,
+    pub channels: u16,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u16,
+    pub color_mode: u16,
+    /// 资源 1005 中的分辨率（Photoshop 始终以每英寸存储）
+    pub dpi: Option<f64>,
+    /// 资源 1039 中的 ICC 描述文件
+    pub icc: Option<Vec<u8>>,
+    /// 资源 1057 中的 hasRealMergedData；缺失时视为有
+    pub has_merged_data: bool,
+}
+
+impl PsdInfo {
+    /// 原生管线能否解码合并图（与 decode 的限制一致）
+    pub fn natively_decodable(&self) -> bool {
+        let color_channels = match self.color_mode {
+            MODE_GRAYSCALE => 1,
+            MODE_RGB => 3,
+            _ => return false,
+        };
+        self.has_merged_data && (self.depth == 8 || self.depth == 16) && self.channels >= color_channels
+    }
+}
+
+fn read_u16<R: Read>(r: &mut R) -> std::io::Result<u16> {
+    let mut b = [0u8; 2];
+    r.read_exact(&mut b)?;
+    Ok(u16::from_be_bytes(b))
+}
+
+fn read_u32<R: Read>(r: &mut R) -> std::io::Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_be_bytes(b))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> std::io::Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_be_bytes(b))
+}
+
+fn io_err(e: std::io::Error) -> String {
+    i18n::tf("err.psd_parse", &[&e])
+}
+
+/// 按文件签名判断，不看扩展名
+pub(crate) fn is_psd(path: &Path) -> bool {
+    let mut sig = [0u8; 4];
+    File::open(path).and_then(|mut f| f.read_exact(&mut sig)).is_ok() && &sig == SIGNATURE
+}
+
+/// 资源区中各资源块的 (id, 数据起点, 数据长度)；遇到结构异常的块就停止
+fn resource_blocks(section: &[u8]) -> Vec<(u16, usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut pos = 0usize;
+    while pos + 12 <= section.len() && &section[pos..pos + 4] == RESOURCE_SIGNATURE {
+        let id = u16::from_be_bytes([section[pos + 4], section[pos + 5]]);
+        // Pascal 字符串名称，含长度字节后补齐到偶数
+        let name_len = section[pos + 6] as usize;
+        let mut p = pos + 6 + ((name_len + 2) & !1);
+        if p + 4 > section.len() {
+            break;
+        }
+        let size = u32::from_be_bytes([section[p], section[p + 1], section[p + 2], section[p + 3]]) as usize;
+        p += 4;
+        if section.get(p..p + size).is_none() {
+            break;
+        }
+        blocks.push((id, p, size));
+        pos = p + ((size + 1) & !1);
+    }
+    blocks
+}
+
+/// 遍历图像资源区，回调 (id, data)
+fn walk_resources(section: &[u8], mut f: impl FnMut(u16, &[u8])) {
+    for (id, start, size) in resource_blocks(section) {
+        f(id, &section[start..start + size]);
+    }
+}
+
+/// 读取文件头与资源区，读完后 r 恰好停在“图层与蒙版”区开头
+fn read_head<R: Read + Seek>(r: &mut R) -> Result<PsdInfo, String> {
+    let mut sig = [0u8; 4];
+    r.read_exact(&mut sig).map_err(io_err)?;
+    if &sig != SIGNATURE {
+        return Err(i18n::t("err.psd_invalid"));
+    }
+    let version = read_u16(r).map_err(io_err)?;
+    if version != 1 && version != 2 {
+        return Err(i18n::tf("err.psd_version", &[&version]));
+    }
+    r.seek(SeekFrom::Current(6)).map_err(io_err)?;
+    let channels = read_u16(r).map_err(io_err)?;
+    let height = read_u32(r).map_err(io_err)?;
+    let width = read_u32(r).map_err(io_err)?;
+    let depth = read_u16(r).map_err(io_err)?;
+    let color_mode = read_u16(r).map_err(io_err)?;
+
+    let color_data_len = read_u32(r).map_err(io_err)?;
+    r.seek(SeekFrom::Current(color_data_len as i64)).map_err(io_err)?;
+
+    let resources_len = read_u32(r).map_err(io_err)? as usize;
+    let mut resources = vec![0u8; resources_len];
+    r.read_exact(&mut resources).map_err(io_err)?;
+
+    let mut info = PsdInfo {
+        version,
+        channels,
+        width,
+        height,
+        depth,
+        color_mode,
+        dpi: None,
+        icc: None,
+        has_merged_data: true,
+    };
+    walk_resources(&resources, |id, data| match id {
+        RES_RESOLUTION if data.len() >= 4 => {
+            let fixed = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            if fixed > 0 {
+                info.dpi = Some(fixed as f64 / 65536.0);
+            }
+        }
+        RES_ICC_PROFILE if !data.is_empty() => info.icc = Some(data.to_vec()),
+        RES_VERSION_INFO if data.len() >= 5 => info.has_merged_data = data[4] != 0,
+        _ => {}
+    });
+    Ok(info)
+}
+
+/// 只读文件头与资源（不解码像素），供尺寸/DPI 探测
+pub(crate) fn read_info(path: &Path) -> Result<PsdInfo, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    read_head(&mut BufReader::new(file))
+}
+
+/// 体检用：图层数与合并图的压缩方式（0 RAW、1 RLE、2/3 ZIP），不解码像素
+pub(crate) fn layout(path: &Path) -> Result<(u32, u16), String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    let mut r = BufReader::new(file);
+    let info = read_head(&mut r)?;
+    // PSB 的区段长度字段为 8 字节
+    let read_len = |r: &mut BufReader<File>| if info.version == 2 { read_u64(r) } else { read_u32(r).map(u64::from) };
+    let layer_len = read_len(&mut r).map_err(io_err)?;
+    let merged_at = r.stream_position().map_err(io_err)? + layer_len;
+    let mut layers = 0;
+    if layer_len > 0 && read_len(&mut r).map_err(io_err)? > 0 {
+        // 图层数为负表示第一个 Alpha 通道存的是合并图的透明度
+        layers = (read_u16(&mut r).map_err(io_err)? as i16).unsigned_abs() as u32;
+    }
+    r.seek(SeekFrom::Start(merged_at)).map_err(io_err)?;
+    Ok((layers, read_u16(&mut r).map_err(io_err)?))
+}
+
+/// 非 PSD 直接放行；PSD 缺合并图时返回明确错误（magick 读出来同样是破图）
+pub(crate) fn ensure_merged_data(path: &Path) -> Result<(), String> {
+    if !is_psd(path) {
+        return Ok(());
+    }
+    if read_info(path)?.has_merged_data {
+        Ok(())
+    } else {
+        Err(i18n::t("err.psd_no_merged"))
+    }
+}
+
+/// PackBits 解压一行
+fn unpack_bits(src: &[u8], dst: &mut [u8]) -> Result<(), String> {
+    let (mut i, mut o) = (0usize, 0usize);
+    while i < src.len() && o < dst.len() {
+        let n = src[i] as i8;
+        i += 1;
+        if n >= 0 {
+            let count = n as usize + 1;
+            let chunk = src.get(i..i + count).ok_or_else(|| i18n::t("err.psd_rle"))?;
+            let end = (o + count).min(dst.len());
+            dst[o..end].copy_from_slice(&chunk[..end - o]);
+            i += count;
+            o = end;
+        } else if n != -128 {
+            let count = (1 - n as isize) as usize;
+            let value = *src.get(i).ok_or_else(|| i18n::t("err.psd_rle"))?;
+            let end = (o + count).min(dst.len());
+            dst[o..end].fill(value);
+            i += 1;
+            o = end;
+        }
+    }
+    Ok(())
+}
+
+/// 解码合并图像（8/16 位灰度与 RGB；多余的 Alpha/专色通道忽略，合并图本身已压在白底上）
+pub(crate) fn decode(path: &Path) -> Result<(DynamicImage, PsdInfo), String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    let mut r = BufReader::new(file);
+    let info = read_head(&mut r)?;
+    if !info.has_merged_data {
+        return Err(i18n::t("err.psd_no_merged"));
+    }
+
+    let color_channels: usize = match info.color_mode {
+        MODE_GRAYSCALE => 1,
+        MODE_RGB => 3,
+        other => return Err(i18n::tf("err.psd_mode", &[&other])),
+    };
+    if info.depth != 8 && info.depth != 16 {
+        return Err(i18n::tf("err.psd_depth", &[&info.depth]));
+    }
+    if (info.channels as usize) < color_channels {
+        return Err(i18n::t("err.psd_channels"));
+    }
+
+    // 跳过图层与蒙版区（PSB 长度字段为 8 字节）
+    let layer_len = if info.version == 2 { read_u64(&mut r).map_err(io_err)? } else { read_u32(&mut r).map_err(io_err)? as u64 };
+    r.seek(SeekFrom::Current(layer_len as i64)).map_err(io_err)?;
+
+    let (w, h) = (info.width as usize, info.height as usize);
+    let row_bytes = w * info.depth as usize / 8;
+    let compression = read_u16(&mut r).map_err(io_err)?;
+    let mut planes = vec![vec![0u8; row_bytes * h]; color_channels];
+
+    match compression {
+        0 => {
+            for plane in planes.iter_mut() {
+                r.read_exact(plane).map_err(io_err)?;
+            }
+        }
+        1 => {
+            // 先是所有通道每一行的字节数表，再是逐行 PackBits 数据
+            let rows = info.channels as usize * h;
+            let mut counts = Vec::with_capacity(rows);
+            for _ in 0..rows {
+                let n = if info.version == 2 { read_u32(&mut r).map_err(io_err)? as usize } else { read_u16(&mut r).map_err(io_err)? as usize };
+                counts.push(n);
+            }
+            let mut packed = Vec::new();
+            for (c, plane) in planes.iter_mut().enumerate() {
+                for y in 0..h {
+                    packed.resize(counts[c * h + y], 0);
+                    r.read_exact(&mut packed).map_err(io_err)?;
+                    unpack_bits(&packed, &mut plane[y * row_bytes..(y + 1) * row_bytes])?;
+                }
+            }
+        }
+        // Photoshop 写合并图只用 RAW/RLE，ZIP 仅出现在图层通道里
+        other => return Err(i18n::tf("err.psd_compression", &[&other])),
+    }
+
+    let (wu, hu) = (info.width, info.height);
+    let bad = || i18n::t("err.psd_merged_length");
+    let img = if info.depth == 8 {
+        let mut buf = vec![0u8; w * h * color_channels];
+        for (c, plane) in planes.iter().enumerate() {
+            for (i, v) in plane.iter().enumerate() {
+                buf[i * color_channels + c] = *v;
+            }
+        }
+        if color_channels == 1 {
+            DynamicImage::ImageLuma8(ImageBuffer::from_raw(wu, hu, buf).ok_or_else(bad)?)
+        } else {
+            DynamicImage::ImageRgb8(ImageBuffer::from_raw(wu, hu, buf).ok_or_else(bad)?)
+        }
+    } else {
+        let mut buf = vec![0u16; w * h * color_channels];
+        for (c, plane) in planes.iter().enumerate() {
+            for (i, pair) in plane.chunks_exact(2).enumerate() {
+                buf[i * color_channels + c] = u16::from_be_bytes([pair[0], pair[1]]);
+            }
+        }
+        if color_channels == 1 {
+            DynamicImage::ImageLuma16(ImageBuffer::from_raw(wu, hu, buf).ok_or_else(bad)?)
+        } else {
+            DynamicImage::ImageRgb16(ImageBuffer::from_raw(wu, hu, buf).ok_or_else(bad)?)
+        }
+    };
+    Ok((img, info))
+}
+
+/// PackBits 压缩一行（连续 3 个以上相同字节才记作重复段）
+fn pack_bits(row: &[u8], out: &mut Vec<u8>) {
+    let n = row.len();
+    let mut i = 0;
+    while i < n {
+        let mut run = 1;
+        while i + run < n && run < 128 && row[i + run] == row[i] {
+            run += 1;
+        }
+        if run >= 3 {
+            out.push((1 - run as i32) as i8 as u8);
+            out.push(row[i]);
+            i += run;
+            continue;
+        }
+        let start = i;
+        while i < n && i - start < 128 {
+            if i + 2 < n && row[i] == row[i + 1] && row[i] == row[i + 2] {
+                break;
+            }
+            i += 1;
+        }
+        out.push((i - start - 1) as u8);
+        out.extend_from_slice(&row[start..i]);
+    }
+}
+
+fn push_resource(out: &mut Vec<u8>, id: u16, data: &[u8]) {
+    out.extend_from_slice(RESOURCE_SIGNATURE);
+    out.extend_from_slice(&id.to_be_bytes());
+    // 空名称：长度字节 0 + 1 字节补齐
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// 资源 1005：水平/垂直分辨率（16.16 定点，每英寸），显示单位沿用“英寸”
+fn resolution_resource(dpi: f64) -> Vec<u8> {
+    let fixed = (dpi * 65536.0).round() as u32;
+    let mut data = Vec::with_capacity(16);
+    for _ in 0..2 {
+        data.extend_from_slice(&fixed.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+    }
+    data
+}
+
+/// 写出单层（仅合并图）PSD，RLE 压缩；超出 PSD 尺寸上限时自动写 PSB
+pub(crate) fn encode(img: &DynamicImage, output: &Path, dpi: f64, icc: Option<&[u8]>) -> Result<(), String> {
+    let (color_mode, channels, depth): (u16, usize, u16) = match img {
+        DynamicImage::ImageLuma8(_) => (MODE_GRAYSCALE, 1, 8),
+        DynamicImage::ImageLuma16(_) => (MODE_GRAYSCALE, 1, 16),
+        DynamicImage::ImageRgb16(_) => (MODE_RGB, 3, 16),
+        _ => (MODE_RGB, 3, 8),
+    };
+    let (w, h) = (img.width(), img.height());
+    let version: u16 = if w > PSD_MAX_SIDE || h > PSD_MAX_SIDE { 2 } else { 1 };
+
+    // 拆成平面通道的大端字节
+    let planar: Vec<Vec<u8>> = match img {
+        DynamicImage::ImageLuma8(buf) => vec![buf.as_raw().clone()],
+        DynamicImage::ImageLuma16(buf) => vec![buf.as_raw().iter().flat_map(|v| v.to_be_bytes()).collect()],
+        DynamicImage::ImageRgb16(buf) => (0..3)
+            .map(|c| buf.as_raw().iter().skip(c).step_by(3).flat_map(|v| v.to_be_bytes()).collect())
+            .collect(),
+        other => {
+            let rgb = other.to_rgb8();
+            (0..3).map(|c| rgb.as_raw().iter().skip(c).step_by(3).copied().collect()).collect()
+        }
+    };
+
+    let row_bytes = w as usize * depth as usize / 8;
+    let mut counts: Vec<usize> = Vec::with_capacity(channels * h as usize);
+    let mut packed = Vec::new();
+    for plane in &planar {
+        for row in plane.chunks_exact(row_bytes.max(1)) {
+            let before = packed.len();
+            pack_bits(row, &mut packed);
+            counts.push(packed.len() - before);
+        }
+    }
+
+    let file = File::create(output).map_err(|e| format!("{}: {}", i18n::t("err.create_output"), e))?;
+    let mut out = BufWriter::new(file);
+    let mut head = Vec::with_capacity(64);
+    head.extend_from_slice(SIGNATURE);
+    head.extend_from_slice(&version.to_be_bytes());
+    head.extend_from_slice(&[0u8; 6]);
+    head.extend_from_slice(&(channels as u16).to_be_bytes());
+    head.extend_from_slice(&h.to_be_bytes());
+    head.extend_from_slice(&w.to_be_bytes());
+    head.extend_from_slice(&depth.to_be_bytes());
+    head.extend_from_slice(&color_mode.to_be_bytes());
+    // 颜色模式数据区为空
+    head.extend_from_slice(&0u32.to_be_bytes());
+
+    let mut resources = Vec::new();
+    push_resource(&mut resources, RES_RESOLUTION, &resolution_resource(dpi));
+    if let Some(profile) = icc {
+        push_resource(&mut resources, RES_ICC_PROFILE, profile);
+    }
+    head.extend_from_slice(&(resources.len() as u32).to_be_bytes());
+    head.extend_from_slice(&resources);
+
+    // 无图层：图层与蒙版区长度为 0
+    if version == 2 {
+        head.extend_from_slice(&0u64.to_be_bytes());
+    } else {
+        head.extend_from_slice(&0u32.to_be_bytes());
+    }
+    head.extend_from_slice(&1u16.to_be_bytes());
+    for n in &counts {
+        if version == 2 {
+            head.extend_from_slice(&(*n as u32).to_be_bytes());
+        } else {
+            head.extend_from_slice(&(*n as u16).to_be_bytes());
+        }
+    }
+
+    let write_err = |e: std::io::Error| format!("{}: {}", i18n::t("err.write_output"), e);
+    out.write_all(&head).map_err(write_err)?;
+    out.write_all(&packed).map_err(write_err)?;
+    out.flush().map_err(write_err)
+}
+
+/// rename_files 用：只改资源 1005 里的两个分辨率值，其余字节（图层、资源名称、其他资源）原样保留。
+/// 先写到输出旁的临时文件再替换，不改名（输入输出相同）时中途失败也不会损坏原图
+pub(crate) fn stamp_density(input: &Path, output: &Path, dpi: f64) -> Result<(), String> {
+    let temp = crate::replace::temp_path(output);
+    let written = write_stamped(input, &temp, dpi).and_then(|_| crate::replace::sync_file(&temp));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+    crate::replace::commit(&temp, output)
+}
+
+/// 只读文件头与资源区；已有 1005 时复制整个文件后就地改写 8 个字节，
+/// 没有时在资源区最前面插入一块，其余内容流式拷贝，大文件也不整个读进内存
+fn write_stamped(input: &Path, temp: &Path, dpi: f64) -> Result<(), String> {
+    let corrupt = || i18n::t("err.psd_structure");
+    let mut reader = BufReader::new(File::open(input).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?);
+    let mut head = [0u8; 30];
+    reader.read_exact(&mut head).map_err(|_| i18n::t("err.psd_invalid"))?;
+    if &head[..4] != SIGNATURE {
+        return Err(i18n::t("err.psd_invalid"));
+    }
+    let color_len = u32::from_be_bytes([head[26], head[27], head[28], head[29]]) as u64;
+    reader.seek(SeekFrom::Current(color_len as i64)).map_err(|_| corrupt())?;
+    let res_len = read_u32(&mut reader).map_err(|_| corrupt())? as u64;
+    let section_start = 30 + color_len + 4;
+    let mut section = Vec::new();
+    (&mut reader).take(res_len).read_to_end(&mut section).map_err(|_| corrupt())?;
+    if section.len() as u64 != res_len {
+        return Err(corrupt());
+    }
+
+    // hRes、vRes 是每英寸像素的 16.16 定点数，显示单位字段保持不变
+    let fixed = ((dpi * 65536.0).round() as u32).to_be_bytes();
+    let write_err = |e: std::io::Error| format!("{}: {}", i18n::t("err.write_output"), e);
+    match resource_blocks(&section).into_iter().find(|(id, _, size)| *id == RES_RESOLUTION && *size >= 12) {
+        Some((_, start, _)) => {
+            drop(reader);
+            std::fs::copy(input, temp).map_err(|e| format!("{}: {}", i18n::t("err.copy"), e))?;
+            let mut file = std::fs::OpenOptions::new().write(true).open(temp).map_err(write_err)?;
+            let at = section_start + start as u64;
+            file.seek(SeekFrom::Start(at)).and_then(|_| file.write_all(&fixed)).map_err(write_err)?;
+            file.seek(SeekFrom::Start(at + 8)).and_then(|_| file.write_all(&fixed)).map_err(write_err)
+        }
+        None => {
+            let mut block = Vec::with_capacity(32);
+            push_resource(&mut block, RES_RESOLUTION, &resolution_resource(dpi));
+            let mut writer = BufWriter::new(File::create(temp).map_err(|e| format!("{}: {}", i18n::t("err.create_output"), e))?);
+            reader.seek(SeekFrom::Start(0)).map_err(|_| corrupt())?;
+            std::io::copy(&mut (&mut reader).take(30 + color_len), &mut writer).map_err(write_err)?;
+            writer.write_all(&((res_len as usize + block.len()) as u32).to_be_bytes()).map_err(write_err)?;
+            writer.write_all(&block).map_err(write_err)?;
+            writer.write_all(&section).map_err(write_err)?;
+            reader.seek(SeekFrom::Start(section_start + res_len)).map_err(|_| corrupt())?;
+            std::io::copy(&mut reader, &mut writer).map_err(write_err)?;
+            writer.flush().map_err(write_err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("psd-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(37, 11, |x, y| {
+            // 左半边大片纯色走重复段，右半边逐像素变化走字面段
+            if x < 20 { image::Rgb([200, 10, 30]) } else { image::Rgb([x as u8 * 7, y as u8 * 13, (x * y) as u8]) }
+        }))
+    }
+
+    /// 手工拼一个 RAW 合并图的 8 位 RGB PSD；merged 为 Some 时写入资源 1057
+    fn raw_psd(img: &image::RgbImage, merged: Option<bool>) -> Vec<u8> {
+        let (w, h) = img.dimensions();
+        let mut out = Vec::new();
+        out.extend_from_slice(SIGNATURE);
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&[0u8; 6]);
+        out.extend_from_slice(&3u16.to_be_bytes());
+        out.extend_from_slice(&h.to_be_bytes());
+        out.extend_from_slice(&w.to_be_bytes());
+        out.extend_from_slice(&8u16.to_be_bytes());
+        out.extend_from_slice(&MODE_RGB.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        let mut resources = Vec::new();
+        if let Some(has_merged) = merged {
+            push_resource(&mut resources, RES_VERSION_INFO, &[0, 0, 0, 1, has_merged as u8, 0, 0, 0, 0]);
+        }
+        out.extend_from_slice(&(resources.len() as u32).to_be_bytes());
+        out.extend_from_slice(&resources);
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(&0u16.to_be_bytes());
+        for c in 0..3 {
+            out.extend(img.as_raw().iter().skip(c).step_by(3));
+        }
+        out
+    }
+
+    #[test]
+    fn pack_bits_round_trips() {
+        let mut long = vec![5u8; 300];
+        long.extend((0..=255u8).cycle().take(400));
+        long.extend_from_slice(&[1, 1, 2, 2, 2, 3]);
+        for row in [vec![], vec![9], vec![1, 2], long] {
+            let mut packed = Vec::new();
+            pack_bits(&row, &mut packed);
+            let mut back = vec![0u8; row.len()];
+            unpack_bits(&packed, &mut back).unwrap();
+            assert_eq!(back, row);
+        }
+    }
+
+    #[test]
+    fn rle_round_trip_keeps_pixels_dpi_and_icc() {
+        let dir = test_dir("rle");
+        let path = dir.join("a.psd");
+        let img = gradient();
+        encode(&img, &path, 300.0, Some(b"fake-icc")).unwrap();
+        let (back, info) = decode(&path).unwrap();
+        assert_eq!(back.to_rgb8(), img.to_rgb8());
+        assert_eq!(info.dpi, Some(300.0));
+        assert_eq!(info.icc.as_deref(), Some(&b"fake-icc"[..]));
+
+        let gray16 = DynamicImage::ImageLuma16(image::ImageBuffer::from_fn(19, 7, |x, y| image::Luma([(x * 3000 + y) as u16])));
+        encode(&gray16, &path, 72.0, None).unwrap();
+        let (back, info) = decode(&path).unwrap();
+        assert_eq!(back, gray16);
+        assert_eq!((info.depth, info.color_mode), (16, MODE_GRAYSCALE));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn raw_merged_image_decodes() {
+        let dir = test_dir("raw");
+        let path = dir.join("raw.psd");
+        let img = gradient().to_rgb8();
+        std::fs::write(&path, raw_psd(&img, None)).unwrap();
+        let (back, info) = decode(&path).unwrap();
+        assert_eq!(back.to_rgb8(), img);
+        assert!(info.dpi.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_merged_data_is_a_clear_error() {
+        let dir = test_dir("nomerged");
+        let path = dir.join("layers-only.psd");
+        std::fs::write(&path, raw_psd(&gradient().to_rgb8(), Some(false))).unwrap();
+        assert_eq!(ensure_merged_data(&path), Err(i18n::t("err.psd_no_merged")));
+        assert_eq!(decode(&path).err(), Some(i18n::t("err.psd_no_merged")));
+
+        std::fs::write(&path, raw_psd(&gradient().to_rgb8(), Some(true))).unwrap();
+        assert!(ensure_merged_data(&path).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stamp_density_only_touches_resolution() {
+        let dir = test_dir("stamp");
+        let original = dir.join("a.psd");
+        encode(&gradient(), &original, 72.0, Some(b"fake-icc")).unwrap();
+        let before = std::fs::read(&original).unwrap();
+
+        // 已有 1005：只有两个定点数变化，文件长度与其余字节不变
+        let stamped = dir.join("b.psd");
+        stamp_density(&original, &stamped, 300.0).unwrap();
+        let after = std::fs::read(&stamped).unwrap();
+        assert_eq!(after.len(), before.len());
+        let changed: Vec<usize> = (0..before.len()).filter(|&i| before[i] != after[i]).collect();
+        assert!(!changed.is_empty() && changed.len() <= 8, "{:?}", changed);
+        assert_eq!(read_info(&stamped).unwrap().dpi, Some(300.0));
+        assert_eq!(decode(&stamped).unwrap().0.to_rgb8(), gradient().to_rgb8());
+
+        // 没有 1005：插入资源块，资源区之后的图层与图像数据逐字节相同；输入输出同一路径
+        let img = gradient().to_rgb8();
+        let bare = raw_psd(&img, Some(true));
+        std::fs::write(&original, &bare).unwrap();
+        stamp_density(&original, &original, 240.0).unwrap();
+        let after = std::fs::read(&original).unwrap();
+        // 文件头 26 + 颜色区长度 4 + 资源区长度 4 + 1057 资源块 22
+        let tail = bare.len() - (26 + 4 + 4 + 22);
+        assert_eq!(after[after.len() - tail..], bare[bare.len() - tail..]);
+        let info = read_info(&original).unwrap();
+        assert_eq!(info.dpi, Some(240.0));
+        assert!(info.has_merged_data);
+        assert_eq!(decode(&original).unwrap().0.to_rgb8(), img);
+        let leftovers = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(leftovers, 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
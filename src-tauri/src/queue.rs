@@ -0,0 +1,245 @@
+// ==========================================
+// 🌟 持久化任务队列：批处理中途关闭或崩溃后可继续或回滚
+// ==========================================
+// 每个批次一个 JSON 文件：<系统数据目录>/<应用标识>/queue/<批次号>.json，
+// 记录整批设置、每张图的参数与状态，状态一变就落盘。批次正常结束（含手动停止）后删除文件，
+// 所以启动时目录里剩下的都是没跑完的批次。
+// 崩溃时正在处理的文件可能已经替换完成：输出文件的修改时间晚于批次创建时间即视为已完成，避免重复裁切。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::batch::{JobSettings, ProcessJob};
+use crate::error::{AppError, ErrorCode};
+use crate::i18n;
+use crate::output::OutputStrategy;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum JobState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueuedJob {
+    pub job: ProcessJob,
+    pub state: JobState,
+    pub new_path: Option<String>,
+    pub new_name: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueueFile {
+    pub batch_id: String,
+    /// 创建时间（毫秒），用于判断崩溃时正在处理的文件是否已写完
+    pub created_ms: u64,
+    pub settings: JobSettings,
+    /// 处理过本批文件的操作日志会话，回滚时逐个撤销
+    pub journal_sessions: Vec<String>,
+    pub jobs: Vec<QueuedJob>,
+}
+
+/// 启动时提示用的摘要
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueueSummary {
+    pub batch_id: String,
+    pub created_ms: u64,
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+}
+
+pub(crate) struct Queue {
+    dir: PathBuf,
+    /// 本次运行中正在执行的批次
+    active: Mutex<HashMap<String, QueueFile>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_millis() as u64)
+}
+
+impl QueueFile {
+    pub fn new(batch_id: &str, settings: JobSettings, jobs: Vec<ProcessJob>, journal_session: String) -> Self {
+        QueueFile {
+            batch_id: batch_id.to_string(),
+            created_ms: now_ms(),
+            settings,
+            journal_sessions: vec![journal_session],
+            jobs: jobs
+                .into_iter()
+                .map(|job| QueuedJob { job, state: JobState::Pending, new_path: None, new_name: None, error: None })
+                .collect(),
+        }
+    }
+
+    fn summary(&self) -> QueueSummary {
+        QueueSummary {
+            batch_id: self.batch_id.clone(),
+            created_ms: self.created_ms,
+            total: self.jobs.len(),
+            done: self.jobs.iter().filter(|j| j.state == JobState::Done).count(),
+            failed: self.jobs.iter().filter(|j| j.state == JobState::Failed).count(),
+        }
+    }
+
+    /// 崩溃时处于 Running 的文件：输出已在批次开始后被写过，就按已完成处理
+    fn reconcile(&mut self) {
+        let Ok(strategy) = OutputStrategy::from_options(self.settings.output.as_ref()) else { return };
+        for queued in self.jobs.iter_mut().filter(|j| j.state == JobState::Running) {
+            let Ok(dest) = strategy.destination(Path::new(&queued.job.path)) else { continue };
+            if modified_ms(&dest).is_some_and(|ms| ms >= self.created_ms) {
+                queued.state = JobState::Done;
+                queued.new_name = dest.file_name().map(|n| n.to_string_lossy().to_string());
+                queued.new_path = Some(dest.to_string_lossy().to_string());
+            } else {
+                queued.state = JobState::Pending;
+            }
+        }
+    }
+}
+
+impl Queue {
+    pub fn open(app: &AppHandle) -> Result<Self, String> {
+        let dir = dirs_next::data_dir()
+            .ok_or_else(|| i18n::t("err.data_dir"))?
+            .join(&app.config().identifier)
+            .join("queue");
+        std::fs::create_dir_all(&dir).map_err(|e| i18n::tf("err.create_dir", &[&dir.display(), &e]))?;
+        Ok(Queue { dir, active: Mutex::new(HashMap::new()) })
+    }
+
+    fn path(&self, batch_id: &str) -> Result<PathBuf, String> {
+        if batch_id.is_empty() || batch_id.contains(['/', '\\']) || batch_id.contains("..") {
+            return Err(i18n::t("err.invalid_batch"));
+        }
+        Ok(self.dir.join(format!("{}.json", batch_id)))
+    }
+
+    /// 先写临时文件再改名，崩溃时不会留下半截的队列文件
+    fn write(&self, file: &QueueFile) -> Result<(), String> {
+        let path = self.path(&file.batch_id)?;
+        let temp = path.with_extension("json.tmp");
+        let text = serde_json::to_string_pretty(file).map_err(|e| format!("{}: {}", i18n::t("err.queue_write"), e))?;
+        std::fs::write(&temp, text).map_err(|e| format!("{}: {}", i18n::t("err.queue_write"), e))?;
+        std::fs::rename(&temp, &path).map_err(|e| format!("{}: {}", i18n::t("err.queue_write"), e))
+    }
+
+    fn read(&self, batch_id: &str) -> Result<QueueFile, String> {
+        let text = std::fs::read_to_string(self.path(batch_id)?).map_err(|e| format!("{}: {}", i18n::t("err.queue_read"), e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", i18n::t("err.queue_corrupt"), e))
+    }
+
+    /// 登记新批次
+    pub fn start(&self, file: QueueFile) -> Result<(), String> {
+        self.write(&file)?;
+        let mut active = self.active.lock().map_err(|_| i18n::t("err.queue_busy"))?;
+        active.insert(file.batch_id.clone(), file);
+        Ok(())
+    }
+
+    /// 重新载入未完成的批次，核对崩溃时正在处理的文件
+    pub fn resume(&self, batch_id: &str, journal_session: String) -> Result<QueueFile, String> {
+        let mut file = self.read(batch_id)?;
+        file.reconcile();
+        if !file.journal_sessions.contains(&journal_session) {
+            file.journal_sessions.push(journal_session);
+        }
+        self.start(file.clone())?;
+        Ok(file)
+    }
+
+    /// 已完成的文件直接返回结果，继续处理时跳过
+    pub fn finished_result(&self, batch_id: &str, index: usize) -> Option<(String, String)> {
+        let active = self.active.lock().ok()?;
+        let queued = active.get(batch_id)?.jobs.get(index)?;
+        match (queued.state, &queued.new_path, &queued.new_name) {
+            (JobState::Done, Some(path), Some(name)) => Some((path.clone(), name.clone())),
+            _ => None,
+        }
+    }
+
+    fn update(&self, batch_id: &str, index: usize, apply: impl FnOnce(&mut QueuedJob)) {
+        let Ok(mut active) = self.active.lock() else { return };
+        let Some(file) = active.get_mut(batch_id) else { return };
+        if let Some(queued) = file.jobs.get_mut(index) {
+            apply(queued);
+        }
+        let _ = self.write(file);
+    }
+
+    pub fn mark_running(&self, batch_id: &str, index: usize) {
+        self.update(batch_id, index, |queued| queued.state = JobState::Running);
+    }
+
+    pub fn mark_result(&self, batch_id: &str, index: usize, result: &Result<(String, String), AppError>) {
+        self.update(batch_id, index, |queued| match result {
+            Ok((path, name)) => {
+                queued.state = JobState::Done;
+                queued.new_path = Some(path.clone());
+                queued.new_name = Some(name.clone());
+                queued.error = None;
+            }
+            // 停止批次时中断的任务下次继续时重做
+            Err(e) if e.code == ErrorCode::Cancelled => queued.state = JobState::Pending,
+            Err(e) => {
+                queued.state = JobState::Failed;
+                queued.error = Some(e.to_string());
+            }
+        });
+    }
+
+    /// 批次结束（含手动停止）后删除队列文件
+    pub fn finish(&self, batch_id: &str) {
+        if let Ok(mut active) = self.active.lock() {
+            active.remove(batch_id);
+        }
+        if let Ok(path) = self.path(batch_id) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// 未跑完的批次（不含本次运行中正在执行的）
+    pub fn unfinished(&self) -> Vec<QueueSummary> {
+        let active: Vec<String> = self.active.lock().map(|a| a.keys().cloned().collect()).unwrap_or_default();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return Vec::new() };
+        let mut summaries: Vec<QueueSummary> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|p| std::fs::read_to_string(p).ok())
+            .filter_map(|text| serde_json::from_str::<QueueFile>(&text).ok())
+            .filter(|file| !active.contains(&file.batch_id))
+            .map(|mut file| {
+                file.reconcile();
+                file.summary()
+            })
+            .collect();
+        summaries.sort_by_key(|s| s.created_ms);
+        summaries
+    }
+
+    /// 回滚前取出批次记录并删除队列文件
+    pub fn take(&self, batch_id: &str) -> Result<QueueFile, String> {
+        let file = self.read(batch_id)?;
+        self.finish(batch_id);
+        Ok(file)
+    }
+}
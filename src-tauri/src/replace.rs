@@ -0,0 +1,102 @@
+// ==========================================
+// 🌟 原子替换：唯一临时文件 → 落盘 → 解码校验 → 一步替换
+// ==========================================
+// 覆盖原图时任何一步失败（崩溃、断电、磁盘写满）都只会留下临时文件，原图保持完整。
+// 临时文件与目标放在同一目录，最后的 rename 不跨盘，在 Windows 与 Unix 上都是原子替换。
+
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 预留给文件系统元数据与日志的余量
+const SPACE_MARGIN_BYTES: u64 = 64 * 1024 * 1024;
+/// 核对 DPI 的容差（JPEG 只存整数 DPI，PNG 按每米像素存）
+const DPI_TOLERANCE: f64 = 1.0;
+
+/// 写出结果的预期值，来自排版计划
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Expected {
+    pub width: u32,
+    pub height: u32,
+    pub dpi: f64,
+}
+
+/// 两个路径是否指向同一个文件：路径相同，或都存在且规范化后相同
+/// （大小写不敏感的文件系统上 IMG.JPG 与 img.jpg 是同一个文件）
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
+    }
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// 目标旁边的唯一临时文件名：隐藏文件、保留扩展名（编码器按扩展名选格式）
+pub(crate) fn temp_path(target: &Path) -> PathBuf {
+    let stem = target.file_stem().unwrap_or_default().to_string_lossy();
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let name = match target.extension() {
+        Some(ext) => format!(".{}.{}-{}.tmp.{}", stem, std::process::id(), nanos, ext.to_string_lossy()),
+        None => format!(".{}.{}-{}.tmp", stem, std::process::id(), nanos),
+    };
+    target.parent().unwrap_or(Path::new("")).join(name)
+}
+
+/// 按像素数粗估输出大小（8 位 RGB 不压缩的上限），不小于原图
+pub(crate) fn estimate_size(input: &Path, expected: Option<Expected>) -> u64 {
+    let input_len = std::fs::metadata(input).map(|m| m.len()).unwrap_or(0);
+    let pixels = expected.map(|e| e.width as u64 * e.height as u64).unwrap_or(0);
+    input_len.max(pixels * 3)
+}
+
+/// 写出前检查目标磁盘剩余空间
+pub(crate) fn ensure_space(dir: &Path, needed: u64) -> Result<(), String> {
+    // 取不到剩余空间（如网络盘）时不拦截，交给写出阶段报错
+    let Ok(available) = fs2::available_space(dir) else { return Ok(()) };
+    if available < needed + SPACE_MARGIN_BYTES {
+        return Err(crate::i18n::tf(
+            "err.disk_space",
+            &[&format!("{:.0}", (needed + SPACE_MARGIN_BYTES) as f64 / 1048576.0), &format!("{:.0}", available as f64 / 1048576.0)],
+        ));
+    }
+    Ok(())
+}
+
+/// 把文件内容刷到磁盘
+pub(crate) fn sync_file(path: &Path) -> Result<(), String> {
+    // Windows 上 FlushFileBuffers 需要写权限
+    let file = OpenOptions::new().write(true).open(path).map_err(|e| format!("{}: {}", crate::i18n::t("err.open_temp"), e))?;
+    file.sync_all().map_err(|e| format!("{}: {}", crate::i18n::t("err.sync"), e))
+}
+
+/// 核对写出的 (宽, 高, DPI) 与计划是否一致
+pub(crate) fn check(actual: (u32, u32, Option<f64>), expected: Option<Expected>) -> Result<(), String> {
+    let Some(expected) = expected else { return Ok(()) };
+    let (w, h, dpi) = actual;
+    if (w, h) != (expected.width, expected.height) {
+        return Err(crate::i18n::tf("err.verify_size", &[&w, &h, &expected.width, &expected.height]));
+    }
+    match dpi {
+        Some(dpi) if (dpi - expected.dpi).abs() <= DPI_TOLERANCE => Ok(()),
+        Some(dpi) => Err(crate::i18n::tf("err.verify_dpi", &[&format!("{:.1}", dpi), &format!("{:.1}", expected.dpi)])),
+        None => Err(crate::i18n::t("err.verify_no_dpi")),
+    }
+}
+
+/// 用已落盘、已校验的临时文件原子替换目标；失败时删除临时文件，目标不受影响
+pub(crate) fn commit(temp: &Path, target: &Path) -> Result<(), String> {
+    if let Err(e) = std::fs::rename(temp, target) {
+        let _ = std::fs::remove_file(temp);
+        return Err(crate::i18n::tf("err.replace_output", &[&e]));
+    }
+    // 目录项也要落盘，否则断电后可能回到替换之前
+    #[cfg(unix)]
+    if let Some(dir) = target.parent() {
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,280 @@
+// ==========================================
+// 🌟 外部进程：超时、资源上限，停止批次或退出程序时连同子进程一起结束
+// ==========================================
+// magick 与无头浏览器都经这里启动。stdout/stderr 由后台线程读取，避免管道写满卡住子进程；
+// 调用线程轮询退出状态，超时、批次被停止或程序退出时杀掉整个进程组
+// （magick 会调 gs 等 delegate，浏览器有渲染子进程），已读到的 stderr 放进错误里。
+// magick 默认不限内存，一张超大 PSD 就能把整机内存吃光，这里统一加上 -limit。
+
+use std::ffi::{OsStr, OsString};
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::engine::{self, ResourceLimits};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::i18n;
+
+/// 轮询间隔从 5ms 起步逐步放宽，短命令不必多等
+const POLL_MIN: Duration = Duration::from_millis(5);
+const POLL_MAX: Duration = Duration::from_millis(50);
+/// 进程结束后等输出管道关闭的最长时间；孙进程占着管道不放时不再死等
+const DRAIN_GRACE: Duration = Duration::from_secs(2);
+/// 磁盘缓存默认上限
+const DEFAULT_DISK_GIB: u64 = 64;
+/// 取不到物理内存时按 8GB 估计
+const FALLBACK_MEMORY_MIB: u64 = 8 * 1024;
+
+/// 带子命令时 -limit 要放在子命令之后
+const SUBCOMMANDS: &[&str] = &["identify", "mogrify", "composite", "compare", "montage", "stream", "convert", "conjure"];
+
+/// 任务类型，决定用哪一档超时
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Task {
+    /// identify、版本与格式探测
+    Probe,
+    /// 缩略图、预览与瓦片解码
+    Preview,
+    /// 正式输出
+    Process,
+    /// 浏览器打印 PDF
+    Print,
+}
+
+impl Task {
+    fn timeout(self) -> Option<Duration> {
+        let timeouts = engine::settings().timeouts;
+        let secs = match self {
+            Task::Probe => timeouts.probe,
+            Task::Preview => timeouts.preview,
+            Task::Process => timeouts.process,
+            Task::Print => timeouts.print,
+        };
+        // 0 表示不限时
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+}
+
+/// 程序正在退出：不再启动新进程，运行中的全部结束
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// 运行中的子进程 id（Unix 上同时是进程组 id）
+static RUNNING: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// 本机物理内存（MiB）
+fn total_memory_mib() -> u64 {
+    static TOTAL: OnceLock<u64> = OnceLock::new();
+    *TOTAL.get_or_init(|| {
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        let total = system.total_memory() / (1024 * 1024);
+        if total > 0 { total } else { FALLBACK_MEMORY_MIB }
+    })
+}
+
+/// 未手动设置时：内存取物理内存的 1/4，内存映射取 1/2，超出的部分落到磁盘缓存
+pub(crate) fn limit_args(limits: &ResourceLimits) -> Vec<String> {
+    let total = total_memory_mib();
+    let memory = limits.memory_mib.filter(|v| *v > 0).unwrap_or(total / 4);
+    let map = limits.map_mib.filter(|v| *v > 0).unwrap_or(total / 2);
+    let disk = limits.disk_gib.filter(|v| *v > 0).unwrap_or(DEFAULT_DISK_GIB);
+    vec![
+        "-limit".to_string(), "memory".to_string(), format!("{}MiB", memory),
+        "-limit".to_string(), "map".to_string(), format!("{}MiB", map),
+        "-limit".to_string(), "disk".to_string(), format!("{}GiB", disk),
+    ]
+}
+
+/// 把 -limit 插到子命令之后（没有子命令时放最前）
+pub(crate) fn with_limits(mut args: Vec<OsString>, limits: &ResourceLimits) -> Vec<OsString> {
+    let at = usize::from(args.first().is_some_and(|first| SUBCOMMANDS.contains(&first.to_string_lossy().as_ref())));
+    args.splice(at..at, limit_args(limits).into_iter().map(OsString::from));
+    args
+}
+
+/// magick 命令，带上资源上限
+pub(crate) fn magick<I, S>(task: Task, args: I) -> Run<'static>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args = args.into_iter().map(|a| a.as_ref().to_os_string()).collect();
+    let mut command = engine::magick_command();
+    command.args(with_limits(args, &engine::settings().limits));
+    Run::new(command, task)
+}
+
+/// 一次待执行的外部命令
+pub(crate) struct Run<'a> {
+    command: Command,
+    task: Task,
+    cancel: Option<&'a AtomicBool>,
+    spawn_error: fn(&std::io::Error) -> AppError,
+}
+
+impl<'a> Run<'a> {
+    pub fn new(mut command: Command, task: Task) -> Self {
+        command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        // 自成一个进程组，结束时连同 delegate / 渲染子进程一起杀掉
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        Run { command, task, cancel: None, spawn_error: AppError::spawn }
+    }
+
+    /// 批次的停止标记；置位后结束进程并返回 Cancelled
+    pub fn cancel_on<'b>(self, cancel: Option<&'b AtomicBool>) -> Run<'b> {
+        Run { command: self.command, task: self.task, cancel, spawn_error: self.spawn_error }
+    }
+
+    /// 把打开的文件接到子进程的 stdin（magick 参数写 `-`）
+    pub fn stdin(mut self, file: std::fs::File) -> Self {
+        self.command.stdin(file);
+        self
+    }
+
+    /// 子进程的 stdout 直接写进文件，不经内存
+    pub fn stdout(mut self, file: std::fs::File) -> Self {
+        self.command.stdout(file);
+        self
+    }
+
+    /// 启动失败时的错误（默认按 magick 缺失处理）
+    pub fn spawn_error(mut self, f: fn(&std::io::Error) -> AppError) -> Self {
+        self.spawn_error = f;
+        self
+    }
+
+    fn stopped(&self) -> bool {
+        SHUTDOWN.load(Ordering::SeqCst) || self.cancel.is_some_and(|c| c.load(Ordering::SeqCst))
+    }
+
+    /// 运行到结束并收集输出；退出码非 0 仍返回 Ok，由调用方按场景给出错误
+    pub fn output(mut self) -> AppResult<Output> {
+        if self.stopped() {
+            return Err(AppError::new(ErrorCode::Cancelled, i18n::t("err.cancelled")));
+        }
+        let mut child = self.command.spawn().map_err(|e| (self.spawn_error)(&e))?;
+        let id = child.id();
+        if let Ok(mut running) = RUNNING.lock() {
+            running.push(id);
+        }
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+
+        let result = self.wait(&mut child);
+        if let Ok(mut running) = RUNNING.lock() {
+            running.retain(|pid| *pid != id);
+        }
+        let stdout = stdout.collect();
+        let stderr = stderr.collect();
+        let stderr_text = || Some(String::from_utf8_lossy(&stderr).trim().to_string()).filter(|s| !s.is_empty());
+        match result {
+            Ok(status) => Ok(Output { status, stdout, stderr }),
+            Err(mut e) => {
+                e.stderr = stderr_text();
+                Err(e)
+            }
+        }
+    }
+
+    fn wait(&self, child: &mut Child) -> AppResult<ExitStatus> {
+        let started = Instant::now();
+        let timeout = self.task.timeout();
+        let mut poll = POLL_MIN;
+        loop {
+            match child.try_wait() {
+                // shutdown 直接按进程组结束时，这里看到的是被杀死的退出状态
+                Ok(Some(status)) if !status.success() && self.stopped() => {
+                    return Err(AppError::new(ErrorCode::Cancelled, i18n::t("err.cancelled")));
+                }
+                Ok(Some(status)) => return Ok(status),
+                Ok(None) => {}
+                Err(e) => {
+                    kill(child);
+                    return Err(AppError::new(ErrorCode::EngineFailed, i18n::tf("err.engine_start", &[&e])));
+                }
+            }
+            if self.stopped() {
+                kill(child);
+                return Err(AppError::new(ErrorCode::Cancelled, i18n::t("err.cancelled")));
+            }
+            if let Some(limit) = timeout.filter(|limit| started.elapsed() >= *limit) {
+                kill(child);
+                return Err(AppError::new(ErrorCode::Timeout, i18n::tf("err.timeout", &[&limit.as_secs()])));
+            }
+            std::thread::sleep(poll);
+            poll = (poll * 2).min(POLL_MAX);
+        }
+    }
+}
+
+/// 后台读取的一路输出
+struct Drained {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    done: Receiver<()>,
+}
+
+impl Drained {
+    fn collect(self) -> Vec<u8> {
+        let _ = self.done.recv_timeout(DRAIN_GRACE);
+        std::mem::take(&mut *self.buffer.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> Drained {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let (tx, done) = mpsc::channel();
+    if let Some(mut pipe) = pipe {
+        let sink = Arc::clone(&buffer);
+        std::thread::spawn(move || {
+            let mut chunk = [0u8; 64 * 1024];
+            while let Ok(n) = pipe.read(&mut chunk) {
+                if n == 0 { break; }
+                sink.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(&chunk[..n]);
+            }
+            let _ = tx.send(());
+        });
+    } else {
+        let _ = tx.send(());
+    }
+    Drained { buffer, done }
+}
+
+/// 结束整棵进程树
+fn kill_tree(id: u32) {
+    #[cfg(unix)]
+    unsafe {
+        // 负号表示整个进程组
+        libc::kill(-(id as i32), libc::SIGKILL);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let _ = Command::new("taskkill")
+            .args(["/PID", &id.to_string(), "/T", "/F"])
+            .creation_flags(0x08000000)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+fn kill(child: &mut Child) {
+    kill_tree(child.id());
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// 程序退出时调用：拒绝新进程，结束所有运行中的
+pub(crate) fn shutdown() {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+    let running = RUNNING.lock().map(|r| r.clone()).unwrap_or_default();
+    for id in running {
+        kill_tree(id);
+    }
+}
@@ -0,0 +1,287 @@
+// ==========================================
+// 🌟 缩略图缓存：按 路径 + 大小 + 修改时间 + 内容校验 落盘，经 thumb:// 协议提供
+// ==========================================
+// 重新打开同一个文件夹时 PSD/TIFF 不再逐张拉起 magick，直接读缓存；
+// 前端拿到的是短地址而不是几十 KB 的 base64 字符串。
+// 总大小超过上限时按最近使用时间淘汰（命中时刷新文件修改时间作为使用记录）。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::http::{header, Response, StatusCode};
+use tauri::AppHandle;
+
+use crate::i18n;
+use crate::tiles::protocol_url;
+
+/// 协议名；Windows 上 webview 以 http://thumb.localhost/ 访问
+pub(crate) const SCHEME: &str = "thumb";
+/// 缓存总大小上限
+const CAP_BYTES: u64 = 512 * 1024 * 1024;
+/// 超限后淘汰到上限的这个比例，避免每写一张都要清理
+const TRIM_TO: f64 = 0.8;
+/// 内容校验取文件首尾各这么多字节，大文件也只读一点
+const SAMPLE_BYTES: u64 = 64 * 1024;
+
+struct Entry {
+    bytes: u64,
+    used: SystemTime,
+}
+
+pub(crate) struct Thumbs {
+    dir: PathBuf,
+    /// 缓存总大小上限，默认 CAP_BYTES
+    cap_bytes: u64,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+/// 首尾采样的 CRC32：只改了像素、大小与时间都没变的文件也能区分
+fn content_crc(path: &Path, len: u64) -> Result<u32, String> {
+    let mut file = File::open(path).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; SAMPLE_BYTES.min(len) as usize];
+    file.read_exact(&mut buf).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    hasher.update(&buf);
+    if len > SAMPLE_BYTES * 2 {
+        file.seek(SeekFrom::End(-(SAMPLE_BYTES as i64))).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+        file.read_exact(&mut buf).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+        hasher.update(&buf);
+    }
+    Ok(hasher.finalize())
+}
+
+/// 缓存文件名：<路径 CRC>-<大小、时间、内容 CRC>-<边长>.jpg
+fn cache_name(path: &Path, max_side: u32) -> Result<String, String> {
+    let meta = std::fs::metadata(path).map_err(|e| format!("{}: {}", i18n::t("err.read_meta"), e))?;
+    let modified = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_nanos()).unwrap_or(0);
+    let path_crc = crc32fast::hash(path.to_string_lossy().as_bytes());
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&meta.len().to_le_bytes());
+    hasher.update(&modified.to_le_bytes());
+    hasher.update(&content_crc(path, meta.len())?.to_le_bytes());
+    Ok(format!("{:08x}-{:08x}-{}.jpg", path_crc, hasher.finalize(), max_side))
+}
+
+/// 直接引用原图的 asset 地址；与前端 convertFileSrc 一样整段编码，
+/// 文件名里的 `[` `%` `#` `?`、空格与中文都不会被当成 URL 语法
+pub(crate) fn asset_url(path: &str) -> String {
+    let encoded: String = path
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    protocol_url("asset", &encoded)
+}
+
+fn valid_name(name: &str) -> bool {
+    name.ends_with(".jpg") && name.trim_end_matches(".jpg").chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+impl Thumbs {
+    pub fn open(app: &AppHandle) -> Result<Self, String> {
+        let dir = dirs_next::cache_dir()
+            .ok_or_else(|| i18n::t("err.cache_dir"))?
+            .join(&app.config().identifier)
+            .join("thumbs");
+        Self::open_at(dir)
+    }
+
+    /// 以指定目录作为缓存（测试用临时目录）
+    pub fn open_at(dir: PathBuf) -> Result<Self, String> {
+        std::fs::create_dir_all(&dir).map_err(|e| i18n::tf("err.create_dir", &[&dir.display(), &e]))?;
+        let entries = std::fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        let name = e.file_name().to_string_lossy().to_string();
+                        let meta = e.metadata().ok()?;
+                        valid_name(&name).then(|| (name, Entry { bytes: meta.len(), used: meta.modified().unwrap_or(UNIX_EPOCH) }))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Thumbs { dir, cap_bytes: CAP_BYTES, entries: Mutex::new(entries) })
+    }
+
+    /// 缩略图在 webview 里的地址
+    pub fn url(&self, name: &str) -> String {
+        protocol_url(SCHEME, name)
+    }
+
+    /// 取缓存的缩略图文件名；没有时用 render 生成并写入缓存（错误类型随 render）
+    pub fn ensure<E: From<String>>(&self, path: &Path, max_side: u32, render: impl FnOnce() -> Result<Vec<u8>, E>) -> Result<String, E> {
+        let name = cache_name(path, max_side)?;
+        let file = self.dir.join(&name);
+        if file.exists() {
+            self.touch(&name);
+            return Ok(name);
+        }
+
+        let bytes = render()?;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let temp = self.dir.join(format!(".{}.{}.tmp", name, nanos));
+        std::fs::write(&temp, &bytes).map_err(|e| format!("{}: {}", i18n::t("err.thumb_cache_write"), e))?;
+        if let Err(e) = std::fs::rename(&temp, &file) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(format!("{}: {}", i18n::t("err.thumb_cache_write"), e).into());
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(name.clone(), Entry { bytes: bytes.len() as u64, used: SystemTime::now() });
+        }
+        self.evict();
+        Ok(name)
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        if !valid_name(name) {
+            return Err(i18n::t("err.thumb_invalid"));
+        }
+        let bytes = std::fs::read(self.dir.join(name)).map_err(|e| format!("{}: {}", i18n::t("err.thumb_cache_read"), e))?;
+        self.touch(name);
+        Ok(bytes)
+    }
+
+    /// 记录最近使用：内存里更新，磁盘上刷新修改时间，重启后仍按使用顺序淘汰
+    fn touch(&self, name: &str) {
+        let now = SystemTime::now();
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(entry) = entries.get_mut(name) {
+                entry.used = now;
+            }
+        }
+        if let Ok(file) = File::options().write(true).open(self.dir.join(name)) {
+            let _ = file.set_modified(now);
+        }
+    }
+
+    /// 超过上限时从最久未用的开始删除
+    fn evict(&self) {
+        let Ok(mut entries) = self.entries.lock() else { return };
+        let mut total: u64 = entries.values().map(|e| e.bytes).sum();
+        if total <= self.cap_bytes {
+            return;
+        }
+        let mut by_age: Vec<(SystemTime, String)> = entries.iter().map(|(name, e)| (e.used, name.clone())).collect();
+        by_age.sort();
+        let target = (self.cap_bytes as f64 * TRIM_TO) as u64;
+        for (_, name) in by_age {
+            if total <= target {
+                break;
+            }
+            if let Some(entry) = entries.remove(&name) {
+                let _ = std::fs::remove_file(self.dir.join(&name));
+                total -= entry.bytes;
+            }
+        }
+    }
+
+    /// 协议入口：/<文件名>
+    pub fn respond(&self, uri_path: &str) -> Response<Vec<u8>> {
+        match self.read(uri_path.trim_start_matches('/')) {
+            Ok(bytes) => Response::builder()
+                .header(header::CONTENT_TYPE, "image/jpeg")
+                // 文件名里带了修改时间与内容校验，内容不会变
+                .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+                .body(bytes)
+                .unwrap_or_default(),
+            Err(e) => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+                .body(e.into_bytes())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("thumbs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn cache_misses_when_mtime_or_content_changes() {
+        let dir = test_dir("hit");
+        let thumbs = Thumbs::open_at(dir.join("cache")).unwrap();
+        let source = dir.join("a.psd");
+        std::fs::write(&source, "first").unwrap();
+        let renders = Cell::new(0);
+        let ensure = || {
+            thumbs
+                .ensure(&source, 256, || {
+                    renders.set(renders.get() + 1);
+                    Ok::<_, String>(vec![0xFF; 10])
+                })
+                .unwrap()
+        };
+
+        let name = ensure();
+        assert_eq!(ensure(), name);
+        assert_eq!(renders.get(), 1);
+        assert_eq!(thumbs.read(&name).unwrap(), vec![0xFF; 10]);
+
+        // 只改修改时间
+        let modified = std::fs::metadata(&source).unwrap().modified().unwrap();
+        File::options().write(true).open(&source).unwrap().set_modified(modified + std::time::Duration::from_secs(5)).unwrap();
+        let touched = ensure();
+        assert_ne!(touched, name);
+        assert_eq!(renders.get(), 2);
+
+        // 大小与修改时间都不变，只改内容
+        let modified = std::fs::metadata(&source).unwrap().modified().unwrap();
+        std::fs::write(&source, "again").unwrap();
+        File::options().write(true).open(&source).unwrap().set_modified(modified).unwrap();
+        assert_ne!(ensure(), touched);
+        assert_eq!(renders.get(), 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn eviction_drops_least_recently_used() {
+        let dir = test_dir("evict");
+        let mut thumbs = Thumbs::open_at(dir.join("cache")).unwrap();
+        thumbs.cap_bytes = 250;
+        let ensure = |stem: &str| {
+            let source = dir.join(format!("{}.tif", stem));
+            std::fs::write(&source, stem).unwrap();
+            thumbs.ensure(&source, 256, || Ok::<_, String>(vec![0; 100])).unwrap()
+        };
+        let a = ensure("a");
+        let b = ensure("b");
+        // a 在 c 写入前被用过一次，超出上限时最久未用的是 b
+        thumbs.read(&a).unwrap();
+        let c = ensure("c");
+        assert!(thumbs.read(&a).is_ok());
+        assert!(thumbs.read(&b).is_err());
+        assert!(thumbs.read(&c).is_ok());
+
+        // 重新打开时按磁盘上的文件恢复记录
+        let reopened = Thumbs::open_at(dir.join("cache")).unwrap();
+        assert_eq!(reopened.entries.lock().unwrap().len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn read_rejects_paths_outside_the_cache() {
+        let dir = test_dir("name");
+        let thumbs = Thumbs::open_at(dir.join("cache")).unwrap();
+        std::fs::write(dir.join("secret.jpg"), "x").unwrap();
+        for name in ["../secret.jpg", "..\\secret.jpg", "sub/a.jpg", "a.png", ".jpg.tmp", ""] {
+            assert_eq!(thumbs.read(name), Err(i18n::t("err.thumb_invalid")), "{}", name);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,660 @@
+// ==========================================
+// 🌟 原生 TIFF 读写：保留压缩方式与分辨率标签
+// ==========================================
+// 条带 (strip) 与瓦片 (tile) 都由 tiff 解码器统一拼成整幅图；
+// 写出时沿用原文件的压缩方式、XResolution/YResolution、ResolutionUnit 与 ICC，
+// 避免 magick 往返后文件膨胀、标签丢失。
+
+use std::fs::File;
+use std::borrow::Cow;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use image::{DynamicImage, ImageBuffer};
+use tiff::decoder::{ifd::Value, Decoder, DecodingResult, Limits};
+use tiff::encoder::colortype::{self, ColorType};
+use tiff::encoder::compression::{Compression, Deflate, Lzw, Packbits, Uncompressed};
+use tiff::encoder::{Rational, TiffEncoder, TiffValue};
+use tiff::tags::{CompressionMethod, ResolutionUnit, Tag, Type};
+
+use crate::i18n;
+
+/// InterColorProfile 标签号
+const TAG_ICC_PROFILE: u16 = 34675;
+
+/// 编码器支持的压缩方式
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum TiffCompression {
+    None,
+    Lzw,
+    Deflate,
+    PackBits,
+}
+
+impl TiffCompression {
+    /// JPEG、CCITT 等编码器写不了的方式返回 None，这类文件交给 magick 按原压缩方式写回
+    fn from_method(method: u16) -> Option<Self> {
+        match CompressionMethod::from_u16(method)? {
+            CompressionMethod::None => Some(TiffCompression::None),
+            CompressionMethod::LZW => Some(TiffCompression::Lzw),
+            CompressionMethod::Deflate | CompressionMethod::OldDeflate => Some(TiffCompression::Deflate),
+            CompressionMethod::PackBits => Some(TiffCompression::PackBits),
+            _ => None,
+        }
+    }
+}
+
+/// 原文件中需要原样带回的 TIFF 标签
+#[derive(Clone, Debug)]
+pub(crate) struct TiffInfo {
+    /// 原文件的压缩方式；编码器写不了时为 None
+    pub compression: Option<TiffCompression>,
+    pub resolution_unit: ResolutionUnit,
+    /// 原始有理数 (分子, 分母)，DPI 未变时原样写回
+    pub x_resolution: Option<(u32, u32)>,
+    pub y_resolution: Option<(u32, u32)>,
+    /// InterColorProfile 中的 ICC 描述文件
+    pub icc: Option<Vec<u8>>,
+}
+
+impl Default for TiffInfo {
+    fn default() -> Self {
+        TiffInfo {
+            compression: Some(TiffCompression::Lzw),
+            resolution_unit: ResolutionUnit::Inch,
+            x_resolution: None,
+            y_resolution: None,
+            icc: None,
+        }
+    }
+}
+
+impl TiffInfo {
+    /// 换算为 DPI；单位为 None 时与 magick 一样按每英寸理解
+    pub fn dpi(&self) -> Option<f64> {
+        let (n, d) = self.x_resolution?;
+        if n == 0 || d == 0 {
+            return None;
+        }
+        let value = n as f64 / d as f64;
+        match self.resolution_unit {
+            ResolutionUnit::Centimeter => Some(value * 2.54),
+            _ => Some(value),
+        }
+    }
+
+    /// 以原单位表达目标 DPI；与原值一致时原样保留有理数
+    fn resolution_for(&self, dpi: f64) -> (Rational, Rational) {
+        if let (Some(orig), Some((xn, xd)), Some((yn, yd))) = (self.dpi(), self.x_resolution, self.y_resolution) {
+            if (orig - dpi).abs() < 0.01 {
+                return (Rational { n: xn, d: xd }, Rational { n: yn, d: yd });
+            }
+        }
+        let value = match self.resolution_unit {
+            ResolutionUnit::Centimeter => dpi / 2.54,
+            _ => dpi,
+        };
+        let r = Rational { n: (value * 1000.0).round() as u32, d: 1000 };
+        (r.clone(), r)
+    }
+}
+
+fn rational_tag<R: std::io::Read + Seek>(decoder: &mut Decoder<R>, tag: Tag) -> Option<(u32, u32)> {
+    match decoder.find_tag(tag).ok()?? {
+        Value::Rational(n, d) => Some((n, d)),
+        Value::Unsigned(n) => Some((n, 1)),
+        _ => None,
+    }
+}
+
+/// ICC 标签规范类型为 UNDEFINED，部分软件写成 BYTE，两种都接受
+fn icc_tag<R: std::io::Read + Seek>(decoder: &mut Decoder<R>) -> Option<Vec<u8>> {
+    let bytes: Vec<u8> = match decoder.find_tag(Tag::Unknown(TAG_ICC_PROFILE)).ok()?? {
+        Value::List(items) => items
+            .into_iter()
+            .filter_map(|v| match v {
+                Value::Byte(b) => Some(b),
+                Value::Unsigned(u) => u8::try_from(u).ok(),
+                _ => None,
+            })
+            .collect(),
+        _ => return None,
+    };
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+/// 按 UNDEFINED 类型写出的 ICC 数据
+struct IccBytes<'a>(&'a [u8]);
+
+impl TiffValue for IccBytes<'_> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: Type = Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.0)
+    }
+}
+
+/// 只读标签不解码像素（rename_files 探测 DPI 用）
+pub(crate) fn read_info(path: &Path) -> Result<TiffInfo, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    let mut decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| i18n::tf("err.tiff_parse", &[&e]))?
+        .with_limits(Limits::unlimited());
+    Ok(info_from(&mut decoder))
+}
+
+fn info_from<R: std::io::Read + Seek>(decoder: &mut Decoder<R>) -> TiffInfo {
+    let compression = decoder
+        .find_tag_unsigned::<u16>(Tag::Compression)
+        .ok()
+        .flatten()
+        .unwrap_or(1);
+    let resolution_unit = decoder
+        .find_tag_unsigned::<u16>(Tag::ResolutionUnit)
+        .ok()
+        .flatten()
+        .and_then(ResolutionUnit::from_u16)
+        .unwrap_or(ResolutionUnit::Inch);
+    let x_resolution = rational_tag(decoder, Tag::XResolution);
+    let y_resolution = rational_tag(decoder, Tag::YResolution).or(x_resolution);
+    TiffInfo {
+        compression: TiffCompression::from_method(compression),
+        resolution_unit,
+        x_resolution,
+        y_resolution,
+        icc: icc_tag(decoder),
+    }
+}
+
+/// 体检用：第一页的色彩类型、Compression 标签值与总页数，不读像素
+pub(crate) fn probe(path: &Path) -> Option<(tiff::ColorType, u16, u32)> {
+    let file = File::open(path).ok()?;
+    let mut decoder = Decoder::new(BufReader::new(file)).ok()?.with_limits(Limits::unlimited());
+    let color = decoder.colortype().ok()?;
+    let compression = decoder.find_tag_unsigned::<u16>(Tag::Compression).ok().flatten().unwrap_or(1);
+    let mut pages = 1;
+    while decoder.more_images() && decoder.next_image().is_ok() {
+        pages += 1;
+    }
+    Some((color, compression, pages))
+}
+
+/// 只看色彩类型判断 decode 能否处理，不读像素
+pub(crate) fn can_decode(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else { return false };
+    let Ok(mut decoder) = Decoder::new(BufReader::new(file)) else { return false };
+    matches!(
+        decoder.colortype(),
+        Ok(tiff::ColorType::Gray(8 | 16) | tiff::ColorType::GrayA(8 | 16) | tiff::ColorType::RGB(8 | 16) | tiff::ColorType::RGBA(8 | 16))
+    )
+}
+
+/// 解码第一页（8/16 位灰度、RGB、RGBA，条带与瓦片均可）
+pub(crate) fn decode(path: &Path) -> Result<(DynamicImage, TiffInfo), String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))?;
+    let mut decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| i18n::tf("err.tiff_parse", &[&e]))?
+        // 大幅面扫描件动辄数 GB，取消默认解码上限
+        .with_limits(Limits::unlimited());
+    let info = info_from(&mut decoder);
+    let (w, h) = decoder.dimensions().map_err(|e| i18n::tf("err.tiff_parse", &[&e]))?;
+    let color = decoder.colortype().map_err(|e| i18n::tf("err.tiff_parse", &[&e]))?;
+    let data = decoder.read_image().map_err(|e| i18n::tf("err.decode", &[&e]))?;
+
+    let unsupported = || i18n::tf("err.tiff_color", &[&format!("{:?}", color)]);
+    let img = match (color, data) {
+        (tiff::ColorType::Gray(8), DecodingResult::U8(buf)) => DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        (tiff::ColorType::Gray(16), DecodingResult::U16(buf)) => DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        (tiff::ColorType::GrayA(8), DecodingResult::U8(buf)) => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        (tiff::ColorType::GrayA(16), DecodingResult::U16(buf)) => DynamicImage::ImageLumaA16(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        (tiff::ColorType::RGB(8), DecodingResult::U8(buf)) => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        (tiff::ColorType::RGB(16), DecodingResult::U16(buf)) => DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        (tiff::ColorType::RGBA(8), DecodingResult::U8(buf)) => DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        (tiff::ColorType::RGBA(16), DecodingResult::U16(buf)) => DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, buf).ok_or_else(unsupported)?),
+        _ => return Err(unsupported()),
+    };
+    Ok((img, info))
+}
+
+fn write_as<W, C, D>(
+    encoder: &mut TiffEncoder<W>,
+    (w, h): (u32, u32),
+    compression: D,
+    (x_res, y_res): (Rational, Rational),
+    unit: ResolutionUnit,
+    icc: Option<&[u8]>,
+    data: &[C::Inner],
+) -> Result<(), String>
+where
+    W: Write + Seek,
+    C: ColorType,
+    D: Compression,
+    [C::Inner]: TiffValue,
+{
+    let mut image = encoder
+        .new_image_with_compression::<C, D>(w, h, compression)
+        .map_err(|e| i18n::tf("err.encode", &[&"TIFF", &e]))?;
+    image.resolution_unit(unit);
+    image.x_resolution(x_res);
+    image.y_resolution(y_res);
+    if let Some(profile) = icc {
+        image
+            .encoder()
+            .write_tag(Tag::Unknown(TAG_ICC_PROFILE), IccBytes(profile))
+            .map_err(|e| i18n::tf("err.encode", &[&"TIFF", &e]))?;
+    }
+    image.write_data(data).map_err(|e| i18n::tf("err.encode", &[&"TIFF", &e]))
+}
+
+fn write_with<W: Write + Seek, D: Compression>(
+    encoder: &mut TiffEncoder<W>,
+    img: &DynamicImage,
+    compression: D,
+    res: (Rational, Rational),
+    unit: ResolutionUnit,
+    icc: Option<&[u8]>,
+) -> Result<(), String> {
+    let dims = (img.width(), img.height());
+    match img {
+        DynamicImage::ImageLuma8(buf) => write_as::<_, colortype::Gray8, _>(encoder, dims, compression, res, unit, icc, buf),
+        DynamicImage::ImageLuma16(buf) => write_as::<_, colortype::Gray16, _>(encoder, dims, compression, res, unit, icc, buf),
+        DynamicImage::ImageRgb8(buf) => write_as::<_, colortype::RGB8, _>(encoder, dims, compression, res, unit, icc, buf),
+        DynamicImage::ImageRgb16(buf) => write_as::<_, colortype::RGB16, _>(encoder, dims, compression, res, unit, icc, buf),
+        DynamicImage::ImageRgba8(buf) => write_as::<_, colortype::RGBA8, _>(encoder, dims, compression, res, unit, icc, buf),
+        DynamicImage::ImageRgba16(buf) => write_as::<_, colortype::RGBA16, _>(encoder, dims, compression, res, unit, icc, buf),
+        _ => Err(i18n::t("err.tiff_write_color")),
+    }
+}
+
+/// 以原文件的压缩方式与分辨率单位写出 TIFF，并嵌入给定的 ICC
+pub(crate) fn encode(img: &DynamicImage, output: &Path, dpi: f64, info: &TiffInfo, icc: Option<&[u8]>) -> Result<(), String> {
+    let file = File::create(output).map_err(|e| format!("{}: {}", i18n::t("err.create_output"), e))?;
+    let mut writer = BufWriter::new(file);
+    {
+        let mut encoder = TiffEncoder::new(&mut writer).map_err(|e| i18n::tf("err.encode", &[&"TIFF", &e]))?;
+        let res = info.resolution_for(dpi);
+        let unit = match info.resolution_unit {
+            ResolutionUnit::Centimeter => ResolutionUnit::Centimeter,
+            _ => ResolutionUnit::Inch,
+        };
+        // 写不了原压缩方式时报错，不悄悄换成 LZW
+        match info.compression.ok_or_else(|| i18n::t("err.tiff_compression"))? {
+            TiffCompression::None => write_with(&mut encoder, img, Uncompressed, res, unit, icc)?,
+            TiffCompression::Lzw => write_with(&mut encoder, img, Lzw, res, unit, icc)?,
+            TiffCompression::Deflate => write_with(&mut encoder, img, Deflate::default(), res, unit, icc)?,
+            TiffCompression::PackBits => write_with(&mut encoder, img, Packbits, res, unit, icc)?,
+        }
+    }
+    writer.flush().map_err(|e| format!("{}: {}", i18n::t("err.write_output"), e))
+}
+
+// ==========================================
+// 🌟 补写 DPI：只改第一页的分辨率标签，其余字节原样复制
+// ==========================================
+// 方向、EXIF、ICC、多页与各种私有标签都不经过编解码。三个标签都在时原位覆盖；
+// 缺标签时在文件末尾写一份补全后的第一页 IFD，并把文件头指向它（原 IFD 留作无用字节）。
+
+const TAG_X_RESOLUTION: u16 = 282;
+const TAG_Y_RESOLUTION: u16 = 283;
+const TAG_RESOLUTION_UNIT: u16 = 296;
+const TYPE_SHORT: u16 = 3;
+const TYPE_RATIONAL: u16 = 5;
+
+/// 文件的字节序与经典 / BigTIFF 布局
+#[derive(Clone, Copy)]
+struct Layout {
+    little: bool,
+    big: bool,
+}
+
+impl Layout {
+    fn u16(self, b: &[u8]) -> u16 {
+        let b = [b[0], b[1]];
+        if self.little { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) }
+    }
+
+    fn u32(self, b: &[u8]) -> u32 {
+        let b = [b[0], b[1], b[2], b[3]];
+        if self.little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) }
+    }
+
+    fn u64(self, b: &[u8]) -> u64 {
+        let b = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+        if self.little { u64::from_le_bytes(b) } else { u64::from_be_bytes(b) }
+    }
+
+    fn put_u16(self, v: u16) -> [u8; 2] {
+        if self.little { v.to_le_bytes() } else { v.to_be_bytes() }
+    }
+
+    fn put_u32(self, v: u32) -> [u8; 4] {
+        if self.little { v.to_le_bytes() } else { v.to_be_bytes() }
+    }
+
+    fn put_u64(self, v: u64) -> [u8; 8] {
+        if self.little { v.to_le_bytes() } else { v.to_be_bytes() }
+    }
+
+    /// 偏移量字段：经典 TIFF 4 字节，BigTIFF 8 字节
+    fn offset(self, b: &[u8]) -> u64 {
+        if self.big { self.u64(b) } else { self.u32(b) as u64 }
+    }
+
+    fn put_offset(self, v: u64) -> Result<Vec<u8>, String> {
+        if self.big {
+            Ok(self.put_u64(v).to_vec())
+        } else {
+            u32::try_from(v).map(|v| self.put_u32(v).to_vec()).map_err(|_| i18n::t("err.tiff_too_large"))
+        }
+    }
+
+    fn entry_len(self) -> usize {
+        if self.big { 20 } else { 12 }
+    }
+
+    /// 条目里“值或偏移”字段的位置
+    fn value_at(self) -> usize {
+        if self.big { 12 } else { 8 }
+    }
+}
+
+/// rename_files 用：像素与其余标签原样保留，仅补写 DPI；input 与 output 可以是同一个文件
+pub(crate) fn stamp_density(input: &Path, output: &Path, dpi: f64) -> Result<(), String> {
+    let temp = crate::replace::temp_path(output);
+    std::fs::copy(input, &temp).map_err(|e| format!("{}: {}", i18n::t("err.copy"), e))?;
+    let patched = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&temp)
+        .map_err(|e| format!("{}: {}", i18n::t("err.read_file"), e))
+        .and_then(|mut file| {
+            patch_density(&mut file, dpi)?;
+            file.sync_all().map_err(|e| format!("{}: {}", i18n::t("err.write_output"), e))
+        });
+    if let Err(e) = patched {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+    crate::replace::commit(&temp, output)
+}
+
+fn read_at<F: Read + Seek>(file: &mut F, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+    let mut buf = vec![0u8; len];
+    file.seek(SeekFrom::Start(offset)).and_then(|_| file.read_exact(&mut buf)).map_err(|e| i18n::tf("err.tiff_parse", &[&e]))?;
+    Ok(buf)
+}
+
+fn write_at<F: Write + Seek>(file: &mut F, offset: u64, bytes: &[u8]) -> Result<(), String> {
+    file.seek(SeekFrom::Start(offset)).and_then(|_| file.write_all(bytes)).map_err(|e| format!("{}: {}", i18n::t("err.write_output"), e))
+}
+
+fn patch_density<F: Read + Write + Seek>(file: &mut F, dpi: f64) -> Result<(), String> {
+    let header = read_at(file, 0, 8)?;
+    let little = match &header[..2] {
+        b"II" => true,
+        b"MM" => false,
+        _ => return Err(i18n::tf("err.tiff_parse", &[&i18n::t("err.tiff_header")])),
+    };
+    let layout = Layout { little, big: Layout { little, big: false }.u16(&header[2..4]) == 43 };
+    let (pointer_at, ifd) = if layout.big { (8, layout.u64(&read_at(file, 8, 8)?)) } else { (4, layout.u32(&header[4..8]) as u64) };
+
+    let count_len = if layout.big { 8 } else { 2 };
+    let count_bytes = read_at(file, ifd, count_len)?;
+    let count = if layout.big { layout.u64(&count_bytes) } else { layout.u16(&count_bytes) as u64 } as usize;
+    let body = read_at(file, ifd + count_len as u64, count * layout.entry_len() + if layout.big { 8 } else { 4 })?;
+    let entries: Vec<&[u8]> = body[..count * layout.entry_len()].chunks(layout.entry_len()).collect();
+    let next_ifd = &body[count * layout.entry_len()..];
+
+    let find = |tag: u16, typ: u16| entries.iter().position(|e| layout.u16(&e[0..2]) == tag && layout.u16(&e[2..4]) == typ && layout.offset(&e[4..layout.value_at()]) == 1);
+    // 单位沿用原文件的厘米，否则（含“无单位”）按英寸写
+    let unit_index = find(TAG_RESOLUTION_UNIT, TYPE_SHORT);
+    let unit: u16 = match unit_index.map(|i| layout.u16(&entries[i][layout.value_at()..])) {
+        Some(3) => 3,
+        _ => 2,
+    };
+    let value = if unit == 3 { dpi / 2.54 } else { dpi };
+    let mut rational = layout.put_u32((value * 1000.0).round() as u32).to_vec();
+    rational.extend_from_slice(&layout.put_u32(1000));
+    let mut unit_value = vec![0u8; if layout.big { 8 } else { 4 }];
+    unit_value[..2].copy_from_slice(&layout.put_u16(unit));
+
+    let entry_at = |i: usize| ifd + count_len as u64 + (i * layout.entry_len() + layout.value_at()) as u64;
+    if let (Some(x), Some(y), Some(u)) = (find(TAG_X_RESOLUTION, TYPE_RATIONAL), find(TAG_Y_RESOLUTION, TYPE_RATIONAL), unit_index) {
+        // 三个标签都在：BigTIFF 的有理数放得进条目，经典 TIFF 写到条目指向的位置
+        for i in [x, y] {
+            let at = if layout.big { entry_at(i) } else { layout.u32(&entries[i][layout.value_at()..]) as u64 };
+            write_at(file, at, &rational)?;
+        }
+        return write_at(file, entry_at(u), &unit_value);
+    }
+
+    // 缺标签：在文件末尾写一份补全的 IFD（条目按标签号排序），下一页的指针不变
+    let end = file.seek(SeekFrom::End(0)).map_err(|e| i18n::tf("err.tiff_parse", &[&e]))?;
+    let start = end + end % 2;
+    let mut kept: Vec<Vec<u8>> = entries
+        .iter()
+        .filter(|e| ![TAG_X_RESOLUTION, TAG_Y_RESOLUTION, TAG_RESOLUTION_UNIT].contains(&layout.u16(&e[0..2])))
+        .map(|e| e.to_vec())
+        .collect();
+    let total = kept.len() + 3;
+    let ifd_len = count_len + total * layout.entry_len() + next_ifd.len();
+    // 经典 TIFF 的有理数放在 IFD 之后
+    let x_data = start + ifd_len as u64;
+    let entry = |tag: u16, typ: u16, value: Vec<u8>| -> Vec<u8> {
+        let mut e = layout.put_u16(tag).to_vec();
+        e.extend_from_slice(&layout.put_u16(typ));
+        e.extend_from_slice(&if layout.big { layout.put_u64(1).to_vec() } else { layout.put_u32(1).to_vec() });
+        e.extend_from_slice(&value);
+        e
+    };
+    let (x_value, y_value) = if layout.big {
+        (rational.clone(), rational.clone())
+    } else {
+        (layout.put_offset(x_data)?, layout.put_offset(x_data + 8)?)
+    };
+    kept.push(entry(TAG_X_RESOLUTION, TYPE_RATIONAL, x_value));
+    kept.push(entry(TAG_Y_RESOLUTION, TYPE_RATIONAL, y_value));
+    kept.push(entry(TAG_RESOLUTION_UNIT, TYPE_SHORT, unit_value));
+    kept.sort_by_key(|e| layout.u16(&e[0..2]));
+
+    let mut out = vec![0u8; (start - end) as usize];
+    if layout.big {
+        out.extend_from_slice(&layout.put_u64(total as u64));
+    } else {
+        out.extend_from_slice(&layout.put_u16(u16::try_from(total).map_err(|_| i18n::tf("err.tiff_parse", &[&i18n::t("err.tiff_tag_count")]))?));
+    }
+    kept.iter().for_each(|e| out.extend_from_slice(e));
+    out.extend_from_slice(next_ifd);
+    if !layout.big {
+        out.extend_from_slice(&rational);
+        out.extend_from_slice(&rational);
+    }
+    write_at(file, end, &out)?;
+    write_at(file, pointer_at, &layout.put_offset(start)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const TYPE_LONG: u16 = 4;
+    const PACKBITS: u16 = 32773;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tiff_io-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn gray() -> image::GrayImage {
+        image::GrayImage::from_fn(4, 3, |x, y| image::Luma([(x * 40 + y * 7) as u8]))
+    }
+
+    /// 手工拼一个两页的 8 位灰度 PackBits TIFF；unit 为 Some 时第一页带 72 的 X/YResolution 与该单位
+    fn fixture(little: bool, big: bool, unit: Option<u16>) -> Vec<u8> {
+        let l = Layout { little, big };
+        let width = if big { 8 } else { 4 };
+        let mut out = if little { b"II".to_vec() } else { b"MM".to_vec() };
+        if big {
+            out.extend_from_slice(&l.put_u16(43));
+            out.extend_from_slice(&l.put_u16(8));
+            out.extend_from_slice(&l.put_u16(0));
+            out.extend_from_slice(&l.put_u64(0));
+        } else {
+            out.extend_from_slice(&l.put_u16(42));
+            out.extend_from_slice(&l.put_u32(0));
+        }
+        let pixels = gray().into_raw();
+        let strip = out.len() as u32;
+        // PackBits 字面段：长度减一 + 原始字节
+        out.push(pixels.len() as u8 - 1);
+        out.extend_from_slice(&pixels);
+        out.push(0);
+        let mut rational = l.put_u32(72).to_vec();
+        rational.extend_from_slice(&l.put_u32(1));
+        let rational_at = out.len() as u32;
+        out.extend_from_slice(&rational);
+
+        let entry = |tag: u16, typ: u16, mut value: Vec<u8>| -> Vec<u8> {
+            value.resize(width, 0);
+            let mut e = l.put_u16(tag).to_vec();
+            e.extend_from_slice(&l.put_u16(typ));
+            e.extend_from_slice(&if big { l.put_u64(1).to_vec() } else { l.put_u32(1).to_vec() });
+            e.extend_from_slice(&value);
+            e
+        };
+        let short = |v: u16| l.put_u16(v).to_vec();
+        let long = |v: u32| l.put_u32(v).to_vec();
+        let mut pointer_at = if big { 8 } else { 4 };
+        for page in 0..2 {
+            let mut entries = vec![
+                entry(256, TYPE_SHORT, short(4)),
+                entry(257, TYPE_SHORT, short(3)),
+                entry(258, TYPE_SHORT, short(8)),
+                entry(259, TYPE_SHORT, short(PACKBITS)),
+                entry(262, TYPE_SHORT, short(1)),
+                entry(273, TYPE_LONG, long(strip)),
+                entry(277, TYPE_SHORT, short(1)),
+                entry(278, TYPE_SHORT, short(3)),
+                entry(279, TYPE_LONG, long(pixels.len() as u32 + 1)),
+            ];
+            if let (0, Some(unit)) = (page, unit) {
+                let value = if big { rational.clone() } else { long(rational_at) };
+                entries.push(entry(TAG_X_RESOLUTION, TYPE_RATIONAL, value.clone()));
+                entries.push(entry(TAG_Y_RESOLUTION, TYPE_RATIONAL, value));
+                entries.push(entry(TAG_RESOLUTION_UNIT, TYPE_SHORT, short(unit)));
+            }
+            let ifd = out.len() as u64;
+            let pointer = l.put_offset(ifd).unwrap();
+            out[pointer_at..pointer_at + pointer.len()].copy_from_slice(&pointer);
+            if big {
+                out.extend_from_slice(&l.put_u64(entries.len() as u64));
+            } else {
+                out.extend_from_slice(&l.put_u16(entries.len() as u16));
+            }
+            entries.iter().for_each(|e| out.extend_from_slice(e));
+            pointer_at = out.len();
+            out.extend_from_slice(&vec![0u8; width]);
+        }
+        out
+    }
+
+    /// 补写后用 tiff 解码器重读：像素、压缩方式不变，第二页仍在，分辨率为目标值
+    fn check_stamped(path: &Path, dpi: f64, unit: ResolutionUnit) {
+        let (img, info) = decode(path).unwrap();
+        assert_eq!(img, DynamicImage::ImageLuma8(gray()));
+        assert_eq!(info.compression, Some(TiffCompression::PackBits));
+        assert_eq!(info.resolution_unit, unit);
+        assert!((info.dpi().unwrap() - dpi).abs() < 0.01, "{:?}", info);
+        assert_eq!(info.y_resolution, info.x_resolution);
+
+        let mut decoder = Decoder::new(BufReader::new(File::open(path).unwrap())).unwrap();
+        assert!(decoder.more_images());
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (4, 3));
+        assert!(matches!(decoder.read_image().unwrap(), DecodingResult::U8(buf) if buf == gray().into_raw()));
+    }
+
+    #[test]
+    fn stamp_density_patches_existing_tags_in_place() {
+        let dir = test_dir("present");
+        for little in [true, false] {
+            let path = dir.join(format!("present-{}.tif", little));
+            let bytes = fixture(little, false, Some(2));
+            std::fs::write(&path, &bytes).unwrap();
+            stamp_density(&path, &path, 300.0).unwrap();
+            assert_eq!(std::fs::metadata(&path).unwrap().len(), bytes.len() as u64);
+            check_stamped(&path, 300.0, ResolutionUnit::Inch);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stamp_density_relocates_ifd_when_tags_are_missing() {
+        let dir = test_dir("missing");
+        for little in [true, false] {
+            let input = dir.join(format!("in-{}.tif", little));
+            let output = dir.join(format!("out-{}.tif", little));
+            let bytes = fixture(little, false, None);
+            std::fs::write(&input, &bytes).unwrap();
+            stamp_density(&input, &output, 300.0).unwrap();
+            // 原文件不动；新文件只在末尾追加，前面的字节除第一页指针外不变
+            assert_eq!(std::fs::read(&input).unwrap(), bytes);
+            let after = std::fs::read(&output).unwrap();
+            assert!(after.len() > bytes.len());
+            assert_eq!(after[8..bytes.len()], bytes[8..]);
+            check_stamped(&output, 300.0, ResolutionUnit::Inch);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stamp_density_handles_bigtiff() {
+        let dir = test_dir("big");
+        for unit in [Some(2), None] {
+            let path = dir.join(format!("big-{:?}.tif", unit));
+            std::fs::write(&path, fixture(true, true, unit)).unwrap();
+            stamp_density(&path, &path, 240.0).unwrap();
+            check_stamped(&path, 240.0, ResolutionUnit::Inch);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stamp_density_keeps_centimeter_unit() {
+        let dir = test_dir("cm");
+        let path = dir.join("cm.tif");
+        std::fs::write(&path, fixture(false, false, Some(3))).unwrap();
+        stamp_density(&path, &path, 300.0).unwrap();
+        check_stamped(&path, 300.0, ResolutionUnit::Centimeter);
+        assert_eq!(read_info(&path).unwrap().x_resolution, Some(((300.0f64 / 2.54 * 1000.0).round() as u32, 1000)));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn encode_round_trip_keeps_compression_and_resolution() {
+        let dir = test_dir("encode");
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(33, 17, |x, y| image::Rgb([x as u8 * 5, y as u8 * 9, 128])));
+        for compression in [TiffCompression::None, TiffCompression::Lzw, TiffCompression::Deflate, TiffCompression::PackBits] {
+            let path = dir.join(format!("{:?}.tif", compression));
+            let info = TiffInfo { compression: Some(compression), resolution_unit: ResolutionUnit::Centimeter, ..Default::default() };
+            encode(&img, &path, 300.0, &info, Some(b"fake-icc")).unwrap();
+            let (back, read) = decode(&path).unwrap();
+            assert_eq!(back, img);
+            assert_eq!(read.compression, Some(compression));
+            assert_eq!(read.resolution_unit, ResolutionUnit::Centimeter);
+            assert!((read.dpi().unwrap() - 300.0).abs() < 0.01);
+            assert_eq!(read.icc.as_deref(), Some(&b"fake-icc"[..]));
+        }
+        let info = TiffInfo { compression: None, ..Default::default() };
+        assert_eq!(encode(&img, &dir.join("jpeg.tif"), 300.0, &info, None), Err(i18n::t("err.tiff_compression")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
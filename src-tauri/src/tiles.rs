@@ -0,0 +1,244 @@
+// ==========================================
+// 🌟 瓦片服务：多级金字塔按需切图、缓存到磁盘，经 imagetile:// 协议提供
+// ==========================================
+// 几百 MB 的原图不再整张交给 webview 解码：open 解码一次原图并记下尺寸，
+// 每块瓦片第一次被请求时才切出来写进缓存目录，之后直接读文件。
+// 缓存按 路径 + 文件大小 + 修改时间 区分，原图被改写后自动换一套。
+// 地址：<前缀>/<级>/<列>/<行>.jpg，第 0 级为原始像素，每升一级长宽减半。
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+use tauri::http::{header, Response, StatusCode};
+use tauri::AppHandle;
+
+use crate::i18n;
+
+/// 协议名；Windows 上 webview 以 http://imagetile.localhost/ 访问
+pub(crate) const SCHEME: &str = "imagetile";
+const TILE_SIZE: u32 = 256;
+const TILE_QUALITY: u8 = 85;
+/// 最多保留的原图缓存套数，超出时删除最久未打开的
+const KEEP_SOURCES: usize = 20;
+
+/// 解码原图为查看用的 sRGB 图像（原生管线优先，读不了的交给 magick）
+pub(crate) type Decoder = fn(&Path) -> Result<RgbImage, String>;
+
+/// 前端拼瓦片地址所需的全部信息
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TileSource {
+    pub key: String,
+    /// 原图路径，缓存键碰撞或原图丢失时用来核对
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    /// 总级数：最高一级整张图放得进一块瓦片
+    pub levels: u32,
+    /// 瓦片地址前缀，后接 /<级>/<列>/<行>.jpg
+    #[serde(skip_deserializing)]
+    pub base_url: String,
+}
+
+pub(crate) struct Tiles {
+    dir: PathBuf,
+    decode: Decoder,
+    /// 最近打开的原图，切瓦片时不必重复解码；持锁解码，避免并发请求同时解出多份大图
+    current: Mutex<Option<(String, Arc<RgbImage>)>>,
+}
+
+/// 自定义协议在 webview 里的地址：Windows 为 http://<协议>.localhost/，其余平台为 <协议>://localhost/
+pub(crate) fn protocol_url(scheme: &str, path: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("http://{}.localhost/{}", scheme, path)
+    } else {
+        format!("{}://localhost/{}", scheme, path)
+    }
+}
+
+fn base_url(key: &str) -> String {
+    protocol_url(SCHEME, key)
+}
+
+/// 缓存键：路径、大小与修改时间的 CRC32
+fn source_key(path: &Path) -> Result<String, String> {
+    let meta = std::fs::metadata(path).map_err(|e| format!("{}: {}", i18n::t("err.read_meta"), e))?;
+    let modified = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_nanos()).unwrap_or(0);
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(&meta.len().to_le_bytes());
+    hasher.update(&modified.to_le_bytes());
+    Ok(format!("{:08x}", hasher.finalize()))
+}
+
+/// 最高一级整张图放得进一块瓦片
+fn level_count(width: u32, height: u32) -> u32 {
+    let mut levels = 1;
+    while (width.max(height) >> (levels - 1)) > TILE_SIZE {
+        levels += 1;
+    }
+    levels
+}
+
+fn not_found(message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(message.as_bytes().to_vec())
+        .unwrap_or_default()
+}
+
+impl Tiles {
+    pub fn open(app: &AppHandle, decode: Decoder) -> Result<Self, String> {
+        let dir = dirs_next::cache_dir()
+            .ok_or_else(|| i18n::t("err.cache_dir"))?
+            .join(&app.config().identifier)
+            .join("tiles");
+        std::fs::create_dir_all(&dir).map_err(|e| i18n::tf("err.create_dir", &[&dir.display(), &e]))?;
+        Ok(Tiles { dir, decode, current: Mutex::new(None) })
+    }
+
+    fn read_source(&self, key: &str) -> Option<TileSource> {
+        let text = std::fs::read_to_string(self.dir.join(key).join("source.json")).ok()?;
+        let mut source: TileSource = serde_json::from_str(&text).ok()?;
+        source.base_url = base_url(key);
+        Some(source)
+    }
+
+    /// 取出原图像素：与最近打开的是同一张就复用，否则重新解码
+    fn image(&self, key: &str, path: &Path) -> Result<Arc<RgbImage>, String> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((current_key, image)) = current.as_ref() {
+            if current_key == key {
+                return Ok(image.clone());
+            }
+        }
+        // 先释放上一张，峰值内存只算一张图
+        *current = None;
+        let image = Arc::new((self.decode)(path)?);
+        *current = Some((key.to_string(), image.clone()));
+        Ok(image)
+    }
+
+    /// 打开原图，返回金字塔信息；同一文件已有缓存时不解码
+    pub fn source(&self, path: &Path) -> Result<TileSource, String> {
+        let key = source_key(path)?;
+        let path_str = path.to_string_lossy().to_string();
+        if let Some(source) = self.read_source(&key).filter(|s| s.path == path_str) {
+            // 刷新修改时间，清理时按最近使用保留
+            let _ = std::fs::write(self.dir.join(&key).join("source.json"), serde_json::to_string(&source).unwrap_or_default());
+            return Ok(source);
+        }
+
+        let image = self.image(&key, path)?;
+        let (width, height) = image.dimensions();
+        let source = TileSource {
+            key: key.clone(),
+            path: path_str,
+            width,
+            height,
+            tile_size: TILE_SIZE,
+            levels: level_count(width, height),
+            base_url: base_url(&key),
+        };
+        // 键相同但路径不同（CRC 碰撞）时整套重建
+        let dir = self.dir.join(&key);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).map_err(|e| i18n::tf("err.create_dir", &[&dir.display(), &e]))?;
+        let text = serde_json::to_string(&source).map_err(|e| format!("{}: {}", i18n::t("err.tile_info_write"), e))?;
+        std::fs::write(dir.join("source.json"), text).map_err(|e| format!("{}: {}", i18n::t("err.tile_info_write"), e))?;
+        self.prune(&key);
+        Ok(source)
+    }
+
+    /// 只保留最近打开的若干套缓存
+    fn prune(&self, keep: &str) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return };
+        let mut sources: Vec<(SystemTime, PathBuf)> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir() && !p.ends_with(keep))
+            .map(|p| (std::fs::metadata(p.join("source.json")).and_then(|m| m.modified()).unwrap_or(UNIX_EPOCH), p))
+            .collect();
+        if sources.len() < KEEP_SOURCES {
+            return;
+        }
+        sources.sort_by_key(|(time, _)| *time);
+        for (_, dir) in sources.iter().take(sources.len() + 1 - KEEP_SOURCES) {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    /// 读取或生成一块瓦片
+    fn tile(&self, key: &str, level: u32, col: u32, row: u32) -> Result<Vec<u8>, String> {
+        let file = self.dir.join(key).join(level.to_string()).join(format!("{}_{}.jpg", col, row));
+        if let Ok(bytes) = std::fs::read(&file) {
+            return Ok(bytes);
+        }
+
+        let source = self.read_source(key).ok_or_else(|| i18n::t("err.tile_source_missing"))?;
+        if level >= source.levels {
+            return Err(i18n::t("err.tile_level"));
+        }
+        // 该级一块瓦片覆盖的原图像素
+        let span = TILE_SIZE << level;
+        let (x, y) = (col.saturating_mul(span), row.saturating_mul(span));
+        if x >= source.width || y >= source.height {
+            return Err(i18n::t("err.tile_coord"));
+        }
+        let (w, h) = (span.min(source.width - x), span.min(source.height - y));
+
+        let image = self.image(key, Path::new(&source.path))?;
+        if image.dimensions() != (source.width, source.height) {
+            return Err(i18n::t("err.source_changed"));
+        }
+        let region = imageops::crop_imm(image.as_ref(), x, y, w, h).to_image();
+        drop(image);
+        let scale = 1u32 << level;
+        let tile = if scale == 1 {
+            region
+        } else {
+            imageops::resize(&region, w.div_ceil(scale), h.div_ceil(scale), FilterType::Triangle)
+        };
+
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut bytes, TILE_QUALITY)
+            .encode_image(&tile)
+            .map_err(|e| i18n::tf("err.encode", &[&"JPEG", &e]))?;
+        // 并发请求同一块时各写各的临时文件，最后改名
+        if let Some(parent) = file.parent() {
+            let _ = std::fs::create_dir_all(parent);
+            let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+            let temp = parent.join(format!(".{}_{}.{:?}-{}.tmp", col, row, std::thread::current().id(), nanos));
+            if std::fs::write(&temp, &bytes).is_ok() && std::fs::rename(&temp, &file).is_err() {
+                let _ = std::fs::remove_file(&temp);
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// 协议入口：解析 /<键>/<级>/<列>/<行>.jpg
+    pub fn respond(&self, uri_path: &str) -> Response<Vec<u8>> {
+        let parts: Vec<&str> = uri_path.trim_matches('/').split('/').collect();
+        let [key, level, col, row] = parts.as_slice() else { return not_found(&i18n::t("err.tile_invalid")) };
+        let valid_key = key.len() == 8 && key.chars().all(|c| c.is_ascii_hexdigit());
+        let numbers = (level.parse::<u32>(), col.parse::<u32>(), row.trim_end_matches(".jpg").parse::<u32>());
+        let (true, (Ok(level), Ok(col), Ok(row))) = (valid_key, numbers) else { return not_found(&i18n::t("err.tile_invalid")) };
+
+        match self.tile(key, level, col, row) {
+            Ok(bytes) => Response::builder()
+                .header(header::CONTENT_TYPE, "image/jpeg")
+                // 键里带了修改时间，内容不会变
+                .header(header::CACHE_CONTROL, "max-age=31536000, immutable")
+                .body(bytes)
+                .unwrap_or_default(),
+            Err(e) => not_found(&e),
+        }
+    }
+}
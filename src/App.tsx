@@ -4,13 +4,18 @@ import { getCurrentWindow } from "@tauri-apps/api/window";
 import { invoke } from "@tauri-apps/api/core";
 import { getVersion } from "@tauri-apps/api/app";
 import { listen } from "@tauri-apps/api/event";
-import { ImageItem, ProcessProgress } from "./types";
+import { BatchEvent, BatchReport, FileSupport, ImageItem, ProcessProgress, QueueSummary, RenameItem, UndoReport } from "./types";
 import Sidebar from "./components/Sidebar";
 import ImageGrid, { DEFAULT_ZOOM } from "./components/ImageGrid";
 import ListImageView from "./components/ListImageView";
 import { PAPER_CATEGORIES } from "./components/PaperSetting";
 import CropSetting, { ProcessPayload } from "./components/CropSetting";
 import ReplicateSetting from "./components/ReplicateSetting";
+import EngineSetting from "./components/EngineSetting";
+import { readOutputOptions } from "./components/OutputSetting";
+import { readRenameContext, readRenameTemplate } from "./components/RenameTemplateSetting";
+import { engineInstallHint, errorMessage, isAppError, isRetryable } from "./errors";
+import { LOCALES, Locale, changeLocale, currentLocale, t } from "./i18n";
 
 const readStoredString = (key: string, fallback: string) => {
   const stored = localStorage.getItem(key);
@@ -24,6 +29,9 @@ const readStoredNumber = (key: string, fallback: number) => {
   return Number.isFinite(parsed) && parsed > 0 ? parsed : fallback;
 };
 
+// 全局默认输出 DPI（CropSetting 中设置），纸张配置可单独覆盖
+const readOutputDpi = () => readStoredNumber("app_output_dpi", 300);
+
 const readStoredTab = (): "paper" | "crop" | "replicate" | "cost" => {
   const stored = localStorage.getItem("app_active_tab");
   if (stored === "paper" || stored === "crop" || stored === "replicate" || stored === "cost") {
@@ -45,16 +53,28 @@ const clampSidebarWidth = (width: number, containerWidth: number) => {
 export default function App() {
   const containerRef = useRef<HTMLDivElement | null>(null);
 
+  const [locale, setLocale] = useState<Locale>(currentLocale);
+  const [showEngineSetting, setShowEngineSetting] = useState(false);
+
   useEffect(() => {
     getVersion()
       .then((version) => {
         const normalizedVersion = version.startsWith("v") ? version.slice(1) : version;
-        const title = `墨印众合-imagetool v${normalizedVersion}`;
+        const title = t("app.title", normalizedVersion);
         document.title = title;
         getCurrentWindow().setTitle(title).catch(() => {});
       })
       .catch(() => {});
-  }, []);
+  }, [locale]);
+
+  const handleLocale = async (value: Locale) => {
+    try {
+      await changeLocale(value);
+      setLocale(value);
+    } catch (e) {
+      alert(errorMessage(e));
+    }
+  };
 
   const withPreviewCacheBuster = (url: string) => {
     if (!url) return url;
@@ -92,6 +112,7 @@ export default function App() {
     statusMessage: "准备处理..."
   });
 
+  const [activeBatchId, setActiveBatchId] = useState<string | null>(null);
   const isProcessing = progress.isProcessing;
 
   useEffect(() => {
@@ -159,6 +180,41 @@ export default function App() {
     };
   }, [isResizingSidebar]);
 
+  // 加图：先按引擎诊断判断能否处理（缺 delegate、未开“最大兼容”的 PSD 等直接标为不支持），再逐张解析尺寸与缩略图
+  const addImages = async (filePaths: string[]) => {
+    const newImages: ImageItem[] = filePaths.map((path: string) => {
+      const fileName = path.split(/[\\/]/).pop() || "未知文件";
+      return { path, url: "", name: fileName, selected: false, size: "解析生成中...", isSupported: true };
+    });
+    setImages(prev => [...prev, ...newImages]);
+
+    let support: FileSupport[] = [];
+    try {
+      support = await invoke<FileSupport[]>("check_support", { paths: filePaths });
+    } catch {
+      // 诊断失败时不拦截，交给后续解析报错
+    }
+    const unsupported = new Map(support.filter(item => !item.isSupported).map(item => [item.path, item.reason]));
+    if (unsupported.size > 0) {
+      setImages(prev => prev.map(p => unsupported.has(p.path) ? { ...p, size: "⚠️ 不支持", isSupported: false, issue: unsupported.get(p.path) ?? undefined } : p));
+    }
+
+    newImages.filter(img => !unsupported.has(img.path)).forEach(async (img) => {
+      try {
+        const [sizeStr, thumbUrl, meta] = await Promise.all([
+          invoke<string>("get_image_size", { pathStr: img.path, outputDpi: readOutputDpi() }),
+          invoke<string>("generate_thumbnail", { pathStr: img.path }),
+          invoke<any>("get_image_meta", { pathStr: img.path, outputDpi: readOutputDpi() })
+        ]);
+        const finalUrl = withPreviewCacheBuster(thumbUrl);
+        setImages(prev => prev.map(p => p.path === img.path ? { ...p, size: sizeStr, url: finalUrl, dpi: meta?.dpi } : p));
+      } catch (error) {
+        const size = isAppError(error) && error.code === "engine_missing" ? "⚠️ 未安装引擎" : "尺寸未知";
+        setImages(prev => prev.map(p => p.path === img.path ? { ...p, size } : p));
+      }
+    });
+  };
+
   useEffect(() => {
     const unlistenPromise = getCurrentWebview().onDragDropEvent((event) => {
       if (event.payload.type === "over") setIsDragging(true);
@@ -168,29 +224,7 @@ export default function App() {
         const filePaths = (event.payload as any).paths || [];
         if (filePaths.length === 0) return;
         
-        const newImages: ImageItem[] = filePaths.map((path: string) => {
-          const fileName = path.split(/[\\/]/).pop() || "未知文件";
-          const isSupported = /\.(jpg|jpeg|tif|tiff|png|psd)$/i.test(path);
-          return { path, url: "", name: fileName, selected: false, size: isSupported ? "解析生成中..." : "⚠️ 不支持", isSupported };
-        });
-        
-        setImages(prev => [...prev, ...newImages]);
-
-        newImages.forEach(async (img) => {
-          if (img.isSupported) {
-            try {
-              const [sizeStr, thumbUrl, meta] = await Promise.all([
-                invoke<string>("get_image_size", { pathStr: img.path }),
-                invoke<string>("generate_thumbnail", { pathStr: img.path }),
-                invoke<any>("get_image_meta", { pathStr: img.path })
-              ]);
-              const finalUrl = withPreviewCacheBuster(thumbUrl);
-              setImages(prev => prev.map(p => p.path === img.path ? { ...p, size: sizeStr, url: finalUrl, dpi: meta?.dpi } : p));
-            } catch (error) {
-              setImages(prev => prev.map(p => p.path === img.path ? { ...p, size: "尺寸未知" } : p));
-            }
-          }
-        });
+        addImages(filePaths);
       }
     });
     return () => { unlistenPromise.then((unlisten) => unlisten()); };
@@ -202,29 +236,7 @@ export default function App() {
       const filePaths = event.payload || [];
       if (filePaths.length === 0) return;
 
-      const newImages: ImageItem[] = filePaths.map((path: string) => {
-        const fileName = path.split(/[\\/]/).pop() || "未知文件";
-        const isSupported = /\.(jpg|jpeg|tif|tiff|png|psd)$/i.test(path);
-        return { path, url: "", name: fileName, selected: false, size: isSupported ? "解析生成中..." : "⚠️ 不支持", isSupported };
-      });
-
-      setImages(prev => [...prev, ...newImages]);
-
-      newImages.forEach(async (img) => {
-        if (img.isSupported) {
-          try {
-            const [sizeStr, thumbUrl, meta] = await Promise.all([
-              invoke<string>("get_image_size", { pathStr: img.path }),
-              invoke<string>("generate_thumbnail", { pathStr: img.path }),
-              invoke<any>("get_image_meta", { pathStr: img.path })
-            ]);
-            const finalUrl = withPreviewCacheBuster(thumbUrl);
-            setImages(prev => prev.map(p => p.path === img.path ? { ...p, size: sizeStr, url: finalUrl, dpi: meta?.dpi } : p));
-          } catch (error) {
-            setImages(prev => prev.map(p => p.path === img.path ? { ...p, size: "尺寸未知" } : p));
-          }
-        }
-      });
+      addImages(filePaths);
     });
     return () => { unlistenPromise.then((unlisten) => unlisten()); };
   }, []);
@@ -238,101 +250,171 @@ export default function App() {
 
   const selectedImages = images.filter(img => img.selected && img.isSupported);
   const supportedImages = images.filter(img => img.isSupported);
-
-  const handleProcessAll = async (payloads: ProcessPayload[]) => {
-    if (isProcessing) return;
-    if (payloads.length === 0) return;
-    
-    let successCount = 0;
-    const processedMap = new Map<string, {newPath: string, newName: string}>();
-    
+  const finalPaperType = customPaper.trim() !== "" ? customPaper.trim() : activePaper;
+  // 改名项：纸张、工艺与设定的份数交给后端按模板拼名
+  const renameItems: RenameItem[] = selectedImages.map(img => ({
+    path: img.path, paper: finalPaperType, craft: activeCraft, copies: replicateCounts[img.path] || null
+  }));
+
+  // 执行一个批次：监听逐张事件更新进度，结束后按报告刷新列表
+  const runBatch = async (batchId: string, total: number, start: () => Promise<BatchReport>): Promise<BatchReport | null> => {
     setProgress({
       isProcessing: true,
       current: 0,
-      total: payloads.length,
+      total,
       currentName: "",
       statusMessage: "初始化中..."
     });
-    
-    for (let i = 0; i < payloads.length; i++) {
-      const payload = payloads[i];
-      const fileName = payload.image.name || "未知文件";
-      
-      setProgress({
-        isProcessing: true,
-        current: i,
-        total: payloads.length,
-        currentName: fileName,
-        statusMessage: `正在处理 (${i + 1}/${payloads.length})`
-      });
-      
-      try {
-        const [newPath, newName] = await invoke<[string, string]>("process_image", {
-          pathStr: payload.image.path, mode: payload.mode, targetWCm: payload.targetW, targetHCm: payload.targetH,
-          cropX: payload.cropData.x, cropY: payload.cropData.y, cropW: payload.cropData.w, cropH: payload.cropData.h,
-          borderTopCm: payload.borderTopCm ?? 0,
-          borderRightCm: payload.borderRightCm ?? 0,
-          borderBottomCm: payload.borderBottomCm ?? 0,
-          borderLeftCm: payload.borderLeftCm ?? 0
-        });
-        successCount++;
-        processedMap.set(payload.image.path, {newPath, newName});
-      } catch (error) { 
-        console.error(`❌ 处理失败:`, error);
-        setProgress(prev => ({
-          ...prev,
-          statusMessage: `处理失败: ${error}`
-        }));
-      }
+    setActiveBatchId(batchId);
+
+    // 后端线程池并行处理，逐张推送事件
+    const unlisteners = await Promise.all([
+      listen<BatchEvent>("batch-progress", (event) => {
+        if (event.payload.batchId !== batchId) return;
+        const name = event.payload.path.split(/[\\/]/).pop() || "未知文件";
+        setProgress(prev => ({ ...prev, currentName: name, statusMessage: `正在处理 (${event.payload.done + 1}/${event.payload.total})` }));
+      }),
+      listen<BatchEvent>("batch-success", (event) => {
+        if (event.payload.batchId !== batchId) return;
+        setProgress(prev => ({ ...prev, current: event.payload.done }));
+      }),
+      listen<BatchEvent>("batch-failure", (event) => {
+        if (event.payload.batchId !== batchId) return;
+        console.error(`❌ 处理失败: ${event.payload.path}`, event.payload.error);
+        setProgress(prev => ({ ...prev, current: event.payload.done, statusMessage: `处理失败: ${event.payload.error?.message ?? ""}` }));
+      })
+    ]);
+
+    let report: BatchReport | null = null;
+    let startError: unknown = null;
+    try {
+      report = await start();
+    } catch (error) {
+      startError = error;
+      console.error("❌ 批量处理失败:", error);
+    } finally {
+      unlisteners.forEach(unlisten => unlisten());
+      setActiveBatchId(null);
     }
 
+    const processedMap = new Map<string, {newPath: string, newName: string}>();
+    report?.items.forEach(item => {
+      if (item.status === "succeeded" && item.newPath && item.newName) {
+        processedMap.set(item.path, { newPath: item.newPath, newName: item.newName });
+      }
+    });
+
     setProgress(prev => ({
       ...prev,
-      current: payloads.length,
+      current: total,
       statusMessage: "正在更新缓存..."
     }));
 
-    if (successCount > 0) {
-       const updatedImages = await Promise.all(images.map(async (img) => {
-          const match = processedMap.get(img.path);
-          if (match) {
-             let newSize = img.size;
-             let newThumb = img.url;
-             
-             try {
-                newSize = await invoke<string>("get_image_size", { pathStr: match.newPath });
-             } catch (e) { console.error("获取尺寸失败", e); }
-
-             try {
-                newThumb = await invoke<string>("generate_thumbnail", { pathStr: match.newPath });
-               newThumb = withPreviewCacheBuster(newThumb);
-             } catch (e) { console.error("获取预览图失败", e); }
-
-             return { ...img, path: match.newPath, name: match.newName, size: newSize, url: newThumb };
-          }
-          return img;
-       }));
-       setImages(updatedImages);
-       
-       setProgress({
-         isProcessing: false,
-         current: payloads.length,
-         total: payloads.length,
-         currentName: "",
-         statusMessage: ""
-       });
-       
-      // 不再弹出前台提醒，保留进度层展示
-    } else { 
-      setProgress({
-        isProcessing: false,
-        current: 0,
-        total: payloads.length,
-        currentName: "",
-        statusMessage: ""
-      });
-      // 不再弹出前台提醒，保留进度层展示
+    if (processedMap.size > 0) {
+      const probe = async (path: string) => {
+        let size = "尺寸未知";
+        let url = "";
+        try {
+          size = await invoke<string>("get_image_size", { pathStr: path, outputDpi: readOutputDpi() });
+        } catch (e) { console.error("获取尺寸失败", e); }
+        try {
+          url = withPreviewCacheBuster(await invoke<string>("generate_thumbnail", { pathStr: path }));
+        } catch (e) { console.error("获取预览图失败", e); }
+        return { size, url };
+      };
+      const listed = new Set(images.map(img => img.path));
+      const updatedImages = await Promise.all(images.map(async (img) => {
+        const match = processedMap.get(img.path);
+        if (!match) return img;
+        const { size, url } = await probe(match.newPath);
+        return { ...img, path: match.newPath, name: match.newName, size, url: url || img.url };
+      }));
+      // 继续上次未完成的批次时，列表里可能还没有这些文件
+      const added = await Promise.all(Array.from(processedMap.entries())
+        .filter(([path]) => !listed.has(path))
+        .map(async ([, match]) => {
+          const { size, url } = await probe(match.newPath);
+          return { path: match.newPath, url, name: match.newName, selected: false, size, isSupported: true };
+        }));
+      setImages([...updatedImages, ...added]);
+    }
+
+    setProgress({
+      isProcessing: false,
+      current: processedMap.size,
+      total,
+      currentName: "",
+      statusMessage: ""
+    });
+
+    // 有失败或中途停止时汇总提示，逐个列出失败原因；缺引擎时只附一次安装指引
+    if (report && (report.failed > 0 || report.cancelled > 0)) {
+      const failed = report.items.filter(item => item.status === "failed");
+      const failures = failed.map(item => `${item.path.split(/[\\/]/).pop()}: ${item.error?.message ?? ""}`);
+      const hint = failed.some(item => item.error?.code === "engine_missing") ? `\n\n${engineInstallHint()}` : "";
+      alert(`完成 ${report.succeeded} 张，失败 ${report.failed} 张，未处理 ${report.cancelled} 张${failures.length ? `\n\n${failures.join("\n")}` : ""}${hint}`);
+    } else if (!report) {
+      alert(startError ? `批量处理失败：${errorMessage(startError)}` : "批量处理失败，请查看日志");
     }
+    return report;
+  };
+
+  const handleProcessAll = async (payloads: ProcessPayload[]) => {
+    if (isProcessing) return;
+    if (payloads.length === 0) return;
+
+    const batchId = `${Date.now()}-${Math.random().toString(36).slice(2, 8)}`;
+    const report = await runBatch(batchId, payloads.length, () => invoke<BatchReport>("process_batch", {
+      batchId,
+      jobs: payloads.map(payload => ({
+        path: payload.image.path, mode: payload.mode, targetWCm: payload.targetW, targetHCm: payload.targetH,
+        cropX: payload.cropData.x, cropY: payload.cropData.y, cropW: payload.cropData.w, cropH: payload.cropData.h,
+        borderTopCm: payload.borderTopCm ?? 0,
+        borderRightCm: payload.borderRightCm ?? 0,
+        borderBottomCm: payload.borderBottomCm ?? 0,
+        borderLeftCm: payload.borderLeftCm ?? 0
+      })),
+      colorPolicy: localStorage.getItem("app_color_policy") || "keep",
+      outputDpi: readOutputDpi(),
+      output: readOutputOptions(payloads.map(p => p.image.path))
+    }));
+
+    // 磁盘满、文件被占用之类的失败可以重试；损坏、不支持的文件直接跳过
+    const retryPaths = new Set(report?.items.filter(item => item.status === "failed" && isRetryable(item.error)).map(item => item.path) ?? []);
+    const retryPayloads = payloads.filter(payload => retryPaths.has(payload.image.path));
+    if (retryPayloads.length > 0 && confirm(`有 ${retryPayloads.length} 张因磁盘、权限或引擎问题失败，处理好后可重试。\n确定：重试这些文件\n取消：跳过`)) {
+      await handleProcessAll(retryPayloads);
+    }
+  };
+
+  // 启动时检查上次崩溃或被关闭时没跑完的批次（开发模式下 effect 会执行两次，只问一次）
+  const unfinishedCheckedRef = useRef(false);
+  useEffect(() => {
+    if (unfinishedCheckedRef.current) return;
+    unfinishedCheckedRef.current = true;
+    invoke<QueueSummary[]>("get_unfinished_batches").then(async (batches) => {
+      for (const batch of batches) {
+        const when = new Date(batch.createdMs).toLocaleString();
+        if (confirm(`发现未完成的批处理（${when}，共 ${batch.total} 张，已完成 ${batch.done} 张）。\n确定：继续处理剩余文件\n取消：选择回滚或放弃`)) {
+          await runBatch(batch.batchId, batch.total, () => invoke<BatchReport>("resume_batch", { batchId: batch.batchId }));
+        } else if (confirm(`是否回滚该批次已处理的 ${batch.done} 张文件，恢复原图？\n取消则保留现状并放弃该批次。`)) {
+          try {
+            const report = await invoke<UndoReport>("rollback_batch", { batchId: batch.batchId });
+            applyUndoReport(report);
+          } catch (e) {
+            alert(`回滚失败：${errorMessage(e)}`);
+          }
+        } else {
+          invoke("discard_batch", { batchId: batch.batchId }).catch(() => {});
+        }
+      }
+    }).catch(() => {});
+  }, []);
+
+  const handleCancelBatch = () => {
+    if (!activeBatchId) return;
+    invoke("cancel_batch", { batchId: activeBatchId }).catch(() => {});
+    setProgress(prev => ({ ...prev, statusMessage: "正在停止，当前文件完成后结束..." }));
   };
 
   const handleRename = async () => {
@@ -348,20 +430,17 @@ export default function App() {
         statusMessage: "初始化中..."
       });
       
-      const finalPaperType = customPaper.trim() !== "" ? customPaper.trim() : activePaper;
-      const finalPrefix = `${finalPaperType}-${activeCraft}`;
-      const payload = selectedImages.map((img, idx) => {
-        setProgress({
-          isProcessing: true,
-          current: idx,
-          total: selectedImages.length,
-          currentName: img.name,
-          statusMessage: `正在重命名 (${idx + 1}/${selectedImages.length})`
-        });
-        return [img.path, finalPrefix];
+      setProgress({
+        isProcessing: true,
+        current: 0,
+        total: selectedImages.length,
+        currentName: "",
+        statusMessage: `正在重命名 (${selectedImages.length})`
       });
       
-      const renamedData = await invoke<[string, string, string][]>("rename_files", { filesToProcess: payload });
+      const renamedData = await invoke<[string, string, string][]>("rename_files", {
+        items: renameItems, template: readRenameTemplate(), context: readRenameContext(), outputDpi: readOutputDpi()
+      });
       
       setProgress(prev => ({
         ...prev,
@@ -373,7 +452,7 @@ export default function App() {
         if (match) {
           const [, newPath, newName] = match;
           try {
-             const newSize = await invoke<string>("get_image_size", { pathStr: newPath });
+             const newSize = await invoke<string>("get_image_size", { pathStr: newPath, outputDpi: readOutputDpi(), paper: finalPaperType });
              let newThumb = await invoke<string>("generate_thumbnail", { pathStr: newPath });
              newThumb = withPreviewCacheBuster(newThumb);
              return { ...img, path: newPath, name: newName, url: newThumb, size: newSize, selected: false };
@@ -402,6 +481,7 @@ export default function App() {
         statusMessage: ""
       });
       console.error("重命名失败：", error);
+      alert(`❌ 重命名失败: ${errorMessage(error)}`);
     }
   };
 
@@ -414,6 +494,7 @@ export default function App() {
     
     let allNewPaths: string[] = [];
     const retainedPaths: string[] = [];
+    const outputOptions = readOutputOptions(supportedImages.map(img => img.path));
     
     setProgress({
       isProcessing: true,
@@ -440,7 +521,7 @@ export default function App() {
           retainedPaths.push(img.path);
           continue;
         }
-        const res = await invoke<string[]>("replicate_image", { pathStr: img.path, totalCopies: count });
+        const res = await invoke<string[]>("replicate_image", { pathStr: img.path, totalCopies: count, output: outputOptions });
         allNewPaths.push(...res);
       }
 
@@ -461,9 +542,9 @@ export default function App() {
         newImagesList.forEach(async (img) => {
           try {
             const [size, thumb, meta] = await Promise.all([
-              invoke<string>("get_image_size", { pathStr: img.path }),
+              invoke<string>("get_image_size", { pathStr: img.path, outputDpi: readOutputDpi() }),
               invoke<string>("generate_thumbnail", { pathStr: img.path }),
-              invoke<any>("get_image_meta", { pathStr: img.path })
+              invoke<any>("get_image_meta", { pathStr: img.path, outputDpi: readOutputDpi() })
             ]);
             const url = withPreviewCacheBuster(thumb);
             setImages(prev => prev.map(p => p.path === img.path ? { ...p, size, url, dpi: meta?.dpi } : p));
@@ -490,6 +571,44 @@ export default function App() {
     }
   };
 
+  // 撤销后按结果刷新列表：消失的文件移除，回来的文件重新解析
+  const applyUndoReport = (report: UndoReport) => {
+    const restored = Array.from(new Set(report.restored));
+    const gone = new Set(report.removed.filter(path => !restored.includes(path)));
+    const existing = new Set(images.map(img => img.path));
+    const newImages: ImageItem[] = restored.filter(path => !existing.has(path)).map(path => ({
+      path, url: "", name: path.split(/[\\/]/).pop() || "", selected: false, size: "解析中...", isSupported: true
+    }));
+    setImages(prev => [...prev.filter(img => !gone.has(img.path)), ...newImages]);
+
+    restored.forEach(async (path) => {
+      try {
+        const [size, thumb, meta] = await Promise.all([
+          invoke<string>("get_image_size", { pathStr: path, outputDpi: readOutputDpi() }),
+          invoke<string>("generate_thumbnail", { pathStr: path }),
+          invoke<any>("get_image_meta", { pathStr: path, outputDpi: readOutputDpi() })
+        ]);
+        const url = withPreviewCacheBuster(thumb);
+        setImages(prev => prev.map(p => p.path === path ? { ...p, size, url, dpi: meta?.dpi } : p));
+      } catch (e) {}
+    });
+    if (report.failed.length > 0) alert(`以下文件未能还原：\n${report.failed.join("\n")}`);
+  };
+
+  const handleUndo = async (wholeSession: boolean) => {
+    if (isProcessing) return;
+    if (wholeSession && !confirm("将本次打开软件以来的所有改动全部还原，确定吗？")) return;
+    try {
+      const report = await invoke<UndoReport>(
+        wholeSession ? "restore_session" : "undo_last", {}
+      );
+      applyUndoReport(report);
+      setReplicateLocked(false);
+    } catch (e) {
+      alert(`撤销失败：${errorMessage(e)}`);
+    }
+  };
+
   const handleSyncReplicateToCost = () => {
     if (isProcessing) return;
     setCostQuantities(() => {
@@ -557,7 +676,24 @@ export default function App() {
               style={{ width: `${isProcessing ? progressPercent : 0}%` }}
             />
           </div>
-          <div className="mt-1 truncate">{progressDescText}</div>
+          <div className="mt-1 flex items-center gap-2">
+            <div className="flex-1 min-w-0 truncate">{progressDescText}</div>
+            {activeBatchId && (
+              <button onClick={handleCancelBatch} className="shrink-0 text-[11px] font-bold text-red-500 hover:text-red-700">停止</button>
+            )}
+            <button disabled={isProcessing} onClick={() => handleUndo(false)} className="shrink-0 text-[11px] font-bold text-gray-500 hover:text-blue-600 disabled:opacity-40 disabled:cursor-not-allowed">撤销上一步</button>
+            <button disabled={isProcessing} onClick={() => handleUndo(true)} className="shrink-0 text-[11px] font-bold text-gray-500 hover:text-red-600 disabled:opacity-40 disabled:cursor-not-allowed">全部还原</button>
+            <select
+              disabled={isProcessing}
+              value={locale}
+              onChange={e => handleLocale(e.target.value as Locale)}
+              title="界面语言 / Language"
+              className="shrink-0 text-[11px] font-bold text-gray-500 bg-transparent outline-none disabled:opacity-40 disabled:cursor-not-allowed"
+            >
+              {LOCALES.map(item => <option key={item.value} value={item.value}>{item.label}</option>)}
+            </select>
+            <button disabled={isProcessing} onClick={() => setShowEngineSetting(true)} className="shrink-0 text-[11px] font-bold text-gray-500 hover:text-blue-600 disabled:opacity-40 disabled:cursor-not-allowed">引擎设置</button>
+          </div>
         </div>
         
         {activeTab !== "cost" && (
@@ -566,7 +702,7 @@ export default function App() {
               <CropSetting selectedImages={selectedImages} panelWidth={sidebarWidth} disabled={isProcessing} onProcessAll={handleProcessAll} />
             )}
             {activeTab === "paper" && (
-              <Sidebar activePaper={activePaper} setActivePaper={setActivePaper} customPaper={customPaper} setCustomPaper={setCustomPaper} activeCraft={activeCraft} setActiveCraft={setActiveCraft} selectedImages={selectedImages} disabled={isProcessing} onExecuteRename={handleRename} />
+              <Sidebar activePaper={activePaper} setActivePaper={setActivePaper} customPaper={customPaper} setCustomPaper={setCustomPaper} activeCraft={activeCraft} setActiveCraft={setActiveCraft} selectedImages={selectedImages} renameItems={renameItems} disabled={isProcessing} onExecuteRename={handleRename} />
             )}
             {activeTab === "replicate" && (
               <ReplicateSetting selectedCount={supportedImages.length} disabled={isProcessing} onExecute={handleReplicate} onSyncToCost={handleSyncReplicateToCost} replicateLocked={replicateLocked} />
@@ -574,6 +710,8 @@ export default function App() {
           </div>
         )}
       </div>
+
+      {showEngineSetting && <EngineSetting onClose={() => setShowEngineSetting(false)} />}
     </div>
   );
-}
\ No newline at end of file
+}
@@ -1,8 +1,13 @@
 import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { openPath } from "@tauri-apps/plugin-opener";
 import ReactCrop, { Crop, centerCrop, makeAspectCrop, PercentCrop } from "react-image-crop";
 import "react-image-crop/dist/ReactCrop.css";
-import { ImageItem } from "../types";
+import { ImageItem, ImageInspection, PreflightReport, ProcessPlan, ProcessPreview } from "../types";
+import OutputSetting from "./OutputSetting";
+import TileViewer from "./TileViewer";
+import { errorMessage } from "../errors";
+import { t } from "../i18n";
 
 export interface ProcessPayload {
   image: ImageItem;
@@ -48,6 +53,17 @@ interface ImageConfig {
   isCropFlipped?: boolean;
 }
 
+export const COLOR_POLICIES = [
+  { value: "keep", label: "保留原 ICC" },
+  { value: "convert_srgb", label: "转换为 sRGB" },
+  { value: "convert_adobe_rgb", label: "转换为 Adobe RGB" },
+  { value: "assign_srgb", label: "无 ICC 时指定 sRGB" },
+  { value: "assign_adobe_rgb", label: "无 ICC 时指定 Adobe RGB" },
+];
+
+// 常用输出分辨率：150 大幅灯箱、240 画布、300 通用、360/720 Epson 原生
+export const OUTPUT_DPI_OPTIONS = [150, 240, 300, 360, 720];
+
 const parseSize = (sizeStr?: string): [number, number] => {
   if (!sizeStr) return [20, 20];
   const match = sizeStr.match(/([\d.]+)\s*x\s*([\d.]+)/);
@@ -60,6 +76,10 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
   const configsRef = useRef<Record<string, ImageConfig>>({});
 
   const [currentDpi, setCurrentDpi] = useState<number | null>(null);
+  const [currentIcc, setCurrentIcc] = useState<string | null>(null);
+  const [inspection, setInspection] = useState<ImageInspection | null>(null);
+  const [colorPolicy, setColorPolicy] = useState<string>(() => localStorage.getItem("app_color_policy") || "keep");
+  const [outputDpi, setOutputDpi] = useState<number>(() => Number(localStorage.getItem("app_output_dpi")) || 300);
   
   const loadedImagePathRef = useRef<string | null>(null);
   const [currentIndex, setCurrentIndex] = useState(0);
@@ -97,6 +117,13 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
   const [crop, setCrop] = useState<Crop>({ unit: "%", x: 0, y: 0, width: 100, height: 100 });
   const [previewUrl, setPreviewUrl] = useState<string>("");
   const [imgRef, setImgRef] = useState<HTMLImageElement | null>(null);
+  const [plan, setPlan] = useState<ProcessPlan | null>(null);
+  const [resultPreview, setResultPreview] = useState<ProcessPreview | null>(null);
+  const [isPreviewing, setIsPreviewing] = useState(false);
+  const [isTileViewerOpen, setIsTileViewerOpen] = useState(false);
+  const [preflightReport, setPreflightReport] = useState<PreflightReport | null>(null);
+  const [isPreflighting, setIsPreflighting] = useState(false);
+  const [isExportingPreflight, setIsExportingPreflight] = useState(false);
 
   useEffect(() => {
     const handleConfigImported = () => {
@@ -255,16 +282,22 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
     }
     // 当预览改变或当前图片改变时，获取结构化元数据（包含 DPI）
     if (currentImage) {
-      invoke("get_image_meta", { pathStr: currentImage.path })
+      invoke("get_image_meta", { pathStr: currentImage.path, outputDpi })
         .then((m: any) => {
           if (m && typeof m.dpi === 'number') setCurrentDpi(m.dpi);
           else setCurrentDpi(null);
+          setCurrentIcc(m?.icc_description ?? null);
         })
-        .catch(() => setCurrentDpi(null));
+        .catch(() => { setCurrentDpi(null); setCurrentIcc(null); });
+      invoke<ImageInspection>("inspect_image", { pathStr: currentImage.path, outputDpi })
+        .then(setInspection)
+        .catch(() => setInspection(null));
     } else {
       setCurrentDpi(null);
+      setCurrentIcc(null);
+      setInspection(null);
     }
-  }, [currentImage, previewUrl]);
+  }, [currentImage, previewUrl, outputDpi]);
 
   // 🌟 终极暴力 Hack：缩短为 10ms，低于显示器单帧刷新时间，彻底消灭视觉闪烁！
   // 🌟 工业级渲染同步方案：完美应对 200MB 级别的大图！
@@ -584,6 +617,161 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
     if (activePreset === label) handlePresetClick("图像尺寸"); 
   };
 
+  // 单张图片的排版参数（执行与计划预览共用）
+  const buildPayload = (img: ImageItem, conf: ImageConfig): ProcessPayload => {
+    let outW = 20, outH = 20, px = 0, py = 0, pw = 100, ph = 100;
+    const finalMode =
+      mode === "border" || mode === "mirror"
+        ? mode
+        : mode === "crop" || mode === "pad"
+          ? mode
+          : conf.mode;
+    if (finalMode === "resize") {
+       outW = Number(conf.resizeW) || 1; outH = Number(conf.resizeH) || 1;
+     } else if (finalMode === "border" || finalMode === "mirror") {
+       const [curW, curH] = parseSize(img.size);
+       outW = curW;
+       outH = curH;
+    } else {
+       if (conf.preset === "图像尺寸") { outW = Number(conf.customW) || 1; outH = Number(conf.customH) || 1; } 
+       else { const p = presets.find(x => x.label === conf.preset); outW = p ? p.w : 20; outH = p ? p.h : 20; }
+       px = conf.crop.x; py = conf.crop.y; pw = conf.crop.width; ph = conf.crop.height;
+       
+       if (finalMode === "crop") {
+           const [origW, origH] = parseSize(img.size);
+           const isImgLandscape = origW >= origH;
+           const isAspectLandscape = outW >= outH;
+           if (isImgLandscape !== isAspectLandscape) {
+               const temp = outW; outW = outH; outH = temp;
+           }
+           if (conf.isCropFlipped) {
+               const temp = outW; outW = outH; outH = temp;
+           }
+       }
+    }
+
+    if (finalMode === "pad") {
+      let [fileRawW, fileRawH] = parseSize(img.size);
+      if ((fileRawW > fileRawH) !== (outW > outH)) { const temp = outW; outW = outH; outH = temp; }
+    }
+    return {
+      image: img,
+      mode: finalMode,
+      targetW: outW,
+      targetH: outH,
+      borderTopCm: (finalMode === "border" || finalMode === "mirror") ? (borderTopCm === '' ? Number(conf.borderTopCm ?? 0) : Number(borderTopCm)) : 0,
+      borderRightCm: (finalMode === "border" || finalMode === "mirror") ? (borderRightCm === '' ? Number(conf.borderRightCm ?? 0) : Number(borderRightCm)) : 0,
+      borderBottomCm: (finalMode === "border" || finalMode === "mirror") ? (borderBottomCm === '' ? Number(conf.borderBottomCm ?? 0) : Number(borderBottomCm)) : 0,
+      borderLeftCm: (finalMode === "border" || finalMode === "mirror") ? (borderLeftCm === '' ? Number(conf.borderLeftCm ?? 0) : Number(borderLeftCm)) : 0,
+      cropData: { x: px, y: py, w: pw, h: ph }
+    };
+  };
+
+  // 当前图片按界面上的参数组装（计划与成品预览共用）
+  const buildCurrentPayload = (): ProcessPayload | null => {
+    const conf = currentImage ? configsRef.current[currentImage.path] : undefined;
+    if (!currentImage || !currentImage.isSupported || !conf) return null;
+    if (mode === "resize") {
+      if (!(Number(resizeW) > 0 && Number(resizeH) > 0)) return null;
+      return { image: currentImage, mode: "resize", targetW: Number(resizeW), targetH: Number(resizeH), cropData: { x: 0, y: 0, w: 100, h: 100 } };
+    }
+    return buildPayload(currentImage, mode === "crop" ? { ...conf, crop } : conf);
+  };
+
+  // 与 process_batch 的单个任务同一结构
+  const jobArgs = (payload: ProcessPayload) => ({
+    path: payload.image.path, mode: payload.mode, targetWCm: payload.targetW, targetHCm: payload.targetH,
+    cropX: payload.cropData.x, cropY: payload.cropData.y, cropW: payload.cropData.w, cropH: payload.cropData.h,
+    borderTopCm: payload.borderTopCm ?? 0,
+    borderRightCm: payload.borderRightCm ?? 0,
+    borderBottomCm: payload.borderBottomCm ?? 0,
+    borderLeftCm: payload.borderLeftCm ?? 0
+  });
+
+  // 当前图片的排版计划：与执行时同一套几何，参数停顿片刻后再算
+  useEffect(() => {
+    const payload = buildCurrentPayload();
+    if (!payload) { setPlan(null); return; }
+
+    let stale = false;
+    const timer = setTimeout(() => {
+      invoke<ProcessPlan>("plan_process", { job: jobArgs(payload), outputDpi })
+        .then(p => { if (!stale) setPlan(p); })
+        .catch(() => { if (!stale) setPlan(null); });
+    }, 150);
+    return () => { stale = true; clearTimeout(timer); };
+  }, [currentImage, mode, crop, activePreset, customW, customH, isCropFlipped, resizeW, resizeH, borderTopCm, borderRightCm, borderBottomCm, borderLeftCm, outputDpi, presets]);
+
+  // 成品预览：缩小的原图套用同一份计划，不写文件
+  const handlePreviewResult = async () => {
+    const payload = buildCurrentPayload();
+    if (!payload || isPreviewing) return;
+    setIsPreviewing(true);
+    try {
+      setResultPreview(await invoke<ProcessPreview>("preview_process", { job: jobArgs(payload), outputDpi, maxSide: 1200 }));
+    } catch (e) {
+      alert(`生成预览失败: ${errorMessage(e)}`);
+    } finally {
+      setIsPreviewing(false);
+    }
+  };
+
+  // 印前检查：所有选中图片按各自的参数与当前纸张逐项体检，不写文件
+  const handlePreflight = async () => {
+    if (disabled || isPreflighting) return;
+    const paper = (localStorage.getItem("app_custom_paper_input") || "").trim() || localStorage.getItem("app_active_paper") || undefined;
+    const jobs = selectedImages.filter(img => img.isSupported).map((img) => {
+      let conf = configsRef.current[img.path];
+      if (!conf) {
+        const [origW, origH] = parseSize(img.size);
+        const fallback = getFallbackConfig(origW, origH);
+        const aspect = getAspectFromParams(origW, origH, fallback.preset, fallback.customW, fallback.customH, fallback.mode, fallback.isCropFlipped || false);
+        conf = { ...fallback, crop: generateDefaultCrop(origW, origH, aspect) };
+      }
+      const payload: ProcessPayload = mode === "resize"
+        ? { image: img, mode: "resize", targetW: Number(conf.resizeW) || Number(resizeW) || 1, targetH: Number(conf.resizeH) || Number(resizeH) || 1, cropData: { x: 0, y: 0, w: 100, h: 100 } }
+        : buildPayload(img, img.path === currentImage?.path && mode === "crop" ? { ...conf, crop } : conf);
+      return { ...jobArgs(payload), paper };
+    });
+    if (jobs.length === 0) return;
+
+    const now = new Date();
+    const title = `${t("preflight.title")} ${now.getFullYear()}${String(now.getMonth() + 1).padStart(2, "0")}${String(now.getDate()).padStart(2, "0")} ${String(now.getHours()).padStart(2, "0")}${String(now.getMinutes()).padStart(2, "0")}`;
+    setIsPreflighting(true);
+    try {
+      setPreflightReport(await invoke<PreflightReport>("preflight", { jobs, outputDpi, title }));
+    } catch (e) {
+      alert(`印前检查失败: ${errorMessage(e)}`);
+    } finally {
+      setIsPreflighting(false);
+    }
+  };
+
+  // 报告与报价单走同一条路：先存 HTML 到桌面，再用浏览器打印为 PDF
+  const handleExportPreflight = async () => {
+    if (!preflightReport || isExportingPreflight) return;
+    setIsExportingPreflight(true);
+    try {
+      const stamp = new Date().toISOString().replace(/[-:T]/g, "").slice(0, 12);
+      const htmlFilename = `${t("preflight.file_name")}_${stamp}.html`;
+      const savedHtmlPath = await invoke<string>("export_file", { content: preflightReport.html, filename: htmlFilename });
+      const pdfFilename = htmlFilename.replace(/\.html$/i, ".pdf");
+      const sep = savedHtmlPath.includes("\\") ? "\\" : "/";
+      const folderPath = savedHtmlPath.substring(0, savedHtmlPath.lastIndexOf(sep));
+      try {
+        await invoke<string>("html_to_pdf", { htmlPath: savedHtmlPath, pdfPath: folderPath + sep + pdfFilename });
+        alert(`已保存到桌面：${pdfFilename}`);
+      } catch {
+        alert(`已保存 HTML：${htmlFilename}，可用浏览器打开后打印为 PDF`);
+      }
+      if (folderPath) await openPath(folderPath).catch(() => {});
+    } catch (e) {
+      alert(`导出失败: ${errorMessage(e)}`);
+    } finally {
+      setIsExportingPreflight(false);
+    }
+  };
+
   const handleExecuteAll = () => {
     if (disabled) return;
     try {
@@ -654,52 +842,7 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
             newConfigsToSave[img.path] = conf; 
           }
 
-          let outW = 20, outH = 20, px = 0, py = 0, pw = 100, ph = 100;
-          const finalMode =
-            mode === "border" || mode === "mirror"
-              ? mode
-              : mode === "crop" || mode === "pad"
-                ? mode
-                : conf.mode;
-          if (finalMode === "resize") {
-             outW = Number(conf.resizeW) || 1; outH = Number(conf.resizeH) || 1;
-           } else if (finalMode === "border" || finalMode === "mirror") {
-             const [curW, curH] = parseSize(img.size);
-             outW = curW;
-             outH = curH;
-          } else {
-             if (conf.preset === "图像尺寸") { outW = Number(conf.customW) || 1; outH = Number(conf.customH) || 1; } 
-             else { const p = presets.find(x => x.label === conf.preset); outW = p ? p.w : 20; outH = p ? p.h : 20; }
-             px = conf.crop.x; py = conf.crop.y; pw = conf.crop.width; ph = conf.crop.height;
-             
-             if (finalMode === "crop") {
-                 const [origW, origH] = parseSize(img.size);
-                 const isImgLandscape = origW >= origH;
-                 const isAspectLandscape = outW >= outH;
-                 if (isImgLandscape !== isAspectLandscape) {
-                     const temp = outW; outW = outH; outH = temp;
-                 }
-                 if (conf.isCropFlipped) {
-                     const temp = outW; outW = outH; outH = temp;
-                 }
-             }
-          }
-
-          if (finalMode === "pad") {
-            let [fileRawW, fileRawH] = parseSize(img.size);
-            if ((fileRawW > fileRawH) !== (outW > outH)) { const temp = outW; outW = outH; outH = temp; }
-          }
-          payloads.push({
-            image: img,
-            mode: finalMode,
-            targetW: outW,
-            targetH: outH,
-            borderTopCm: (finalMode === "border" || finalMode === "mirror") ? (borderTopCm === '' ? Number(conf.borderTopCm ?? 0) : Number(borderTopCm)) : 0,
-            borderRightCm: (finalMode === "border" || finalMode === "mirror") ? (borderRightCm === '' ? Number(conf.borderRightCm ?? 0) : Number(borderRightCm)) : 0,
-            borderBottomCm: (finalMode === "border" || finalMode === "mirror") ? (borderBottomCm === '' ? Number(conf.borderBottomCm ?? 0) : Number(borderBottomCm)) : 0,
-            borderLeftCm: (finalMode === "border" || finalMode === "mirror") ? (borderLeftCm === '' ? Number(conf.borderLeftCm ?? 0) : Number(borderLeftCm)) : 0,
-            cropData: { x: px, y: py, w: pw, h: ph }
-          });
+          payloads.push(buildPayload(img, conf));
         });
 
         onProcessAll(payloads);
@@ -737,6 +880,11 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
         <div className="absolute top-2 left-2 z-10 bg-black/60 text-white text-[10px] px-2 py-0.5 rounded backdrop-blur-sm font-bold shadow-sm">
           正在查阅: {currentIndex + 1} / {selectedImages.length}
         </div>
+        {currentImage?.isSupported && (
+          <button onClick={() => setIsTileViewerOpen(true)} title="原图平移缩放，可放大到 100% 核对细节" className="absolute top-2 right-2 z-10 bg-black/60 hover:bg-black/80 text-white text-[10px] px-2 py-0.5 rounded backdrop-blur-sm font-bold shadow-sm">
+            放大查看
+          </button>
+        )}
         
         {mode === "crop" ? (
           <ReactCrop 
@@ -782,6 +930,31 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
         </div>
       )}
 
+      {/* 排版计划：原图 → 裁切 → 成品像素与物理尺寸 */}
+      {plan && (
+        <div className="mb-2 px-2 py-1 w-full text-[10px] text-gray-500 bg-gray-50 rounded border border-gray-100 leading-relaxed">
+          原图 {plan.sourcePx.width}×{plan.sourcePx.height}px
+          {plan.crop && <> → 裁切 {plan.crop.width}×{plan.crop.height}</>}
+          {plan.resample && <> → 缩放 {plan.resample.width}×{plan.resample.height}</>}
+          {" → "}<strong className="text-gray-700">{plan.finalPx.width}×{plan.finalPx.height}px</strong>
+          （{plan.finalWidthCm.toFixed(2)}×{plan.finalHeightCm.toFixed(2)} cm @{Math.round(plan.outputDpi)}dpi）
+          <span className={plan.effectiveDpi < 100 ? "text-red-500 font-bold" : ""}> · 有效 {Math.round(plan.effectiveDpi)} DPI</span>
+        </div>
+      )}
+
+      {/* 图像体检：格式、色彩模型、位深、透明、压缩、图层/页数、相机 */}
+      {inspection && inspection.path === currentImage?.path && (
+        <div className="mb-2 px-2 w-full text-[10px] text-gray-400 truncate" title={inspection.captureDate ? `拍摄于 ${inspection.captureDate}` : undefined}>
+          {inspection.format} · <span className={inspection.colorModel === "CMYK" ? "text-orange-500 font-bold" : ""}>{inspection.colorModel}</span> {inspection.bitDepth} 位
+          {inspection.hasAlpha && " · 含透明"}
+          {inspection.compression && ` · ${inspection.compression}`}
+          {inspection.layerCount !== null ? ` · ${inspection.layerCount} 个图层` : inspection.pageCount > 1 ? ` · ${inspection.pageCount} 页` : ""}
+          {` · ${(inspection.fileSize / 1048576).toFixed(1)} MB`}
+          {(inspection.cameraMake || inspection.cameraModel) && ` · ${[inspection.cameraMake, inspection.cameraModel].filter(Boolean).join(" ")}`}
+          {!inspection.dpiFromFile && ` · 未记录 DPI，按 ${Math.round(inspection.dpi)} 计`}
+        </div>
+      )}
+
       <div className="flex-1 overflow-y-auto pr-1 space-y-3 custom-scrollbar">
         <div className={`border-2 rounded-xl overflow-hidden transition-all duration-300 ${isModuleAActive ? 'border-blue-400 shadow-sm bg-white' : 'border-gray-200 bg-gray-50/50 hover:border-blue-200 cursor-pointer'}`}>
           <div className={`p-2 flex items-center gap-2 ${isModuleAActive ? 'bg-blue-50 border-b border-blue-100' : ''}`} onClick={() => !disabled && !isModuleAActive && handleSetMode('crop')}>
@@ -1044,10 +1217,46 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
         </div>
       </div>
 
+      <div className="mt-2 pt-2 border-t border-gray-100 shrink-0 flex items-center gap-2">
+        <span className="text-[11px] font-bold text-gray-500 shrink-0">色彩</span>
+        <select
+          disabled={disabled}
+          value={colorPolicy}
+          onChange={e => { setColorPolicy(e.target.value); localStorage.setItem("app_color_policy", e.target.value); }}
+          className="flex-1 min-w-0 px-1 py-1 text-[11px] font-bold border rounded border-gray-200 outline-none focus:border-blue-500 disabled:opacity-40 disabled:cursor-not-allowed"
+        >
+          {COLOR_POLICIES.map(p => <option key={p.value} value={p.value}>{p.label}</option>)}
+        </select>
+        <span className="text-[10px] text-gray-400 truncate max-w-[45%]" title={currentIcc ?? "未嵌入 ICC"}>{currentIcc ?? "未嵌入 ICC"}</span>
+      </div>
+
+      <div className="mt-2 flex items-center gap-2 shrink-0">
+        <span className="text-[11px] font-bold text-gray-500 shrink-0">输出</span>
+        <select
+          disabled={disabled}
+          value={outputDpi}
+          onChange={e => { setOutputDpi(Number(e.target.value)); localStorage.setItem("app_output_dpi", e.target.value); }}
+          className="flex-1 min-w-0 px-1 py-1 text-[11px] font-bold border rounded border-gray-200 outline-none focus:border-blue-500 disabled:opacity-40 disabled:cursor-not-allowed"
+        >
+          {OUTPUT_DPI_OPTIONS.map(v => <option key={v} value={v}>{v} DPI</option>)}
+        </select>
+        <span className="text-[10px] text-gray-400 shrink-0">纸张配置可单独覆盖</span>
+      </div>
+
+      <div className="mt-2 shrink-0">
+        <OutputSetting disabled={disabled} />
+      </div>
+
       <div className="mt-2 pt-2 border-t border-gray-100 shrink-0 flex items-center justify-between gap-2">
         <button onClick={() => setCurrentIndex(prev => Math.max(0, prev - 1))} disabled={disabled || currentIndex === 0} className="w-10 h-10 flex items-center justify-center bg-gray-100 hover:bg-gray-200 disabled:opacity-30 disabled:hover:bg-gray-100 text-gray-600 rounded-lg transition-colors">
           <svg className="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24"><path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2.5} d="M15 19l-7-7 7-7" /></svg>
         </button>
+        <button disabled={disabled || isPreviewing || !plan} onClick={handlePreviewResult} title="按当前参数预览成品（不写入文件）" className="h-10 px-3 bg-gray-100 hover:bg-gray-200 text-gray-700 rounded-lg text-[12px] font-bold transition-colors disabled:opacity-40 disabled:cursor-not-allowed">
+          {isPreviewing ? '生成中…' : '预览'}
+        </button>
+        <button disabled={disabled || isPreflighting || selectedImages.length === 0} onClick={handlePreflight} title="上纸前逐张检查分辨率、色彩、透明、比例等问题" className="h-10 px-3 bg-gray-100 hover:bg-gray-200 text-gray-700 rounded-lg text-[12px] font-bold transition-colors disabled:opacity-40 disabled:cursor-not-allowed">
+          {isPreflighting ? '检查中…' : '检查'}
+        </button>
         <button disabled={disabled} onClick={handleExecuteAll} className={`flex-1 h-10 text-white rounded-lg text-[13px] font-bold shadow-md active:scale-95 transition-all disabled:opacity-40 disabled:cursor-not-allowed ${mode === 'resize' ? 'bg-purple-600 hover:bg-purple-700' : mode === 'border' ? 'bg-emerald-600 hover:bg-emerald-700' : mode === 'mirror' ? 'bg-orange-600 hover:bg-orange-700' : 'bg-[#0B1527] hover:bg-black'}`}>
           {mode === 'resize' ? (selectedImages.length > 1 ? `批量缩放 (${selectedImages.length}张)` : `执行当前图像 (${currentIndex + 1} / ${selectedImages.length})`) : mode === 'border' ? `加白边 (${selectedImages.length}张)` : mode === 'mirror' ? `镜像包边 (${selectedImages.length}张)` : `裁切 (${selectedImages.length}张)`}
         </button>
@@ -1055,6 +1264,54 @@ export default function CropSetting({ selectedImages, panelWidth = 288, disabled
           <svg className="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24"><path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2.5} d="M9 5l7 7-7 7" /></svg>
         </button>
       </div>
+
+      {isTileViewerOpen && currentImage && (
+        <TileViewer
+          path={currentImage.path}
+          crop={mode === "crop" && crop.unit === "%" ? { x: crop.x, y: crop.y, width: crop.width, height: crop.height } : undefined}
+          onClose={() => setIsTileViewerOpen(false)}
+        />
+      )}
+
+      {preflightReport && (
+        <div className="fixed inset-0 z-50 bg-black/60 flex items-center justify-center p-6" onClick={() => setPreflightReport(null)}>
+          <div className="bg-white rounded-lg shadow-2xl w-full max-w-2xl max-h-[85vh] flex flex-col" onClick={e => e.stopPropagation()}>
+            <div className="flex items-center gap-2 px-4 py-3 border-b border-gray-100 shrink-0">
+              <span className="flex-1 text-sm font-bold text-gray-800">
+                印前检查：共 {preflightReport.items.length} 张，
+                <span className={preflightReport.errors > 0 ? "text-red-600" : ""}>{preflightReport.errors} 张有错误</span>，
+                <span className={preflightReport.warnings > 0 ? "text-orange-600" : ""}>{preflightReport.warnings} 张有警告</span>
+              </span>
+              <button disabled={isExportingPreflight} onClick={handleExportPreflight} className="px-2 py-1 text-xs font-bold rounded bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-40">{isExportingPreflight ? '导出中…' : '导出报告'}</button>
+              <button onClick={() => setPreflightReport(null)} className="px-2 py-1 text-xs font-bold rounded bg-gray-100 text-gray-600 hover:bg-gray-200">关闭</button>
+            </div>
+            <div className="overflow-y-auto px-4 py-2 text-[11px]">
+              {preflightReport.items.map(item => (
+                <div key={item.path} className="py-1.5 border-b border-gray-50 last:border-0">
+                  <div className="font-bold text-gray-700 truncate">
+                    {item.path.split(/[\\/]/).pop()}
+                    {item.plan && <span className="ml-2 font-normal text-gray-400">有效 {Math.round(item.plan.effectiveDpi)} PPI</span>}
+                  </div>
+                  {item.issues.length === 0
+                    ? <div className="text-green-600 font-bold">通过</div>
+                    : item.issues.map(issue => (
+                        <div key={issue.code} className={issue.severity === "error" ? "text-red-600 font-bold" : issue.severity === "warning" ? "text-orange-600" : "text-gray-500"}>{issue.message}</div>
+                      ))}
+                </div>
+              ))}
+            </div>
+          </div>
+        </div>
+      )}
+
+      {resultPreview && (
+        <div className="fixed inset-0 z-50 bg-black/70 flex flex-col items-center justify-center p-6" onClick={() => setResultPreview(null)}>
+          <img src={resultPreview.url} alt="成品预览" className="max-w-full max-h-[85vh] shadow-2xl bg-white" />
+          <div className="mt-3 text-xs text-white/90 font-bold">
+            成品 {resultPreview.plan.finalPx.width}×{resultPreview.plan.finalPx.height}px（{resultPreview.plan.finalWidthCm.toFixed(2)}×{resultPreview.plan.finalHeightCm.toFixed(2)} cm @{Math.round(resultPreview.plan.outputDpi)}dpi）· 点击任意处关闭
+          </div>
+        </div>
+      )}
     </div>
   );
 }
\ No newline at end of file
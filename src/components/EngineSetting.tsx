@@ -0,0 +1,175 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { EngineInfo, EngineLimits, EngineStatus, EngineTimeouts } from "../types";
+import { errorMessage } from "../errors";
+
+const SOURCE_LABELS: Record<string, string> = {
+  sidecar: "随包附带",
+  setting: "手动指定",
+  path: "系统 PATH"
+};
+
+interface EngineSettingProps {
+  onClose: () => void;
+}
+
+const TIMEOUT_FIELDS: { key: keyof EngineTimeouts; label: string }[] = [
+  { key: "probe", label: "读取信息" },
+  { key: "preview", label: "预览 / 缩略图" },
+  { key: "process", label: "正式输出" },
+  { key: "print", label: "打印 PDF" }
+];
+
+const LIMIT_FIELDS: { key: keyof EngineLimits; label: string }[] = [
+  { key: "memoryMib", label: "内存 (MiB)" },
+  { key: "mapMib", label: "内存映射 (MiB)" },
+  { key: "diskGib", label: "磁盘缓存 (GiB)" }
+];
+
+const DEFAULT_TIMEOUTS: EngineTimeouts = { probe: 30, preview: 120, process: 900, print: 120 };
+const DEFAULT_LIMITS: EngineLimits = { memoryMib: null, mapMib: null, diskGib: null };
+
+// 诊断页只列出与打印相关的资源上限
+const RESOURCE_NAMES = ["Memory", "Map", "Disk", "Area", "Thread", "Time"];
+
+// 引擎设置与诊断：自动找不到 magick / 浏览器时（如 Linux 装在非常规目录）手动指定路径，
+// 并显示 magick 的版本、缺失的格式与资源上限
+export default function EngineSetting({ onClose }: EngineSettingProps) {
+  const [info, setInfo] = useState<EngineInfo | null>(null);
+  const [magickPath, setMagickPath] = useState("");
+  const [browserPath, setBrowserPath] = useState("");
+  const [timeouts, setTimeouts] = useState<EngineTimeouts>(DEFAULT_TIMEOUTS);
+  const [limits, setLimits] = useState<EngineLimits>(DEFAULT_LIMITS);
+  const [isSaving, setIsSaving] = useState(false);
+  const [status, setStatus] = useState<EngineStatus | null>(null);
+  const [isProbing, setIsProbing] = useState(false);
+
+  const loadStatus = async (refresh: boolean) => {
+    setIsProbing(true);
+    try {
+      setStatus(await invoke<EngineStatus>("engine_status", { refresh }));
+    } catch (e) {
+      alert(errorMessage(e));
+    } finally {
+      setIsProbing(false);
+    }
+  };
+
+  const apply = (next: EngineInfo) => {
+    setInfo(next);
+    setMagickPath(next.settings.magickPath ?? "");
+    setBrowserPath(next.settings.browserPath ?? "");
+    setTimeouts(next.settings.timeouts ?? DEFAULT_TIMEOUTS);
+    setLimits(next.settings.limits ?? DEFAULT_LIMITS);
+  };
+
+  useEffect(() => {
+    invoke<EngineInfo>("get_engine_settings").then(apply).catch(e => alert(errorMessage(e)));
+    loadStatus(false);
+  }, []);
+
+  const handleSave = async () => {
+    setIsSaving(true);
+    try {
+      apply(await invoke<EngineInfo>("set_engine_settings", {
+        settings: { magickPath: magickPath.trim() || null, browserPath: browserPath.trim() || null, timeouts, limits }
+      }));
+      await loadStatus(true);
+    } catch (e) {
+      alert(errorMessage(e));
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  return (
+    <div className="fixed inset-0 z-50 bg-black/60 flex items-center justify-center p-6" onClick={onClose}>
+      <div className="bg-white rounded-lg shadow-2xl w-full max-w-lg max-h-[85vh] flex flex-col" onClick={e => e.stopPropagation()}>
+        <div className="flex items-center gap-2 px-4 py-3 border-b border-gray-100">
+          <span className="flex-1 text-sm font-bold text-gray-800">引擎设置与诊断</span>
+          <button disabled={isProbing} onClick={() => loadStatus(true)} className="px-2 py-1 text-xs font-bold rounded bg-gray-100 text-gray-600 hover:bg-gray-200 disabled:opacity-40">{isProbing ? "检测中…" : "重新检测"}</button>
+          <button disabled={isSaving} onClick={handleSave} className="px-2 py-1 text-xs font-bold rounded bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-40">{isSaving ? "保存中…" : "保存"}</button>
+          <button onClick={onClose} className="px-2 py-1 text-xs font-bold rounded bg-gray-100 text-gray-600 hover:bg-gray-200">关闭</button>
+        </div>
+        <div className="overflow-y-auto px-4 py-3 space-y-3 text-[11px]">
+          <div className="space-y-1">
+            <div className="font-bold text-gray-600">ImageMagick（magick）</div>
+            <div className={info?.magick ? "text-gray-500 break-all" : "text-red-500 font-bold"}>
+              {info?.magick ? `当前：${info.magick.path}（${SOURCE_LABELS[info.magick.source]}）` : "⚠️ 未找到 magick"}
+            </div>
+            <input
+              type="text" value={magickPath} placeholder="留空自动查找，例如 /usr/local/bin/magick"
+              onChange={e => setMagickPath(e.target.value)}
+              className="w-full px-2 py-1 border border-gray-200 rounded outline-none focus:border-blue-500"
+            />
+            <div className="text-gray-400">查找顺序：随包附带 → 手动指定 → 系统 PATH</div>
+          </div>
+          <div className="space-y-1">
+            <div className="font-bold text-gray-600">打印 PDF 的浏览器</div>
+            <div className={info?.browser ? "text-gray-500 break-all" : "text-orange-600 font-bold"}>
+              {info?.browser ? `当前：${info.browser}` : "⚠️ 未找到 Chrome / Chromium / Edge，报表只能导出 HTML"}
+            </div>
+            <input
+              type="text" value={browserPath} placeholder="留空自动查找，例如 /usr/bin/chromium"
+              onChange={e => setBrowserPath(e.target.value)}
+              className="w-full px-2 py-1 border border-gray-200 rounded outline-none focus:border-blue-500"
+            />
+          </div>
+          <div className="space-y-1">
+            <div className="font-bold text-gray-600">超时（秒，0 为不限时）</div>
+            <div className="grid grid-cols-4 gap-2">
+              {TIMEOUT_FIELDS.map(({ key, label }) => (
+                <label key={key} className="space-y-0.5 text-gray-500">
+                  <div>{label}</div>
+                  <input
+                    type="number" min={0} value={timeouts[key]}
+                    onChange={e => setTimeouts({ ...timeouts, [key]: Math.max(0, Math.round(Number(e.target.value) || 0)) })}
+                    className="w-full px-2 py-1 border border-gray-200 rounded outline-none focus:border-blue-500"
+                  />
+                </label>
+              ))}
+            </div>
+            <div className="text-gray-400">超时或停止批次时会结束 magick / 浏览器进程</div>
+          </div>
+          <div className="space-y-1">
+            <div className="font-bold text-gray-600">magick 资源上限</div>
+            <div className="grid grid-cols-3 gap-2">
+              {LIMIT_FIELDS.map(({ key, label }) => (
+                <label key={key} className="space-y-0.5 text-gray-500">
+                  <div>{label}</div>
+                  <input
+                    type="number" min={1} value={limits[key] ?? ""} placeholder="自动"
+                    onChange={e => setLimits({ ...limits, [key]: Number(e.target.value) > 0 ? Math.round(Number(e.target.value)) : null })}
+                    className="w-full px-2 py-1 border border-gray-200 rounded outline-none focus:border-blue-500"
+                  />
+                </label>
+              ))}
+            </div>
+            <div className="text-gray-400">留空自动：内存取本机的 1/4，内存映射取 1/2，磁盘缓存 64 GiB</div>
+          </div>
+          {status && (
+            <div className="space-y-1 border-t border-gray-100 pt-3">
+              <div className="font-bold text-gray-600">诊断</div>
+              {status.error
+                ? <div className="text-red-500 font-bold break-all">⚠️ {errorMessage(status.error)}</div>
+                : <div className="text-gray-500">版本：{status.version ?? "未知"}</div>}
+              {status.delegates.length > 0 && (
+                <div className="text-gray-500 break-all">Delegates：{status.delegates.join(" ")}</div>
+              )}
+              {status.missingFormats.length > 0 && (
+                <div className="text-orange-600">magick 不能读取：{status.missingFormats.join("、")}{status.missingFormats.some(f => ["JPEG", "PNG", "TIFF", "PSD"].includes(f)) && "（这些格式的常见文件仍由内置解码处理）"}</div>
+              )}
+              <div className="text-gray-500">可加入的文件：{status.supportedExtensions.map(ext => `.${ext}`).join(" ")}</div>
+              <div className={status.ghostscript ? "text-gray-500 break-all" : "text-gray-400"}>Ghostscript（PDF/EPS）：{status.ghostscript ?? "未安装"}</div>
+              {status.resources.length > 0 && (
+                <div className="text-gray-500">
+                  资源上限：{status.resources.filter(r => RESOURCE_NAMES.includes(r.name)).map(r => `${r.name} ${r.value}`).join(" · ")}
+                </div>
+              )}
+            </div>
+          )}
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -95,7 +95,7 @@ export default function ImageGrid({
                 </div>
               </div>
               <div className={`text-xs text-center truncate px-1 font-medium ${img.isSupported ? 'text-gray-600' : 'text-red-500 font-bold'}`}>{img.name}</div>
-              <div className={`text-[11px] text-center truncate px-1 font-mono tracking-tight ${img.isSupported ? 'text-blue-500/80' : 'text-red-500 font-bold'}`}>{img.size}</div>
+              <div title={img.issue} className={`text-[11px] text-center truncate px-1 font-mono tracking-tight ${img.isSupported ? 'text-blue-500/80' : 'text-red-500 font-bold'}`}>{img.size}</div>
             </div>
           ))}
         </div>
@@ -2,6 +2,8 @@ import { useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { openPath } from "@tauri-apps/plugin-opener";
 import { ImageItem } from "../types";
+import { errorMessage } from "../errors";
+import { currentLocale, t } from "../i18n";
 
 interface ListImageViewProps {
   images: ImageItem[];
@@ -102,10 +104,13 @@ export default function ListImageView({
       const now = new Date();
       const dateStr = `${now.getFullYear()}${String(now.getMonth() + 1).padStart(2, "0")}${String(now.getDate()).padStart(2, "0")}`;
       const timeStr = `${String(now.getHours()).padStart(2, "0")}${String(now.getMinutes()).padStart(2, "0")}`;
-      const title = `报价单 ${dateStr} ${timeStr}`;
+      const title = `${t("quote.title")} ${dateStr} ${timeStr}`;
+      const headers = ["index", "thumb", "file", "qty", "size", "area", "total_area", "unit_price", "amount", "note"]
+        .map(col => `<th>${escapeHtml(t(`quote.col.${col}`))}</th>`)
+        .join("");
 
       const html = `<!DOCTYPE html>
-<html lang="zh-CN">
+<html lang="${currentLocale()}">
 <head>
 <meta charset="utf-8"/>
 <meta name="viewport" content="width=device-width,initial-scale=1"/>
@@ -125,17 +130,17 @@ export default function ListImageView({
 <h1>${escapeHtml(title)}</h1>
 <table>
 <thead><tr>
-  <th>序号</th><th>缩略图</th><th>文件名</th><th>数量</th><th>物理尺寸(cm)</th><th>单张面积(㎡)</th><th>总面积(㎡)</th><th>单价(元/㎡)</th><th>金额(元)</th><th>备注</th>
+  ${headers}
 </tr></thead>
 <tbody>
 ${rows}
-<tr class="total"><td colspan="6">合计</td><td>${grandTotalArea.toFixed(4)}</td><td></td><td>${grandTotalAmount.toFixed(2)}</td><td></td></tr>
+<tr class="total"><td colspan="6">${escapeHtml(t("quote.total"))}</td><td>${grandTotalArea.toFixed(4)}</td><td></td><td>${grandTotalAmount.toFixed(2)}</td><td></td></tr>
 </tbody>
 </table>
 </body>
 </html>`;
 
-      const htmlFilename = `报价单_${dateStr}_${timeStr}.html`;
+      const htmlFilename = `${t("quote.file_name")}_${dateStr}_${timeStr}.html`;
       const savedHtmlPath = await invoke<string>("export_file", { content: html, filename: htmlFilename });
       const pdfFilename = htmlFilename.replace(/\.html$/i, ".pdf");
       const sep = savedHtmlPath.includes("\\") ? "\\" : "/";
@@ -150,7 +155,7 @@ ${rows}
         setExportMsg({ type: "ok", text: `已保存 HTML：${htmlFilename}，可用浏览器打开后打印为 PDF` });
       }
     } catch (e) {
-      setExportMsg({ type: "err", text: `导出失败：${e}` });
+      setExportMsg({ type: "err", text: `导出失败：${errorMessage(e)}` });
     } finally {
       setIsExporting(false);
       setTimeout(() => setExportMsg(null), 4000);
@@ -287,7 +292,7 @@ ${rows}
                       className="w-full px-2 py-1 text-xs font-bold text-center border border-orange-300 rounded-md focus:outline-none focus:border-orange-500 focus:bg-orange-50 disabled:opacity-40 disabled:cursor-not-allowed"
                     />
                     
-                    <div className={`text-xs text-center font-mono ${img.isSupported ? "text-blue-600" : "text-red-500 font-bold"}`}>
+                    <div title={img.issue} className={`text-xs text-center font-mono ${img.isSupported ? "text-blue-600" : "text-red-500 font-bold"}`}>
                       {img.size}
                     </div>
                     
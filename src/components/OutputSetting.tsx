@@ -0,0 +1,90 @@
+import { useState } from "react";
+import { t } from "../i18n";
+
+const STORAGE_KEY_MODE = "app_output_mode";
+const STORAGE_KEY_SUFFIX = "app_output_suffix";
+const STORAGE_KEY_ROOT = "app_output_root";
+
+export type OutputMode = "overwrite" | "beside" | "mirror";
+
+export interface OutputOptions {
+  mode: OutputMode;
+  suffix: string;
+  sourceRoot: string;
+  outputRoot: string;
+}
+
+const readMode = (): OutputMode => {
+  const stored = localStorage.getItem(STORAGE_KEY_MODE);
+  return stored === "beside" || stored === "mirror" ? stored : "overwrite";
+};
+
+// 本批文件的公共父目录，镜像输出按它之下的相对路径重建目录树
+const commonRoot = (paths: string[]) => {
+  const dirs = paths.map(p => p.split(/[\\/]/).slice(0, -1));
+  if (dirs.length === 0) return "";
+  const first = dirs[0];
+  let len = first.length;
+  for (const dir of dirs.slice(1)) {
+    let i = 0;
+    while (i < len && i < dir.length && dir[i] === first[i]) i++;
+    len = i;
+  }
+  const sep = paths[0].includes("\\") ? "\\" : "/";
+  // 只剩盘符或 Unix 根时补上分隔符（"C:" → "C:\"，"" → "/"），不同盘符则为空
+  return len === 1 ? first[0] + sep : first.slice(0, len).join(sep);
+};
+
+export const readOutputOptions = (paths: string[]): OutputOptions => ({
+  mode: readMode(),
+  suffix: localStorage.getItem(STORAGE_KEY_SUFFIX) || t("file.output_suffix"),
+  sourceRoot: commonRoot(paths),
+  outputRoot: localStorage.getItem(STORAGE_KEY_ROOT) || ""
+});
+
+interface OutputSettingProps {
+  disabled?: boolean;
+}
+
+export default function OutputSetting({ disabled }: OutputSettingProps) {
+  const [mode, setMode] = useState<OutputMode>(readMode);
+  const [suffix, setSuffix] = useState(() => localStorage.getItem(STORAGE_KEY_SUFFIX) || "");
+  const [outputRoot, setOutputRoot] = useState(() => localStorage.getItem(STORAGE_KEY_ROOT) || "");
+
+  const handleMode = (value: OutputMode) => { setMode(value); localStorage.setItem(STORAGE_KEY_MODE, value); };
+
+  return (
+    <div className="w-full space-y-1.5 text-left">
+      <div className="flex items-center gap-2">
+        <span className="text-[11px] font-bold text-gray-500 shrink-0">保存</span>
+        <select
+          disabled={disabled}
+          value={mode}
+          onChange={e => handleMode(e.target.value as OutputMode)}
+          className={`flex-1 min-w-0 px-1 py-1 text-[11px] font-bold border rounded outline-none disabled:opacity-40 disabled:cursor-not-allowed ${mode === "overwrite" ? "border-red-300 text-red-600" : "border-gray-200 focus:border-blue-500"}`}
+        >
+          <option value="overwrite">覆盖原图</option>
+          <option value="beside">原图旁另存（加后缀）</option>
+          <option value="mirror">另存到输出目录</option>
+        </select>
+      </div>
+      {mode === "overwrite" && (
+        <div className="text-[10px] text-red-500">⚠️ 原图将被直接覆盖，客户源文件无法找回</div>
+      )}
+      {mode === "beside" && (
+        <input
+          disabled={disabled} type="text" value={suffix} placeholder={t("file.output_suffix")}
+          onChange={e => { setSuffix(e.target.value); localStorage.setItem(STORAGE_KEY_SUFFIX, e.target.value); }}
+          className="w-full px-2 py-1 text-[11px] border border-gray-200 rounded outline-none focus:border-blue-500 disabled:opacity-40"
+        />
+      )}
+      {mode === "mirror" && (
+        <input
+          disabled={disabled} type="text" value={outputRoot} placeholder="输出根目录，例如 D:\输出"
+          onChange={e => { setOutputRoot(e.target.value); localStorage.setItem(STORAGE_KEY_ROOT, e.target.value); }}
+          className={`w-full px-2 py-1 text-[11px] border rounded outline-none focus:border-blue-500 disabled:opacity-40 ${outputRoot.trim() === "" ? "border-red-300" : "border-gray-200"}`}
+        />
+      )}
+    </div>
+  );
+}
@@ -1,4 +1,8 @@
 import { useState, useEffect, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../errors";
+import { RenameItem } from "../types";
+import RenameTemplateSetting from "./RenameTemplateSetting";
 
 export const PAPER_CATEGORIES = [
   "210蚀刻", "315蚀刻", "水彩纸", "硫化钡", "博物馆蚀刻",
@@ -13,6 +17,21 @@ const STORAGE_KEY_CRAFTS = "app_custom_crafts";
 const STORAGE_KEY_PRESETS = "user_custom_presets";
 const STORAGE_PREFIXES = ["app_", "user_"];
 
+export interface PaperProfile {
+  name: string;
+  outputIcc: string | null;
+  intent: "perceptual" | "relative" | "saturation" | "absolute";
+  sharpen: number;
+  dpi: number | null;
+  mirror: boolean;
+  toneBoost: number;
+  maxWidthCm: number | null;
+}
+
+const emptyProfile = (name: string): PaperProfile => ({
+  name, outputIcc: null, intent: "relative", sharpen: 0, dpi: null, mirror: false, toneBoost: 0, maxWidthCm: null
+});
+
 const isStringArray = (value: unknown): value is string[] =>
   Array.isArray(value) && value.every((item) => typeof item === "string");
 
@@ -64,11 +83,12 @@ interface PaperSettingProps {
   setActiveCraft: (craft: string) => void;
   disabled?: boolean;
   selectedCount: number;
+  renameItems: RenameItem[];
   onExecute: () => void;
 }
 
 export default function PaperSetting({
-  activePaper, setActivePaper, customPaper, setCustomPaper, activeCraft, setActiveCraft, disabled, selectedCount, onExecute
+  activePaper, setActivePaper, customPaper, setCustomPaper, activeCraft, setActiveCraft, disabled, selectedCount, renameItems, onExecute
 }: PaperSettingProps) {
 
   const [papers, setPapers] = useState<string[]>(() => {
@@ -85,6 +105,24 @@ export default function PaperSetting({
   
   const fileInputRef = useRef<HTMLInputElement>(null);
 
+  // 纸张输出配置（后端登记，改名/排版时自动套用）
+  const [profiles, setProfiles] = useState<PaperProfile[]>([]);
+  useEffect(() => {
+    invoke<PaperProfile[]>("get_paper_profiles").then(setProfiles).catch(() => setProfiles([]));
+  }, []);
+  const activeProfile = profiles.find(p => p.name === activePaper) ?? emptyProfile(activePaper);
+
+  const updateProfile = (patch: Partial<PaperProfile>) => {
+    const next = { ...activeProfile, ...patch };
+    const updated = profiles.some(p => p.name === activePaper)
+      ? profiles.map(p => p.name === activePaper ? next : p)
+      : [...profiles, next];
+    setProfiles(updated);
+    invoke("save_paper_profiles", { profiles: updated }).catch(err => alert(`❌ 保存纸张配置失败: ${errorMessage(err)}`));
+  };
+
+  const numberOrNull = (value: string) => value.trim() === "" || !Number.isFinite(Number(value)) ? null : Number(value);
+
   useEffect(() => {
     if (papers.length > 0 && !papers.includes(activePaper)) setActivePaper(papers[0]);
   }, [papers, activePaper, setActivePaper]);
@@ -237,10 +275,12 @@ export default function PaperSetting({
               纸张与工艺分配
               {isEditMode && <span className="text-[10px] bg-red-500 text-white px-1.5 py-0.5 rounded-full font-normal animate-pulse">配置模式</span>}
             </h2>
-            <p className="text-[11px] text-gray-500 mt-1 mb-3">应用后将自动命名为 <br/><span className="font-mono text-gray-700 bg-gray-100 px-1 py-0.5 rounded">类目-工艺-序号.后缀</span></p>
+            <p className="text-[11px] text-gray-500 mt-1 mb-3">应用后按下方模板自动命名，后缀沿用原图</p>
           </div>
         </div>
 
+        <RenameTemplateSetting items={renameItems} disabled={disabled} />
+
         {/* 老板模式：导入/导出 控制台 */}
         {isEditMode && (
           <div className="flex gap-2 mb-4 p-2 bg-gray-100 rounded-lg border border-gray-200 shadow-inner">
@@ -285,6 +325,59 @@ export default function PaperSetting({
           </div>
         )}
 
+        {/* 老板模式：当前纸张的输出配置 */}
+        {isEditMode && activePaper && (
+          <div className="mb-2 p-2 bg-indigo-50/50 rounded-lg border border-indigo-100 space-y-2">
+            <h3 className="text-[10px] font-bold text-indigo-700 uppercase tracking-wider">「{activePaper}」输出配置</h3>
+            <div className="grid grid-cols-2 gap-2 text-[11px] font-bold text-gray-600">
+              <label className="flex flex-col gap-1 col-span-2">
+                <span>输出 ICC</span>
+                <input disabled={disabled} type="text" value={activeProfile.outputIcc ?? ""} placeholder="srgb / adobe_rgb / .icc 路径，留空不转换"
+                  onChange={e => updateProfile({ outputIcc: e.target.value.trim() === "" ? null : e.target.value.trim() })}
+                  className="px-2 py-1 text-xs border border-gray-200 rounded outline-none focus:border-indigo-400 disabled:opacity-40" />
+              </label>
+              <label className="flex flex-col gap-1">
+                <span>渲染意图</span>
+                <select disabled={disabled} value={activeProfile.intent} onChange={e => updateProfile({ intent: e.target.value as PaperProfile["intent"] })}
+                  className="px-1 py-1 text-xs border border-gray-200 rounded outline-none focus:border-indigo-400 disabled:opacity-40">
+                  <option value="perceptual">可感知</option>
+                  <option value="relative">相对比色</option>
+                  <option value="saturation">饱和度</option>
+                  <option value="absolute">绝对比色</option>
+                </select>
+              </label>
+              <label className="flex flex-col gap-1">
+                <span>输出 DPI</span>
+                <input disabled={disabled} type="number" min="1" value={activeProfile.dpi ?? ""} placeholder="默认"
+                  onChange={e => updateProfile({ dpi: numberOrNull(e.target.value) })}
+                  className="px-2 py-1 text-xs border border-gray-200 rounded outline-none focus:border-indigo-400 disabled:opacity-40" />
+              </label>
+              <label className="flex flex-col gap-1">
+                <span>锐化半径 (px)</span>
+                <input disabled={disabled} type="number" min="0" step="0.1" value={activeProfile.sharpen}
+                  onChange={e => updateProfile({ sharpen: Math.max(0, Number(e.target.value) || 0) })}
+                  className="px-2 py-1 text-xs border border-gray-200 rounded outline-none focus:border-indigo-400 disabled:opacity-40" />
+              </label>
+              <label className="flex flex-col gap-1">
+                <span>密度加深 (%)</span>
+                <input disabled={disabled} type="number" step="1" value={activeProfile.toneBoost}
+                  onChange={e => updateProfile({ toneBoost: Number(e.target.value) || 0 })}
+                  className="px-2 py-1 text-xs border border-gray-200 rounded outline-none focus:border-indigo-400 disabled:opacity-40" />
+              </label>
+              <label className="flex flex-col gap-1">
+                <span>最大幅宽 (cm)</span>
+                <input disabled={disabled} type="number" min="0" step="0.1" value={activeProfile.maxWidthCm ?? ""} placeholder="不限"
+                  onChange={e => updateProfile({ maxWidthCm: numberOrNull(e.target.value) })}
+                  className="px-2 py-1 text-xs border border-gray-200 rounded outline-none focus:border-indigo-400 disabled:opacity-40" />
+              </label>
+              <label className="flex items-center gap-1.5 self-end pb-1">
+                <input disabled={disabled} type="checkbox" checked={activeProfile.mirror} onChange={e => updateProfile({ mirror: e.target.checked })} />
+                <span>水平镜像</span>
+              </label>
+            </div>
+          </div>
+        )}
+
         {/* 🌟 恢复：手动输入材质名称 */}
         <div className="mt-1 border-t border-gray-100 pt-3">
           <h3 className="text-[10px] font-bold text-gray-500 mb-2 uppercase tracking-wider">或手动输入特殊材质 (优先)</h3>
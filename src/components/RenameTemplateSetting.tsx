@@ -0,0 +1,199 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { PlannedName, RenameContext, RenameItem, RenamePreset, RenameTemplate } from "../types";
+import { errorMessage } from "../errors";
+
+const STORAGE_KEY_TEMPLATE = "app_rename_template";
+const STORAGE_KEY_CONTEXT = "app_rename_context";
+
+export const DEFAULT_TEMPLATE: RenameTemplate = {
+  pattern: "{paper}-{craft}-{index}", start: 1, pad: 0, case: "keep", lowerExt: false, maxLen: 0
+};
+
+const TOKENS: { token: string; label: string }[] = [
+  { token: "{paper}", label: "纸张" },
+  { token: "{craft}", label: "工艺" },
+  { token: "{index}", label: "序号" },
+  { token: "{stem}", label: "原文件名" },
+  { token: "{size}", label: "尺寸cm" },
+  { token: "{order}", label: "订单号" },
+  { token: "{customer}", label: "客户" },
+  { token: "{date}", label: "日期" },
+  { token: "{copy}", label: "第几份" },
+  { token: "{copies}", label: "共几份" }
+];
+
+const PREVIEW_COUNT = 3;
+
+// 本地日期 YYYYMMDD，与后端 {date} 的格式一致
+const localDate = () => {
+  const now = new Date();
+  return `${now.getFullYear()}${String(now.getMonth() + 1).padStart(2, "0")}${String(now.getDate()).padStart(2, "0")}`;
+};
+
+const readJson = <T,>(key: string, fallback: T): T => {
+  const saved = localStorage.getItem(key);
+  if (!saved) return fallback;
+  try {
+    return { ...fallback, ...(JSON.parse(saved) as Partial<T>) };
+  } catch {
+    return fallback;
+  }
+};
+
+export const readRenameTemplate = (): RenameTemplate => readJson(STORAGE_KEY_TEMPLATE, DEFAULT_TEMPLATE);
+
+export const readRenameContext = (): RenameContext => ({
+  ...readJson<RenameContext>(STORAGE_KEY_CONTEXT, { orderNo: "", customer: "", date: "" }),
+  date: localDate()
+});
+
+interface RenameTemplateSettingProps {
+  items: RenameItem[];
+  disabled?: boolean;
+}
+
+// 改名模板：每家冲印厂一套命名规则，存成预设随时切换，执行前预览前几张的新文件名
+export default function RenameTemplateSetting({ items, disabled }: RenameTemplateSettingProps) {
+  const [template, setTemplate] = useState<RenameTemplate>(readRenameTemplate);
+  const [context, setContext] = useState<RenameContext>(readRenameContext);
+  const [presets, setPresets] = useState<RenamePreset[]>([]);
+  const [preview, setPreview] = useState<PlannedName[]>([]);
+  const [previewError, setPreviewError] = useState("");
+
+  useEffect(() => {
+    invoke<RenamePreset[]>("get_rename_presets").then(setPresets).catch(() => setPresets([]));
+  }, []);
+
+  const updateTemplate = (patch: Partial<RenameTemplate>) => {
+    const next = { ...template, ...patch };
+    setTemplate(next);
+    localStorage.setItem(STORAGE_KEY_TEMPLATE, JSON.stringify(next));
+  };
+
+  const updateContext = (patch: Partial<RenameContext>) => {
+    const next = { ...context, ...patch };
+    setContext(next);
+    localStorage.setItem(STORAGE_KEY_CONTEXT, JSON.stringify({ orderNo: next.orderNo, customer: next.customer }));
+  };
+
+  // 模板或选中的图变化后稍等再预览，避免每敲一个字都读一遍文件头
+  const itemsKey = JSON.stringify(items);
+  useEffect(() => {
+    if (items.length === 0) { setPreview([]); setPreviewError(""); return; }
+    const timer = setTimeout(() => {
+      invoke<PlannedName[]>("preview_rename", { items, template, context, outputDpi: Number(localStorage.getItem("app_output_dpi")) || 300 })
+        .then(planned => { setPreview(planned.slice(0, PREVIEW_COUNT)); setPreviewError(""); })
+        .catch(e => { setPreview([]); setPreviewError(errorMessage(e)); });
+    }, 300);
+    return () => clearTimeout(timer);
+  }, [itemsKey, template, context]);
+
+  const savePresets = async (next: RenamePreset[]) => {
+    try {
+      await invoke("save_rename_presets", { presets: next });
+      setPresets(next);
+    } catch (e) {
+      alert(`❌ 保存命名预设失败: ${errorMessage(e)}`);
+    }
+  };
+
+  const handleSavePreset = () => {
+    const name = prompt("预设名称（如冲印厂名）")?.trim();
+    if (!name) return;
+    const exists = presets.some(p => p.name === name);
+    if (exists && !confirm(`已有预设「${name}」，要覆盖吗？`)) return;
+    const preset = { name, template };
+    savePresets(exists ? presets.map(p => p.name === name ? preset : p) : [...presets, preset]);
+  };
+
+  const handleDeletePreset = (name: string) => {
+    if (!confirm(`删除预设「${name}」？`)) return;
+    savePresets(presets.filter(p => p.name !== name));
+  };
+
+  const activePreset = presets.find(p => JSON.stringify({ ...DEFAULT_TEMPLATE, ...p.template }) === JSON.stringify(template));
+
+  const inputClass = "w-full px-2 py-1 border border-gray-200 rounded outline-none focus:border-blue-500 disabled:opacity-40";
+
+  return (
+    <div className="mb-3 p-2 bg-gray-50 rounded-lg border border-gray-200 space-y-2 text-[11px] text-gray-600">
+      <div className="flex items-center gap-1.5">
+        <span className="font-bold shrink-0">命名预设</span>
+        <select
+          disabled={disabled}
+          value={activePreset?.name ?? ""}
+          onChange={e => { const preset = presets.find(p => p.name === e.target.value); if (preset) updateTemplate({ ...DEFAULT_TEMPLATE, ...preset.template }); }}
+          className="flex-1 min-w-0 px-1 py-1 border border-gray-200 rounded outline-none focus:border-blue-500 disabled:opacity-40"
+        >
+          <option value="" disabled>（未保存）</option>
+          {presets.map(p => <option key={p.name} value={p.name}>{p.name}</option>)}
+        </select>
+        <button disabled={disabled} onClick={handleSavePreset} className="px-2 py-1 rounded bg-blue-600 text-white font-bold hover:bg-blue-700 disabled:opacity-40">存为预设</button>
+        {activePreset && (
+          <button disabled={disabled} onClick={() => handleDeletePreset(activePreset.name)} className="px-2 py-1 rounded bg-red-50 text-red-500 font-bold hover:bg-red-100 disabled:opacity-40">删除</button>
+        )}
+      </div>
+
+      <input
+        disabled={disabled} type="text" value={template.pattern} spellCheck={false}
+        onChange={e => updateTemplate({ pattern: e.target.value })}
+        className={`${inputClass} font-mono`}
+      />
+      <div className="flex flex-wrap gap-1">
+        {TOKENS.map(({ token, label }) => (
+          <button
+            key={token} disabled={disabled} title={token}
+            onClick={() => updateTemplate({ pattern: template.pattern + token })}
+            className="px-1.5 py-0.5 rounded bg-white border border-gray-200 hover:border-blue-400 hover:text-blue-600 disabled:opacity-40"
+          >
+            {label}
+          </button>
+        ))}
+      </div>
+      <div className="text-gray-400">字段后加 :N，序号补零到 N 位、文字最多 N 个字，如 {"{index:3}"}</div>
+
+      <div className="grid grid-cols-4 gap-1.5">
+        <label className="space-y-0.5">
+          <div>起始序号</div>
+          <input disabled={disabled} type="number" min={0} value={template.start} onChange={e => updateTemplate({ start: Math.max(0, Math.round(Number(e.target.value) || 0)) })} className={inputClass} />
+        </label>
+        <label className="space-y-0.5">
+          <div>补零位数</div>
+          <input disabled={disabled} type="number" min={0} max={8} value={template.pad} onChange={e => updateTemplate({ pad: Math.min(8, Math.max(0, Math.round(Number(e.target.value) || 0))) })} className={inputClass} />
+        </label>
+        <label className="space-y-0.5">
+          <div>最长字数</div>
+          <input disabled={disabled} type="number" min={0} value={template.maxLen} placeholder="0 不限" onChange={e => updateTemplate({ maxLen: Math.max(0, Math.round(Number(e.target.value) || 0)) })} className={inputClass} />
+        </label>
+        <label className="space-y-0.5">
+          <div>大小写</div>
+          <select disabled={disabled} value={template.case} onChange={e => updateTemplate({ case: e.target.value as RenameTemplate["case"] })} className={inputClass}>
+            <option value="keep">不变</option>
+            <option value="lower">小写</option>
+            <option value="upper">大写</option>
+          </select>
+        </label>
+      </div>
+      <label className="flex items-center gap-1.5">
+        <input disabled={disabled} type="checkbox" checked={template.lowerExt} onChange={e => updateTemplate({ lowerExt: e.target.checked })} />
+        <span>后缀统一小写（.JPG → .jpg）</span>
+      </label>
+
+      <div className="grid grid-cols-2 gap-1.5">
+        <input disabled={disabled} type="text" value={context.orderNo} placeholder="订单号 {order}" onChange={e => updateContext({ orderNo: e.target.value })} className={inputClass} />
+        <input disabled={disabled} type="text" value={context.customer} placeholder="客户 {customer}" onChange={e => updateContext({ customer: e.target.value })} className={inputClass} />
+      </div>
+
+      {previewError
+        ? <div className="text-red-500 font-bold break-all">⚠️ {previewError}</div>
+        : preview.length > 0 && (
+          <div className="space-y-0.5">
+            <div className="font-bold">预览</div>
+            {preview.map(p => <div key={p.path} className="font-mono text-gray-700 bg-white px-1 py-0.5 rounded break-all">{p.newName}</div>)}
+            {items.length > PREVIEW_COUNT && <div className="text-gray-400">…共 {items.length} 张</div>}
+          </div>
+        )}
+    </div>
+  );
+}
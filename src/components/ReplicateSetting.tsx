@@ -1,3 +1,5 @@
+import OutputSetting from "./OutputSetting";
+
 interface ReplicateSettingProps {
   selectedCount: number;
   disabled?: boolean;
@@ -17,6 +19,9 @@ export default function ReplicateSetting({ selectedCount, disabled, onExecute, o
         请在左侧预览图中点击<span className="text-purple-600 font-bold mx-1">紫色标签</span>修改每个文件份数。<br/>
         复制将对全部可用文件生效（无需勾选）。
       </p>
+      <div className="w-full mb-3">
+        <OutputSetting disabled={disabled} />
+      </div>
       <div className="w-full flex flex-col gap-3">
         <button disabled={disabled} onClick={onSyncToCost} className="py-4 bg-green-600 hover:bg-green-700 text-white rounded-2xl font-bold transition-all shadow-lg active:scale-95 flex items-center justify-center gap-2 disabled:opacity-40 disabled:cursor-not-allowed">
           <span>核算</span>
@@ -1,4 +1,4 @@
-import { ImageItem } from "../types";
+import { ImageItem, RenameItem } from "../types";
 import PaperSetting from "./PaperSetting";
 
 interface SidebarProps {
@@ -10,6 +10,7 @@ interface SidebarProps {
   activeCraft: string;
   setActiveCraft: (craft: string) => void;
   selectedImages: ImageItem[];
+  renameItems: RenameItem[];
   disabled?: boolean;
   onExecuteRename: () => void;
 }
@@ -22,6 +23,7 @@ export default function Sidebar({
   activeCraft,
   setActiveCraft,
   selectedImages,
+  renameItems,
   disabled,
   onExecuteRename
 }: SidebarProps) {
@@ -37,6 +39,7 @@ export default function Sidebar({
         setActiveCraft={setActiveCraft}
         disabled={disabled}
         selectedCount={selectedImages.length} 
+        renameItems={renameItems}
         onExecute={onExecuteRename} 
       />
     </div>
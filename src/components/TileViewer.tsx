@@ -0,0 +1,159 @@
+import { useState, useEffect, useRef } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { TileSource } from "../types";
+import { errorMessage } from "../errors";
+
+interface TileViewerProps {
+  path: string;
+  // 叠加显示的裁切框（百分比），用于核对 100% 下的裁切位置
+  crop?: { x: number; y: number; width: number; height: number };
+  onClose: () => void;
+}
+
+// 最大放大倍数（400%）
+const MAX_SCALE = 4;
+
+export default function TileViewer({ path, crop, onClose }: TileViewerProps) {
+  const [source, setSource] = useState<TileSource | null>(null);
+  const [error, setError] = useState<string>("");
+  const [scale, setScale] = useState(1);
+  const [offset, setOffset] = useState({ x: 0, y: 0 });
+  const [viewport, setViewport] = useState({ w: 0, h: 0 });
+  const containerRef = useRef<HTMLDivElement>(null);
+  const dragRef = useRef<{ x: number; y: number; ox: number; oy: number } | null>(null);
+
+  const fitScale = source && viewport.w > 0 ? Math.min(viewport.w / source.width, viewport.h / source.height, 1) : 1;
+
+  const fit = (s: TileSource, w: number, h: number) => {
+    const next = Math.min(w / s.width, h / s.height, 1);
+    setScale(next);
+    setOffset({ x: (w - s.width * next) / 2, y: (h - s.height * next) / 2 });
+  };
+
+  // 以视口中的某一点为中心缩放
+  const zoomTo = (next: number, cx = viewport.w / 2, cy = viewport.h / 2) => {
+    const clamped = Math.max(fitScale / 2, Math.min(MAX_SCALE, next));
+    setOffset(o => ({ x: cx - (cx - o.x) * (clamped / scale), y: cy - (cy - o.y) * (clamped / scale) }));
+    setScale(clamped);
+  };
+
+  useEffect(() => {
+    let stale = false;
+    setSource(null); setError("");
+    invoke<TileSource>("open_tiles", { pathStr: path })
+      .then(s => { if (!stale) setSource(s); })
+      .catch(e => { if (!stale) setError(errorMessage(e)); });
+    return () => { stale = true; };
+  }, [path]);
+
+  useEffect(() => {
+    const el = containerRef.current;
+    if (!el) return;
+    const observer = new ResizeObserver(() => setViewport({ w: el.clientWidth, h: el.clientHeight }));
+    observer.observe(el);
+    return () => observer.disconnect();
+  }, []);
+
+  useEffect(() => {
+    if (source && viewport.w > 0) fit(source, viewport.w, viewport.h);
+  }, [source, viewport.w > 0]);
+
+  useEffect(() => {
+    const handleKey = (e: KeyboardEvent) => { if (e.key === "Escape") onClose(); };
+    window.addEventListener("keydown", handleKey);
+    return () => window.removeEventListener("keydown", handleKey);
+  }, [onClose]);
+
+  const handleWheel = (e: React.WheelEvent<HTMLDivElement>) => {
+    const rect = e.currentTarget.getBoundingClientRect();
+    zoomTo(scale * Math.pow(1.0015, -e.deltaY), e.clientX - rect.left, e.clientY - rect.top);
+  };
+
+  const handlePointerDown = (e: React.PointerEvent<HTMLDivElement>) => {
+    e.currentTarget.setPointerCapture(e.pointerId);
+    dragRef.current = { x: e.clientX, y: e.clientY, ox: offset.x, oy: offset.y };
+  };
+
+  const handlePointerMove = (e: React.PointerEvent<HTMLDivElement>) => {
+    const drag = dragRef.current;
+    if (!drag) return;
+    setOffset({ x: drag.ox + e.clientX - drag.x, y: drag.oy + e.clientY - drag.y });
+  };
+
+  // 只渲染视口内的瓦片；级别按当前缩放选最接近且不低于屏幕像素的一级
+  const tiles: { key: string; url: string; left: number; top: number; width: number; height: number }[] = [];
+  if (source) {
+    const level = Math.max(0, Math.min(source.levels - 1, Math.floor(Math.log2(1 / scale))));
+    const span = source.tileSize * Math.pow(2, level);
+    const cols = Math.ceil(source.width / span);
+    const rows = Math.ceil(source.height / span);
+    const firstCol = Math.max(0, Math.floor(-offset.x / scale / span));
+    const lastCol = Math.min(cols - 1, Math.floor((viewport.w - offset.x) / scale / span));
+    const firstRow = Math.max(0, Math.floor(-offset.y / scale / span));
+    const lastRow = Math.min(rows - 1, Math.floor((viewport.h - offset.y) / scale / span));
+    for (let row = firstRow; row <= lastRow; row++) {
+      for (let col = firstCol; col <= lastCol; col++) {
+        tiles.push({
+          key: `${level}/${col}/${row}`,
+          url: `${source.baseUrl}/${level}/${col}/${row}.jpg`,
+          left: offset.x + col * span * scale,
+          top: offset.y + row * span * scale,
+          width: Math.min(span, source.width - col * span) * scale,
+          height: Math.min(span, source.height - row * span) * scale,
+        });
+      }
+    }
+  }
+
+  return (
+    <div className="fixed inset-0 z-50 bg-black/85 flex flex-col">
+      <div className="flex items-center gap-2 px-3 py-2 text-white text-xs font-bold shrink-0">
+        <span className="truncate flex-1">{path.split(/[\\/]/).pop()}{source && `（${source.width}×${source.height}px）`}</span>
+        <span className="w-14 text-right">{Math.round(scale * 100)}%</span>
+        <button disabled={!source} onClick={() => source && fit(source, viewport.w, viewport.h)} className="px-2 py-1 rounded bg-white/10 hover:bg-white/20 disabled:opacity-40">适合窗口</button>
+        <button disabled={!source} onClick={() => zoomTo(1)} className="px-2 py-1 rounded bg-white/10 hover:bg-white/20 disabled:opacity-40">100%</button>
+        <button onClick={onClose} className="px-2 py-1 rounded bg-white/10 hover:bg-white/20">关闭</button>
+      </div>
+      <div
+        ref={containerRef}
+        className="relative flex-1 overflow-hidden cursor-grab active:cursor-grabbing select-none"
+        onWheel={handleWheel}
+        onPointerDown={handlePointerDown}
+        onPointerMove={handlePointerMove}
+        onPointerUp={() => { dragRef.current = null; }}
+      >
+        {!source && (
+          <div className="absolute inset-0 flex items-center justify-center text-white/70 text-xs font-bold">
+            {error ? `打开失败: ${error}` : "正在解码原图…"}
+          </div>
+        )}
+        {source && (
+          <>
+            {/* 最高一级整图垫底，细节瓦片加载前不留空白 */}
+            <img
+              src={`${source.baseUrl}/${source.levels - 1}/0/0.jpg`}
+              draggable={false}
+              className="absolute max-w-none"
+              style={{ left: offset.x, top: offset.y, width: source.width * scale, height: source.height * scale }}
+            />
+            {tiles.map(tile => (
+              <img key={tile.key} src={tile.url} draggable={false} className="absolute max-w-none" style={{ left: tile.left, top: tile.top, width: tile.width, height: tile.height }} />
+            ))}
+            {crop && (
+              <div
+                className="absolute border-2 border-blue-400 pointer-events-none"
+                style={{
+                  left: offset.x + (crop.x / 100) * source.width * scale,
+                  top: offset.y + (crop.y / 100) * source.height * scale,
+                  width: (crop.width / 100) * source.width * scale,
+                  height: (crop.height / 100) * source.height * scale,
+                  boxShadow: "0 0 0 9999px rgba(0,0,0,0.35)"
+                }}
+              />
+            )}
+          </>
+        )}
+      </div>
+    </div>
+  );
+}
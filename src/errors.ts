@@ -0,0 +1,25 @@
+import { AppError } from "./types";
+import { t } from "./i18n";
+
+// 引擎缺失时给出的安装指引
+export const engineInstallHint = () => t("hint.engine_install");
+
+// stderr 可能很长，提示框里只留开头
+const STDERR_PREVIEW = 300;
+
+export const isAppError = (e: unknown): e is AppError =>
+  typeof e === "object" && e !== null && "code" in e && "message" in e;
+
+// 给用户看的一句话；引擎缺失时附上安装指引
+export const errorMessage = (e: unknown): string => {
+  if (!isAppError(e)) return String(e);
+  const stderr = e.stderr ? `: ${e.stderr.length > STDERR_PREVIEW ? `${e.stderr.slice(0, STDERR_PREVIEW)}…` : e.stderr}` : "";
+  const hint = e.code === "engine_missing" ? `\n${engineInstallHint()}`
+    : e.code === "resource_limit" ? `\n${t("hint.resource_limit")}` : "";
+  return `${e.message}${stderr}${hint}`;
+};
+
+// 换个时机可能成功的错误（清理了磁盘、关掉了占用文件的程序、机器不那么忙等），批处理结束后提示重试；
+// 文件损坏、格式不支持、参数错误之类重试也没用，直接跳过
+export const isRetryable = (e: AppError | null | undefined): boolean =>
+  !!e && ["disk_full", "permission_denied", "io", "engine_failed", "timeout", "internal"].includes(e.code);
@@ -0,0 +1,32 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// 界面语言：提示文案、报表文字与导出文件名都取后端的同一份文案目录
+export type Locale = "zh-CN" | "en";
+
+export const LOCALES: { value: Locale; label: string }[] = [
+  { value: "zh-CN", label: "中文" },
+  { value: "en", label: "English" }
+];
+
+let locale: Locale = "zh-CN";
+let messages: Record<string, string> = {};
+
+// 启动时和切换语言后各拉取一次
+export const loadMessages = async () => {
+  locale = await invoke<Locale>("get_locale");
+  messages = await invoke<Record<string, string>>("get_messages", { prefix: "" });
+};
+
+export const currentLocale = () => locale;
+
+// 后端记住选择并同步窗口标题
+export const changeLocale = async (value: Locale) => {
+  await invoke<string>("set_locale", { locale: value });
+  await loadMessages();
+};
+
+// 按顺序替换 {}；目录里没有的 key 原样返回
+export const t = (key: string, ...args: (string | number)[]) => {
+  let i = 0;
+  return (messages[key] ?? key).replace(/\{\}/g, () => (i < args.length ? String(args[i++]) : "{}"));
+};
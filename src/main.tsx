@@ -2,9 +2,15 @@ import React from "react";
 import ReactDOM from "react-dom/client";
 import App from "./App";
 import "./App.css"; // 👈 统一改成大写 A
+import { loadMessages } from "./i18n";
 
-ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
-  <React.StrictMode>
-    <App />
-  </React.StrictMode>,
-);
\ No newline at end of file
+// 先取到界面语言的文案再渲染，取不到时按 key 显示
+loadMessages()
+  .catch(() => {})
+  .finally(() => {
+    ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
+      <React.StrictMode>
+        <App />
+      </React.StrictMode>,
+    );
+  });
\ No newline at end of file
@@ -10,6 +10,7 @@ export interface ImageItem {
   size: string;
   isSupported: boolean;
   dpi?: number;  // DPI 分辨率
+  issue?: string;  // 不支持的原因
 }
 
 export interface ProcessProgress {
@@ -18,4 +19,241 @@ export interface ProcessProgress {
   total: number;
   currentName: string;
   statusMessage: string;
-}
\ No newline at end of file
+}
+// 后端命令统一返回的错误，code 稳定不变（见 src-tauri/src/error.rs）
+export type ErrorCode =
+  | "engine_missing" | "engine_failed" | "browser_missing"
+  | "file_not_found" | "file_corrupt" | "unsupported"
+  | "disk_full" | "permission_denied" | "io"
+  | "invalid_input" | "paper_too_narrow" | "verify_failed"
+  | "timeout" | "cancelled" | "resource_limit" | "internal";
+
+export interface AppError {
+  code: ErrorCode;
+  message: string;
+  path: string | null;
+  stderr: string | null;
+}
+
+// process_batch 逐张推送的事件
+export interface BatchEvent {
+  batchId: string;
+  index: number;
+  path: string;
+  done: number;
+  total: number;
+  newPath?: string;
+  newName?: string;
+  error?: AppError;
+}
+
+export interface BatchReport {
+  batchId: string;
+  total: number;
+  succeeded: number;
+  failed: number;
+  cancelled: number;
+  items: {
+    index: number;
+    path: string;
+    status: "succeeded" | "failed" | "cancelled";
+    newPath: string | null;
+    newName: string | null;
+    error: AppError | null;
+  }[];
+}
+
+// 上次未跑完的持久化批次
+export interface QueueSummary {
+  batchId: string;
+  createdMs: number;
+  total: number;
+  done: number;
+  failed: number;
+}
+
+// 撤销 / 回滚结果
+export interface UndoReport {
+  restored: string[];
+  removed: string[];
+  failed: string[];
+}
+
+// plan_process 返回的排版计划（像素几何，不读写文件）
+export interface ProcessPlan {
+  mode: string;
+  sourcePx: { width: number; height: number };
+  sourceDpi: number;
+  crop: { x: number; y: number; width: number; height: number } | null;
+  resample: { width: number; height: number; scaleX: number; scaleY: number } | null;
+  sides: { top: number; right: number; bottom: number; left: number };
+  mirrorFill: boolean;
+  finalPx: { width: number; height: number };
+  finalWidthCm: number;
+  finalHeightCm: number;
+  outputDpi: number;
+  effectiveDpi: number;
+}
+
+// preview_process 返回的成品预览
+export interface ProcessPreview {
+  url: string;
+  plan: ProcessPlan;
+}
+
+// open_tiles 返回的瓦片金字塔信息
+export interface TileSource {
+  key: string;
+  path: string;
+  width: number;
+  height: number;
+  tileSize: number;
+  levels: number;
+  baseUrl: string;
+}
+
+// inspect_image / inspect_images 返回的图像体检结果
+export interface ImageInspection {
+  path: string;
+  fileSize: number;
+  format: string;
+  colorModel: string;
+  bitDepth: number;
+  hasAlpha: boolean;
+  iccDescription: string | null;
+  compression: string | null;
+  pageCount: number;
+  layerCount: number | null;
+  widthPx: number;
+  heightPx: number;
+  orientation: number;
+  dpi: number;
+  dpiFromFile: boolean;
+  cameraMake: string | null;
+  cameraModel: string | null;
+  captureDate: string | null;
+}
+
+export interface InspectItem {
+  path: string;
+  info: ImageInspection | null;
+  error: AppError | null;
+}
+
+// preflight 返回的印前检查结果
+export interface PreflightIssue {
+  code: string;
+  severity: "error" | "warning" | "info";
+  message: string;
+}
+
+export interface PreflightItem {
+  path: string;
+  paper: string | null;
+  mode: string;
+  targetWCm: number;
+  targetHCm: number;
+  info: ImageInspection | null;
+  plan: ProcessPlan | null;
+  issues: PreflightIssue[];
+}
+
+export interface PreflightReport {
+  minPpi: number;
+  items: PreflightItem[];
+  errors: number;
+  warnings: number;
+  html: string;
+}
+
+// get_engine_settings / set_engine_settings：手动指定的路径与实际生效的引擎
+// 外部进程超时（秒），0 表示不限时
+export interface EngineTimeouts {
+  probe: number;
+  preview: number;
+  process: number;
+  print: number;
+}
+
+// magick 的 -limit，null 按本机内存自动
+export interface EngineLimits {
+  memoryMib: number | null;
+  mapMib: number | null;
+  diskGib: number | null;
+}
+
+export interface EngineSettings {
+  magickPath: string | null;
+  browserPath: string | null;
+  timeouts: EngineTimeouts;
+  limits: EngineLimits;
+}
+
+export interface EngineInfo {
+  settings: EngineSettings;
+  magick: { path: string; source: "sidecar" | "setting" | "path" } | null;
+  browser: string | null;
+}
+
+// engine_status：引擎诊断
+export interface FormatSupport {
+  name: string;
+  read: boolean;
+  write: boolean;
+  description: string;
+}
+
+export interface EngineStatus {
+  magick: EngineInfo["magick"];
+  version: string | null;
+  delegates: string[];
+  formats: FormatSupport[];
+  missingFormats: string[];
+  resources: { name: string; value: string }[];
+  ghostscript: string | null;
+  browser: string | null;
+  supportedExtensions: string[];
+  error: AppError | null;
+}
+
+// check_support：文件能否处理
+export interface FileSupport {
+  path: string;
+  isSupported: boolean;
+  reason: string | null;
+}
+
+// 改名模板（rename_files / preview_rename），字段见 naming.rs
+export interface RenameTemplate {
+  pattern: string;
+  start: number;
+  pad: number;
+  case: "keep" | "lower" | "upper";
+  lowerExt: boolean;
+  maxLen: number;
+}
+
+export interface RenamePreset {
+  name: string;
+  template: RenameTemplate;
+}
+
+// 整批共用的字段；date 为空时后端取当天
+export interface RenameContext {
+  orderNo: string;
+  customer: string;
+  date: string;
+}
+
+export interface RenameItem {
+  path: string;
+  paper: string | null;
+  craft: string | null;
+  copies: number | null;
+}
+
+export interface PlannedName {
+  path: string;
+  newPath: string;
+  newName: string;
+}